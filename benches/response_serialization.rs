@@ -0,0 +1,52 @@
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use criterion::{Criterion, criterion_group, criterion_main};
+use crud_rust::model::{
+    http::{ApiResponse, Links, Response},
+    item::Item,
+};
+use serde_json::json;
+
+fn items() -> Vec<Item> {
+    (0..100)
+        .map(|i| Item {
+            id: format!("00000000-0000-0000-0000-{:012}", i),
+            name: format!("item {}", i),
+            slug: format!("item-{}", i),
+            owner_id: None,
+            version: 1,
+        })
+        .collect()
+}
+
+fn bench_list_response(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_response");
+    // The former handler path: typed envelope -> serde_json::Value -> bytes.
+    group.bench_function("json_value", |b| {
+        b.iter(|| {
+            (
+                StatusCode::OK,
+                Json(json!(Response::<Vec<Item>> {
+                    correlation_id: "abc".into(),
+                    message: "ok".into(),
+                    error: "".into(),
+                    error_code: None,
+                    errors: vec![],
+                    data: Some(items()),
+                    links: Some(Links::collection("/api/items")),
+                })),
+            )
+                .into_response()
+        })
+    });
+    group.bench_function("api_response", |b| {
+        b.iter(|| {
+            ApiResponse::ok("abc".into(), items())
+                .links(Links::collection("/api/items"))
+                .into_response()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_list_response);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use crud_rust::{
+    config::Config,
+    event::NoopPublisher,
+    model::{context::Ctx, item::Item},
+    repository::{ErasedRepository, InMemoryRepository},
+    service::{Service, ServiceApi},
+};
+
+fn repository() -> Arc<InMemoryRepository> {
+    let repo = InMemoryRepository::new();
+    repo.item.items.lock().unwrap().push(Item {
+        id: "1".into(),
+        name: "book".into(),
+        slug: "book".into(),
+        owner_id: None,
+        version: 1,
+    });
+    Arc::new(repo)
+}
+
+fn bench_get_item(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let config = Arc::new(Config::default());
+
+    let generic = Service::new(config.clone(), repository(), Arc::new(NoopPublisher));
+    let dynamic = Service::new_dyn(
+        config,
+        Arc::new(ErasedRepository::new(repository())),
+        Arc::new(NoopPublisher),
+    );
+
+    let ctx = Ctx::new("bench");
+
+    let mut group = c.benchmark_group("get_item");
+    group.bench_function("generic", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { generic.get_item(&ctx, "1".into()).await.unwrap() })
+    });
+    group.bench_function("dyn", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { dynamic.get_item(&ctx, "1".into()).await.unwrap() })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_get_item);
+criterion_main!(benches);
@@ -0,0 +1,288 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{Fields, Ident, ItemStruct, LitStr, Path, parse_macro_input, parse_quote};
+
+/// Generates the five standard handlers, the router and the OpenAPI doc for
+/// the annotated model, next to the model itself.
+///
+/// ```ignore
+/// #[crud_resource(plural = "tags", payload = crate::service::tag::TagPayload)]
+/// #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+/// pub struct Tag {
+///     pub id: String,
+///     pub name: String,
+/// }
+/// ```
+///
+/// expands to `TagApi` and `router_setup_tags()`, whose handlers call
+/// `list_tags`, `get_tag`, `create_tag`, `update_tag` and `delete_tag` on
+/// `Arc<dyn ServiceApi>`, the same layout `generate entity` scaffolds.
+///
+/// Arguments:
+/// - `payload`, required, the body type of create and update.
+/// - `plural`, route and method suffix, defaults to the snake case name plus `s`.
+/// - `service`, the trait the handlers call, defaults to `crud_rust::service::ServiceApi`.
+/// - `tag`, the OpenAPI tag, defaults to `plural`.
+#[proc_macro_attribute]
+pub fn crud_resource(args: TokenStream, input: TokenStream) -> TokenStream {
+    let model = parse_macro_input!(input as ItemStruct);
+    let mut args_parsed = Args::default();
+    let parser = syn::meta::parser(|meta| args_parsed.parse(meta));
+    parse_macro_input!(args with parser);
+
+    match expand(&model, args_parsed) {
+        Ok(handlers) => quote!(#model #handlers).into(),
+        Err(e) => {
+            let e = e.to_compile_error();
+            quote!(#model #e).into()
+        }
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    payload: Option<Path>,
+    plural: Option<LitStr>,
+    service: Option<Path>,
+    tag: Option<LitStr>,
+}
+
+impl Args {
+    fn parse(&mut self, meta: syn::meta::ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("payload") {
+            self.payload = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("plural") {
+            self.plural = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("service") {
+            self.service = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("tag") {
+            self.tag = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `payload`, `plural`, `service` or `tag`"));
+        }
+        Ok(())
+    }
+}
+
+fn expand(model: &ItemStruct, args: Args) -> syn::Result<proc_macro2::TokenStream> {
+    let has_id = matches!(&model.fields, Fields::Named(fields)
+        if fields.named.iter().any(|f| f.ident.as_ref().is_some_and(|i| i == "id")));
+    if !has_id {
+        return Err(syn::Error::new_spanned(
+            &model.ident,
+            "crud_resource needs a model with an `id` field",
+        ));
+    }
+    let Some(payload) = args.payload else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "crud_resource needs `payload = <type>`",
+        ));
+    };
+
+    let model_ident = &model.ident;
+    let singular = snake_case(&model_ident.to_string());
+    let plural = args
+        .plural
+        .map(|p| p.value())
+        .unwrap_or_else(|| format!("{}s", singular));
+    let tag = args
+        .tag
+        .map(|t| t.value())
+        .unwrap_or_else(|| plural.clone());
+    let service: Path = args
+        .service
+        .unwrap_or_else(|| parse_quote!(::crud_rust::service::ServiceApi));
+
+    let label = capitalize(&singular.replace('_', " "));
+    let lower = singular.replace('_', " ");
+    let api = format_ident!("{}Api", model_ident);
+    let router = format_ident!("router_setup_{}", plural);
+    let list = format_ident!("list_{}", plural);
+    let create = format_ident!("create_{}", singular);
+    let get = format_ident!("get_{}", singular);
+    let update = format_ident!("update_{}", singular);
+    let delete = format_ident!("delete_{}", singular);
+    let var = Ident::new(&singular, Span::call_site());
+    let vars = Ident::new(&plural, Span::call_site());
+
+    let list_doc = format!("List all {}", plural.replace('_', " "));
+    let id_doc = format!("{} id", label);
+    let created_doc = format!("{} created", label);
+    let found_doc = format!("{} found", label);
+    let updated_doc = format!("{} updated", label);
+    let deleted_doc = format!("{} deleted", label);
+    let invalid_doc = format!("Invalid {}", lower);
+    let not_found_doc = format!("{} not found", label);
+    let deleted_message = format!("Deleted {} with id {{}}", lower);
+
+    Ok(quote! {
+        #[derive(::utoipa::OpenApi)]
+        #[openapi(paths(#list, #create, #get, #update, #delete))]
+        pub struct #api;
+
+        pub fn #router<S>() -> ::axum::Router<S>
+        where
+            S: Clone + Send + Sync + 'static,
+            ::std::sync::Arc<dyn #service>: ::axum::extract::FromRef<S>,
+        {
+            ::axum::Router::new()
+                .route("/", ::axum::routing::get(#list).post(#create))
+                .route(
+                    "/{id}",
+                    ::axum::routing::get(#get).put(#update).delete(#delete),
+                )
+        }
+
+        #[::utoipa::path(
+            get,
+            path = "",
+            tag = #tag,
+            responses(
+                (status = 200, description = #list_doc, body = ::crud_rust::model::http::Response<Vec<#model_ident>>),
+                (status = 500, description = "Internal error", body = ::crud_rust::model::http::Response<::serde_json::Value>),
+            )
+        )]
+        async fn #list(
+            ::axum::extract::State(service): ::axum::extract::State<::std::sync::Arc<dyn #service>>,
+            ctx: ::crud_rust::model::context::Ctx,
+            nested: ::axum::extract::NestedPath,
+        ) -> ::crud_rust::model::http::ApiResult<Vec<#model_ident>> {
+            let #vars = service
+                .#list(&ctx)
+                .await
+                .map_err(|e| ::crud_rust::model::http::ApiResponse::error(ctx.correlation_id.clone(), e))?;
+            Ok(::crud_rust::model::http::ApiResponse::ok(ctx.correlation_id, #vars)
+                .links(::crud_rust::model::http::Links::collection(nested.as_str())))
+        }
+
+        #[::utoipa::path(
+            post,
+            path = "",
+            tag = #tag,
+            request_body = #payload,
+            responses(
+                (status = 201, description = #created_doc, body = ::crud_rust::model::http::Response<#model_ident>),
+                (status = 400, description = #invalid_doc, body = ::crud_rust::model::http::Response<::serde_json::Value>),
+                (status = 500, description = "Internal error", body = ::crud_rust::model::http::Response<::serde_json::Value>),
+            )
+        )]
+        async fn #create(
+            ::axum::extract::State(service): ::axum::extract::State<::std::sync::Arc<dyn #service>>,
+            ctx: ::crud_rust::model::context::Ctx,
+            nested: ::axum::extract::NestedPath,
+            ::crud_rust::extract::ValidatedJson(payload): ::crud_rust::extract::ValidatedJson<#payload>,
+        ) -> ::crud_rust::model::http::ApiResult<#model_ident> {
+            let #var = service
+                .#create(&ctx, payload)
+                .await
+                .map_err(|e| ::crud_rust::model::http::ApiResponse::error(ctx.correlation_id.clone(), e))?;
+            let links = ::crud_rust::model::http::Links::resource(nested.as_str(), &#var.id);
+            Ok(::crud_rust::model::http::ApiResponse::created(ctx.correlation_id, #var, #created_doc).links(links))
+        }
+
+        #[::utoipa::path(
+            get,
+            path = "/{id}",
+            tag = #tag,
+            params(("id" = String, Path, description = #id_doc)),
+            responses(
+                (status = 200, description = #found_doc, body = ::crud_rust::model::http::Response<#model_ident>),
+                (status = 404, description = #not_found_doc, body = ::crud_rust::model::http::Response<::serde_json::Value>),
+                (status = 500, description = "Internal error", body = ::crud_rust::model::http::Response<::serde_json::Value>),
+            )
+        )]
+        async fn #get(
+            ::axum::extract::State(service): ::axum::extract::State<::std::sync::Arc<dyn #service>>,
+            ctx: ::crud_rust::model::context::Ctx,
+            nested: ::axum::extract::NestedPath,
+            ::axum::extract::Path(id): ::axum::extract::Path<String>,
+        ) -> ::crud_rust::model::http::ApiResult<#model_ident> {
+            let #var = service
+                .#get(&ctx, &id)
+                .await
+                .map_err(|e| ::crud_rust::model::http::ApiResponse::error(ctx.correlation_id.clone(), e))?;
+            let links = ::crud_rust::model::http::Links::resource(nested.as_str(), &#var.id);
+            Ok(::crud_rust::model::http::ApiResponse::ok(ctx.correlation_id, #var).links(links))
+        }
+
+        #[::utoipa::path(
+            put,
+            path = "/{id}",
+            tag = #tag,
+            params(("id" = String, Path, description = #id_doc)),
+            request_body = #payload,
+            responses(
+                (status = 200, description = #updated_doc, body = ::crud_rust::model::http::Response<#model_ident>),
+                (status = 400, description = #invalid_doc, body = ::crud_rust::model::http::Response<::serde_json::Value>),
+                (status = 404, description = #not_found_doc, body = ::crud_rust::model::http::Response<::serde_json::Value>),
+                (status = 500, description = "Internal error", body = ::crud_rust::model::http::Response<::serde_json::Value>),
+            )
+        )]
+        async fn #update(
+            ::axum::extract::State(service): ::axum::extract::State<::std::sync::Arc<dyn #service>>,
+            ctx: ::crud_rust::model::context::Ctx,
+            nested: ::axum::extract::NestedPath,
+            ::axum::extract::Path(id): ::axum::extract::Path<String>,
+            ::crud_rust::extract::ValidatedJson(payload): ::crud_rust::extract::ValidatedJson<#payload>,
+        ) -> ::crud_rust::model::http::ApiResult<#model_ident> {
+            let #var = service
+                .#update(&ctx, &id, payload)
+                .await
+                .map_err(|e| ::crud_rust::model::http::ApiResponse::error(ctx.correlation_id.clone(), e))?;
+            let links = ::crud_rust::model::http::Links::resource(nested.as_str(), &#var.id);
+            Ok(::crud_rust::model::http::ApiResponse::ok(ctx.correlation_id, #var)
+                .message(#updated_doc)
+                .links(links))
+        }
+
+        #[::utoipa::path(
+            delete,
+            path = "/{id}",
+            tag = #tag,
+            params(("id" = String, Path, description = #id_doc)),
+            responses(
+                (status = 200, description = #deleted_doc, body = ::crud_rust::model::http::Response<::serde_json::Value>),
+                (status = 500, description = "Internal error", body = ::crud_rust::model::http::Response<::serde_json::Value>),
+            )
+        )]
+        async fn #delete(
+            ::axum::extract::State(service): ::axum::extract::State<::std::sync::Arc<dyn #service>>,
+            ctx: ::crud_rust::model::context::Ctx,
+            ::axum::extract::Path(id): ::axum::extract::Path<String>,
+        ) -> ::crud_rust::model::http::ApiResult<()> {
+            service
+                .#delete(&ctx, &id)
+                .await
+                .map_err(|e| ::crud_rust::model::http::ApiResponse::error(ctx.correlation_id.clone(), e))?;
+            Ok(::crud_rust::model::http::ApiResponse::done(
+                ctx.correlation_id,
+                format!(#deleted_message, id),
+            ))
+        }
+    })
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
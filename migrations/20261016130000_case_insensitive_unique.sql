@@ -0,0 +1,15 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+CREATE UNIQUE INDEX users_email_lower_key ON users (LOWER(email));
+ALTER TABLE items DROP CONSTRAINT IF EXISTS items_name_key;
+CREATE UNIQUE INDEX items_name_lower_key ON items (LOWER(name));
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS items_name_lower_key;
+ALTER TABLE items ADD CONSTRAINT items_name_key UNIQUE (name);
+DROP INDEX IF EXISTS users_email_lower_key;
+ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+-- +goose StatementEnd
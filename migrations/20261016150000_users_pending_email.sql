@@ -0,0 +1,15 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE users
+    ADD COLUMN pending_email VARCHAR(255),
+    ADD COLUMN email_token_hash VARCHAR(64),
+    ADD COLUMN email_token_expires_at TIMESTAMPTZ;
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+ALTER TABLE users
+    DROP COLUMN IF EXISTS email_token_expires_at,
+    DROP COLUMN IF EXISTS email_token_hash,
+    DROP COLUMN IF EXISTS pending_email;
+-- +goose StatementEnd
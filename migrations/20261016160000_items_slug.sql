@@ -0,0 +1,30 @@
+-- +goose Up
+-- +goose StatementBegin
+ALTER TABLE items ADD COLUMN slug VARCHAR(255);
+-- Same rules as `slugify`, repeated slugs get the start of the item id.
+UPDATE items
+SET slug = CASE WHEN s.n = 1 THEN s.base ELSE s.base || '-' || LEFT(items.id, 8) END
+FROM (
+    SELECT id, base, ROW_NUMBER() OVER (PARTITION BY base ORDER BY id) AS n
+    FROM (
+        SELECT id,
+            COALESCE(
+                NULLIF(TRIM(BOTH '-' FROM LEFT(
+                    TRIM(BOTH '-' FROM REGEXP_REPLACE(LOWER(name), '[^a-z0-9]+', '-', 'g')),
+                    80
+                )), ''),
+                'item'
+            ) AS base
+        FROM items
+    ) bases
+) s
+WHERE items.id = s.id;
+ALTER TABLE items ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX items_slug_key ON items (slug);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS items_slug_key;
+ALTER TABLE items DROP COLUMN IF EXISTS slug;
+-- +goose StatementEnd
@@ -0,0 +1,24 @@
+-- +goose Up
+-- +goose StatementBegin
+CREATE TABLE changes (
+    seq BIGSERIAL PRIMARY KEY,
+    entity VARCHAR(32) NOT NULL,
+    entity_id VARCHAR(255) NOT NULL,
+    action VARCHAR(16) NOT NULL,
+    data JSONB,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX changes_entity_changed_at_idx ON changes (entity, entity_id, changed_at);
+-- History starts now, the existing records are taken as created at this point.
+INSERT INTO changes (entity, entity_id, action, data)
+SELECT 'item', id, 'created', jsonb_build_object('id', id, 'name', name, 'slug', slug)
+FROM items;
+INSERT INTO changes (entity, entity_id, action, data)
+SELECT 'user', id, 'created', jsonb_build_object('id', id, 'email', email)
+FROM users;
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP TABLE IF EXISTS changes;
+-- +goose StatementEnd
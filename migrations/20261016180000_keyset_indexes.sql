@@ -0,0 +1,12 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Serve the keyset pages, which walk the lists in (name, id) and (email, id) order.
+CREATE INDEX items_name_id_idx ON items (name, id);
+CREATE INDEX users_email_id_idx ON users (email, id);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS users_email_id_idx;
+DROP INDEX IF EXISTS items_name_id_idx;
+-- +goose StatementEnd
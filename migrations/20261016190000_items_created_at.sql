@@ -0,0 +1,12 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Backs the created_after filter, rows from before this migration get its time.
+ALTER TABLE items ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX items_created_at_idx ON items (created_at);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS items_created_at_idx;
+ALTER TABLE items DROP COLUMN IF EXISTS created_at;
+-- +goose StatementEnd
@@ -0,0 +1,11 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Typo tolerant user search, the `%` operator uses this index.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX users_email_trgm_idx ON users USING gin (email gin_trgm_ops);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS users_email_trgm_idx;
+-- +goose StatementEnd
@@ -0,0 +1,12 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Items stay when their owner is deleted, they just lose the owner.
+ALTER TABLE items ADD COLUMN owner_id VARCHAR(255) REFERENCES users (id) ON DELETE SET NULL;
+CREATE INDEX items_owner_id_idx ON items (owner_id, name);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS items_owner_id_idx;
+ALTER TABLE items DROP COLUMN IF EXISTS owner_id;
+-- +goose StatementEnd
@@ -0,0 +1,12 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Bumped on every update, checked against `If-Match` for optimistic locking.
+ALTER TABLE items ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+ALTER TABLE users DROP COLUMN IF EXISTS version;
+ALTER TABLE items DROP COLUMN IF EXISTS version;
+-- +goose StatementEnd
@@ -0,0 +1,73 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Every write of an item or user records its change in the same transaction,
+-- so history can't miss a committed write or hold one that was rolled back.
+-- Writes leaving the fields of the record as they were, e.g. a pending email
+-- change, aren't changes.
+CREATE FUNCTION record_item_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO changes (entity, entity_id, action, data)
+        VALUES ('item', OLD.id, 'deleted', NULL);
+        RETURN OLD;
+    END IF;
+    INSERT INTO changes (entity, entity_id, action, data)
+    VALUES (
+        'item',
+        NEW.id,
+        CASE TG_OP WHEN 'INSERT' THEN 'created' ELSE 'updated' END,
+        jsonb_strip_nulls(jsonb_build_object(
+            'id', NEW.id,
+            'name', NEW.name,
+            'slug', NEW.slug,
+            'owner_id', NEW.owner_id,
+            'version', NEW.version
+        ))
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION record_user_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO changes (entity, entity_id, action, data)
+        VALUES ('user', OLD.id, 'deleted', NULL);
+        RETURN OLD;
+    END IF;
+    INSERT INTO changes (entity, entity_id, action, data)
+    VALUES (
+        'user',
+        NEW.id,
+        CASE TG_OP WHEN 'INSERT' THEN 'created' ELSE 'updated' END,
+        jsonb_build_object('id', NEW.id, 'email', NEW.email, 'version', NEW.version)
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER items_record_insert_delete AFTER INSERT OR DELETE ON items
+FOR EACH ROW EXECUTE FUNCTION record_item_change();
+CREATE TRIGGER items_record_update AFTER UPDATE ON items
+FOR EACH ROW
+WHEN ((OLD.name, OLD.slug, OLD.owner_id, OLD.version)
+    IS DISTINCT FROM (NEW.name, NEW.slug, NEW.owner_id, NEW.version))
+EXECUTE FUNCTION record_item_change();
+
+CREATE TRIGGER users_record_insert_delete AFTER INSERT OR DELETE ON users
+FOR EACH ROW EXECUTE FUNCTION record_user_change();
+CREATE TRIGGER users_record_update AFTER UPDATE ON users
+FOR EACH ROW
+WHEN ((OLD.email, OLD.version) IS DISTINCT FROM (NEW.email, NEW.version))
+EXECUTE FUNCTION record_user_change();
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP TRIGGER IF EXISTS users_record_update ON users;
+DROP TRIGGER IF EXISTS users_record_insert_delete ON users;
+DROP TRIGGER IF EXISTS items_record_update ON items;
+DROP TRIGGER IF EXISTS items_record_insert_delete ON items;
+DROP FUNCTION IF EXISTS record_user_change();
+DROP FUNCTION IF EXISTS record_item_change();
+-- +goose StatementEnd
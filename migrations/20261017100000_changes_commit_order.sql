@@ -0,0 +1,22 @@
+-- +goose Up
+-- +goose StatementBegin
+-- Transactions recording changes take turns, each holding the lock from its
+-- first change until it ends, so `seq` follows commit order. A reader that
+-- got up to some `seq` then never misses a change committed later with a
+-- smaller one. Taken before the rows get their `seq`.
+CREATE FUNCTION lock_changes() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('changes'));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER changes_commit_order BEFORE INSERT ON changes
+FOR EACH STATEMENT EXECUTE FUNCTION lock_changes();
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP TRIGGER IF EXISTS changes_commit_order ON changes;
+DROP FUNCTION IF EXISTS lock_changes();
+-- +goose StatementEnd
@@ -0,0 +1,14 @@
+-- +goose Up
+-- +goose StatementBegin
+-- How far each consumer of the change history, e.g. the search indexer, got.
+CREATE TABLE change_cursors (
+    consumer VARCHAR(64) PRIMARY KEY,
+    seq BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP TABLE IF EXISTS change_cursors;
+-- +goose StatementEnd
@@ -0,0 +1,15 @@
+-- +goose Up
+-- +goose StatementBegin
+-- A running job's worker keeps extending it. Once it lapses, e.g. because the
+-- worker died, the job can be claimed again.
+ALTER TABLE jobs ADD COLUMN locked_until TIMESTAMPTZ;
+-- Jobs running while this is deployed get one lease before they are reclaimed.
+UPDATE jobs SET locked_until = now() + interval '5 minutes' WHERE status = 'running';
+CREATE INDEX jobs_running_locked_until_idx ON jobs (locked_until) WHERE status = 'running';
+-- +goose StatementEnd
+
+-- +goose Down
+-- +goose StatementBegin
+DROP INDEX IF EXISTS jobs_running_locked_until_idx;
+ALTER TABLE jobs DROP COLUMN IF EXISTS locked_until;
+-- +goose StatementEnd
@@ -0,0 +1,588 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    middleware::{from_fn, from_fn_with_state},
+};
+
+use crate::{
+    cache::{CachePolicies, cache_control_middleware},
+    config::Config,
+    handler::{
+        ADMIN_CHAOS_PATH, ADMIN_JOBS_PATH, ADMIN_VERSIONS_PATH, API_PREFIX, DEFAULT_GROUP,
+        EVENTS_PATH, EXPORT_JOBS_PATH, IMPORT_JOBS_PATH, ITEMS_PATH, SYNC_PATH, USERS_PATH,
+        chaos::{chaos_middleware, router_setup_chaos},
+        event::router_setup_events,
+        export::router_setup_exports,
+        import::router_setup_imports,
+        index::{method_not_allowed, not_found, router_setup_index},
+        item::router_setup_items,
+        job::router_setup_jobs,
+        status::{router_setup_status, stats_middleware},
+        sync::router_setup_sync,
+        user::router_setup_users,
+        version::{ApiMount, ApiVersion, ApiVersions, router_setup_versions, version_middleware},
+    },
+    limit::{ConcurrencyLimiter, RateLimiter, concurrency_middleware, rate_limit_middleware},
+    messages::messages_middleware,
+    middleware::{
+        delete_no_content_middleware, envelope_middleware, jsonapi_middleware,
+        not_modified_middleware, problem_details_middleware, request_middleware, xml_middleware,
+    },
+    mirror::{Mirror, mirror_middleware},
+    openapi::router_setup_docs,
+    proxy::{TrustedProxies, client_ip_middleware},
+    schema::{SchemaRegistry, schema_middleware},
+    state::AppState,
+    timeout::{RouteTimeouts, deadline_middleware, timeout_middleware},
+};
+
+/// Layers set per route group: its timeout and cache policy, and the body
+/// schemas checked on every API version.
+#[derive(Debug, Clone, Default)]
+pub struct RouteLayers {
+    pub timeouts: RouteTimeouts,
+    pub cache: CachePolicies,
+    pub schemas: Option<Arc<SchemaRegistry>>,
+}
+
+impl RouteLayers {
+    /// Reads the groups' settings from config, checked at startup, see `main`.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            timeouts: RouteTimeouts::parse(&config.route_timeouts_ms).unwrap_or_default(),
+            cache: CachePolicies::parse(&config.cache_policies).unwrap_or_default(),
+            // The built-in schemas are generated from types that always compile.
+            schemas: config
+                .strict_request_schemas
+                .then(SchemaRegistry::builtin)
+                .and_then(Result::ok)
+                .map(Arc::new),
+        }
+    }
+
+    pub fn apply<S>(&self, group: &str, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router
+            .layer(from_fn_with_state(
+                self.timeouts.get(group),
+                timeout_middleware,
+            ))
+            .layer(from_fn_with_state(
+                self.cache.get(group),
+                cache_control_middleware,
+            ))
+    }
+}
+
+/// Resource routes of one API version, to be nested under `mount.prefix`.
+/// Each route group gets its own [`RouteLayers`].
+pub fn router_setup_api(
+    mount: ApiMount,
+    versions: Arc<ApiVersions>,
+    layers: &RouteLayers,
+) -> Router<AppState> {
+    Router::new()
+        .nest(ITEMS_PATH, layers.apply("items", router_setup_items()))
+        .nest(USERS_PATH, layers.apply("users", router_setup_users()))
+        .nest(EVENTS_PATH, layers.apply("events", router_setup_events()))
+        .nest(
+            EXPORT_JOBS_PATH,
+            layers.apply("exports", router_setup_exports()),
+        )
+        .nest(
+            IMPORT_JOBS_PATH,
+            layers.apply("imports", router_setup_imports()),
+        )
+        .nest(SYNC_PATH, layers.apply("sync", router_setup_sync()))
+        .nest(ADMIN_JOBS_PATH, layers.apply("admin", router_setup_jobs()))
+        .nest(
+            ADMIN_VERSIONS_PATH,
+            layers.apply("admin", router_setup_versions()),
+        )
+        .layer(from_fn_with_state(
+            layers.schemas.clone(),
+            schema_middleware,
+        ))
+        .layer(from_fn_with_state((mount, versions), version_middleware))
+}
+
+/// Builds the full application router, ready to be served or nested into
+/// another axum application.
+pub fn build_router(state: AppState) -> Router {
+    let versions = state.api_versions.clone();
+    let mut layers = RouteLayers::from_config(&state.config);
+    if let Some(cache) = &state.cache {
+        layers.cache = cache.clone();
+    }
+    let v1 = ApiMount::of(ApiVersion::V1);
+    let mut api = Router::new()
+        .nest(
+            API_PREFIX,
+            router_setup_api(ApiMount::default(), versions.clone(), &layers),
+        )
+        .nest(v1.prefix, router_setup_api(v1, versions.clone(), &layers));
+    if versions.is_enabled(ApiVersion::V2) {
+        let v2 = ApiMount::of(ApiVersion::V2);
+        api = api.nest(v2.prefix, router_setup_api(v2, versions, &layers));
+    }
+    // Only the resource routes are limited, probes and docs are always served.
+    let concurrency = Arc::new(ConcurrencyLimiter::new(state.config.client_max_concurrency));
+    let rate_limit = Arc::new(RateLimiter::new(
+        state.config.rate_limit_requests,
+        Duration::from_secs(state.config.rate_limit_window_secs),
+    ));
+    let mut api = api
+        .layer(from_fn_with_state(concurrency, concurrency_middleware))
+        .layer(from_fn_with_state(rate_limit, rate_limit_middleware));
+    // Checked at startup, see `main`.
+    if let Ok(Some(mirror)) = Mirror::from_config(&state.config) {
+        api = api.layer(from_fn_with_state(Arc::new(mirror), mirror_middleware));
+    }
+    // Checked at startup, see `main`.
+    let proxies =
+        Arc::new(TrustedProxies::parse(&state.config.trusted_proxies).unwrap_or_default());
+    let mut router = Router::new()
+        .merge(layers.apply("healthcheck", router_setup_index()))
+        .merge(layers.apply("status", router_setup_status()))
+        .merge(api);
+    if state.config.chaos_enabled {
+        tracing::warn!("Chaos mode is enabled, requests may be slowed down or failed");
+        router = router
+            .nest(
+                &format!("{}{}", v1.prefix, ADMIN_CHAOS_PATH),
+                router_setup_chaos(),
+            )
+            .layer(from_fn_with_state(state.chaos.clone(), chaos_middleware));
+    }
+    router
+        .merge(layers.apply(DEFAULT_GROUP, router_setup_docs()))
+        .fallback(not_found)
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(from_fn(not_modified_middleware))
+        .layer(from_fn_with_state(
+            state.messages.clone(),
+            messages_middleware,
+        ))
+        .layer(from_fn_with_state(state.config.clone(), jsonapi_middleware))
+        .layer(from_fn_with_state(
+            state.config.clone(),
+            problem_details_middleware,
+        ))
+        .layer(from_fn_with_state(
+            state.config.clone(),
+            envelope_middleware,
+        ))
+        .layer(from_fn(xml_middleware))
+        .layer(from_fn_with_state(
+            state.config.clone(),
+            delete_no_content_middleware,
+        ))
+        .layer(from_fn_with_state(state.stats.clone(), stats_middleware))
+        .layer(from_fn_with_state(proxies, client_ip_middleware))
+        .layer(from_fn(deadline_middleware))
+        .layer(from_fn(request_middleware))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{Body, to_bytes},
+        http::{Request, StatusCode, header::CACHE_CONTROL},
+    };
+    use serde_json::Value;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use std::sync::Arc;
+
+    use axum::extract::FromRef;
+
+    use crate::{
+        config::Config,
+        middleware::X_CORRELATION_ID,
+        model::{
+            error::{AppError, AppErrorCode},
+            item::Item,
+        },
+        repository::InMemoryRepository,
+        service::{ServiceApi, registry::MockServiceApi},
+    };
+
+    #[derive(Clone)]
+    struct EmbedderState {
+        crud: Arc<dyn ServiceApi>,
+    }
+
+    impl FromRef<EmbedderState> for Arc<dyn ServiceApi> {
+        fn from_ref(state: &EmbedderState) -> Self {
+            state.crud.clone()
+        }
+    }
+
+    fn test_state() -> AppState {
+        // Never connects, the routes under test don't touch the database.
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        AppState::builder()
+            .config(Config::default())
+            .postgres(pool)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_healthcheck() {
+        let app = build_router(test_state());
+        let req = Request::builder()
+            .uri("/api/healthcheck")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().contains_key(X_CORRELATION_ID));
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["message"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_builder_cache_replaces_configured_policies() {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let state = AppState::builder()
+            .config(Config::default())
+            .postgres(pool)
+            .cache(CachePolicies::parse("default=no-store").unwrap())
+            .build()
+            .unwrap();
+        let req = Request::builder()
+            .uri("/api/healthcheck")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = build_router(state).oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()[CACHE_CONTROL], "no-store");
+    }
+
+    #[tokio::test]
+    async fn test_openapi_spec_served() {
+        let app = build_router(test_state());
+        let req = Request::builder()
+            .uri("/api/openapi.json")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    fn missing_item_app(config: Config) -> Router {
+        let mut service = MockServiceApi::new();
+        service.expect_get_item().returning(|_, _| {
+            Box::pin(async {
+                Err(AppError {
+                    code: AppErrorCode::NotFound,
+                    message: "Item with id 1 not found".into(),
+                    error_code: None,
+                })
+            })
+        });
+        let state = AppState::builder()
+            .config(config)
+            .service(Arc::new(service))
+            .build()
+            .unwrap();
+        build_router(state)
+    }
+
+    async fn get(app: Router, uri: &str) -> axum::response::Response {
+        let req = Request::get(uri).body(Body::empty()).unwrap();
+        app.oneshot(req).await.unwrap()
+    }
+
+    async fn json(res: axum::response::Response) -> Value {
+        serde_json::from_slice(&to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_versioned_prefixes() {
+        let app = missing_item_app(Config::default());
+        for uri in ["/api/items/1", "/api/v1/items/1"] {
+            let res = get(app.clone(), uri).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+            assert_eq!(res.headers()["content-type"], "application/json");
+        }
+        // v2 isn't mounted unless enabled.
+        let res = get(app, "/api/v2/items/1").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body = json(res).await;
+        assert_eq!(body["message"], "No route for /api/v2/items/1");
+    }
+
+    #[tokio::test]
+    async fn test_fallbacks_answer_envelopes() {
+        let app = missing_item_app(Config::default());
+        let req = Request::get("/nope")
+            .header(X_CORRELATION_ID, "c1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body = json(res).await;
+        assert_eq!(body["correlation_id"], "c1");
+        assert_eq!(body["error_code"], "NOT_FOUND");
+
+        let req = Request::patch("/api/v1/items/1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(res.headers().contains_key("allow"));
+        let body = json(res).await;
+        assert_eq!(body["error_code"], "METHOD_NOT_ALLOWED");
+        assert_eq!(body["message"], "PATCH is not allowed on /api/v1/items/1");
+    }
+
+    #[tokio::test]
+    async fn test_v2_answers_problem_details() {
+        let config = Config {
+            api_v2_enabled: true,
+            ..Default::default()
+        };
+        let res = get(missing_item_app(config), "/api/v2/items/1").await;
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()["content-type"], "application/problem+json");
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["instance"], "/api/v2/items/1");
+    }
+
+    #[tokio::test]
+    async fn test_alias_negotiates_version_and_flags_deprecation() {
+        let app = missing_item_app(Config {
+            api_v2_enabled: true,
+            api_deprecated_versions: vec!["v1".into()],
+            api_sunset: Some("Thu, 31 Dec 2026 23:59:59 GMT".into()),
+            ..Default::default()
+        });
+
+        let res = get(app.clone(), "/api/items/1").await;
+        assert_eq!(res.headers()["api-version"], "v1");
+        assert_eq!(res.headers()["deprecation"], "true");
+        assert_eq!(res.headers()["sunset"], "Thu, 31 Dec 2026 23:59:59 GMT");
+
+        let req = Request::get("/api/items/1")
+            .header("Api-Version", "2")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.headers()["api-version"], "v2");
+        assert!(!res.headers().contains_key("deprecation"));
+        assert_eq!(res.headers()["content-type"], "application/problem+json");
+
+        let req = Request::get("/api/items/1")
+            .header("Api-Version", "7")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = get(app, "/api/v1/admin/versions").await;
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["data"][0]["version"], "v1");
+        // The usage request itself counts too.
+        assert_eq!(body["data"][0]["requests"], 2);
+        assert_eq!(body["data"][1]["requests"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_status_page() {
+        let app = missing_item_app(Config::default());
+        get(app.clone(), "/api/items/1").await;
+
+        let res = get(app, "/status").await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/html; charset=utf-8");
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(html.contains(env!("CARGO_PKG_VERSION")));
+        assert!(html.contains("0.00% (0 of 1)"));
+    }
+
+    #[tokio::test]
+    async fn test_status_page_reports_unreachable_database() {
+        let res = get(build_router(test_state()), "/status").await;
+
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("database</th><td>failing"));
+    }
+
+    #[tokio::test]
+    async fn test_chaos_mode() {
+        let res = get(missing_item_app(Config::default()), "/api/v1/admin/chaos").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let app = missing_item_app(Config {
+            chaos_enabled: true,
+            ..Default::default()
+        });
+        let req = Request::get("/api/items/1")
+            .header("X-Chaos-Error-Rate", "1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let req = Request::post("/api/v1/admin/chaos")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"route": "/api/v1/items", "db_failure_rate": 1}"#,
+            ))
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = get(app.clone(), "/api/v1/items/1").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        // Other routes and the alias are not matched by the rule.
+        assert_eq!(
+            get(app.clone(), "/api/items/1").await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let req = Request::delete("/api/v1/admin/chaos")
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(req).await.unwrap();
+        let res = get(app, "/api/v1/items/1").await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_route_timeouts() {
+        let mut service = MockServiceApi::new();
+        service.expect_get_item().returning(|_, _| {
+            Box::pin(async {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                Ok(Item {
+                    id: "1".into(),
+                    name: "book".into(),
+                    slug: "book".into(),
+                    owner_id: None,
+                    version: 1,
+                })
+            })
+        });
+        let state = AppState::builder()
+            .config(Config {
+                route_timeouts_ms: "default=0,items=10".into(),
+                ..Default::default()
+            })
+            .service(Arc::new(service))
+            .build()
+            .unwrap();
+        let app = build_router(state);
+
+        for uri in ["/api/items/1", "/api/v1/items/1"] {
+            let res = get(app.clone(), uri).await;
+            assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+        let res = get(app, "/api/healthcheck").await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cache_policies() {
+        let state = AppState::builder()
+            .config(Config {
+                cache_policies: "healthcheck=public, max-age=5".into(),
+                ..Default::default()
+            })
+            .repository(Arc::new(InMemoryRepository::new()))
+            .build()
+            .unwrap();
+        let app = build_router(state);
+
+        let res = get(app.clone(), "/api/healthcheck").await;
+        assert_eq!(res.headers()["cache-control"], "public, max-age=5");
+        assert!(res.headers().contains_key("expires"));
+        let res = get(app, "/api/v1/items").await;
+        assert!(res.headers().get("cache-control").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_strict_request_schemas() {
+        let state = AppState::builder()
+            .config(Config {
+                strict_request_schemas: true,
+                ..Default::default()
+            })
+            .repository(Arc::new(InMemoryRepository::new()))
+            .build()
+            .unwrap();
+        let app = build_router(state);
+
+        for uri in ["/api/items", "/api/v1/items"] {
+            let req = Request::post(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"name": ["book"]}"#))
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+            let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["errors"][0]["field"], "/name");
+        }
+        let req = Request::post("/api/items")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"name": "book"}"#))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_items_router_mounted_under_custom_prefix() {
+        let mut service = MockServiceApi::new();
+        service.expect_list_items().returning(|_, _, _, _| {
+            Box::pin(async {
+                Ok(vec![Item {
+                    id: "1".into(),
+                    name: "book".into(),
+                    slug: "book".into(),
+                    owner_id: None,
+                    version: 1,
+                }])
+            })
+        });
+
+        let app = Router::new()
+            .nest("/inventory", router_setup_items())
+            .layer(from_fn(request_middleware))
+            .with_state(EmbedderState {
+                crud: Arc::new(service),
+            });
+        let req = Request::builder()
+            .uri("/inventory")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["links"]["self"], "/inventory");
+        assert_eq!(body["data"][0]["name"], "book");
+    }
+}
@@ -0,0 +1,270 @@
+//! Command line client for a running instance, e.g.
+//! `crud-cli items list --filter name=foo` or `crud-cli users create --email a@b.com`.
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crud_rust::{
+    client::{Client, Resource},
+    handler::{
+        crud::CrudResource,
+        item::{CreateItem, UpdateItem},
+    },
+    model::error::{AppError, AppErrorCode},
+    service::user::{CreateUser, UpdateUser},
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Base URL of the instance.
+    #[arg(long, env = "CRUD_URL", default_value = "http://localhost:3000")]
+    url: String,
+    /// Bearer token sent with every request.
+    #[arg(long, env = "CRUD_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    #[arg(long, value_enum, default_value_t = Output::Table)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    Items {
+        #[command(subcommand)]
+        action: Action<ItemFields>,
+    },
+    Users {
+        #[command(subcommand)]
+        action: Action<UserFields>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Action<F: clap::Args> {
+    /// Lists every entity, across all pages.
+    List {
+        /// `field=value`, keeps entities whose field contains the value,
+        /// ignoring case. Repeat to narrow further.
+        #[arg(long)]
+        filter: Vec<String>,
+    },
+    Get {
+        id: String,
+    },
+    Create {
+        #[command(flatten)]
+        fields: F,
+    },
+    Update {
+        id: String,
+        #[command(flatten)]
+        fields: F,
+    },
+    Delete {
+        id: String,
+    },
+}
+
+#[derive(clap::Args)]
+struct ItemFields {
+    #[arg(long)]
+    name: String,
+}
+
+#[derive(clap::Args)]
+struct UserFields {
+    #[arg(long)]
+    email: String,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut client = Client::new(&cli.url);
+    if let Some(token) = cli.token {
+        client = client.token(token);
+    }
+    let result = match cli.command {
+        Command::Items { action } => {
+            run(
+                client.items(),
+                action,
+                |f| CreateItem { name: f.name },
+                |f| UpdateItem { name: f.name },
+            )
+            .await
+        }
+        Command::Users { action } => {
+            run(
+                client.users(),
+                action,
+                |f| CreateUser { email: f.email },
+                |f| UpdateUser { email: f.email },
+            )
+            .await
+        }
+    };
+    match result {
+        Ok(Some(value)) => {
+            print!("{}", render(&value, cli.output));
+            ExitCode::SUCCESS
+        }
+        Ok(None) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e.get_message());
+            for error in e.get_field_errors() {
+                eprintln!("  {}: {}", error.field, error.message);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run<T, F: clap::Args>(
+    resource: Resource<'_, T>,
+    action: Action<F>,
+    create: impl FnOnce(F) -> T::Create,
+    update: impl FnOnce(F) -> T::Update,
+) -> Result<Option<Value>, AppError>
+where
+    T: CrudResource + DeserializeOwned,
+    T::Create: Serialize,
+    T::Update: Serialize,
+    T::Id: From<String>,
+{
+    let value = match action {
+        Action::List { filter } => {
+            let filters = parse_filters(&filter)?;
+            let entities: Vec<Value> = resource
+                .list()
+                .await?
+                .iter()
+                .map(|entity| serde_json::json!(entity))
+                .filter(|entity| matches(entity, &filters))
+                .collect();
+            Value::Array(entities)
+        }
+        Action::Get { id } => serde_json::json!(resource.get(&id.into()).await?),
+        Action::Create { fields } => serde_json::json!(resource.create(&create(fields)).await?),
+        Action::Update { id, fields } => {
+            serde_json::json!(resource.update(&id.into(), &update(fields)).await?)
+        }
+        Action::Delete { id } => {
+            resource.delete(&id.into()).await?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(value))
+}
+
+fn parse_filters(filters: &[String]) -> Result<Vec<(String, String)>, AppError> {
+    filters
+        .iter()
+        .map(|filter| {
+            filter
+                .split_once('=')
+                .map(|(field, value)| (field.trim().to_string(), value.trim().to_lowercase()))
+                .ok_or_else(|| AppError {
+                    code: AppErrorCode::InvalidInput,
+                    message: format!("Invalid filter '{}', expected field=value", filter),
+                    error_code: None,
+                })
+        })
+        .collect()
+}
+
+fn matches(entity: &Value, filters: &[(String, String)]) -> bool {
+    filters.iter().all(|(field, value)| {
+        entity
+            .get(field)
+            .is_some_and(|v| cell(v).to_lowercase().contains(value))
+    })
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        v => v.to_string(),
+    }
+}
+
+/// JSON as is, or one aligned row per entity under its field names.
+fn render(value: &Value, output: Output) -> String {
+    let rows = match (output, value) {
+        (Output::Json, _) => return format!("{:#}\n", value),
+        (Output::Table, Value::Array(rows)) => rows.clone(),
+        (Output::Table, row) => vec![row.clone()],
+    };
+    let columns: Vec<String> = match rows.first() {
+        Some(Value::Object(first)) => first.keys().cloned().collect(),
+        _ => return format!("{:#}\n", value),
+    };
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|c| cell(&row[c])).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([c.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |values: &[String]| {
+        let padded: Vec<String> = values
+            .iter()
+            .zip(&widths)
+            .map(|(v, w)| format!("{:<w$}", v, w = w))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    let mut out = line(&header);
+    for row in &cells {
+        out.push_str(&line(row));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        let filters = parse_filters(&["name=BO".into()]).unwrap();
+        assert!(matches(&json!({"id": "1", "name": "book"}), &filters));
+        assert!(!matches(&json!({"id": "2", "name": "pen"}), &filters));
+        assert!(!matches(&json!({"id": "3"}), &filters));
+        assert!(parse_filters(&["name".into()]).is_err());
+    }
+
+    #[test]
+    fn test_render_table() {
+        let value = json!([{"id": "1", "name": "book"}, {"id": "22", "name": "pen"}]);
+        assert_eq!(
+            render(&value, Output::Table),
+            "ID  NAME\n1   book\n22  pen\n"
+        );
+        assert_eq!(render(&json!([]), Output::Table), "[]\n");
+    }
+}
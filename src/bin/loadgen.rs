@@ -0,0 +1,357 @@
+//! Drives a mix of item CRUD traffic against a running instance and reports
+//! latency percentiles and error rates per operation, e.g.
+//! `loadgen --concurrency 16 --duration 30 --mix create=1,get=6,list=2,update=1`.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    process::ExitCode,
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use clap::{Parser, ValueEnum};
+use futures::TryStreamExt;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crud_rust::{
+    client::Client,
+    handler::item::{CreateItem, UpdateItem},
+    model::error::{AppError, AppErrorCode},
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Base URL of the instance.
+    #[arg(long, env = "CRUD_URL", default_value = "http://localhost:3000")]
+    url: String,
+    /// Bearer token sent with every request.
+    #[arg(long, env = "CRUD_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Requests in flight at once.
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+    /// Seconds to run for.
+    #[arg(long, default_value_t = 10)]
+    duration: u64,
+    /// Stops after this many requests, even before the duration is up.
+    #[arg(long)]
+    requests: Option<u64>,
+    /// Relative weights of `create`, `get`, `list`, `update` and `delete`.
+    #[arg(long, default_value = "create=1,get=5,list=2,update=1,delete=1")]
+    mix: String,
+    #[arg(long, value_enum, default_value_t = Output::Table)]
+    output: Output,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Op {
+    Create,
+    Get,
+    List,
+    Update,
+    Delete,
+}
+
+impl Op {
+    const ALL: [Self; 5] = [
+        Self::Create,
+        Self::Get,
+        Self::List,
+        Self::Update,
+        Self::Delete,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Get => "get",
+            Self::List => "list",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// One round of the mix, each operation repeated by its weight and spread
+/// out so that any window of the schedule is close to the mix.
+fn parse_mix(mix: &str) -> Result<Vec<Op>, AppError> {
+    let invalid = |message: String| AppError {
+        code: AppErrorCode::InvalidInput,
+        message,
+        error_code: None,
+    };
+    let mut weights = BTreeMap::new();
+    for part in mix.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (name, weight) = part
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("Invalid mix entry '{}', expected op=weight", part)))?;
+        let op = Op::ALL
+            .into_iter()
+            .find(|op| op.as_str() == name.trim())
+            .ok_or_else(|| invalid(format!("Unknown operation '{}'", name.trim())))?;
+        let weight: u32 = weight
+            .trim()
+            .parse()
+            .map_err(|_| invalid(format!("Invalid weight '{}' of {}", weight, name)))?;
+        weights.insert(op, weight);
+    }
+    let total: u32 = weights.values().sum();
+    if total == 0 {
+        return Err(invalid(
+            "The mix has no operation with a weight".to_string(),
+        ));
+    }
+    let mut schedule: Vec<(f64, Op)> = weights
+        .into_iter()
+        .flat_map(|(op, weight)| (0..weight).map(move |i| ((i as f64 + 0.5) / weight as f64, op)))
+        .collect();
+    schedule.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(schedule.into_iter().map(|(_, op)| op).collect())
+}
+
+/// Nearest-rank percentile of sorted samples.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct OpReport {
+    op: String,
+    requests: u64,
+    errors: u64,
+    error_rate: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+impl OpReport {
+    fn new(op: &str, mut samples: Samples) -> Self {
+        samples.latencies.sort();
+        let requests = samples.latencies.len() as u64;
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        Self {
+            op: op.to_string(),
+            requests,
+            errors: samples.errors,
+            error_rate: if requests == 0 {
+                0.0
+            } else {
+                samples.errors as f64 / requests as f64
+            },
+            p50_ms: ms(percentile(&samples.latencies, 50.0)),
+            p90_ms: ms(percentile(&samples.latencies, 90.0)),
+            p99_ms: ms(percentile(&samples.latencies, 99.0)),
+            max_ms: ms(samples.latencies.last().copied().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    elapsed_secs: f64,
+    requests_per_sec: f64,
+    operations: Vec<OpReport>,
+    total: OpReport,
+}
+
+/// Runs its share of the schedule, starting at `offset` so the workers don't
+/// move in lockstep. Operations needing an item create one when the worker
+/// has none left.
+async fn worker(
+    client: Client,
+    schedule: Vec<Op>,
+    offset: usize,
+    deadline: Instant,
+    budget: Arc<AtomicI64>,
+) -> (BTreeMap<Op, Samples>, Vec<String>) {
+    let items = client.items();
+    let mut samples: BTreeMap<Op, Samples> = BTreeMap::new();
+    let mut ids: Vec<String> = vec![];
+    let mut n = offset;
+    while Instant::now() < deadline && budget.fetch_sub(1, Ordering::Relaxed) > 0 {
+        let mut op = schedule[n % schedule.len()];
+        n += 1;
+        if ids.is_empty() && matches!(op, Op::Get | Op::Update | Op::Delete) {
+            op = Op::Create;
+        }
+        // Names are unique, so workers never touch each other's items.
+        let name = format!("load-{}", Uuid::new_v4());
+        let started = Instant::now();
+        let result = match op {
+            Op::Create => items
+                .create(&CreateItem { name })
+                .await
+                .map(|item| ids.push(item.id)),
+            Op::Get => items.get(&ids[n % ids.len()]).await.map(|_| ()),
+            Op::List => Box::pin(items.pages()).try_next().await.map(|_| ()),
+            Op::Update => items
+                .update(&ids[n % ids.len()], &UpdateItem { name })
+                .await
+                .map(|_| ()),
+            Op::Delete => {
+                let id = ids.swap_remove(n % ids.len());
+                items.delete(&id).await
+            }
+        };
+        let sample = samples.entry(op).or_default();
+        sample.latencies.push(started.elapsed());
+        if result.is_err() {
+            sample.errors += 1;
+        }
+    }
+    (samples, ids)
+}
+
+async fn run(cli: &Cli) -> Result<Report, AppError> {
+    let schedule = parse_mix(&cli.mix)?;
+    let mut client = Client::new(&cli.url);
+    if let Some(token) = &cli.token {
+        client = client.token(token.clone());
+    }
+    let budget = Arc::new(AtomicI64::new(cli.requests.map_or(i64::MAX, |n| n as i64)));
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(cli.duration);
+    let workers: Vec<_> = (0..cli.concurrency.max(1))
+        .map(|i| {
+            tokio::spawn(worker(
+                client.clone(),
+                schedule.clone(),
+                i * schedule.len() / cli.concurrency.max(1),
+                deadline,
+                budget.clone(),
+            ))
+        })
+        .collect();
+
+    let mut samples: BTreeMap<Op, Samples> = BTreeMap::new();
+    let mut leftovers = vec![];
+    for worker in workers {
+        let (worker_samples, ids) = worker.await.map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Load worker failed".to_string(),
+            error_code: None,
+        })?;
+        for (op, s) in worker_samples {
+            let total = samples.entry(op).or_default();
+            total.latencies.extend(s.latencies);
+            total.errors += s.errors;
+        }
+        leftovers.extend(ids);
+    }
+    let elapsed = started.elapsed();
+
+    // Items created by the run are removed again, outside the measurements.
+    for id in leftovers {
+        let _ = client.items().delete(&id).await;
+    }
+
+    let mut all = Samples::default();
+    let operations = samples
+        .into_iter()
+        .map(|(op, s)| {
+            all.latencies.extend(&s.latencies);
+            all.errors += s.errors;
+            OpReport::new(op.as_str(), s)
+        })
+        .collect();
+    let total = OpReport::new("total", all);
+    Ok(Report {
+        elapsed_secs: elapsed.as_secs_f64(),
+        requests_per_sec: total.requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        operations,
+        total,
+    })
+}
+
+fn render(report: &Report) -> String {
+    let mut out = format!(
+        "{:<8}{:>10}{:>8}{:>9}{:>10}{:>10}{:>10}{:>10}\n",
+        "OP", "REQUESTS", "ERRORS", "ERROR%", "P50 MS", "P90 MS", "P99 MS", "MAX MS"
+    );
+    for op in report.operations.iter().chain([&report.total]) {
+        let _ = writeln!(
+            out,
+            "{:<8}{:>10}{:>8}{:>9.2}{:>10.2}{:>10.2}{:>10.2}{:>10.2}",
+            op.op,
+            op.requests,
+            op.errors,
+            op.error_rate * 100.0,
+            op.p50_ms,
+            op.p90_ms,
+            op.p99_ms,
+            op.max_ms
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{:.1} requests/s over {:.1}s",
+        report.requests_per_sec, report.elapsed_secs
+    );
+    out
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli).await {
+        Ok(report) => {
+            match cli.output {
+                Output::Table => print!("{}", render(&report)),
+                Output::Json => println!("{:#}", serde_json::json!(report)),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e.get_message());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mix() {
+        let schedule = parse_mix("get=3, create=1").unwrap();
+        assert_eq!(schedule, vec![Op::Get, Op::Create, Op::Get, Op::Get]);
+        assert!(parse_mix("get=0").is_err());
+        assert!(parse_mix("get").is_err());
+        assert!(parse_mix("patch=1").is_err());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&samples, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,173 @@
+//! `Cache-Control` per route group, so CDNs and browsers cache what is safe
+//! to cache without every handler setting headers. Configured with
+//! `CACHE_POLICIES`, e.g. `default=no-store;items=public, max-age=30`, and
+//! applied as a layer on each group's router by [`crate::app::build_router`].
+
+use std::{collections::HashMap, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        HeaderValue, Method, StatusCode,
+        header::{CACHE_CONTROL, EXPIRES},
+    },
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+
+use crate::{
+    handler::{DEFAULT_GROUP, ROUTE_GROUPS},
+    model::{
+        error::{AppError, AppErrorCode},
+        http::http_date,
+    },
+};
+
+/// One `Cache-Control` value, with the `max-age` it grants if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    pub cache_control: HeaderValue,
+    pub max_age: Option<Duration>,
+}
+
+impl CachePolicy {
+    pub fn parse(directives: &str) -> Option<Self> {
+        let cache_control = HeaderValue::from_str(directives.trim()).ok()?;
+        let max_age = directives
+            .split(',')
+            .filter_map(|d| d.trim().strip_prefix("max-age="))
+            .find_map(|secs| secs.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        Some(Self {
+            cache_control,
+            max_age,
+        })
+    }
+}
+
+/// Cache policy of each route group, groups without one send no headers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CachePolicies {
+    default: Option<CachePolicy>,
+    groups: HashMap<String, CachePolicy>,
+}
+
+impl CachePolicies {
+    /// Parses `default=no-store;items=public, max-age=30`.
+    pub fn parse(policies: &str) -> Result<Self, AppError> {
+        let invalid = |message: String| AppError {
+            code: AppErrorCode::InvalidInput,
+            message,
+            error_code: None,
+        };
+        let mut parsed = Self::default();
+        for entry in policies.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (group, directives) = entry
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("Invalid cache policy '{}'", entry)))?;
+            let group = group.trim();
+            if !ROUTE_GROUPS.contains(&group) {
+                return Err(invalid(format!(
+                    "Unknown route group '{}', expected one of {}",
+                    group,
+                    ROUTE_GROUPS.join(", ")
+                )));
+            }
+            let policy = CachePolicy::parse(directives)
+                .filter(|_| !directives.trim().is_empty())
+                .ok_or_else(|| invalid(format!("Invalid cache policy '{}'", entry)))?;
+            if group == DEFAULT_GROUP {
+                parsed.default = Some(policy);
+            } else {
+                parsed.groups.insert(group.to_string(), policy);
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn get(&self, group: &str) -> Option<CachePolicy> {
+        self.groups.get(group).or(self.default.as_ref()).cloned()
+    }
+}
+
+/// Sets the policy on successful and not modified `GET`/`HEAD` responses,
+/// with `Expires` matching its `max-age`. Headers a handler set itself are
+/// left alone.
+pub async fn cache_control_middleware(
+    State(policy): State<Option<CachePolicy>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD);
+    let mut res = next.run(req).await;
+    let Some(policy) = policy else {
+        return res;
+    };
+    if !cacheable
+        || !(res.status().is_success() || res.status() == StatusCode::NOT_MODIFIED)
+        || res.headers().contains_key(CACHE_CONTROL)
+    {
+        return res;
+    }
+    res.headers_mut()
+        .insert(CACHE_CONTROL, policy.cache_control.clone());
+    if let Some(max_age) = policy.max_age
+        && let Ok(expires) = HeaderValue::from_str(&http_date(Utc::now() + max_age))
+    {
+        res.headers_mut().insert(EXPIRES, expires);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, middleware::from_fn_with_state, routing::get};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let policies =
+            CachePolicies::parse("default=no-store; items=public, max-age=30;admin=private")
+                .unwrap();
+        let items = policies.get("items").unwrap();
+        assert_eq!(items.cache_control, "public, max-age=30");
+        assert_eq!(items.max_age, Some(Duration::from_secs(30)));
+        assert_eq!(policies.get("users").unwrap().cache_control, "no-store");
+        assert_eq!(policies.get("admin").unwrap().max_age, None);
+        assert_eq!(CachePolicies::parse("").unwrap().get("items"), None);
+        assert!(CachePolicies::parse("items").is_err());
+        assert!(CachePolicies::parse("items=").is_err());
+        assert!(CachePolicies::parse("reports=no-store").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_cacheable_responses() {
+        let policy = CachePolicy::parse("public, max-age=30");
+        let app = Router::new()
+            .route("/", get(|| async {}).post(|| async {}))
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .route("/own", get(|| async { [(CACHE_CONTROL, "no-cache")] }))
+            .layer(from_fn_with_state(policy, cache_control_middleware));
+        let send = |method: Method, uri: &str| {
+            let req = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(req)
+        };
+
+        let res = send(Method::GET, "/").await.unwrap();
+        assert_eq!(res.headers()[CACHE_CONTROL], "public, max-age=30");
+        assert!(res.headers().contains_key(EXPIRES));
+        let res = send(Method::POST, "/").await.unwrap();
+        assert!(res.headers().get(CACHE_CONTROL).is_none());
+        let res = send(Method::GET, "/missing").await.unwrap();
+        assert!(res.headers().get(CACHE_CONTROL).is_none());
+        let res = send(Method::GET, "/own").await.unwrap();
+        assert_eq!(res.headers()[CACHE_CONTROL], "no-cache");
+    }
+}
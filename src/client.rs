@@ -0,0 +1,268 @@
+//! Typed client for a running instance, for other Rust services.
+//!
+//! ```ignore
+//! let client = Client::new("http://localhost:3000").token("secret");
+//! let item = client.items().create(&CreateItem { name: "book".into() }).await?;
+//! let mut pages = client.users().pages();
+//! while let Some(users) = pages.try_next().await? { ... }
+//! ```
+
+use std::marker::PhantomData;
+
+use futures::{Stream, TryStreamExt, stream};
+use reqwest::{Method, StatusCode};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    handler::{API_V1_PREFIX, ITEMS_PATH, USERS_PATH, crud::CrudResource},
+    model::{
+        error::{AppError, AppErrorCode},
+        http::Response,
+        item::Item,
+        user::User,
+    },
+};
+
+/// Error the server answered with `status`, rebuilt from its envelope.
+fn status_error(status: StatusCode, envelope: Option<Response<serde_json::Value>>) -> AppError {
+    let (message, error, error_code, errors) = match envelope {
+        Some(envelope) => (
+            envelope.message,
+            envelope.error,
+            envelope.error_code,
+            envelope.errors,
+        ),
+        None => (status.to_string(), String::new(), None, vec![]),
+    };
+    let code = match status {
+        StatusCode::NOT_FOUND => AppErrorCode::NotFound,
+        StatusCode::BAD_REQUEST if !errors.is_empty() => AppErrorCode::Validation(errors),
+        StatusCode::BAD_REQUEST => AppErrorCode::InvalidInput,
+        StatusCode::CONFLICT => AppErrorCode::Conflict,
+        StatusCode::UNPROCESSABLE_ENTITY => AppErrorCode::Unprocessable(errors),
+        StatusCode::UNAUTHORIZED => AppErrorCode::Unauthorized,
+        StatusCode::FORBIDDEN => AppErrorCode::Forbidden,
+        StatusCode::METHOD_NOT_ALLOWED => AppErrorCode::MethodNotAllowed,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => AppErrorCode::UnsupportedMediaType,
+        StatusCode::TOO_MANY_REQUESTS => AppErrorCode::TooManyRequests,
+        StatusCode::PRECONDITION_FAILED => AppErrorCode::PreconditionFailed,
+        StatusCode::SERVICE_UNAVAILABLE => AppErrorCode::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => AppErrorCode::DeadlineExceeded,
+        _ if error.is_empty() => AppErrorCode::InternalError(status.to_string()),
+        _ => AppErrorCode::InternalError(error),
+    };
+    AppError {
+        code,
+        message,
+        error_code,
+    }
+}
+
+fn transport_error(e: reqwest::Error) -> AppError {
+    AppError {
+        code: AppErrorCode::Unavailable,
+        message: format!("Request failed: {}", e),
+        error_code: None,
+    }
+}
+
+/// Talks to the `/api/v1` routes of the instance at `base_url`.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Sent as a bearer token, for instances behind an authenticating proxy.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn items(&self) -> Resource<'_, Item> {
+        Resource::new(self, ITEMS_PATH)
+    }
+
+    pub fn users(&self) -> Resource<'_, User> {
+        Resource::new(self, USERS_PATH)
+    }
+
+    /// Sends `body` to `path`, a server path such as `/api/v1/items` or a
+    /// `next` link, and returns the whole envelope.
+    pub async fn send<T: DeserializeOwned, B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<Response<T>, AppError> {
+        // Asks for the envelope even where it is turned off, it has the links.
+        let mut req = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path))
+            .query(&[("envelope", "true")])
+            .header(reqwest::header::ACCEPT, "application/json");
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            req = req.json(body);
+        }
+        let res = req.send().await.map_err(transport_error)?;
+        let status = res.status();
+        let bytes = res.bytes().await.map_err(transport_error)?;
+        if !status.is_success() {
+            return Err(status_error(status, serde_json::from_slice(&bytes).ok()));
+        }
+        serde_json::from_slice(&bytes).map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: format!("Unexpected response from {}", path),
+            error_code: None,
+        })
+    }
+}
+
+/// The CRUD routes of one resource, e.g. [`Client::items`].
+pub struct Resource<'a, T> {
+    client: &'a Client,
+    path: String,
+    resource: PhantomData<T>,
+}
+
+impl<'a, T> Resource<'a, T>
+where
+    T: CrudResource + DeserializeOwned,
+    T::Create: Serialize,
+    T::Update: Serialize,
+{
+    fn new(client: &'a Client, path: &str) -> Self {
+        Self {
+            client,
+            path: format!("{}{}", API_V1_PREFIX, path),
+            resource: PhantomData,
+        }
+    }
+
+    fn data<D>(response: Response<D>) -> Result<D, AppError> {
+        response.data.ok_or_else(|| AppError {
+            code: AppErrorCode::InternalError("missing data".to_string()),
+            message: "Response has no data".to_string(),
+            error_code: None,
+        })
+    }
+
+    /// Every page, following the `next` links as they come.
+    pub fn pages(&self) -> impl Stream<Item = Result<Vec<T>, AppError>> + 'a {
+        let client = self.client;
+        stream::try_unfold(Some(self.path.clone()), move |path| async move {
+            let Some(path) = path else {
+                return Ok(None);
+            };
+            let page = client.send::<Vec<T>, ()>(Method::GET, &path, None).await?;
+            let next = page.links.as_ref().and_then(|links| links.next.clone());
+            Ok(Some((Self::data(page)?, next)))
+        })
+    }
+
+    /// Every entity, across all pages.
+    pub async fn list(&self) -> Result<Vec<T>, AppError> {
+        self.pages().try_concat().await
+    }
+
+    pub async fn get(&self, id: &T::Id) -> Result<T, AppError> {
+        let path = format!("{}/{}", self.path, id);
+        Self::data(self.client.send::<T, ()>(Method::GET, &path, None).await?)
+    }
+
+    pub async fn create(&self, payload: &T::Create) -> Result<T, AppError> {
+        Self::data(
+            self.client
+                .send(Method::POST, &self.path, Some(payload))
+                .await?,
+        )
+    }
+
+    pub async fn update(&self, id: &T::Id, payload: &T::Update) -> Result<T, AppError> {
+        let path = format!("{}/{}", self.path, id);
+        Self::data(self.client.send(Method::PUT, &path, Some(payload)).await?)
+    }
+
+    pub async fn delete(&self, id: &T::Id) -> Result<(), AppError> {
+        let path = format!("{}/{}", self.path, id);
+        self.client
+            .send::<serde_json::Value, ()>(Method::DELETE, &path, None)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        app::build_router, handler::item::CreateItem, repository::InMemoryRepository,
+        service::user::CreateUser, state::AppState,
+    };
+
+    async fn serve() -> String {
+        let state = AppState::builder()
+            .config(crate::config::Config::default())
+            .repository(Arc::new(InMemoryRepository::new()))
+            .build()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, build_router(state)).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_item_roundtrip() {
+        let client = Client::new(&serve().await);
+        let items = client.items();
+
+        let item = items
+            .create(&CreateItem {
+                name: "book".into(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(items.get(&item.id).await.unwrap(), item);
+        assert_eq!(items.list().await.unwrap(), vec![item.clone()]);
+
+        items.delete(&item.id).await.unwrap();
+        let err = items.get(&item.id).await.unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_maps_validation_errors() {
+        let client = Client::new(&serve().await);
+
+        let err = client
+            .users()
+            .create(&CreateUser {
+                email: "not an email".into(),
+            })
+            .await
+            .unwrap_err();
+
+        match err.code {
+            AppErrorCode::Validation(errors) => assert_eq!(errors[0].field, "email"),
+            code => panic!("unexpected error {:?}", code),
+        }
+    }
+}
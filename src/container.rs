@@ -0,0 +1,234 @@
+use std::{
+    any::{Any, TypeId, type_name},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::model::error::{AppError, AppErrorCode};
+
+/// Lifecycle hooks of a long-lived part of the application, e.g. a broker
+/// connection or a worker pool.
+#[async_trait]
+pub trait Component: Send + Sync {
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
+    async fn start(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Component for PgPool {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn shutdown(&self) -> Result<(), AppError> {
+        self.close().await;
+        Ok(())
+    }
+}
+
+/// Typed registry of the application's shared parts, keyed by type.
+///
+/// Parts are registered in construction order, so a factory can `require`
+/// anything registered before it. Components start in that order and shut
+/// down in reverse.
+#[derive(Default)]
+pub struct Container {
+    values: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    components: Vec<Arc<dyn Component>>,
+}
+
+impl Container {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `value` under `T`, which may be a trait object such as
+    /// `dyn ServiceApi`, replacing any previous registration.
+    pub fn insert<T: ?Sized + Send + Sync + 'static>(&mut self, value: Arc<T>) -> Arc<T> {
+        self.values
+            .insert(TypeId::of::<Arc<T>>(), Box::new(value.clone()));
+        value
+    }
+
+    /// Registers `value` under `T` and adds it to the lifecycle.
+    pub fn insert_component<T: Component + 'static>(&mut self, value: Arc<T>) -> Arc<T> {
+        self.components.push(value.clone());
+        self.insert(value)
+    }
+
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.values
+            .get(&TypeId::of::<Arc<T>>())
+            .and_then(|value| value.downcast_ref::<Arc<T>>())
+            .cloned()
+    }
+
+    /// Like [`Container::get`], failing when `T` hasn't been registered yet.
+    pub fn require<T: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<T>, AppError> {
+        self.get().ok_or_else(|| AppError {
+            code: AppErrorCode::InternalError(type_name::<T>().to_string()),
+            message: format!("Missing dependency {}", type_name::<T>()),
+            error_code: None,
+        })
+    }
+
+    /// Starts every component in registration order. When one fails, those
+    /// already started are shut down again before the error is returned.
+    pub async fn start(&self) -> Result<(), AppError> {
+        for (started, component) in self.components.iter().enumerate() {
+            if let Err(e) = component.start().await {
+                tracing::error!(
+                    component = component.name(),
+                    "{}: {}",
+                    e.get_message(),
+                    e.get_error()
+                );
+                shutdown_all(&self.components[..started]).await;
+                return Err(e);
+            }
+            tracing::info!(component = component.name(), "Component started");
+        }
+        Ok(())
+    }
+
+    /// Shuts every component down in reverse registration order, failures are
+    /// logged and don't stop the others.
+    pub async fn shutdown(&self) {
+        shutdown_all(&self.components).await;
+    }
+}
+
+async fn shutdown_all(components: &[Arc<dyn Component>]) {
+    for component in components.iter().rev() {
+        match component.shutdown().await {
+            Ok(()) => tracing::info!(component = component.name(), "Component stopped"),
+            Err(e) => tracing::warn!(
+                component = component.name(),
+                "{}: {}",
+                e.get_message(),
+                e.get_error()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    trait Greeter: Send + Sync {
+        fn greet(&self) -> String;
+    }
+
+    struct English;
+
+    impl Greeter for English {
+        fn greet(&self) -> String {
+            "hello".into()
+        }
+    }
+
+    struct Recorder {
+        name: &'static str,
+        fail_start: bool,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Component for Recorder {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn start(&self) -> Result<(), AppError> {
+            if self.fail_start {
+                return Err(AppError {
+                    code: AppErrorCode::Unavailable,
+                    message: format!("{} failed", self.name),
+                    error_code: None,
+                });
+            }
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("start {}", self.name));
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> Result<(), AppError> {
+            self.log.lock().unwrap().push(format!("stop {}", self.name));
+            Ok(())
+        }
+    }
+
+    fn recorder(
+        name: &'static str,
+        fail_start: bool,
+        log: &Arc<Mutex<Vec<String>>>,
+    ) -> Arc<Recorder> {
+        Arc::new(Recorder {
+            name,
+            fail_start,
+            log: log.clone(),
+        })
+    }
+
+    #[test]
+    fn test_resolves_concrete_and_trait_object_types() {
+        let mut container = Container::new();
+        container.insert(Arc::new(42u32));
+        container.insert::<dyn Greeter>(Arc::new(English));
+
+        assert_eq!(*container.require::<u32>().unwrap(), 42);
+        assert_eq!(container.require::<dyn Greeter>().unwrap().greet(), "hello");
+        assert!(container.get::<String>().is_none());
+        assert_eq!(
+            container.require::<String>().unwrap_err().get_message(),
+            "Missing dependency alloc::string::String"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_order() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut container = Container::new();
+        container.insert_component(recorder("db", false, &log));
+        container.insert_component(recorder("broker", false, &log));
+
+        container.start().await.unwrap();
+        container.shutdown().await;
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["start db", "start broker", "stop broker", "stop db"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_start_stops_started_components() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut container = Container::new();
+        container.insert_component(recorder("db", false, &log));
+        container.insert_component(recorder("broker", true, &log));
+        container.insert_component(recorder("jobs", false, &log));
+
+        let err = container.start().await.unwrap_err();
+
+        assert_eq!(err.get_message(), "broker failed");
+        assert_eq!(*log.lock().unwrap(), vec!["start db", "stop db"]);
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lapin::{
+    BasicProperties, Channel, Connection, ConnectionProperties, ExchangeKind,
+    options::{BasicPublishOptions, ConfirmSelectOptions, ExchangeDeclareOptions},
+    types::FieldTable,
+};
+
+use super::EventPublisher;
+use crate::{
+    container::Component,
+    model::{
+        error::{AppError, AppErrorCode},
+        event::Event,
+    },
+};
+
+/// Times an event is sent before a publish gives up on the broker confirming it.
+const PUBLISH_ATTEMPTS: u32 = 3;
+
+/// Publishes to a topic exchange with publisher confirms. A publish returns
+/// once the broker confirmed the event, sending it again when the broker
+/// rejects it or doesn't confirm it within the confirm timeout.
+pub struct AmqpPublisher {
+    connection: Connection,
+    channel: Channel,
+    exchange: String,
+    routing_key_prefix: String,
+    confirm_timeout: Duration,
+}
+
+impl AmqpPublisher {
+    /// Connects, declares a durable topic exchange and enables publisher confirms.
+    pub async fn connect(
+        url: &str,
+        exchange: &str,
+        routing_key_prefix: &str,
+    ) -> Result<Self, AppError> {
+        let connection = Connection::connect(url, ConnectionProperties::default())
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to connect to AMQP broker".to_string(),
+                error_code: None,
+            })?;
+        let channel = connection.create_channel().await.map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to open AMQP channel".to_string(),
+            error_code: None,
+        })?;
+        channel
+            .exchange_declare(
+                exchange,
+                ExchangeKind::Topic,
+                ExchangeDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: format!("Failed to declare exchange '{}'", exchange),
+                error_code: None,
+            })?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to enable publisher confirms".to_string(),
+                error_code: None,
+            })?;
+
+        Ok(Self {
+            connection,
+            channel,
+            exchange: exchange.into(),
+            routing_key_prefix: routing_key_prefix.into(),
+            confirm_timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// Waits at most `timeout` for the broker to confirm each send.
+    pub fn with_confirm_timeout(mut self, timeout: Duration) -> Self {
+        self.confirm_timeout = timeout;
+        self
+    }
+
+    fn routing_key(&self, event: &Event) -> String {
+        if self.routing_key_prefix.is_empty() {
+            event.topic()
+        } else {
+            format!("{}.{}", self.routing_key_prefix, event.topic())
+        }
+    }
+
+    /// Sends the event once and waits for the broker to confirm it.
+    async fn send(&self, event: &Event, payload: &[u8]) -> Result<(), AppError> {
+        let properties = BasicProperties::default()
+            .with_content_type("application/json".into())
+            .with_message_id(event.id.clone().into())
+            .with_delivery_mode(2);
+        let failed = |message: &str, error: String| AppError {
+            code: AppErrorCode::InternalError(error),
+            message: message.to_string(),
+            error_code: None,
+        };
+
+        let confirm = self
+            .channel
+            .basic_publish(
+                &self.exchange,
+                &self.routing_key(event),
+                BasicPublishOptions::default(),
+                payload,
+                properties,
+            )
+            .await
+            .map_err(|e| failed("Failed to publish event to AMQP", e.to_string()))?;
+        match tokio::time::timeout(self.confirm_timeout, confirm).await {
+            Ok(Ok(confirm)) if confirm.is_nack() => {
+                Err(failed("AMQP broker rejected event", "nack".to_string()))
+            }
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(failed(
+                "Failed to receive AMQP publisher confirm",
+                e.to_string(),
+            )),
+            Err(_) => Err(failed(
+                "AMQP broker didn't confirm event in time",
+                format!("no confirm within {:?}", self.confirm_timeout),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Component for AmqpPublisher {
+    fn name(&self) -> &'static str {
+        "amqp"
+    }
+
+    /// Waits for any confirm still outstanding, e.g. of a publish that timed out.
+    async fn shutdown(&self) -> Result<(), AppError> {
+        if let Err(e) = self.channel.wait_for_confirms().await {
+            tracing::warn!(error = %e, "Failed to receive AMQP publisher confirms");
+        }
+        self.channel.close(200, "shutdown").await.ok();
+        self.connection
+            .close(200, "shutdown")
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to close AMQP connection".to_string(),
+                error_code: None,
+            })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for AmqpPublisher {
+    async fn publish(&self, event: &Event) -> Result<(), AppError> {
+        let payload = serde_json::to_vec(event).map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to serialize event".to_string(),
+            error_code: None,
+        })?;
+        let mut attempt = 1;
+        loop {
+            match self.send(event, &payload).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < PUBLISH_ATTEMPTS => {
+                    tracing::warn!(
+                        event_id = %event.id,
+                        attempt,
+                        error = %e.get_error(),
+                        "{}, sending it again",
+                        e.get_message()
+                    );
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
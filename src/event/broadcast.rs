@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use super::EventPublisher;
+use crate::model::{error::AppError, event::Event};
+
+/// In-process fan-out of events to realtime subscribers such as SSE streams.
+pub struct Broadcaster {
+    sender: Sender<Event>,
+}
+
+impl Broadcaster {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    pub fn send(&self, event: Event) {
+        // An error only means nobody is listening right now.
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for Broadcaster {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+#[async_trait]
+impl EventPublisher for Broadcaster {
+    async fn publish(&self, event: &Event) -> Result<(), AppError> {
+        self.send(event.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event::EventAction;
+
+    #[tokio::test]
+    async fn test_broadcaster_delivers_to_subscribers() {
+        let broadcaster = Broadcaster::default();
+        let mut rx = broadcaster.subscribe();
+
+        let event = Event::new::<()>("item", EventAction::Deleted, "1", None);
+        broadcaster.publish(&event).await.unwrap();
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.id, event.id);
+    }
+
+    #[tokio::test]
+    async fn test_broadcaster_without_subscribers_is_ok() {
+        let broadcaster = Broadcaster::default();
+        let event = Event::new::<()>("item", EventAction::Deleted, "1", None);
+        assert!(broadcaster.publish(&event).await.is_ok());
+    }
+}
@@ -0,0 +1,108 @@
+pub mod amqp;
+pub mod broadcast;
+pub mod nats;
+pub mod redis;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+
+use crate::model::{error::AppError, event::Event};
+
+#[async_trait]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(&self, event: &Event) -> Result<(), AppError>;
+}
+
+/// Publisher used when no broker is configured, events are dropped.
+pub struct NoopPublisher;
+
+#[async_trait]
+impl EventPublisher for NoopPublisher {
+    async fn publish(&self, _: &Event) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+/// Delivers every event to each configured sink, e.g. NATS and AMQP together.
+pub struct FanoutPublisher {
+    sinks: Vec<Arc<dyn EventPublisher>>,
+}
+
+impl FanoutPublisher {
+    pub fn new(sinks: Vec<Arc<dyn EventPublisher>>) -> Self {
+        Self { sinks }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for FanoutPublisher {
+    async fn publish(&self, event: &Event) -> Result<(), AppError> {
+        let results = join_all(self.sinks.iter().map(|sink| sink.publish(event))).await;
+        results.into_iter().collect()
+    }
+}
+
+/// Publishes an event, logging instead of failing the caller when delivery fails.
+pub async fn publish_or_log(publisher: &dyn EventPublisher, event: Event) {
+    if let Err(e) = publisher.publish(&event).await {
+        tracing::warn!(
+            event_id = %event.id,
+            topic = %event.topic(),
+            error = %e.get_error(),
+            "{}",
+            e.get_message()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{error::AppErrorCode, event::EventAction};
+
+    fn event() -> Event {
+        Event::new::<()>("item", EventAction::Deleted, "1", None)
+    }
+
+    #[tokio::test]
+    async fn test_fanout_publishes_to_every_sink() {
+        let mut first = MockEventPublisher::new();
+        first
+            .expect_publish()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let mut second = MockEventPublisher::new();
+        second
+            .expect_publish()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+
+        let fanout = FanoutPublisher::new(vec![Arc::new(first), Arc::new(second)]);
+        assert!(fanout.publish(&event()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fanout_reports_failed_sink() {
+        let mut failing = MockEventPublisher::new();
+        failing.expect_publish().times(1).returning(|_| {
+            Box::pin(async {
+                Err(AppError {
+                    code: AppErrorCode::InternalError("down".into()),
+                    message: "Failed to publish".into(),
+                    error_code: None,
+                })
+            })
+        });
+        let mut healthy = MockEventPublisher::new();
+        healthy
+            .expect_publish()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+
+        let fanout = FanoutPublisher::new(vec![Arc::new(failing), Arc::new(healthy)]);
+        assert!(fanout.publish(&event()).await.is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use async_nats::{Client, Message};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::EventPublisher;
+use crate::{
+    container::Component,
+    middleware::{X_CORRELATION_ID, is_valid_correlation_id},
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+        event::Event,
+        http::Response,
+    },
+    service::ServiceApi,
+};
+
+pub struct NatsPublisher {
+    client: Client,
+    prefix: String,
+}
+
+impl NatsPublisher {
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self, AppError> {
+        let client = async_nats::connect(url).await.map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to connect to NATS".to_string(),
+            error_code: None,
+        })?;
+        Ok(Self {
+            client,
+            prefix: prefix.into(),
+        })
+    }
+
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+}
+
+#[async_trait]
+impl Component for NatsPublisher {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    /// Flushes buffered publishes so no event is lost on exit.
+    async fn shutdown(&self) -> Result<(), AppError> {
+        self.client.flush().await.map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to flush NATS".to_string(),
+            error_code: None,
+        })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NatsPublisher {
+    async fn publish(&self, event: &Event) -> Result<(), AppError> {
+        let payload = serde_json::to_vec(event).map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to serialize event".to_string(),
+            error_code: None,
+        })?;
+        self.client
+            .publish(format!("{}.{}", self.prefix, event.topic()), payload.into())
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to publish event to NATS".to_string(),
+                error_code: None,
+            })
+    }
+}
+
+/// Answers `<prefix>.item.get` and `<prefix>.user.get` requests, the message
+/// payload is the entity id and the reply is the standard response envelope.
+/// Up to `concurrency` requests are handled at once, each in its own task.
+pub async fn serve_request_reply(
+    client: Client,
+    prefix: String,
+    service: Arc<dyn ServiceApi>,
+    concurrency: usize,
+) -> Result<(), AppError> {
+    let slots = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut subscriber = client
+        .subscribe(format!("{}.*.get", prefix))
+        .await
+        .map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to subscribe to NATS".to_string(),
+            error_code: None,
+        })?;
+
+    while let Some(message) = subscriber.next().await {
+        let Some(reply) = message.reply.clone() else {
+            continue;
+        };
+        let entity = message
+            .subject
+            .strip_prefix(&format!("{}.", prefix))
+            .and_then(|s| s.strip_suffix(".get"))
+            .unwrap_or_default()
+            .to_string();
+        // Waiting for a slot stops reading, so NATS buffers the backlog
+        // rather than this process.
+        let Ok(slot) = slots.clone().acquire_owned().await else {
+            break;
+        };
+        let (client, service) = (client.clone(), service.clone());
+        tokio::spawn(async move {
+            let payload = handle_get(service.as_ref(), &entity, &message).await;
+            if let Err(e) = client.publish(reply, payload.into()).await {
+                tracing::warn!(subject = %message.subject, error = %e, "Failed to send NATS reply");
+            }
+            drop(slot);
+        });
+    }
+    Ok(())
+}
+
+async fn handle_get(service: &dyn ServiceApi, entity: &str, message: &Message) -> Vec<u8> {
+    let correlation_id = message
+        .headers
+        .as_ref()
+        .and_then(|h| h.get(X_CORRELATION_ID))
+        .map(|v| v.to_string())
+        .filter(|id| is_valid_correlation_id(id))
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let id = String::from_utf8_lossy(&message.payload).trim().to_string();
+    let ctx = Ctx::new(correlation_id.clone());
+
+    match entity {
+        "item" => to_payload(correlation_id, service.get_item(&ctx, id).await),
+        "user" => to_payload(correlation_id, service.get_user(&ctx, &id).await),
+        _ => to_payload::<()>(
+            correlation_id,
+            Err(AppError {
+                code: AppErrorCode::NotFound,
+                message: format!("Unknown entity '{}'", entity),
+                error_code: None,
+            }),
+        ),
+    }
+}
+
+fn to_payload<T: Serialize>(correlation_id: String, result: Result<T, AppError>) -> Vec<u8> {
+    let response = match result {
+        Ok(data) => Response {
+            correlation_id,
+            message: "ok".into(),
+            error: "".into(),
+            error_code: None,
+            errors: vec![],
+            data: Some(data),
+            links: None,
+        },
+        Err(e) => Response {
+            correlation_id,
+            message: e.get_message(),
+            error: e.get_error(),
+            error_code: Some(e.get_error_code()),
+            errors: e.get_field_errors(),
+            data: None,
+            links: None,
+        },
+    };
+    serde_json::to_vec(&response).unwrap_or_default()
+}
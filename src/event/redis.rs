@@ -0,0 +1,142 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use redis::{AsyncCommands, Client, aio::MultiplexedConnection};
+use serde::{Deserialize, Serialize};
+
+use super::{EventPublisher, broadcast::Broadcaster};
+use crate::model::{
+    error::{AppError, AppErrorCode},
+    event::Event,
+};
+
+/// Wire format on the Redis channel, `origin` lets an instance skip its own events.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    origin: String,
+    event: Event,
+}
+
+pub struct RedisPublisher {
+    conn: MultiplexedConnection,
+    channel: String,
+    instance_id: String,
+}
+
+impl RedisPublisher {
+    pub async fn connect(url: &str, channel: &str, instance_id: &str) -> Result<Self, AppError> {
+        let conn = open_client(url)?
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to connect to Redis".to_string(),
+                error_code: None,
+            })?;
+        Ok(Self {
+            conn,
+            channel: channel.into(),
+            instance_id: instance_id.into(),
+        })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for RedisPublisher {
+    async fn publish(&self, event: &Event) -> Result<(), AppError> {
+        let payload = serde_json::to_string(&Envelope {
+            origin: self.instance_id.clone(),
+            event: event.clone(),
+        })
+        .map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to serialize event".to_string(),
+            error_code: None,
+        })?;
+        self.conn
+            .clone()
+            .publish::<_, _, ()>(&self.channel, payload)
+            .await
+            .map_err(|e| AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to publish event to Redis".to_string(),
+                error_code: None,
+            })
+    }
+}
+
+/// Longest wait between attempts to reconnect the bridge.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Relays events published by other instances into the local broadcaster,
+/// so realtime subscribers see changes regardless of which node served the write.
+/// Reconnects with exponential backoff whenever the connection drops, only
+/// an invalid URL ends it.
+pub async fn run_bridge(
+    url: &str,
+    channel: &str,
+    instance_id: &str,
+    broadcaster: Arc<Broadcaster>,
+) -> Result<(), AppError> {
+    let client = open_client(url)?;
+    let mut delay = Duration::from_secs(1);
+    loop {
+        match relay(&client, channel, instance_id, &broadcaster).await {
+            Ok(()) => {
+                tracing::warn!(channel = %channel, "Redis bridge disconnected, reconnecting");
+                delay = Duration::from_secs(1);
+            }
+            Err(e) => tracing::warn!(
+                channel = %channel,
+                retry_in_secs = delay.as_secs(),
+                "{}: {}",
+                e.get_message(),
+                e.get_error()
+            ),
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Subscribes to `channel` and relays its events until the connection drops.
+async fn relay(
+    client: &Client,
+    channel: &str,
+    instance_id: &str,
+    broadcaster: &Broadcaster,
+) -> Result<(), AppError> {
+    let mut pubsub = client.get_async_pubsub().await.map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to connect to Redis".to_string(),
+        error_code: None,
+    })?;
+    pubsub.subscribe(channel).await.map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: format!("Failed to subscribe to Redis channel '{}'", channel),
+        error_code: None,
+    })?;
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let envelope = message
+            .get_payload::<String>()
+            .ok()
+            .and_then(|payload| serde_json::from_str::<Envelope>(&payload).ok());
+        match envelope {
+            Some(envelope) if envelope.origin != instance_id => broadcaster.send(envelope.event),
+            Some(_) => {}
+            None => tracing::warn!(channel = %channel, "Ignoring malformed Redis event"),
+        }
+    }
+    Ok(())
+}
+
+fn open_client(url: &str) -> Result<Client, AppError> {
+    Client::open(url).map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Invalid Redis URL".to_string(),
+        error_code: None,
+    })
+}
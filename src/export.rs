@@ -0,0 +1,262 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow_array::{RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use async_trait::async_trait;
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncWriteExt, BufWriter},
+};
+
+use crate::{
+    model::{
+        error::{AppError, AppErrorCode},
+        export::ExportFormat,
+        item::{Item, ListItemFilter},
+        job::Job,
+        sort::ItemSort,
+    },
+    repository::{DynRepository, Repository, item::ItemRepository, job::JobRepository},
+    worker::JobHandler,
+};
+
+/// Job kind writing every item to a file, handled by [`ItemExportHandler`].
+pub const EXPORT_ITEMS_JOB: &str = "export.items";
+
+/// Rows written between two progress updates.
+const PROGRESS_EVERY: usize = 500;
+
+/// Where the file of export job `id` lives once it is done.
+pub fn export_path(dir: impl AsRef<Path>, id: &str, format: ExportFormat) -> PathBuf {
+    dir.as_ref().join(format!("{}.{}", id, format.as_str()))
+}
+
+fn io_error(e: std::io::Error) -> AppError {
+    AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to write export file".to_string(),
+        error_code: None,
+    }
+}
+
+fn parquet_error(e: impl ToString) -> AppError {
+    AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to write Parquet export".to_string(),
+        error_code: None,
+    }
+}
+
+/// Quotes a CSV field when it holds a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One CSV line, line break included.
+pub fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// Writes the items to `<dir>/<job id>.<format>`, reporting progress on the
+/// job as it goes. The file only appears under its final name once complete.
+pub struct ItemExportHandler<R: Repository + ?Sized = DynRepository> {
+    repo: Arc<R>,
+    dir: PathBuf,
+}
+
+impl<R: Repository + ?Sized> ItemExportHandler<R> {
+    pub fn new(repo: Arc<R>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            repo,
+            dir: dir.into(),
+        }
+    }
+
+    async fn progress(&self, job: &Job, done: usize, total: usize) -> Result<(), AppError> {
+        let progress = (done * 100 / total) as i32;
+        self.repo
+            .job()
+            .progress(&job.id, progress.min(99), None)
+            .await
+    }
+
+    async fn write_csv(&self, job: &Job, items: &[Item], path: &Path) -> Result<(), AppError> {
+        let mut file = BufWriter::new(File::create(path).await.map_err(io_error)?);
+        file.write_all(b"id,name\n").await.map_err(io_error)?;
+        for (i, item) in items.iter().enumerate() {
+            let row = csv_row(&[&item.id, &item.name]);
+            file.write_all(row.as_bytes()).await.map_err(io_error)?;
+            if (i + 1) % PROGRESS_EVERY == 0 {
+                self.progress(job, i + 1, items.len()).await?;
+            }
+        }
+        file.flush().await.map_err(io_error)
+    }
+
+    /// One Snappy compressed row group per [`PROGRESS_EVERY`] items, with
+    /// the same columns as the CSV plus the slug.
+    async fn write_parquet(&self, job: &Job, items: &[Item], path: &Path) -> Result<(), AppError> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("slug", DataType::Utf8, false),
+        ]));
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(PROGRESS_EVERY)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(Vec::new(), schema.clone(), Some(props)).map_err(parquet_error)?;
+        let mut done = 0;
+        for chunk in items.chunks(PROGRESS_EVERY) {
+            let column = |f: fn(&Item) -> &str| {
+                Arc::new(StringArray::from_iter_values(chunk.iter().map(f))) as _
+            };
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    column(|item| &item.id),
+                    column(|item| &item.name),
+                    column(|item| &item.slug),
+                ],
+            )
+            .map_err(parquet_error)?;
+            writer.write(&batch).map_err(parquet_error)?;
+            done += chunk.len();
+            if chunk.len() == PROGRESS_EVERY {
+                self.progress(job, done, items.len()).await?;
+            }
+        }
+        let bytes = writer.into_inner().map_err(parquet_error)?;
+        fs::write(path, bytes).await.map_err(io_error)
+    }
+}
+
+#[async_trait]
+impl<R: Repository + ?Sized> JobHandler for ItemExportHandler<R> {
+    async fn handle(&self, job: &Job) -> Result<(), AppError> {
+        let items = self
+            .repo
+            .item()
+            .list(ItemSort::default(), ListItemFilter::default())
+            .await?;
+        let format = ExportFormat::of(job);
+
+        fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        let path = export_path(&self.dir, &job.id, format);
+        let partial = path.with_extension(format!("{}.part", format.as_str()));
+        match format {
+            ExportFormat::Csv => self.write_csv(job, &items, &partial).await?,
+            ExportFormat::Parquet => self.write_parquet(job, &items, &partial).await?,
+        }
+        fs::rename(&partial, &path).await.map_err(io_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        model::job::NewJob,
+        repository::{InMemoryRepository, item::ItemRepository, job::JobRepository},
+    };
+
+    #[test]
+    fn test_csv_field_quotes() {
+        assert_eq!(csv_field("book"), "book");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_row(&["1", "a,b", ""]), "1,\"a,b\",\n");
+    }
+
+    #[tokio::test]
+    async fn test_writes_items_csv() {
+        let repo = Arc::new(InMemoryRepository::new());
+        for i in 0..1000 {
+            repo.item
+                .add(Item {
+                    id: i.to_string(),
+                    name: format!("item, {}", i),
+                    slug: format!("item-{}", i),
+                    owner_id: None,
+                    version: 1,
+                })
+                .await
+                .unwrap();
+        }
+        let job = repo
+            .job
+            .add(Job::from(NewJob::new(EXPORT_ITEMS_JOB, json!({}))))
+            .await
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("crud-export-{}", job.id));
+        let handler = ItemExportHandler::new(repo.clone(), &dir);
+
+        handler.handle(&job).await.unwrap();
+
+        let csv = std::fs::read_to_string(export_path(&dir, &job.id, ExportFormat::Csv)).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 1001);
+        assert_eq!(lines[0], "id,name");
+        assert_eq!(lines[1], "0,\"item, 0\"");
+        assert_eq!(repo.job.get(&job.id).await.unwrap().progress, 99);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_writes_items_parquet() {
+        let repo = Arc::new(InMemoryRepository::new());
+        for i in 0..1200 {
+            repo.item
+                .add(Item {
+                    id: i.to_string(),
+                    name: format!("item, {}", i),
+                    slug: format!("item-{}", i),
+                    owner_id: None,
+                    version: 1,
+                })
+                .await
+                .unwrap();
+        }
+        let job = repo
+            .job
+            .add(Job::from(NewJob::new(
+                EXPORT_ITEMS_JOB,
+                json!({"format": "parquet"}),
+            )))
+            .await
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("crud-export-{}", job.id));
+        let handler = ItemExportHandler::new(repo.clone(), &dir);
+
+        handler.handle(&job).await.unwrap();
+
+        let file = std::fs::File::open(export_path(&dir, &job.id, ExportFormat::Parquet)).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 3);
+        let batches: Vec<RecordBatch> = reader.build().unwrap().map(Result::unwrap).collect();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 1200);
+        let names = batches[0]
+            .column_by_name("name")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(names.value(1), "item, 1");
+        assert_eq!(repo.job.get(&job.id).await.unwrap().progress, 83);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,439 @@
+use std::{convert::Infallible, marker::PhantomData};
+
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{
+        Extensions, StatusCode,
+        header::{CONTENT_TYPE, IF_MATCH},
+        request::Parts,
+    },
+};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+use serde_json::Value;
+use unicode_normalization::UnicodeNormalization;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::{
+    middleware::context_from_headers,
+    model::{
+        context::{AuthUser, Ctx},
+        error::{AppError, AppErrorCode, FieldError},
+        http::{ApiResponse, Fields, Projectable, parse_version_etag},
+    },
+    patch::{JSON_PATCH_JSON, MERGE_PATCH_JSON, Patch, PatchOp},
+};
+
+/// Reads the context stored by `request_middleware`, picking up the user that
+/// authentication added afterwards. Without the middleware it is built from
+/// the request headers.
+impl<S: Send + Sync> FromRequestParts<S> for Ctx {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let mut ctx = parts
+            .extensions
+            .get::<Ctx>()
+            .cloned()
+            .unwrap_or_else(|| context_from_headers(&parts.headers));
+        if let Some(user) = parts.extensions.get::<AuthUser>() {
+            ctx.user = Some(user.clone());
+        }
+        Ok(ctx)
+    }
+}
+
+/// Correlation id of the request, for rejections and for the envelopes
+/// middlewares build.
+pub(crate) fn correlation_id(extensions: &Extensions) -> String {
+    extensions
+        .get::<Ctx>()
+        .map(|ctx| ctx.correlation_id.clone())
+        .unwrap_or_default()
+}
+
+/// The error an axum extractor rejection with `status` stands for, so it's
+/// sent in the standard envelope: 415 for a body that isn't JSON, 422 for
+/// JSON of the wrong shape and 400 for anything else malformed.
+pub fn rejected(status: StatusCode, message: String) -> AppError {
+    let code = match status {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => AppErrorCode::UnsupportedMediaType,
+        StatusCode::UNPROCESSABLE_ENTITY => AppErrorCode::Unprocessable(vec![]),
+        _ => AppErrorCode::InvalidInput,
+    };
+    AppError {
+        code,
+        message,
+        error_code: None,
+    }
+}
+
+/// Path parameters, rejected with the standard envelope when they don't
+/// parse, e.g. a numeric id that isn't a number.
+pub struct Path<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) = axum::extract::Path::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| {
+                ApiResponse::error(
+                    correlation_id(&parts.extensions),
+                    rejected(e.status(), e.body_text()),
+                )
+            })?;
+        Ok(Self(value))
+    }
+}
+
+/// Query string parameters, rejected with the standard envelope when they
+/// don't parse, e.g. an unknown enum value or a limit that isn't a number.
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::<T>::from_request_parts(parts, state)
+                .await
+                .map_err(|e| {
+                    ApiResponse::error(
+                        correlation_id(&parts.extensions),
+                        rejected(e.status(), e.body_text()),
+                    )
+                })?;
+        Ok(Self(value))
+    }
+}
+
+/// `multipart/form-data` body, rejected with the standard envelope when the
+/// request isn't one.
+pub struct Multipart(pub axum::extract::Multipart);
+
+impl<S: Send + Sync> FromRequest<S> for Multipart {
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let correlation_id = correlation_id(req.extensions());
+        axum::extract::Multipart::from_request(req, state)
+            .await
+            .map(Self)
+            .map_err(|e| ApiResponse::error(correlation_id, rejected(e.status(), e.body_text())))
+    }
+}
+
+/// JSON body, rejected with the standard envelope when it isn't JSON or
+/// doesn't deserialize into `T`.
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let correlation_id = correlation_id(req.extensions());
+        axum::Json::<T>::from_request(req, state)
+            .await
+            .map(|axum::Json(value)| Self(value))
+            .map_err(|e| ApiResponse::error(correlation_id, rejected(e.status(), e.body_text())))
+    }
+}
+
+/// JSON body that is validated before reaching the handler, failures are
+/// rejected with the standard envelope and field level `errors`.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let correlation_id = correlation_id(req.extensions());
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value
+            .validate()
+            .map_err(|e| ApiResponse::error(correlation_id, e.into()))?;
+        Ok(Self(value))
+    }
+}
+
+/// Body of an update route, told apart by `Content-Type`: the full payload
+/// as JSON, or a merge or JSON patch of the stored entity, see
+/// [`crate::patch`].
+pub enum UpdateBody<T> {
+    Replace(T),
+    Patch(Patch),
+}
+
+impl<T, S> FromRequest<S> for UpdateBody<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let merge_patch = content_type.starts_with(MERGE_PATCH_JSON);
+        if !merge_patch && !content_type.starts_with(JSON_PATCH_JSON) {
+            let ValidatedJson(payload) = ValidatedJson::from_request(req, state).await?;
+            return Ok(Self::Replace(payload));
+        }
+        let patch = if merge_patch {
+            let Json(patch) = Json::<Value>::from_request(req, state).await?;
+            Patch::Merge(patch)
+        } else {
+            let Json(ops) = Json::<Vec<PatchOp>>::from_request(req, state).await?;
+            Patch::Json(ops)
+        };
+        Ok(Self::Patch(patch))
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldsQuery {
+    /// Comma separated top-level fields to send, e.g. `id,name`. Defaults to
+    /// every field.
+    fields: Option<String>,
+}
+
+/// What a `DELETE` answers with, as in RFC 7240's `Prefer: return=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Return {
+    /// A confirmation, or no content with `DELETE_NO_CONTENT`.
+    #[default]
+    Minimal,
+    /// The deleted entity.
+    Representation,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteQuery {
+    /// `representation` to get the deleted entity back.
+    #[serde(default, rename = "return")]
+    pub returns: Return,
+}
+
+/// The `?fields=` sparse fieldset of a `T` response, see [`Fields`]. Names
+/// that aren't fields of `T` are rejected.
+pub struct SparseFields<T> {
+    pub fields: Fields,
+    entity: PhantomData<T>,
+}
+
+impl<T, S> FromRequestParts<S> for SparseFields<T>
+where
+    T: Projectable,
+    S: Send + Sync,
+{
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let correlation_id = correlation_id(&parts.extensions);
+        let error = |e| ApiResponse::error(correlation_id.clone(), e);
+        let Query(query) = Query::<FieldsQuery>::from_request_parts(parts, state).await?;
+        let fields = Fields::parse(query.fields.as_deref(), T::FIELDS).map_err(error)?;
+        Ok(Self {
+            fields,
+            entity: PhantomData,
+        })
+    }
+}
+
+/// The entity version an update must find, from an `If-Match` header holding
+/// the `ETag` of a read, e.g. `"3"`. `*` or no header matches any version.
+/// Weak tags never match, as RFC 9110 compares `If-Match` strongly.
+pub struct IfMatch(pub Option<i64>);
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(IF_MATCH) else {
+            return Ok(Self(None));
+        };
+        let value = value.to_str().unwrap_or_default().trim();
+        if value == "*" {
+            return Ok(Self(None));
+        }
+        if let Some(version) = parse_version_etag(value) {
+            return Ok(Self(Some(version)));
+        }
+        let error = if value.starts_with("W/") {
+            AppError {
+                code: AppErrorCode::PreconditionFailed,
+                message: "A weak ETag never matches If-Match".to_string(),
+                error_code: None,
+            }
+        } else {
+            AppError::validation(vec![FieldError::new(
+                "If-Match",
+                "invalid",
+                "If-Match must be the ETag of a read, e.g. \"3\"",
+            )])
+        };
+        Err(ApiResponse::error(correlation_id(&parts.extensions), error))
+    }
+}
+
+/// Deserializes a string with surrounding whitespace removed, so length rules
+/// apply to the meaningful value.
+pub fn trimmed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(normalized(&String::deserialize(deserializer)?))
+}
+
+/// Trims and NFC normalizes `value`, so visually identical input composed
+/// from different code points compares and stores the same.
+pub fn normalized(value: &str) -> String {
+    value.trim().nfc().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::StatusCode, routing::post};
+    use tower::ServiceExt;
+
+    #[derive(Deserialize, Validate)]
+    struct Payload {
+        #[serde(deserialize_with = "trimmed")]
+        #[validate(length(min = 1, message = "Name is required"))]
+        name: String,
+        #[validate(email(message = "Email is invalid"))]
+        email: String,
+    }
+
+    async fn handler(ValidatedJson(payload): ValidatedJson<Payload>) -> String {
+        payload.name
+    }
+
+    async fn send(body: &str) -> (StatusCode, Vec<u8>) {
+        let app = Router::new().route("/", post(handler));
+        let res = app
+            .oneshot(
+                Request::post("/")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = res.status();
+        let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_valid_payload_is_trimmed() {
+        let (status, body) = send(r#"{"name": "  book ", "email": "a@b.com"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"book");
+    }
+
+    #[test]
+    fn test_normalized_composes_code_points() {
+        assert_eq!(normalized(" cafe\u{301} "), "caf\u{e9}");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_fields_are_reported() {
+        let (status, body) = send(r#"{"name": "   ", "email": "nope"}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_code"], "VALIDATION_FAILED");
+        assert_eq!(
+            body["errors"],
+            serde_json::json!([
+                {"field": "email", "code": "email", "message": "Email is invalid"},
+                {"field": "name", "code": "length", "message": "Name is required"},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_is_rejected() {
+        let (status, _) = send("{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = send(r#"{"name": 1, "email": "a@b.com"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_code"], "UNPROCESSABLE");
+    }
+
+    #[tokio::test]
+    async fn test_rejections_keep_status_in_envelope() {
+        async fn numbered(Path(id): Path<u32>) -> String {
+            id.to_string()
+        }
+        async fn returning(Query(query): Query<DeleteQuery>) -> String {
+            format!("{:?}", query.returns)
+        }
+        async fn counted(Json(names): Json<Vec<String>>) -> String {
+            names.len().to_string()
+        }
+        let app = Router::new()
+            .route("/", post(handler).delete(returning).put(counted))
+            .route("/{id}", axum::routing::get(numbered));
+        let ctx = Ctx {
+            correlation_id: "c1".into(),
+            ..Default::default()
+        };
+        let requests = [
+            (
+                Request::post("/").body(Body::from("{}")),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ),
+            (
+                Request::get("/one").body(Body::empty()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Request::delete("/?return=everything").body(Body::empty()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Request::put("/")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"name": "book"}"#)),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+        ];
+        for (req, status) in requests {
+            let mut req = req.unwrap();
+            req.extensions_mut().insert(ctx.clone());
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), status);
+            let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(body["correlation_id"], "c1");
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{DefaultBodyLimit, FromRef, NestedPath, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    extract::{Multipart, Path, Query},
+    model::{
+        attachment::Attachment,
+        context::Ctx,
+        error::{AppError, AppErrorCode, FieldError},
+        http::{ApiResponse, ApiResult, Links, Response, etag_matches, http_date},
+    },
+    service::{
+        ServiceApi,
+        attachment::{AttachmentData, NewAttachment},
+    },
+    thumbnail::thumbnail_key,
+};
+
+/// Largest request accepted by the upload route, the configured
+/// `ATTACHMENT_MAX_BYTES` is checked by the service below it.
+pub const MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    upload_attachment,
+    list_attachments,
+    delete_attachment,
+    download_attachment
+))]
+pub struct AttachmentApi;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownloadQuery {
+    /// Configured thumbnail size, e.g. `thumb`, for image attachments.
+    size: Option<String>,
+}
+
+/// Attachment routes, merged into the item routes.
+pub fn router_setup_attachments<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
+    axum::Router::new()
+        .route(
+            "/{id}/attachments",
+            axum::routing::get(list_attachments)
+                .post(upload_attachment)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}",
+            axum::routing::delete(delete_attachment),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}/download",
+            axum::routing::get(download_attachment),
+        )
+}
+
+fn multipart_error(e: impl ToString) -> AppError {
+    AppError {
+        code: AppErrorCode::InvalidInput,
+        message: format!("Invalid multipart body: {}", e.to_string()),
+        error_code: None,
+    }
+}
+
+/// Reads the `file` part, other parts are skipped.
+pub(crate) async fn read_file(
+    multipart: &mut axum::extract::Multipart,
+) -> Result<NewAttachment, AppError> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or_default().to_string();
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let data = field.bytes().await.map_err(multipart_error)?;
+        return Ok(NewAttachment {
+            filename,
+            content_type,
+            data: data.to_vec(),
+        });
+    }
+    Err(AppError::validation(vec![FieldError::new(
+        "file",
+        "required",
+        "Missing 'file' part",
+    )]))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/attachments",
+    tag = "attachments",
+    params(("id" = String, Path, description = "Item id")),
+    request_body(content_type = "multipart/form-data", description = "The file in a `file` part"),
+    responses(
+        (status = 201, description = "Attachment uploaded", body = Response<Attachment>),
+        (status = 400, description = "Missing, empty, too large or unsupported file", body = Response<Value>),
+        (status = 404, description = "Item not found", body = Response<Value>),
+        (status = 413, description = "Request too large"),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn upload_attachment(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    Multipart(mut multipart): Multipart,
+) -> ApiResult<Attachment> {
+    let file = read_file(&mut multipart)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let attachment = service
+        .upload_attachment(&ctx, &id, file)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let path = format!("{}/{}/attachments", nested.as_str(), id);
+    let links = Links::resource(&path, &attachment.id)
+        .with_related("download", &format!("{}/{}/download", path, attachment.id));
+    let message = format!("Uploaded attachment '{}'", attachment.filename);
+    let args = [
+        ("id", attachment.id.clone()),
+        ("filename", attachment.filename.clone()),
+    ];
+    Ok(
+        ApiResponse::created(ctx.correlation_id, attachment, message)
+            .message_key("attachment.created", args)
+            .links(links),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/attachments",
+    tag = "attachments",
+    params(("id" = String, Path, description = "Item id")),
+    responses(
+        (status = 200, description = "Attachments of the item, oldest first", body = Response<Vec<Attachment>>),
+        (status = 404, description = "Item not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn list_attachments(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<Attachment>> {
+    let attachments = service
+        .list_attachments(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::collection(&format!("{}/{}/attachments", nested.as_str(), id));
+    Ok(ApiResponse::ok(ctx.correlation_id, attachments).links(links))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}/attachments/{attachment_id}",
+    tag = "attachments",
+    params(
+        ("id" = String, Path, description = "Item id"),
+        ("attachment_id" = String, Path, description = "Attachment id"),
+    ),
+    responses(
+        (status = 200, description = "Attachment deleted", body = Response<Value>),
+        (status = 404, description = "Attachment not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn delete_attachment(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Path((id, attachment_id)): Path<(String, String)>,
+) -> ApiResult<()> {
+    service
+        .delete_attachment(&ctx, &id, &attachment_id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    Ok(ApiResponse::done(
+        ctx.correlation_id,
+        format!("Deleted attachment with id {}", attachment_id),
+    )
+    .message_key("attachment.deleted", [("id", attachment_id)]))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/attachments/{attachment_id}/download",
+    tag = "attachments",
+    params(
+        ("id" = String, Path, description = "Item id"),
+        ("attachment_id" = String, Path, description = "Attachment id"),
+        DownloadQuery,
+    ),
+    responses(
+        (status = 200, description = "The uploaded file, with its original content type"),
+        (status = 206, description = "The byte range asked for with `Range`"),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag or `If-Modified-Since`"),
+        (status = 307, description = "Redirect to a presigned URL of the object storage"),
+        (status = 400, description = "Unknown thumbnail size", body = Response<Value>),
+        (status = 404, description = "Attachment or thumbnail not found", body = Response<Value>),
+        (status = 416, description = "Range past the end of the file"),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn download_attachment(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    headers: HeaderMap,
+    Path((id, attachment_id)): Path<(String, String)>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<impl IntoResponse, ApiResponse<()>> {
+    // Stored bytes never change under a key, so the key is a strong validator.
+    let etag = match &query.size {
+        Some(size) => format!("\"{}\"", thumbnail_key(&attachment_id, size)),
+        None => format!("\"{}\"", attachment_id),
+    };
+    let (attachment, data) = service
+        .download_attachment(&ctx, &id, &attachment_id, query.size)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let data = match data {
+        AttachmentData::Bytes(data) => data,
+        AttachmentData::Url(url) => return Ok(Redirect::temporary(&url).into_response()),
+    };
+    let disposition = format!("attachment; filename=\"{}\"", attachment.filename);
+    let mut res = ranged(&headers, &etag, data);
+    let has_body = res.status().is_success();
+    let res_headers = res.headers_mut();
+    let mut values = vec![
+        (header::ETAG, etag),
+        (header::LAST_MODIFIED, http_date(attachment.created_at)),
+    ];
+    if has_body {
+        values.push((header::CONTENT_TYPE, attachment.content_type));
+        values.push((header::CONTENT_DISPOSITION, disposition));
+    }
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::try_from(value) {
+            res_headers.insert(name, value);
+        }
+    }
+    res_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    res_headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    Ok(res)
+}
+
+/// Parses a single `bytes=` range of a `len` bytes body into inclusive
+/// bounds. `None` serves the whole body, as for multiple ranges or other
+/// units, `Some(Err(()))` when the range lies past the end.
+fn parse_range(value: &str, len: usize) -> Option<Result<(usize, usize), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix = suffix.parse::<usize>().ok()?;
+            if suffix == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let (start, end) = (start.parse().ok()?, end.parse::<usize>().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end)))
+}
+
+/// Answers conditional and range requests on `data`: `304` when the client
+/// has it, `206` with the requested slice, `416` for ranges past the end.
+fn ranged(headers: &HeaderMap, etag: &str, data: Vec<u8>) -> axum::response::Response {
+    if etag_matches(headers.get(header::IF_NONE_MATCH), etag) {
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        // A stale If-Range means the client's partial copy is outdated.
+        .filter(|_| {
+            headers.get(header::IF_RANGE).is_none()
+                || etag_matches(headers.get(header::IF_RANGE), etag)
+        })
+        .and_then(|value| parse_range(value, data.len()));
+    match range {
+        None => data.into_response(),
+        Some(Err(())) => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", data.len()))],
+        )
+            .into_response(),
+        Some(Ok((start, end))) => {
+            let content_range = format!("bytes {}-{}/{}", start, end, data.len());
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [(header::CONTENT_RANGE, content_range)],
+                data[start..=end].to_vec(),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+        middleware::from_fn,
+    };
+    use chrono::Utc;
+    use tower::ServiceExt;
+
+    use crate::{middleware::request_middleware, service::registry::MockServiceApi};
+
+    const BOUNDARY: &str = "X-BOUNDARY";
+
+    fn app(service: MockServiceApi) -> Router {
+        let service: Arc<dyn ServiceApi> = Arc::new(service);
+        Router::new()
+            .nest("/api/items", router_setup_attachments())
+            .layer(from_fn(request_middleware))
+            .with_state(service)
+    }
+
+    fn upload(part: &str, filename: &str, content_type: &str, data: &str) -> Request<Body> {
+        let body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{part}\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n{data}\r\n--{BOUNDARY}--\r\n"
+        );
+        Request::post("/api/items/1/attachments")
+            .header(
+                "content-type",
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    fn attachment(file: NewAttachment) -> Attachment {
+        Attachment {
+            id: "a1".into(),
+            item_id: "1".into(),
+            filename: file.filename,
+            content_type: file.content_type,
+            size: file.data.len() as i64,
+            created_at: Utc::now(),
+        }
+    }
+
+    async fn send(app: Router, req: Request<Body>) -> (StatusCode, Value) {
+        let res = app.oneshot(req).await.unwrap();
+        let status = res.status();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_upload_attachment() {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_upload_attachment()
+            .withf(|_, item_id, file| item_id == "1" && file.data == b"hello")
+            .returning(|_, _, file| Box::pin(async move { Ok(attachment(file)) }));
+
+        let (status, body) = send(
+            app(service),
+            upload("file", "notes.txt", "text/plain", "hello"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["data"]["filename"], "notes.txt");
+        assert_eq!(body["data"]["content_type"], "text/plain");
+        assert_eq!(body["links"]["self"], "/api/items/1/attachments/a1");
+        assert_eq!(
+            body["links"]["related"]["download"],
+            "/api/items/1/attachments/a1/download"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_without_file_part() {
+        let mut service = MockServiceApi::new();
+        service.expect_upload_attachment().never();
+
+        let (status, body) = send(
+            app(service),
+            upload("document", "notes.txt", "text/plain", "hello"),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["errors"][0]["field"], "file");
+    }
+
+    #[tokio::test]
+    async fn test_download_attachment() {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_download_attachment()
+            .withf(|_, _, _, size| size.as_deref() == Some("thumb"))
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    let file = NewAttachment {
+                        filename: "logo.png".into(),
+                        content_type: "image/png".into(),
+                        data: b"png".to_vec(),
+                    };
+                    Ok((attachment(file.clone()), AttachmentData::Bytes(file.data)))
+                })
+            });
+        let req = Request::get("/api/items/1/attachments/a1/download?size=thumb")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app(service).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(
+            res.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"logo.png\""
+        );
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"png");
+    }
+
+    #[tokio::test]
+    async fn test_download_redirects_to_presigned_url() {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_download_attachment()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    let file = NewAttachment {
+                        filename: "logo.png".into(),
+                        content_type: "image/png".into(),
+                        data: vec![],
+                    };
+                    let url = "http://minio:9000/attachments/a1?X-Amz-Signature=x".to_string();
+                    Ok((attachment(file), AttachmentData::Url(url)))
+                })
+            });
+        let req = Request::get("/api/items/1/attachments/a1/download")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app(service).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            res.headers()[header::LOCATION],
+            "http://minio:9000/attachments/a1?X-Amz-Signature=x"
+        );
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-3", 10), Some(Ok((0, 3))));
+        assert_eq!(parse_range("bytes=4-", 10), Some(Ok((4, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range("bytes=5-100", 10), Some(Ok((5, 9))));
+        assert_eq!(parse_range("bytes=10-", 10), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), None);
+        assert_eq!(parse_range("items=0-1", 10), None);
+        assert_eq!(parse_range("bytes=3-1", 10), None);
+    }
+
+    fn download_app() -> Router {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_download_attachment()
+            .returning(|_, _, _, _| {
+                Box::pin(async {
+                    let file = NewAttachment {
+                        filename: "clip.mp4".into(),
+                        content_type: "video/mp4".into(),
+                        data: b"0123456789".to_vec(),
+                    };
+                    Ok((attachment(file.clone()), AttachmentData::Bytes(file.data)))
+                })
+            });
+        app(service)
+    }
+
+    async fn download(headers: &[(&str, &str)]) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut req = Request::get("/api/items/1/attachments/a1/download");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let res = download_app()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = res.into_parts();
+        let bytes = to_bytes(body, usize::MAX).await.unwrap();
+        (parts.status, parts.headers, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_download_range() {
+        let (status, headers, body) = download(&[("range", "bytes=2-5")]).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(headers[header::CONTENT_TYPE], "video/mp4");
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(body, b"2345");
+
+        let (status, headers, _) = download(&[("range", "bytes=20-")]).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes */10");
+
+        let (status, _, body) =
+            download(&[("range", "bytes=2-5"), ("if-range", "\"stale\"")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_download_etag() {
+        let (status, headers, _) = download(&[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::ETAG], "\"a1\"");
+        assert!(headers.contains_key(header::LAST_MODIFIED));
+
+        let (status, _, body) = download(&[("if-none-match", "W/\"a1\"")]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert!(body.is_empty());
+    }
+}
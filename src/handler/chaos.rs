@@ -0,0 +1,263 @@
+//! Fault injection for resilience testing, only served with `CHAOS_ENABLED`.
+//! Requests are slowed down or failed by the rules added through the admin
+//! routes, or by `X-Chaos-*` headers on the request itself.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{FromRef, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{ADMIN_CHAOS_PATH, API_V1_PREFIX};
+use crate::{
+    extract::ValidatedJson,
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode, FieldError},
+        http::{ApiResponse, ApiResult},
+    },
+};
+
+pub const CHAOS_LATENCY_HEADER: &str = "X-Chaos-Latency-Ms";
+pub const CHAOS_ERROR_RATE_HEADER: &str = "X-Chaos-Error-Rate";
+pub const CHAOS_DB_FAILURE_RATE_HEADER: &str = "X-Chaos-Db-Failure-Rate";
+
+/// Faults injected into the requests under `route`. Rates are the share of
+/// requests affected, from 0 to 1.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+pub struct ChaosRule {
+    /// Path prefix, e.g. `/api/v1/items`, every route when absent.
+    pub route: Option<String>,
+    #[serde(default)]
+    #[validate(range(max = 60000, message = "latency_ms must be at most 60000"))]
+    pub latency_ms: u64,
+    /// Answered with a 500.
+    #[serde(default)]
+    #[validate(range(min = 0.0, max = 1.0, message = "error_rate must be between 0 and 1"))]
+    pub error_rate: f64,
+    /// Answered as if the database were down.
+    #[serde(default)]
+    #[validate(range(
+        min = 0.0,
+        max = 1.0,
+        message = "db_failure_rate must be between 0 and 1"
+    ))]
+    pub db_failure_rate: f64,
+}
+
+impl ChaosRule {
+    fn matches(&self, path: &str) -> bool {
+        self.route
+            .as_deref()
+            .is_none_or(|route| path.starts_with(route))
+    }
+
+    /// The rule described by the `X-Chaos-*` headers, if any is sent.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, AppError> {
+        fn header<T: std::str::FromStr>(
+            headers: &HeaderMap,
+            name: &str,
+        ) -> Result<Option<T>, AppError> {
+            headers
+                .get(name)
+                .map(|value| {
+                    value
+                        .to_str()
+                        .ok()
+                        .and_then(|v| v.trim().parse().ok())
+                        .ok_or_else(|| {
+                            AppError::validation(vec![FieldError::new(
+                                name,
+                                "invalid",
+                                format!("{} is not a number", name),
+                            )])
+                        })
+                })
+                .transpose()
+        }
+
+        let latency_ms = header(headers, CHAOS_LATENCY_HEADER)?;
+        let error_rate = header(headers, CHAOS_ERROR_RATE_HEADER)?;
+        let db_failure_rate = header(headers, CHAOS_DB_FAILURE_RATE_HEADER)?;
+        if latency_ms.is_none() && error_rate.is_none() && db_failure_rate.is_none() {
+            return Ok(None);
+        }
+        let rule = Self {
+            route: None,
+            latency_ms: latency_ms.unwrap_or_default(),
+            error_rate: error_rate.unwrap_or_default(),
+            db_failure_rate: db_failure_rate.unwrap_or_default(),
+        };
+        rule.validate()?;
+        Ok(Some(rule))
+    }
+}
+
+/// The rules in force, shared by the middleware and the admin routes.
+#[derive(Default)]
+pub struct Chaos {
+    rules: std::sync::RwLock<Vec<ChaosRule>>,
+}
+
+impl Chaos {
+    pub fn rules(&self) -> Vec<ChaosRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn add(&self, rule: ChaosRule) {
+        self.rules.write().unwrap().push(rule);
+    }
+
+    pub fn clear(&self) {
+        self.rules.write().unwrap().clear();
+    }
+
+    fn matching(&self, path: &str) -> Vec<ChaosRule> {
+        self.rules
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Uniform in `[0, 1)`, from the random bits of a v4 UUID.
+fn random() -> f64 {
+    (Uuid::new_v4().as_u128() >> 80) as f64 / (1u64 << 48) as f64
+}
+
+/// True with probability `rate`, in `[0, 1]`.
+pub(crate) fn roll(rate: f64) -> bool {
+    rate > 0.0 && random() < rate
+}
+
+/// Applies the matching rules, then those of the request headers. Each adds
+/// its latency and may fail the request. The chaos routes themselves are
+/// left alone so the faults can always be turned off.
+pub async fn chaos_middleware(
+    State(chaos): State<Arc<Chaos>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path().to_string();
+    if path.starts_with(&format!("{}{}", API_V1_PREFIX, ADMIN_CHAOS_PATH)) {
+        return next.run(req).await;
+    }
+    let correlation_id = req
+        .extensions()
+        .get::<Ctx>()
+        .map(|ctx| ctx.correlation_id.clone())
+        .unwrap_or_default();
+    let mut rules = chaos.matching(&path);
+    match ChaosRule::from_headers(req.headers()) {
+        Ok(rule) => rules.extend(rule),
+        Err(e) => return ApiResponse::<()>::error(correlation_id, e).into_response(),
+    }
+
+    for rule in rules {
+        if rule.latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(rule.latency_ms)).await;
+        }
+        let fault = if roll(rule.error_rate) {
+            AppError {
+                code: AppErrorCode::InternalError("chaos".to_string()),
+                message: "Injected failure".to_string(),
+                error_code: None,
+            }
+        } else if roll(rule.db_failure_rate) {
+            // Same error as a pool that cannot reach the database.
+            AppError {
+                code: AppErrorCode::Unavailable,
+                message: "Database unavailable".to_string(),
+                error_code: None,
+            }
+        } else {
+            continue;
+        };
+        tracing::warn!(%path, message = fault.get_message(), "Chaos fault injected");
+        return ApiResponse::<()>::error(correlation_id, fault).into_response();
+    }
+    next.run(req).await
+}
+
+/// Dev only fault injection rules, not part of the published spec.
+pub fn router_setup_chaos<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<Chaos>: FromRef<S>,
+{
+    axum::Router::new().route(
+        "/",
+        axum::routing::get(list_rules)
+            .post(add_rule)
+            .delete(clear_rules),
+    )
+}
+
+async fn list_rules(State(chaos): State<Arc<Chaos>>, ctx: Ctx) -> ApiResult<Vec<ChaosRule>> {
+    Ok(ApiResponse::ok(ctx.correlation_id, chaos.rules()))
+}
+
+async fn add_rule(
+    State(chaos): State<Arc<Chaos>>,
+    ctx: Ctx,
+    ValidatedJson(rule): ValidatedJson<ChaosRule>,
+) -> ApiResult<Vec<ChaosRule>> {
+    tracing::warn!(?rule, "Chaos rule added");
+    chaos.add(rule);
+    Ok(ApiResponse::ok(ctx.correlation_id, chaos.rules()))
+}
+
+async fn clear_rules(State(chaos): State<Arc<Chaos>>, ctx: Ctx) -> ApiResult<()> {
+    chaos.clear();
+    Ok(ApiResponse::no_content(ctx.correlation_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_rule_matches_route_prefix() {
+        let rule = ChaosRule {
+            route: Some("/api/v1/items".into()),
+            ..Default::default()
+        };
+        assert!(rule.matches("/api/v1/items/1"));
+        assert!(!rule.matches("/api/v1/users"));
+        assert!(ChaosRule::default().matches("/api/v1/users"));
+    }
+
+    #[test]
+    fn test_rule_from_headers() {
+        assert_eq!(ChaosRule::from_headers(&HeaderMap::new()).unwrap(), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CHAOS_ERROR_RATE_HEADER, HeaderValue::from_static("0.5"));
+        let rule = ChaosRule::from_headers(&headers).unwrap().unwrap();
+        assert_eq!(rule.error_rate, 0.5);
+        assert_eq!(rule.latency_ms, 0);
+
+        headers.insert(CHAOS_ERROR_RATE_HEADER, HeaderValue::from_static("2"));
+        assert!(ChaosRule::from_headers(&headers).is_err());
+        headers.insert(CHAOS_ERROR_RATE_HEADER, HeaderValue::from_static("often"));
+        assert!(ChaosRule::from_headers(&headers).is_err());
+    }
+
+    #[test]
+    fn test_roll_bounds() {
+        assert!(!roll(0.0));
+        assert!(roll(1.0));
+        assert!((0..100).map(|_| random()).all(|r| (0.0..1.0).contains(&r)));
+    }
+}
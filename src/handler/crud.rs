@@ -0,0 +1,214 @@
+use std::{fmt::Display, sync::Arc};
+
+use async_trait::async_trait;
+use axum::extract::{FromRef, NestedPath, State};
+use serde::{Serialize, de::DeserializeOwned};
+use validator::Validate;
+
+use crate::{
+    extract::{Path, ValidatedJson},
+    model::{
+        context::Ctx,
+        error::AppError,
+        http::{ApiResponse, ApiResult, Links},
+    },
+};
+
+/// An entity served by [`crud_router`]: its id and payload types, and how
+/// each operation maps onto the service it is bound to.
+#[async_trait]
+pub trait CrudResource: Serialize + Send + Sync + Sized + 'static {
+    /// Path parameter of the `/{id}` routes.
+    type Id: DeserializeOwned + Display + Send + Sync + 'static;
+    type Create: DeserializeOwned + Validate + Send + 'static;
+    type Update: DeserializeOwned + Validate + Send + 'static;
+    /// Taken from the router state as `Arc<Self::Service>`, e.g. `dyn ServiceApi`.
+    type Service: ?Sized + Send + Sync + 'static;
+
+    /// Singular name used in response messages, e.g. "item".
+    const NAME: &'static str;
+
+    fn id(&self) -> String;
+
+    async fn list(service: &Self::Service, ctx: &Ctx) -> Result<Vec<Self>, AppError>;
+    async fn get(service: &Self::Service, ctx: &Ctx, id: Self::Id) -> Result<Self, AppError>;
+    async fn create(
+        service: &Self::Service,
+        ctx: &Ctx,
+        payload: Self::Create,
+    ) -> Result<Self, AppError>;
+    async fn update(
+        service: &Self::Service,
+        ctx: &Ctx,
+        id: Self::Id,
+        payload: Self::Update,
+    ) -> Result<Self, AppError>;
+    async fn delete(service: &Self::Service, ctx: &Ctx, id: Self::Id) -> Result<(), AppError>;
+}
+
+/// The standard five routes for `T`, e.g.
+/// `Router::new().nest("/api/items", crud_router::<Item, _>())`.
+pub fn crud_router<T, S>() -> axum::Router<S>
+where
+    T: CrudResource,
+    S: Clone + Send + Sync + 'static,
+    Arc<T::Service>: FromRef<S>,
+{
+    axum::Router::new()
+        .route("/", axum::routing::get(list::<T>).post(create::<T>))
+        .route(
+            "/{id}",
+            axum::routing::get(get::<T>)
+                .put(update::<T>)
+                .delete(delete::<T>),
+        )
+}
+
+async fn list<T: CrudResource>(
+    State(service): State<Arc<T::Service>>,
+    ctx: Ctx,
+    nested: NestedPath,
+) -> ApiResult<Vec<T>> {
+    let entities = T::list(service.as_ref(), &ctx)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    Ok(ApiResponse::ok(ctx.correlation_id, entities).links(Links::collection(nested.as_str())))
+}
+
+async fn create<T: CrudResource>(
+    State(service): State<Arc<T::Service>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    ValidatedJson(payload): ValidatedJson<T::Create>,
+) -> ApiResult<T> {
+    let entity = T::create(service.as_ref(), &ctx, payload)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let id = entity.id();
+    let message = format!("Created {} with id {}", T::NAME, id);
+    Ok(ApiResponse::created(ctx.correlation_id, entity, message)
+        .message_key(format!("{}.created", T::NAME), [("id", id.clone())])
+        .links(Links::resource(nested.as_str(), &id)))
+}
+
+async fn get<T: CrudResource>(
+    State(service): State<Arc<T::Service>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<T::Id>,
+) -> ApiResult<T> {
+    let entity = T::get(service.as_ref(), &ctx, id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &entity.id());
+    Ok(ApiResponse::ok(ctx.correlation_id, entity).links(links))
+}
+
+async fn update<T: CrudResource>(
+    State(service): State<Arc<T::Service>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<T::Id>,
+    ValidatedJson(payload): ValidatedJson<T::Update>,
+) -> ApiResult<T> {
+    let entity = T::update(service.as_ref(), &ctx, id, payload)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let id = entity.id();
+    let message = format!("Updated {} with id {}", T::NAME, id);
+    Ok(ApiResponse::ok(ctx.correlation_id, entity)
+        .message(message)
+        .message_key(format!("{}.updated", T::NAME), [("id", id.clone())])
+        .links(Links::resource(nested.as_str(), &id)))
+}
+
+async fn delete<T: CrudResource>(
+    State(service): State<Arc<T::Service>>,
+    ctx: Ctx,
+    Path(id): Path<T::Id>,
+) -> ApiResult<()> {
+    let message = format!("Deleted {} with id {}", T::NAME, id);
+    let args = [("id", id.to_string())];
+    T::delete(service.as_ref(), &ctx, id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    Ok(ApiResponse::done(ctx.correlation_id, message)
+        .message_key(format!("{}.deleted", T::NAME), args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::{
+        config::Config,
+        event::NoopPublisher,
+        model::{item::Item, user::User},
+        repository::InMemoryRepository,
+        service::{Service, ServiceApi},
+    };
+
+    fn service() -> Arc<dyn ServiceApi> {
+        Arc::new(Service::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        ))
+    }
+
+    async fn send(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+        let res = app.clone().oneshot(req).await.unwrap();
+        let status = res.status();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn post(uri: &str, body: &'static str) -> Request<Body> {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_item_routes() {
+        let app = Router::new()
+            .nest("/items", crud_router::<Item, _>())
+            .with_state(service());
+
+        let (status, body) = send(&app, post("/items", r#"{"name": "book"}"#)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = body["data"]["id"].as_str().unwrap().to_string();
+        assert_eq!(body["message"], format!("Created item with id {}", id));
+        assert_eq!(body["links"]["self"], format!("/items/{}", id));
+
+        let (status, body) = send(&app, Request::get("/items").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["name"], "book");
+
+        let uri = format!("/items/{}", id);
+        let (status, _) = send(&app, Request::delete(&uri).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Request::get(&uri).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_user_routes_validate_payload() {
+        let app = Router::new()
+            .nest("/users", crud_router::<User, _>())
+            .with_state(service());
+
+        let (status, body) = send(&app, post("/users", r#"{"email": "nope"}"#)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["errors"][0]["field"], "email");
+    }
+}
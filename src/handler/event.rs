@@ -0,0 +1,42 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{FromRef, State},
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
+};
+use futures::{Stream, stream};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::event::broadcast::Broadcaster;
+
+pub fn router_setup_events<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<Broadcaster>: FromRef<S>,
+{
+    axum::Router::new().route("/", axum::routing::get(stream_events))
+}
+
+async fn stream_events(
+    State(broadcaster): State<Arc<Broadcaster>>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let rx = broadcaster.subscribe();
+    let events = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let sse = SseEvent::default()
+                        .id(event.id.clone())
+                        .event(event.topic())
+                        .data(serde_json::to_string(&event).unwrap_or_default());
+                    return Some((Ok(sse), rx));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "SSE subscriber lagged behind, events dropped");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{FromRef, NestedPath, State},
+    http::header,
+    response::IntoResponse,
+};
+use futures::{StreamExt, TryStreamExt, stream};
+use serde_json::Value;
+use utoipa::OpenApi;
+
+use crate::{
+    export::csv_row,
+    extract::Path,
+    model::{
+        context::Ctx,
+        error::AppError,
+        export::ExportJob,
+        http::{ApiResponse, ApiResult, Links, Response},
+    },
+    service::{ServiceApi, cursor::Page},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_export_job, download_export))]
+pub struct ExportApi;
+
+/// Export job routes, the exports themselves are started from the exported
+/// collection, e.g. `POST /api/items/export-jobs`.
+pub fn router_setup_exports<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
+    axum::Router::new()
+        .route("/{id}", axum::routing::get(get_export_job))
+        .route("/{id}/download", axum::routing::get(download_export))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "exports",
+    params(("id" = String, Path, description = "Export job id")),
+    responses(
+        (status = 200, description = "Export job status and progress", body = Response<ExportJob>),
+        (status = 404, description = "Export job not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn get_export_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<ExportJob> {
+    let job = service
+        .get_export_job(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &job.id);
+    let export = ExportJob::from_job(job, nested.as_str());
+    Ok(ApiResponse::ok(ctx.correlation_id, export).links(links))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/download",
+    tag = "exports",
+    params(("id" = String, Path, description = "Export job id")),
+    responses(
+        (status = 200, description = "Exported file, CSV or Parquet as the export was started with", content(
+            (String = "text/csv"),
+            (Vec<u8> = "application/vnd.apache.parquet"),
+        )),
+        (status = 404, description = "Export job not found", body = Response<Value>),
+        (status = 409, description = "Export not finished yet", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn download_export(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiResponse<()>> {
+    let (format, file) = service
+        .download_export(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let disposition = format!("attachment; filename=\"export-{}.{}\"", id, format.as_str());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        file,
+    ))
+}
+
+/// Streams a keyset paged list as a CSV attachment named `filename`, one
+/// page at a time so the whole table never sits in memory. The first page is
+/// read before answering so its errors still get an envelope, a later failure
+/// cuts the download short.
+pub(crate) async fn csv_download<T, F, Fut>(
+    ctx: &Ctx,
+    filename: &str,
+    columns: &[&str],
+    row: fn(&T) -> String,
+    fetch: F,
+) -> Result<impl IntoResponse + use<T, F, Fut>, ApiResponse<()>>
+where
+    T: Send + 'static,
+    F: Fn(Option<String>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<Page<T>, AppError>> + Send + 'static,
+{
+    let first = fetch(None)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let lines = move |rows: Vec<T>| rows.iter().map(row).collect::<String>();
+    let head = stream::iter([Ok(csv_row(columns) + &lines(first.rows))]);
+    let rest = stream::try_unfold(first.next, move |cursor| {
+        let fetch = fetch.clone();
+        async move {
+            let Some(cursor) = cursor else {
+                return Ok(None);
+            };
+            let page = fetch(Some(cursor)).await?;
+            Ok(Some((lines(page.rows), page.next)))
+        }
+    });
+    let body = head.chain(rest).inspect_err(|e: &AppError| {
+        tracing::error!(error = %e, "CSV download cut short");
+    });
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(body),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+        middleware::from_fn,
+    };
+    use chrono::Utc;
+    use tower::ServiceExt;
+
+    use crate::{
+        middleware::request_middleware,
+        model::{
+            export::ExportFormat,
+            job::{Job, JobStatus},
+        },
+        service::registry::MockServiceApi,
+    };
+
+    fn app(service: MockServiceApi) -> Router {
+        let service: Arc<dyn ServiceApi> = Arc::new(service);
+        Router::new()
+            .nest("/api/export-jobs", router_setup_exports())
+            .layer(from_fn(request_middleware))
+            .with_state(service)
+    }
+
+    fn job(status: JobStatus) -> Job {
+        Job {
+            id: "1".into(),
+            kind: "export.items".into(),
+            payload: Value::Null,
+            status,
+            attempts: 1,
+            max_attempts: 5,
+            progress: 100,
+            result: None,
+            last_error: None,
+            run_at: Utc::now(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_done_job_links_download() {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_get_export_job()
+            .returning(|_, _| Box::pin(async { Ok(job(JobStatus::Done)) }));
+
+        let req = Request::builder()
+            .uri("/api/export-jobs/1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app(service).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["data"]["status"], "done");
+        assert_eq!(body["data"]["download_url"], "/api/export-jobs/1/download");
+    }
+
+    #[tokio::test]
+    async fn test_csv_download_streams_every_page() {
+        let fetch = |cursor: Option<String>| async move {
+            let page = match cursor.as_deref() {
+                None => Page::forward(vec!["a", "b,c"], Some("2".to_string())),
+                Some(_) => Page::forward(vec!["d"], None),
+            };
+            Ok(page)
+        };
+        let row = |name: &&str| csv_row(&[name]);
+
+        let res = csv_download(&Ctx::default(), "names.csv", &["name"], row, fetch)
+            .await
+            .into_response();
+
+        assert_eq!(
+            res.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"names.csv\""
+        );
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"name\na\n\"b,c\"\nd\n");
+    }
+
+    #[tokio::test]
+    async fn test_download_serves_csv() {
+        let mut service = MockServiceApi::new();
+        service.expect_download_export().returning(|_, _| {
+            Box::pin(async { Ok((ExportFormat::Csv, b"id,name\n1,book\n".to_vec())) })
+        });
+
+        let req = Request::builder()
+            .uri("/api/export-jobs/1/download")
+            .body(Body::empty())
+            .unwrap();
+        let res = app(service).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/csv");
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"id,name\n1,book\n");
+    }
+}
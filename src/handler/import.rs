@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, NestedPath, State};
+use serde_json::Value;
+use utoipa::OpenApi;
+
+use crate::{
+    extract::Path,
+    model::{
+        context::Ctx,
+        http::{ApiResponse, ApiResult, Links, Response},
+        import::ImportJob,
+    },
+    service::ServiceApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_import_job))]
+pub struct ImportApi;
+
+/// Import job routes, the imports themselves are started from the target
+/// collection, e.g. `POST /api/items/import-jobs`.
+pub fn router_setup_imports<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
+    axum::Router::new().route("/{id}", axum::routing::get(get_import_job))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "imports",
+    params(("id" = String, Path, description = "Import job id")),
+    responses(
+        (status = 200, description = "Import progress, processed rows and row errors", body = Response<ImportJob>),
+        (status = 404, description = "Import job not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn get_import_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<ImportJob> {
+    let job = service
+        .get_import_job(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &job.id);
+    Ok(ApiResponse::ok(ctx.correlation_id, ImportJob::from(job)).links(links))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+        middleware::from_fn,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use crate::{
+        middleware::request_middleware,
+        model::job::{Job, NewJob},
+        service::registry::MockServiceApi,
+    };
+
+    #[tokio::test]
+    async fn test_get_import_job_reports_rows() {
+        let mut service = MockServiceApi::new();
+        service.expect_get_import_job().returning(|_, _| {
+            let mut job = Job::from(NewJob::new("import.items", json!({})));
+            job.progress = 40;
+            job.result = Some(json!({
+                "rows_processed": 40,
+                "rows_failed": 1,
+                "errors": [{"row": 7, "message": "Item name must be 1 to 255 characters"}]
+            }));
+            Box::pin(async { Ok(job) })
+        });
+        let service: Arc<dyn ServiceApi> = Arc::new(service);
+        let app = Router::new()
+            .nest("/api/import-jobs", router_setup_imports())
+            .layer(from_fn(request_middleware))
+            .with_state(service);
+
+        let req = Request::builder()
+            .uri("/api/import-jobs/1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["data"]["progress"], 40);
+        assert_eq!(body["data"]["rows_processed"], 40);
+        assert_eq!(body["data"]["errors"][0]["row"], 7);
+    }
+}
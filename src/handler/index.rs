@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{Method, Uri},
+};
+use serde_json::Value;
+
+use super::{
+    EVENTS_PATH, HEALTHCHECK_PATH, ITEMS_PATH, USERS_PATH,
+    version::{ApiMount, ApiVersion},
+};
+use crate::{
+    config::Config,
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+        http::{ApiResponse, Links, Response},
+    },
+    openapi::DOCS_PATH,
+    state::AppState,
+};
+
+pub fn router_setup_index() -> axum::Router<AppState> {
+    axum::Router::new()
+        .route("/", axum::routing::get(index))
+        .route(HEALTHCHECK_PATH, axum::routing::get(healthcheck))
+}
+
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "meta",
+    responses((status = 200, description = "Entry point linking to every collection", body = Response<Value>))
+)]
+pub(crate) async fn index(State(config): State<Arc<Config>>, ctx: Ctx) -> ApiResponse<()> {
+    let api = ApiMount::of(ApiVersion::V1);
+    ApiResponse::done(
+        ctx.correlation_id,
+        format!("Welcome to {}!", &config.app_name),
+    )
+    .links(
+        Links::collection("/")
+            .with_related("items", &api.path(ITEMS_PATH))
+            .with_related("users", &api.path(USERS_PATH))
+            .with_related("events", &api.path(EVENTS_PATH))
+            .with_related("healthcheck", HEALTHCHECK_PATH)
+            .with_related("docs", DOCS_PATH),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/healthcheck",
+    tag = "meta",
+    responses((status = 200, description = "Service is up", body = Response<Value>))
+)]
+pub(crate) async fn healthcheck(ctx: Ctx) -> ApiResponse<()> {
+    ApiResponse::done(ctx.correlation_id, "ok")
+}
+
+/// Answers paths no route matches with an error envelope, not an empty body.
+pub(crate) async fn not_found(ctx: Ctx, uri: Uri) -> ApiResponse<()> {
+    ApiResponse::error(
+        ctx.correlation_id,
+        AppError {
+            code: AppErrorCode::NotFound,
+            message: format!("No route for {}", uri.path()),
+            error_code: None,
+        },
+    )
+}
+
+/// Answers routes called with a method they don't serve with an error
+/// envelope, not an empty body.
+pub(crate) async fn method_not_allowed(ctx: Ctx, method: Method, uri: Uri) -> ApiResponse<()> {
+    ApiResponse::error(
+        ctx.correlation_id,
+        AppError {
+            code: AppErrorCode::MethodNotAllowed,
+            message: format!("{} is not allowed on {}", method, uri.path()),
+            error_code: None,
+        },
+    )
+}
@@ -1,164 +1,897 @@
-use axum::{Extension, Json, extract::State, http::StatusCode};
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, FromRef, NestedPath, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::Value;
 use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use validator::Validate;
 
-use crate::middleware::CorrelationId;
-use crate::model::{http::Response, item::Item};
-use crate::state::AppState;
+use super::attachment::{read_file, router_setup_attachments};
+use super::crud::CrudResource;
+use super::{EXPORT_JOBS_PATH, IMPORT_JOBS_PATH, export::csv_download, version::ApiMount};
+use crate::export::csv_row;
+use crate::extract::{
+    DeleteQuery, FieldsQuery, IfMatch, Json, Multipart, Path, Query, Return, SparseFields,
+    UpdateBody, ValidatedJson, trimmed,
+};
+use crate::model::{
+    context::Ctx,
+    error::{AppError, FieldError},
+    export::{ExportFormat, ExportJob},
+    http::{ApiResponse, ApiResult, Links, Response},
+    import::{ImportJob, ImportResult},
+    item::{ExpandedItem, Item},
+};
+use crate::patch::PatchOp;
+use crate::repository::item::item_not_found;
+use crate::service::{
+    ServiceApi,
+    cursor::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT},
+    item::ListItemFilter,
+};
 
-#[derive(Serialize, Deserialize)]
-struct CreateItem {
+/// Largest CSV accepted by `POST /import-jobs` and `POST /import`.
+pub const MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    list_items,
+    export_items_csv,
+    search_items,
+    create_item,
+    get_item,
+    head_item,
+    get_item_by_slug,
+    update_item,
+    bulk_update_items,
+    regenerate_item_slug,
+    delete_item,
+    create_export_job,
+    create_import_job,
+    import_items
+))]
+pub struct ItemApi;
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct CreateItem {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1, max = 255, message = "Item name must be 1 to 255 characters"))]
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct UpdateItem {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1, max = 255, message = "Item name must be 1 to 255 characters"))]
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize)]
-struct UpdateItem {
+/// One rename of `PUT /items/bulk`.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkUpdateItem {
+    pub id: String,
+    #[serde(deserialize_with = "trimmed")]
     pub name: String,
 }
 
-pub fn router_setup_items() -> axum::Router<Arc<AppState>> {
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Text to look for in item names, typos are tolerated when a search
+    /// engine is configured.
+    q: String,
+    /// At most 100, defaults to 20.
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListItemsQuery {
+    /// RFC 3339 timestamp, answers with the items as they were at that
+    /// moment rather than now. Can't be combined with paging.
+    as_of: Option<DateTime<Utc>>,
+    /// Cursor of a `next`, `prev` or `last` link, pages through the items by
+    /// name.
+    cursor: Option<String>,
+    /// Items per page, at most 1000, defaults to 100. Setting it alone
+    /// fetches the first page.
+    limit: Option<i64>,
+    /// `name`, the default, `slug` or `id`. Only for current, unpaged lists.
+    sort: Option<String>,
+    /// `asc`, the default, or `desc`.
+    order: Option<String>,
+    /// Related resources to embed in each item, only `owner` so far. Owners
+    /// are looked up all at once. Not for paged lists.
+    include: Option<String>,
+}
+
+/// Whether `include` asks for the owners, other relations are rejected.
+fn includes_owner(include: Option<&str>) -> Result<bool, AppError> {
+    let Some(include) = include else {
+        return Ok(false);
+    };
+    let errors: Vec<FieldError> = include
+        .split(',')
+        .map(str::trim)
+        .filter(|name| *name != "owner")
+        .map(|name| {
+            FieldError::new(
+                "include",
+                "unknown",
+                format!("Unknown relation {}, expected owner", name),
+            )
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(AppError::validation(errors));
+    }
+    Ok(true)
+}
+
+/// Filters of `GET /items`, only for current, unpaged lists.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListItemFilterQuery {
+    /// Part of the name, ignoring case.
+    name_contains: Option<String>,
+    /// RFC 3339 timestamp, only items created after it.
+    created_after: Option<DateTime<Utc>>,
+}
+
+impl From<ListItemFilterQuery> for ListItemFilter {
+    fn from(query: ListItemFilterQuery) -> Self {
+        Self {
+            name_contains: query.name_contains,
+            created_after: query.created_after,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AsOfQuery {
+    /// RFC 3339 timestamp, answers with the item as it was at that moment
+    /// rather than now.
+    as_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// Only check the rows and report their errors, nothing is created.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// `csv`, the default, or `parquet`.
+    format: Option<ExportFormat>,
+}
+
+#[async_trait]
+impl CrudResource for Item {
+    type Id = String;
+    type Create = CreateItem;
+    type Update = UpdateItem;
+    type Service = dyn ServiceApi;
+
+    const NAME: &'static str = "item";
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    async fn list(service: &dyn ServiceApi, ctx: &Ctx) -> Result<Vec<Item>, AppError> {
+        service
+            .list_items(ctx, None, None, ListItemFilter::default())
+            .await
+    }
+
+    async fn get(service: &dyn ServiceApi, ctx: &Ctx, id: String) -> Result<Item, AppError> {
+        service.get_item(ctx, id).await
+    }
+
+    async fn create(
+        service: &dyn ServiceApi,
+        ctx: &Ctx,
+        payload: CreateItem,
+    ) -> Result<Item, AppError> {
+        service.create_item(ctx, payload.name).await
+    }
+
+    async fn update(
+        service: &dyn ServiceApi,
+        ctx: &Ctx,
+        id: String,
+        payload: UpdateItem,
+    ) -> Result<Item, AppError> {
+        service.update_item(ctx, id, payload.name, None).await
+    }
+
+    async fn delete(service: &dyn ServiceApi, ctx: &Ctx, id: String) -> Result<(), AppError> {
+        service.delete_item(ctx, id).await.map(|_| ())
+    }
+}
+
+/// Item routes, mountable under any prefix of any router whose state can
+/// provide the service, e.g. `Router::new().nest("/inventory", router_setup_items())`.
+pub fn router_setup_items<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
     axum::Router::new()
         .route("/", axum::routing::get(list_items).post(create_item))
+        .route("/export.csv", axum::routing::get(export_items_csv))
+        .route("/export-jobs", axum::routing::post(create_export_job))
+        .route(
+            "/import-jobs",
+            axum::routing::post(create_import_job).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route(
+            "/import",
+            axum::routing::post(import_items).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route("/bulk", axum::routing::put(bulk_update_items))
+        .route("/search", axum::routing::get(search_items))
+        .route("/slug/{slug}", axum::routing::get(get_item_by_slug))
         .route(
             "/{id}",
             axum::routing::get(get_item)
+                .head(head_item)
                 .put(update_item)
                 .delete(delete_item),
         )
+        .route(
+            "/{id}/regenerate-slug",
+            axum::routing::post(regenerate_item_slug),
+        )
+        .merge(router_setup_attachments())
 }
 
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "items",
+    params(ListItemsQuery, ListItemFilterQuery, FieldsQuery),
+    responses(
+        (status = 200, description = "List all items, or one page of them linking `self`, `first`, `last` and, where there are such pages, `prev` and `next`", body = Response<Vec<ExpandedItem>>),
+        (status = 400, description = "Invalid timestamp, cursor, limit, sort, filter, include or fields", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn list_items(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.item.list().await {
-        Ok(items) => (
-            StatusCode::OK,
-            Json(json!(Response::<Vec<Item>> {
-                correlation_id,
-                message: "ok".into(),
-                error: "".into(),
-                data: Some(items),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
-    }
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Query(query): Query<ListItemsQuery>,
+    Query(filter): Query<ListItemFilterQuery>,
+    sparse: SparseFields<Item>,
+) -> ApiResult<Vec<ExpandedItem>> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let owner = includes_owner(query.include.as_deref()).map_err(error)?;
+    let filtered = filter.name_contains.is_some() || filter.created_after.is_some();
+    let mut links = Links::collection(nested.as_str());
+    let paged = query.cursor.is_some() || query.limit.is_some();
+    let sorted = query.sort.is_some() || query.order.is_some();
+    let conflict = |field, message| {
+        error(AppError::validation(vec![FieldError::new(
+            field, "conflict", message,
+        )]))
+    };
+    let items = match query.as_of {
+        Some(_) if paged => return Err(conflict("as_of", "Point-in-time lists can't be paged")),
+        _ if sorted && (paged || query.as_of.is_some()) => {
+            return Err(conflict(
+                "sort",
+                "Only current, unpaged lists can be sorted",
+            ));
+        }
+        _ if filtered && (paged || query.as_of.is_some()) => {
+            return Err(conflict(
+                "filter",
+                "Only current, unpaged lists can be filtered",
+            ));
+        }
+        _ if owner && paged => {
+            return Err(conflict(
+                "include",
+                "Only unpaged lists can include related resources",
+            ));
+        }
+        Some(at) => service.list_items_as_of(&ctx, at).await.map_err(error)?,
+        None if paged => {
+            let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+            let page = service
+                .list_items_page(&ctx, query.cursor.clone(), limit)
+                .await
+                .map_err(error)?;
+            links = Links::cursor_page(
+                nested.as_str(),
+                query.cursor.as_deref(),
+                page.prev,
+                page.next,
+                page.last,
+                limit,
+                &sparse.fields,
+            );
+            page.rows
+        }
+        None => service
+            .list_items(&ctx, query.sort, query.order, filter.into())
+            .await
+            .map_err(error)?,
+    };
+    let (items, fields) = if owner {
+        let items = service
+            .include_item_owners(&ctx, items)
+            .await
+            .map_err(error)?;
+        (items, sparse.fields.keep("owner"))
+    } else {
+        (
+            items.into_iter().map(ExpandedItem::from).collect(),
+            sparse.fields,
+        )
+    };
+    Ok(ApiResponse::ok(ctx.correlation_id, items)
+        .links(links)
+        .fields(fields))
 }
 
+#[utoipa::path(
+    get,
+    path = "/export.csv",
+    tag = "items",
+    responses(
+        (status = 200, description = "Every item as a CSV attachment, in page order", content_type = "text/csv", body = String),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn export_items_csv(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+) -> Result<impl IntoResponse, ApiResponse<()>> {
+    let fetch = {
+        let ctx = ctx.clone();
+        move |cursor| {
+            let (service, ctx) = (service.clone(), ctx.clone());
+            async move { service.list_items_page(&ctx, cursor, MAX_PAGE_LIMIT).await }
+        }
+    };
+    let columns = ["id", "name", "slug", "owner_id"];
+    csv_download(
+        &ctx,
+        "items.csv",
+        &columns,
+        |item: &Item| {
+            let owner = item.owner_id.as_deref().unwrap_or_default();
+            csv_row(&[&item.id, &item.name, &item.slug, owner])
+        },
+        fetch,
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "items",
+    params(SearchQuery, FieldsQuery),
+    responses(
+        (status = 200, description = "Matching items, best first", body = Response<Vec<Item>>),
+        (status = 400, description = "Missing query, invalid limit or unknown fields", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn search_items(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Query(query): Query<SearchQuery>,
+    sparse: SparseFields<Item>,
+) -> ApiResult<Vec<Item>> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let items = service
+        .search_items(&ctx, query.q, query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+        .map_err(error)?;
+    let links = Links::collection(&format!("{}/search", nested.as_str()));
+    Ok(ApiResponse::ok(ctx.correlation_id, items)
+        .links(links)
+        .fields(sparse.fields))
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "items",
+    request_body = CreateItem,
+    responses(
+        (status = 201, description = "Item created", body = Response<Item>),
+        (status = 400, description = "Invalid item name or malformed JSON", body = Response<Value>),
+        (status = 409, description = "Name taken by another item", body = Response<Value>),
+        (status = 415, description = "Body isn't JSON", body = Response<Value>),
+        (status = 422, description = "JSON that isn't an item", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn create_item(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    Json(payload): Json<CreateItem>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.item.create(payload.name).await {
-        Ok(item) => (
-            StatusCode::CREATED,
-            Json(json!(Response::<Item> {
-                correlation_id,
-                message: format!("Created item '{}'", item.name),
-                error: "".into(),
-                data: Some(item),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
-    }
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    ValidatedJson(payload): ValidatedJson<CreateItem>,
+) -> ApiResult<Item> {
+    let item = service
+        .create_item(&ctx, payload.name)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &item.id);
+    let message = format!("Created item '{}'", item.name);
+    let args = [("id", item.id.clone()), ("name", item.name.clone())];
+    Ok(ApiResponse::created(ctx.correlation_id, item, message)
+        .message_key("item.created", args)
+        .links(links))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id"), AsOfQuery, FieldsQuery),
+    responses(
+        (status = 200, description = "Item found", body = Response<Item>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid item id, timestamp or fields", body = Response<Value>),
+        (status = 404, description = "Item not found, or it didn't exist at the given time", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn get_item(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.item.get(id).await {
-        Ok(item) => (
-            StatusCode::OK,
-            Json(json!(Response::<Item> {
-                correlation_id,
-                message: "ok".into(),
-                error: "".into(),
-                data: Some(item),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    Query(query): Query<AsOfQuery>,
+    sparse: SparseFields<Item>,
+) -> ApiResult<Item> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let item = match query.as_of {
+        Some(at) => service.get_item_as_of(&ctx, id, at).await,
+        None => service.get_item(&ctx, id).await,
     }
+    .map_err(error)?;
+    let links = Links::resource(nested.as_str(), &item.id);
+    let version = item.version;
+    Ok(ApiResponse::ok(ctx.correlation_id, item)
+        .links(links)
+        .fields(sparse.fields)
+        .etag(version))
 }
 
-async fn update_item(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-    Json(payload): Json<UpdateItem>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.item.update(id, payload.name.clone()).await {
-        Ok(item) => (
-            StatusCode::OK,
-            Json(json!(Response::<Item> {
-                correlation_id,
-                message: format!("Updated item '{}' with id {}", item.name, item.id),
-                error: "".into(),
-                data: Some(item),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
+#[utoipa::path(
+    head,
+    path = "/{id}",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id")),
+    responses(
+        (status = 200, description = "Item exists"),
+        (status = 404, description = "Item not found"),
+        (status = 500, description = "Internal error"),
+    )
+)]
+async fn head_item(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiResponse<()>> {
+    let exists = service
+        .item_exists(&ctx, id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    Ok(if exists {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/slug/{slug}",
+    tag = "items",
+    params(("slug" = String, Path, description = "Item slug"), FieldsQuery),
+    responses(
+        (status = 200, description = "Item found", body = Response<Item>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Unknown fields", body = Response<Value>),
+        (status = 404, description = "Item not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn get_item_by_slug(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(slug): Path<String>,
+    sparse: SparseFields<Item>,
+) -> ApiResult<Item> {
+    let item = service
+        .get_item_by_slug(&ctx, slug)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &item.id);
+    let version = item.version;
+    Ok(ApiResponse::ok(ctx.correlation_id, item)
+        .links(links)
+        .fields(sparse.fields)
+        .etag(version))
+}
+
+#[utoipa::path(
+    put,
+    path = "/{id}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "Item id"),
+        ("If-Match" = Option<String>, Header, description = "`ETag` of a read, e.g. `\"3\"`, updates only while the item is at that version"),
+    ),
+    request_body(
+        description = "The new name, or a JSON Merge Patch or JSON Patch of the item",
+        content(
+            (UpdateItem = "application/json"),
+            (Value = "application/merge-patch+json"),
+            (Vec<PatchOp> = "application/json-patch+json"),
         ),
+    ),
+    responses(
+        (status = 200, description = "Item updated", body = Response<Item>),
+        (status = 400, description = "Invalid item id or name", body = Response<Value>),
+        (status = 404, description = "Item not found", body = Response<Value>),
+        (status = 409, description = "Name taken by another item", body = Response<Value>),
+        (status = 412, description = "The item is no longer at the If-Match version, or the tag is weak", body = Response<Value>),
+        (status = 422, description = "A JSON Patch operation failed, its index is the error's field", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn update_item(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    IfMatch(version): IfMatch,
+    body: UpdateBody<UpdateItem>,
+) -> ApiResult<Item> {
+    let item = match body {
+        UpdateBody::Replace(payload) => service.update_item(&ctx, id, payload.name, version).await,
+        UpdateBody::Patch(patch) => service.patch_item(&ctx, id, patch, version).await,
     }
+    .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &item.id);
+    let message = format!("Updated item '{}' with id {}", item.name, item.id);
+    let args = [("id", item.id.clone()), ("name", item.name.clone())];
+    Ok(ApiResponse::ok(ctx.correlation_id, item)
+        .message(message)
+        .message_key("item.updated", args)
+        .links(links))
+}
+
+#[utoipa::path(
+    put,
+    path = "/bulk",
+    tag = "items",
+    request_body(
+        content = Vec<BulkUpdateItem>,
+        description = "At most 100 renames, applied all or none",
+    ),
+    responses(
+        (status = 200, description = "Items updated, in request order", body = Response<Vec<Item>>),
+        (status = 400, description = "Empty or too large batch, or an invalid id or name", body = Response<Value>),
+        (status = 422, description = "An item was not found or its name is taken, its index is the error's field and nothing was updated", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn bulk_update_items(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Json(payload): Json<Vec<BulkUpdateItem>>,
+) -> ApiResult<Vec<Item>> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let updates = payload.into_iter().map(|u| (u.id, u.name)).collect();
+    let items = service
+        .bulk_update_items(&ctx, updates)
+        .await
+        .map_err(error)?;
+    let links = Links::collection(nested.as_str());
+    let message = format!("Updated {} items", items.len());
+    let args = [("count", items.len().to_string())];
+    Ok(ApiResponse::ok(ctx.correlation_id, items)
+        .message(message)
+        .message_key("item.bulk_updated", args)
+        .links(links))
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/regenerate-slug",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id")),
+    responses(
+        (status = 200, description = "Slug derived from the current name", body = Response<Item>),
+        (status = 400, description = "Invalid item id", body = Response<Value>),
+        (status = 404, description = "Item not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn regenerate_item_slug(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<Item> {
+    let item = service
+        .regenerate_item_slug(&ctx, id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &item.id);
+    let message = format!("Item '{}' has slug {}", item.name, item.slug);
+    let args = [("id", item.id.clone()), ("slug", item.slug.clone())];
+    Ok(ApiResponse::ok(ctx.correlation_id, item)
+        .message(message)
+        .message_key("item.slug_regenerated", args)
+        .links(links))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "items",
+    params(("id" = String, Path, description = "Item id"), DeleteQuery),
+    responses(
+        (status = 200, description = "Item deleted, with it as data for `return=representation`", body = Response<Value>),
+        (status = 204, description = "Item deleted, with `DELETE_NO_CONTENT`"),
+        (status = 400, description = "Invalid item id or return", body = Response<Value>),
+        (status = 404, description = "No item to return", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn delete_item(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.item.delete(id.clone()).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: format!("Deleted item with id {}", id),
-                error: "".into(),
-                data: None,
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> ApiResult<Item> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let deleted = service.delete_item(&ctx, id.clone()).await.map_err(error)?;
+    let message = format!("Deleted item with id {}", id);
+    let res = match query.returns {
+        Return::Minimal => ApiResponse::new(StatusCode::OK, ctx.correlation_id, message),
+        Return::Representation => {
+            let item = deleted.ok_or_else(|| error(item_not_found(&id)))?;
+            ApiResponse::ok(ctx.correlation_id, item).message(message)
+        }
+    };
+    Ok(res.message_key("item.deleted", [("id", id)]))
+}
+
+#[utoipa::path(
+    post,
+    path = "/export-jobs",
+    tag = "items",
+    params(ExportQuery),
+    responses(
+        (status = 202, description = "Export queued, poll the export job for progress", body = Response<ExportJob>),
+        (status = 400, description = "Unknown format", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn create_export_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    mount: ApiMount,
+    ctx: Ctx,
+    Query(query): Query<ExportQuery>,
+) -> ApiResult<ExportJob> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let job = service
+        .export_items(&ctx, query.format.unwrap_or_default())
+        .await
+        .map_err(error)?;
+    let path = mount.path(EXPORT_JOBS_PATH);
+    let links = Links::resource(&path, &job.id);
+    let export = ExportJob::from_job(job, &path);
+    Ok(
+        ApiResponse::new(StatusCode::ACCEPTED, ctx.correlation_id, "Export queued")
+            .data(export)
+            .message_key("export.queued", [])
+            .links(links),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/import-jobs",
+    tag = "items",
+    request_body(content = String, content_type = "text/csv", description = "CSV with a `name` column"),
+    responses(
+        (status = 202, description = "Import queued, poll the import job for progress", body = Response<ImportJob>),
+        (status = 400, description = "Empty file or missing `name` column", body = Response<Value>),
+        (status = 413, description = "File too large"),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn create_import_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    mount: ApiMount,
+    ctx: Ctx,
+    body: Bytes,
+) -> ApiResult<ImportJob> {
+    let job = service
+        .import_items(&ctx, body.to_vec())
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(&mount.path(IMPORT_JOBS_PATH), &job.id);
+    Ok(
+        ApiResponse::new(StatusCode::ACCEPTED, ctx.correlation_id, "Import queued")
+            .data(ImportJob::from(job))
+            .message_key("import.queued", [])
+            .links(links),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/import",
+    tag = "items",
+    params(ImportQuery),
+    request_body(content_type = "multipart/form-data", description = "CSV with a `name` column in a `file` part"),
+    responses(
+        (status = 200, description = "Dry run, the rows checked and their errors", body = Response<ImportResult>),
+        (status = 201, description = "Every row imported", body = Response<ImportResult>),
+        (status = 400, description = "Missing or empty file, missing `name` column, too many rows or invalid rows", body = Response<Value>),
+        (status = 413, description = "File too large"),
+        (status = 422, description = "A row could not be added, nothing was imported", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn import_items(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Query(query): Query<ImportQuery>,
+    Multipart(mut multipart): Multipart,
+) -> ApiResult<ImportResult> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let file = read_file(&mut multipart).await.map_err(error)?;
+    let result = service
+        .import_items_csv(&ctx, file.data, query.dry_run)
+        .await
+        .map_err(error)?;
+    let args = [
+        ("rows", result.rows_processed.to_string()),
+        ("failed", result.rows_failed.to_string()),
+    ];
+    if query.dry_run {
+        let message = format!(
+            "Checked {} rows, {} failed",
+            result.rows_processed, result.rows_failed
+        );
+        return Ok(ApiResponse::ok(ctx.correlation_id, result)
+            .message(message)
+            .message_key("item.import_checked", args));
+    }
+    let message = format!("Imported {} items", result.rows_processed);
+    Ok(
+        ApiResponse::created(ctx.correlation_id, result, message)
+            .message_key("item.imported", args),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+        middleware::from_fn,
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        middleware::{X_TENANT_ID, request_middleware},
+        model::{
+            error::{AppError, AppErrorCode, ErrorCode},
+            item::slugify,
+        },
+        service::registry::MockServiceApi,
+    };
+
+    fn app(service: MockServiceApi) -> Router {
+        let service: Arc<dyn ServiceApi> = Arc::new(service);
+        Router::new()
+            .nest("/api/items", router_setup_items())
+            .layer(from_fn(request_middleware))
+            .with_state(service)
+    }
+
+    async fn send(app: Router, req: Request<Body>) -> (StatusCode, Value) {
+        let res = app.oneshot(req).await.unwrap();
+        let status = res.status();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_create_item_returns_created() {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_create_item()
+            .withf(|ctx, name| name == "book" && ctx.tenant.as_deref() == Some("acme"))
+            .returning(|_, name| {
+                Box::pin(async move {
+                    Ok(Item {
+                        id: "1".into(),
+                        slug: slugify(&name),
+                        name,
+                        owner_id: None,
+                        version: 1,
+                    })
+                })
+            });
+        let req = Request::post("/api/items")
+            .header("content-type", "application/json")
+            .header(X_TENANT_ID, "acme")
+            .body(Body::from(r#"{"name": " book "}"#))
+            .unwrap();
+
+        let (status, body) = send(app(service), req).await;
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["message"], "Created item 'book'");
+        assert_eq!(body["links"]["self"], "/api/items/1");
+    }
+
+    #[tokio::test]
+    async fn test_get_item_not_found() {
+        let mut service = MockServiceApi::new();
+        service.expect_get_item().returning(|_, id| {
+            Box::pin(async move {
+                Err(AppError {
+                    code: AppErrorCode::NotFound,
+                    message: format!("Item with id {} not found", id),
+                    error_code: Some(ErrorCode::ItemNotFound),
+                })
+            })
+        });
+        let req = Request::get("/api/items/404").body(Body::empty()).unwrap();
+
+        let (status, body) = send(app(service), req).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error_code"], "ITEM_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_create_item_rejects_blank_name_before_service() {
+        let mut service = MockServiceApi::new();
+        service.expect_create_item().never();
+        let req = Request::post("/api/items")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"name": "  "}"#))
+            .unwrap();
+
+        let (status, body) = send(app(service), req).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["errors"][0]["field"], "name");
     }
 }
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, NestedPath, State};
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    extract::{Path, Query},
+    model::{
+        context::Ctx,
+        error::{AppError, FieldError},
+        http::{ApiResponse, ApiResult, Links, Response},
+        job::{Job, JobStatus},
+    },
+    service::ServiceApi,
+};
+
+const DEFAULT_LIST_LIMIT: i64 = 100;
+
+#[derive(OpenApi)]
+#[openapi(paths(list_jobs, get_job, retry_job, cancel_job))]
+pub struct JobApi;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobQuery {
+    /// `pending`, `running`, `done`, `failed` or `cancelled`.
+    status: Option<String>,
+    /// At most 500, defaults to 100.
+    limit: Option<i64>,
+}
+
+/// Background queue administration for operators, mount it behind whatever
+/// guards the other admin tooling.
+pub fn router_setup_jobs<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
+    axum::Router::new()
+        .route("/", axum::routing::get(list_jobs))
+        .route("/{id}", axum::routing::get(get_job))
+        .route("/{id}/retry", axum::routing::post(retry_job))
+        .route("/{id}/cancel", axum::routing::post(cancel_job))
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "admin",
+    params(JobQuery),
+    responses(
+        (status = 200, description = "Jobs, newest first", body = Response<Vec<Job>>),
+        (status = 400, description = "Invalid status or limit", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn list_jobs(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Query(query): Query<JobQuery>,
+) -> ApiResult<Vec<Job>> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let status = match query.status.as_deref() {
+        None => None,
+        Some(status) => Some(JobStatus::parse(status).ok_or_else(|| {
+            error(AppError::validation(vec![FieldError::new(
+                "status",
+                "invalid",
+                format!("Unknown job status '{}'", status),
+            )]))
+        })?),
+    };
+    let jobs = service
+        .list_jobs(&ctx, status, query.limit.unwrap_or(DEFAULT_LIST_LIMIT))
+        .await
+        .map_err(error)?;
+    Ok(ApiResponse::ok(ctx.correlation_id, jobs).links(Links::collection(nested.as_str())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Job with its payload and last error", body = Response<Job>),
+        (status = 404, description = "Job not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn get_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<Job> {
+    let job = service
+        .get_job(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &job.id);
+    Ok(ApiResponse::ok(ctx.correlation_id, job).links(links))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/retry",
+    tag = "admin",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Job queued again", body = Response<Job>),
+        (status = 404, description = "Job not found", body = Response<Value>),
+        (status = 409, description = "Job is neither failed nor cancelled", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn retry_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<Job> {
+    let job = service
+        .retry_job(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &job.id);
+    let message = format!("Retrying job with id {}", job.id);
+    let args = [("id", job.id.clone())];
+    Ok(ApiResponse::ok(ctx.correlation_id, job)
+        .message(message)
+        .message_key("job.retried", args)
+        .links(links))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/cancel",
+    tag = "admin",
+    params(("id" = String, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Job cancelled", body = Response<Job>),
+        (status = 404, description = "Job not found", body = Response<Value>),
+        (status = 409, description = "Job is not pending", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn cancel_job(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+) -> ApiResult<Job> {
+    let job = service
+        .cancel_job(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &job.id);
+    let message = format!("Cancelled job with id {}", job.id);
+    let args = [("id", job.id.clone())];
+    Ok(ApiResponse::ok(ctx.correlation_id, job)
+        .message(message)
+        .message_key("job.cancelled", args)
+        .links(links))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+        middleware::from_fn,
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        middleware::request_middleware,
+        model::{error::AppErrorCode, job::NewJob},
+        service::registry::MockServiceApi,
+    };
+
+    fn app(service: MockServiceApi) -> Router {
+        let service: Arc<dyn ServiceApi> = Arc::new(service);
+        Router::new()
+            .nest("/api/admin/jobs", router_setup_jobs())
+            .layer(from_fn(request_middleware))
+            .with_state(service)
+    }
+
+    async fn send(app: Router, req: Request<Body>) -> (StatusCode, Value) {
+        let res = app.oneshot(req).await.unwrap();
+        let status = res.status();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs_by_status() {
+        let mut service = MockServiceApi::new();
+        service
+            .expect_list_jobs()
+            .withf(|_, status, limit| *status == Some(JobStatus::Failed) && *limit == 20)
+            .returning(|_, _, _| {
+                Box::pin(async { Ok(vec![Job::from(NewJob::new("email.send", Value::Null))]) })
+            });
+
+        let req = Request::builder()
+            .uri("/api/admin/jobs?status=failed&limit=20")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app(service), req).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"][0]["kind"], "email.send");
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs_rejects_unknown_status() {
+        let req = Request::builder()
+            .uri("/api/admin/jobs?status=stuck")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app(MockServiceApi::new()), req).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["errors"][0]["field"], "status");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_conflict() {
+        let mut service = MockServiceApi::new();
+        service.expect_cancel_job().returning(|_, _| {
+            Box::pin(async {
+                Err(AppError {
+                    code: AppErrorCode::Conflict,
+                    message: "Job 1 is running and cannot be cancelled".to_string(),
+                    error_code: None,
+                })
+            })
+        });
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/admin/jobs/1/cancel")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app(service), req).await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["message"], "Job 1 is running and cannot be cancelled");
+    }
+}
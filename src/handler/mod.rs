@@ -1,2 +1,52 @@
+pub mod attachment;
+pub mod chaos;
+pub mod crud;
+pub mod event;
+pub mod export;
+pub mod import;
+pub mod index;
 pub mod item;
+pub mod job;
+pub mod status;
+pub mod sync;
 pub mod user;
+pub mod user_item;
+pub mod version;
+
+/// Unversioned prefix, an alias of v1, see [`version`].
+pub const API_PREFIX: &str = "/api";
+pub const API_V1_PREFIX: &str = "/api/v1";
+pub const API_V2_PREFIX: &str = "/api/v2";
+pub const HEALTHCHECK_PATH: &str = "/api/healthcheck";
+/// Server-rendered HTML page for operators.
+pub const STATUS_PATH: &str = "/status";
+
+// Resource routes, relative to an API version prefix.
+pub const ITEMS_PATH: &str = "/items";
+pub const USERS_PATH: &str = "/users";
+pub const EVENTS_PATH: &str = "/events";
+pub const EXPORT_JOBS_PATH: &str = "/export-jobs";
+pub const IMPORT_JOBS_PATH: &str = "/import-jobs";
+pub const SYNC_PATH: &str = "/sync";
+pub const ADMIN_JOBS_PATH: &str = "/admin/jobs";
+pub const ADMIN_VERSIONS_PATH: &str = "/admin/versions";
+/// Only mounted under v1, and only with `CHAOS_ENABLED`.
+pub const ADMIN_CHAOS_PATH: &str = "/admin/chaos";
+
+/// Route group of the settings no group overrides, see [`ROUTE_GROUPS`].
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Route groups timeouts and cache policies are set for. `healthcheck` also
+/// covers the entry point at `/`, `admin` the job and version administration.
+pub const ROUTE_GROUPS: [&str; 10] = [
+    DEFAULT_GROUP,
+    "healthcheck",
+    "status",
+    "items",
+    "users",
+    "events",
+    "exports",
+    "imports",
+    "sync",
+    "admin",
+];
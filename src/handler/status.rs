@@ -0,0 +1,299 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::{HEALTHCHECK_PATH, STATUS_PATH};
+use crate::{config::Config, openapi::DOCS_PATH, state::AppState};
+
+/// Minutes of requests the error rate is computed over.
+const WINDOW_MINUTES: u64 = 5;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Bucket {
+    minute: u64,
+    requests: u64,
+    errors: u64,
+}
+
+/// Uptime and per-minute request counts, fed by [`stats_middleware`].
+pub struct RequestStats {
+    started: Instant,
+    started_at: DateTime<Utc>,
+    buckets: Mutex<VecDeque<Bucket>>,
+}
+
+impl Default for RequestStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: Utc::now(),
+            buckets: Mutex::default(),
+        }
+    }
+}
+
+impl RequestStats {
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn minute(&self) -> u64 {
+        self.uptime().as_secs() / 60
+    }
+
+    /// Counts a response, server errors (5xx) as errors.
+    pub fn record(&self, status: StatusCode) {
+        self.record_at(self.minute(), status);
+    }
+
+    fn record_at(&self, minute: u64, status: StatusCode) {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return;
+        };
+        if buckets.back().is_none_or(|b| b.minute != minute) {
+            buckets.push_back(Bucket {
+                minute,
+                ..Default::default()
+            });
+        }
+        while buckets
+            .front()
+            .is_some_and(|b| b.minute + WINDOW_MINUTES <= minute)
+        {
+            buckets.pop_front();
+        }
+        if let Some(bucket) = buckets.back_mut() {
+            bucket.requests += 1;
+            bucket.errors += u64::from(status.is_server_error());
+        }
+    }
+
+    /// Requests and server errors over the last [`WINDOW_MINUTES`].
+    pub fn recent(&self) -> (u64, u64) {
+        self.recent_at(self.minute())
+    }
+
+    fn recent_at(&self, minute: u64) -> (u64, u64) {
+        let Ok(buckets) = self.buckets.lock() else {
+            return (0, 0);
+        };
+        buckets
+            .iter()
+            .filter(|b| b.minute + WINDOW_MINUTES > minute)
+            .fold((0, 0), |(requests, errors), b| {
+                (requests + b.requests, errors + b.errors)
+            })
+    }
+}
+
+pub async fn stats_middleware(
+    State(stats): State<Arc<RequestStats>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let res = next.run(req).await;
+    stats.record(res.status());
+    res
+}
+
+pub fn router_setup_status() -> axum::Router<AppState> {
+    axum::Router::new().route(STATUS_PATH, axum::routing::get(status))
+}
+
+struct Check {
+    name: &'static str,
+    ok: bool,
+    detail: String,
+}
+
+async fn check_database(pool: &PgPool) -> Check {
+    let started = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await;
+    let (ok, detail) = match result {
+        Ok(Ok(_)) => (true, format!("{} ms", started.elapsed().as_millis())),
+        Ok(Err(e)) => (false, e.to_string()),
+        Err(_) => (false, format!("no answer within {:?}", CHECK_TIMEOUT)),
+    };
+    Check {
+        name: "database",
+        ok,
+        detail,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match (secs / 86400, secs / 3600 % 24, secs / 60 % 60) {
+        (0, 0, m) => format!("{}m {}s", m, secs % 60),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, m) => format!("{}d {}h {}m", d, h, m),
+    }
+}
+
+fn rows(rows: &[(String, String)]) -> String {
+    rows.iter()
+        .map(|(key, value)| {
+            format!(
+                "<tr><th>{}</th><td>{}</td></tr>",
+                escape(key),
+                escape(value)
+            )
+        })
+        .collect()
+}
+
+/// Quick look for operators: version, uptime, health checks, pool usage and
+/// the recent error rate. Answers 503 when a check fails, so it can double
+/// as a probe.
+async fn status(
+    State(config): State<Arc<Config>>,
+    State(stats): State<Arc<RequestStats>>,
+    State(pool): State<Option<PgPool>>,
+) -> impl IntoResponse {
+    let mut checks = vec![];
+    if let Some(pool) = &pool {
+        checks.push(check_database(pool).await);
+    }
+    let healthy = checks.iter().all(|c| c.ok);
+
+    let (requests, errors) = stats.recent();
+    let error_rate = match requests {
+        0 => "no requests".to_string(),
+        _ => format!(
+            "{:.2}% ({} of {})",
+            errors as f64 * 100.0 / requests as f64,
+            errors,
+            requests
+        ),
+    };
+    let mut overview = vec![
+        ("Version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        (
+            "Started".to_string(),
+            stats.started_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        ),
+        ("Uptime".to_string(), format_uptime(stats.uptime())),
+        (
+            format!("Error rate, last {} minutes", WINDOW_MINUTES),
+            error_rate,
+        ),
+    ];
+    let pool_rows = match &pool {
+        Some(pool) => vec![
+            ("Connections".to_string(), pool.size().to_string()),
+            ("Idle".to_string(), pool.num_idle().to_string()),
+            (
+                "Max".to_string(),
+                pool.options().get_max_connections().to_string(),
+            ),
+        ],
+        None => vec![("Pool".to_string(), "no database configured".to_string())],
+    };
+    let check_rows: Vec<_> = checks
+        .iter()
+        .map(|c| {
+            let state = if c.ok { "ok" } else { "failing" };
+            (c.name.to_string(), format!("{}, {}", state, c.detail))
+        })
+        .collect();
+    overview.push((
+        "Health".to_string(),
+        if healthy { "ok" } else { "degraded" }.to_string(),
+    ));
+
+    let html = format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="30">
+<title>{name} status</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 1.5rem; }}
+th, td {{ text-align: left; padding: .25rem 1rem .25rem 0; }}
+th {{ font-weight: 600; }}
+.ok {{ color: #1a7f37; }} .degraded {{ color: #cf222e; }}
+</style>
+</head>
+<body>
+<h1>{name} <span class="{health}">{health}</span></h1>
+<table>{overview}</table>
+<h2>Checks</h2>
+<table>{checks}</table>
+<h2>Database pool</h2>
+<table>{pool}</table>
+<p><a href="{healthcheck}">Health check</a> · <a href="{docs}">API docs</a></p>
+</body>
+</html>
+"#,
+        name = escape(&config.app_name),
+        health = if healthy { "ok" } else { "degraded" },
+        overview = rows(&overview),
+        checks = match check_rows.is_empty() {
+            true => rows(&[("None".to_string(), "nothing to check".to_string())]),
+            false => rows(&check_rows),
+        },
+        pool = rows(&pool_rows),
+        healthcheck = HEALTHCHECK_PATH,
+        docs = DOCS_PATH,
+    );
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Html(html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_rate_window() {
+        let stats = RequestStats::default();
+        stats.record_at(0, StatusCode::OK);
+        stats.record_at(0, StatusCode::INTERNAL_SERVER_ERROR);
+        stats.record_at(3, StatusCode::NOT_FOUND);
+        assert_eq!(stats.recent_at(3), (3, 1));
+
+        stats.record_at(5, StatusCode::BAD_GATEWAY);
+        assert_eq!(stats.recent_at(5), (2, 1));
+        assert_eq!(stats.recent_at(20), (0, 0));
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(75)), "1m 15s");
+        assert_eq!(format_uptime(Duration::from_secs(3 * 3600 + 120)), "3h 2m");
+        assert_eq!(format_uptime(Duration::from_secs(90000)), "1d 1h 0m");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
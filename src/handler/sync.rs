@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, NestedPath, State};
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    extract::Query,
+    model::{
+        change::SyncPage,
+        context::Ctx,
+        http::{ApiResponse, ApiResult, Links, Response},
+    },
+    service::ServiceApi,
+};
+
+const DEFAULT_SYNC_LIMIT: i64 = 100;
+
+#[derive(OpenApi)]
+#[openapi(paths(sync_changes))]
+pub struct SyncApi;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncQuery {
+    /// `cursor` of the last page synced, leave out for a full sync.
+    since: Option<i64>,
+    /// At most 1000, defaults to 100.
+    limit: Option<i64>,
+}
+
+/// Delta sync for offline-first clients, which keep the last cursor and
+/// only fetch what changed after it.
+pub fn router_setup_sync<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
+    axum::Router::new().route("/", axum::routing::get(sync_changes))
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "sync",
+    params(SyncQuery),
+    responses(
+        (status = 200, description = "Item and user changes after the cursor, oldest first", body = Response<SyncPage>),
+        (status = 400, description = "Invalid cursor or limit", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn sync_changes(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Query(query): Query<SyncQuery>,
+) -> ApiResult<SyncPage> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let limit = query.limit.unwrap_or(DEFAULT_SYNC_LIMIT);
+    let page = service
+        .sync_changes(&ctx, query.since.unwrap_or(0), limit)
+        .await
+        .map_err(error)?;
+    let mut links = Links::collection(nested.as_str());
+    if page.has_more {
+        links.next = Some(format!(
+            "{}?since={}&limit={}",
+            nested.as_str(),
+            page.cursor,
+            limit
+        ));
+    }
+    Ok(ApiResponse::ok(ctx.correlation_id, page).links(links))
+}
@@ -1,157 +1,499 @@
 use std::sync::Arc;
 
-use axum::{Extension, Json, extract::State};
-use hyper::StatusCode;
-use serde_json::json;
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRef, NestedPath, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::{IntoParams, OpenApi};
 
+use super::crud::CrudResource;
+use super::export::csv_download;
+use super::user_item::router_setup_user_items;
 use crate::{
-    middleware::CorrelationId,
-    model::{http::Response, user::User},
-    service::user::{CreateUser, UpdateUser},
-    state::AppState,
+    export::csv_row,
+    extract::{
+        DeleteQuery, FieldsQuery, IfMatch, Path, Query, Return, SparseFields, UpdateBody,
+        ValidatedJson,
+    },
+    model::{
+        context::Ctx,
+        error::{AppError, FieldError},
+        http::{ApiResponse, ApiResult, Links, Response},
+        user::User,
+    },
+    patch::PatchOp,
+    repository::user::user_not_found,
+    service::{
+        ServiceApi,
+        cursor::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT},
+        user::{ConfirmEmail, CreateUser, UpdateUser},
+    },
 };
 
-pub fn router_setup_users() -> axum::Router<Arc<AppState>> {
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    add_user,
+    list_users,
+    export_users_csv,
+    search_users,
+    get_user,
+    head_user,
+    get_user_by_email,
+    update_user,
+    confirm_email,
+    delete_user
+))]
+pub struct UserApi;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListUsersQuery {
+    /// Cursor of a `next`, `prev` or `last` link, pages through the users by
+    /// email.
+    cursor: Option<String>,
+    /// Users per page, at most 1000, defaults to 100. Setting it alone
+    /// fetches the first page.
+    limit: Option<i64>,
+    /// `email`, the default, or `id`. Only for unpaged lists.
+    sort: Option<String>,
+    /// `asc`, the default, or `desc`.
+    order: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchUsersQuery {
+    /// Text to look for in emails, typos are tolerated.
+    q: String,
+    /// At most 100, defaults to 20.
+    limit: Option<i64>,
+}
+
+#[async_trait]
+impl CrudResource for User {
+    type Id = String;
+    type Create = CreateUser;
+    type Update = UpdateUser;
+    type Service = dyn ServiceApi;
+
+    const NAME: &'static str = "user";
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    async fn list(service: &dyn ServiceApi, ctx: &Ctx) -> Result<Vec<User>, AppError> {
+        service.list_users(ctx, None, None).await
+    }
+
+    async fn get(service: &dyn ServiceApi, ctx: &Ctx, id: String) -> Result<User, AppError> {
+        service.get_user(ctx, &id).await
+    }
+
+    async fn create(
+        service: &dyn ServiceApi,
+        ctx: &Ctx,
+        payload: CreateUser,
+    ) -> Result<User, AppError> {
+        service.add_user(ctx, payload).await
+    }
+
+    async fn update(
+        service: &dyn ServiceApi,
+        ctx: &Ctx,
+        id: String,
+        payload: UpdateUser,
+    ) -> Result<User, AppError> {
+        service.update_user(ctx, &id, payload, None).await
+    }
+
+    async fn delete(service: &dyn ServiceApi, ctx: &Ctx, id: String) -> Result<(), AppError> {
+        service.delete_user(ctx, &id).await.map(|_| ())
+    }
+}
+
+/// User routes, mountable under any prefix of any router whose state can
+/// provide the service.
+pub fn router_setup_users<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
     axum::Router::new()
         .route("/", axum::routing::post(add_user).get(list_users))
         .route(
             "/{id}",
             axum::routing::get(get_user)
+                .head(head_user)
                 .put(update_user)
                 .delete(delete_user),
         )
+        .route("/export.csv", axum::routing::get(export_users_csv))
+        .route("/search", axum::routing::get(search_users))
+        .route("/by-email/{email}", axum::routing::get(get_user_by_email))
+        .route("/{id}/confirm-email", axum::routing::post(confirm_email))
+        .merge(router_setup_user_items())
 }
 
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "users",
+    request_body = CreateUser,
+    responses(
+        (status = 201, description = "User created", body = Response<User>),
+        (status = 400, description = "Invalid email or malformed JSON", body = Response<Value>),
+        (status = 415, description = "Body isn't JSON", body = Response<Value>),
+        (status = 422, description = "JSON that isn't a user", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn add_user(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    Json(payload): Json<CreateUser>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.user.add(payload).await {
-        Ok(user) => (
-            StatusCode::CREATED,
-            Json(json!(Response::<User> {
-                correlation_id,
-                message: "User created successfully".into(),
-                error: "".into(),
-                data: Some(user),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
-    }
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    ValidatedJson(payload): ValidatedJson<CreateUser>,
+) -> ApiResult<User> {
+    let user = service
+        .add_user(&ctx, payload)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &user.id);
+    let args = [("id", user.id.clone()), ("email", user.email.clone())];
+    Ok(
+        ApiResponse::created(ctx.correlation_id, user, "User created successfully")
+            .message_key("user.created", args)
+            .links(links),
+    )
 }
 
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "users",
+    params(ListUsersQuery, FieldsQuery),
+    responses(
+        (status = 200, description = "List all users, or one page of them linking `self`, `first`, `last` and, where there are such pages, `prev` and `next`", body = Response<Vec<User>>),
+        (status = 400, description = "Invalid cursor, limit, sort or fields", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn list_users(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.user.list().await {
-        Ok(users) => (
-            StatusCode::OK,
-            Json(json!(Response::<Vec<User>> {
-                correlation_id,
-                message: "Users fetched successfully".into(),
-                error: "".into(),
-                data: Some(users),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Query(query): Query<ListUsersQuery>,
+    sparse: SparseFields<User>,
+) -> ApiResult<Vec<User>> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let mut links = Links::collection(nested.as_str());
+    let paged = query.cursor.is_some() || query.limit.is_some();
+    if paged && (query.sort.is_some() || query.order.is_some()) {
+        return Err(error(AppError::validation(vec![FieldError::new(
+            "sort",
+            "conflict",
+            "Only unpaged lists can be sorted",
+        )])));
     }
+    let users = if paged {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        let page = service
+            .list_users_page(&ctx, query.cursor.clone(), limit)
+            .await
+            .map_err(error)?;
+        links = Links::cursor_page(
+            nested.as_str(),
+            query.cursor.as_deref(),
+            page.prev,
+            page.next,
+            page.last,
+            limit,
+            &sparse.fields,
+        );
+        page.rows
+    } else {
+        service
+            .list_users(&ctx, query.sort, query.order)
+            .await
+            .map_err(error)?
+    };
+    Ok(ApiResponse::ok(ctx.correlation_id, users)
+        .message("Users fetched successfully")
+        .message_key("user.listed", [])
+        .links(links)
+        .fields(sparse.fields))
 }
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "User id (UUID)"), FieldsQuery),
+    responses(
+        (status = 200, description = "User found", body = Response<User>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid user id or fields", body = Response<Value>),
+        (status = 404, description = "User not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn get_user(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.user.get(&id).await {
-        Ok(user) => (
-            StatusCode::OK,
-            Json(json!(Response::<User> {
-                correlation_id,
-                message: "User fetched successfully".into(),
-                error: "".into(),
-                data: Some(user),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
-    }
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    sparse: SparseFields<User>,
+) -> ApiResult<User> {
+    let user = service
+        .get_user(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &user.id);
+    let args = [("id", user.id.clone()), ("email", user.email.clone())];
+    let version = user.version;
+    Ok(ApiResponse::ok(ctx.correlation_id, user)
+        .message("User fetched successfully")
+        .message_key("user.fetched", args)
+        .links(links)
+        .fields(sparse.fields)
+        .etag(version))
 }
 
-async fn update_user(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-    Json(payload): Json<UpdateUser>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.user.update(&id, payload).await {
-        Ok(user) => (
-            StatusCode::OK,
-            Json(json!(Response::<User> {
-                correlation_id,
-                message: "User updated successfully".into(),
-                error: "".into(),
-                data: Some(user),
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
+#[utoipa::path(
+    head,
+    path = "/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "User id (UUID)")),
+    responses(
+        (status = 200, description = "User exists"),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Internal error"),
+    )
+)]
+async fn head_user(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiResponse<()>> {
+    let exists = service
+        .user_exists(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    Ok(if exists {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/export.csv",
+    tag = "users",
+    responses(
+        (status = 200, description = "Every user as a CSV attachment, in page order", content_type = "text/csv", body = String),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn export_users_csv(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+) -> Result<impl IntoResponse, ApiResponse<()>> {
+    let fetch = {
+        let ctx = ctx.clone();
+        move |cursor| {
+            let (service, ctx) = (service.clone(), ctx.clone());
+            async move { service.list_users_page(&ctx, cursor, MAX_PAGE_LIMIT).await }
+        }
+    };
+    let columns = ["id", "email"];
+    csv_download(
+        &ctx,
+        "users.csv",
+        &columns,
+        |user: &User| csv_row(&[&user.id, &user.email]),
+        fetch,
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "users",
+    params(SearchUsersQuery, FieldsQuery),
+    responses(
+        (status = 200, description = "Users with a similar email, best first", body = Response<Vec<User>>),
+        (status = 400, description = "Missing query, invalid limit or unknown fields", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn search_users(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Query(query): Query<SearchUsersQuery>,
+    sparse: SparseFields<User>,
+) -> ApiResult<Vec<User>> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let users = service
+        .search_users(&ctx, query.q, query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .await
+        .map_err(error)?;
+    let links = Links::collection(&format!("{}/search", nested.as_str()));
+    Ok(ApiResponse::ok(ctx.correlation_id, users)
+        .links(links)
+        .fields(sparse.fields))
+}
+
+#[utoipa::path(
+    get,
+    path = "/by-email/{email}",
+    tag = "users",
+    params(("email" = String, Path, description = "User email, matched case-insensitively"), FieldsQuery),
+    responses(
+        (status = 200, description = "User found", body = Response<User>),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid email or unknown fields", body = Response<Value>),
+        (status = 404, description = "User not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn get_user_by_email(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(email): Path<String>,
+    sparse: SparseFields<User>,
+) -> ApiResult<User> {
+    let user = service
+        .get_user_by_email(&ctx, &email)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &user.id);
+    let args = [("id", user.id.clone()), ("email", user.email.clone())];
+    let version = user.version;
+    Ok(ApiResponse::ok(ctx.correlation_id, user)
+        .message("User fetched successfully")
+        .message_key("user.fetched", args)
+        .links(links)
+        .fields(sparse.fields)
+        .etag(version))
+}
+
+#[utoipa::path(
+    put,
+    path = "/{id}",
+    tag = "users",
+    params(
+        ("id" = String, Path, description = "User id (UUID)"),
+        ("If-Match" = Option<String>, Header, description = "`ETag` of a read, e.g. `\"3\"`, updates only while the user is at that version"),
+    ),
+    request_body(
+        description = "The new email, or a JSON Merge Patch or JSON Patch of the user",
+        content(
+            (UpdateUser = "application/json"),
+            (Value = "application/merge-patch+json"),
+            (Vec<PatchOp> = "application/json-patch+json"),
         ),
+    ),
+    responses(
+        (status = 200, description = "User updated", body = Response<User>),
+        (status = 400, description = "Invalid user id or email", body = Response<Value>),
+        (status = 404, description = "User not found", body = Response<Value>),
+        (status = 409, description = "Email taken by another user", body = Response<Value>),
+        (status = 412, description = "The user is no longer at the If-Match version, or the tag is weak", body = Response<Value>),
+        (status = 422, description = "A JSON Patch operation failed, its index is the error's field", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn update_user(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    IfMatch(version): IfMatch,
+    body: UpdateBody<UpdateUser>,
+) -> ApiResult<User> {
+    let user = match body {
+        UpdateBody::Replace(payload) => service.update_user(&ctx, &id, payload, version).await,
+        UpdateBody::Patch(patch) => service.patch_user(&ctx, &id, patch, version).await,
     }
+    .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &user.id);
+    let args = [("id", user.id.clone()), ("email", user.email.clone())];
+    Ok(ApiResponse::ok(ctx.correlation_id, user)
+        .message("User updated successfully")
+        .message_key("user.updated", args)
+        .links(links))
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/confirm-email",
+    tag = "users",
+    params(("id" = String, Path, description = "User id (UUID)")),
+    request_body = ConfirmEmail,
+    responses(
+        (status = 200, description = "Pending email change applied", body = Response<User>),
+        (status = 400, description = "Invalid user id or token", body = Response<Value>),
+        (status = 404, description = "User not found", body = Response<Value>),
+        (status = 409, description = "Email taken by another user", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn confirm_email(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    ValidatedJson(payload): ValidatedJson<ConfirmEmail>,
+) -> ApiResult<User> {
+    let user = service
+        .confirm_user_email(&ctx, &id, payload)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::resource(nested.as_str(), &user.id);
+    let args = [("id", user.id.clone()), ("email", user.email.clone())];
+    Ok(ApiResponse::ok(ctx.correlation_id, user)
+        .message("User email confirmed successfully")
+        .message_key("user.email_confirmed", args)
+        .links(links))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "users",
+    params(("id" = String, Path, description = "User id (UUID)"), DeleteQuery),
+    responses(
+        (status = 200, description = "User deleted, with it as data for `return=representation`", body = Response<Value>),
+        (status = 204, description = "User deleted, with `DELETE_NO_CONTENT`"),
+        (status = 400, description = "Invalid user id or return", body = Response<Value>),
+        (status = 404, description = "No user to return", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
 async fn delete_user(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    match state.service.user.delete(&id).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: "User deleted successfully".into(),
-                error: "".into(),
-                data: None,
-            })),
-        ),
-        Err(e) => (
-            e.get_http_status(),
-            Json(json!(Response::<serde_json::Value> {
-                correlation_id,
-                message: e.get_message(),
-                error: e.get_error(),
-                data: None,
-            })),
-        ),
-    }
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> ApiResult<User> {
+    let error = |e| ApiResponse::error(ctx.correlation_id.clone(), e);
+    let deleted = service.delete_user(&ctx, &id).await.map_err(error)?;
+    let message = "User deleted successfully";
+    let res = match query.returns {
+        Return::Minimal => ApiResponse::new(StatusCode::OK, ctx.correlation_id, message),
+        Return::Representation => {
+            let user = deleted.ok_or_else(|| error(user_not_found(&id)))?;
+            ApiResponse::ok(ctx.correlation_id, user).message(message)
+        }
+    };
+    Ok(res.message_key("user.deleted", [("id", id)]))
 }
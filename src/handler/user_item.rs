@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, NestedPath, State};
+use serde_json::Value;
+use utoipa::OpenApi;
+
+use super::{ITEMS_PATH, item::CreateItem, version::ApiMount};
+use crate::{
+    extract::{FieldsQuery, Path, SparseFields, ValidatedJson},
+    model::{
+        context::Ctx,
+        http::{ApiResponse, ApiResult, Links, Response},
+        item::Item,
+    },
+    service::ServiceApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(list_user_items, create_user_item))]
+pub struct UserItemApi;
+
+/// Routes of the items a user owns, merged into the user routes.
+pub fn router_setup_user_items<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<dyn ServiceApi>: FromRef<S>,
+{
+    axum::Router::new().route(
+        "/{id}/items",
+        axum::routing::get(list_user_items).post(create_user_item),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/items",
+    tag = "users",
+    params(("id" = String, Path, description = "User id (UUID)"), FieldsQuery),
+    responses(
+        (status = 200, description = "Items owned by the user, by name", body = Response<Vec<Item>>),
+        (status = 400, description = "Invalid user id or fields", body = Response<Value>),
+        (status = 404, description = "User not found", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn list_user_items(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    nested: NestedPath,
+    Path(id): Path<String>,
+    sparse: SparseFields<Item>,
+) -> ApiResult<Vec<Item>> {
+    let items = service
+        .list_user_items(&ctx, &id)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    let links = Links::collection(&format!("{}/{}/items", nested.as_str(), id));
+    Ok(ApiResponse::ok(ctx.correlation_id, items)
+        .links(links)
+        .fields(sparse.fields))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/items",
+    tag = "users",
+    params(("id" = String, Path, description = "User id (UUID)")),
+    request_body = CreateItem,
+    responses(
+        (status = 201, description = "Item created for the user", body = Response<Item>),
+        (status = 400, description = "Invalid user id or item name", body = Response<Value>),
+        (status = 404, description = "User not found", body = Response<Value>),
+        (status = 409, description = "Name taken by another item", body = Response<Value>),
+        (status = 500, description = "Internal error", body = Response<Value>),
+    )
+)]
+async fn create_user_item(
+    State(service): State<Arc<dyn ServiceApi>>,
+    ctx: Ctx,
+    mount: ApiMount,
+    Path(id): Path<String>,
+    ValidatedJson(payload): ValidatedJson<CreateItem>,
+) -> ApiResult<Item> {
+    let item = service
+        .create_user_item(&ctx, &id, payload.name)
+        .await
+        .map_err(|e| ApiResponse::error(ctx.correlation_id.clone(), e))?;
+    // The item lives under the item routes, not the user's.
+    let links = Links::resource(&mount.path(ITEMS_PATH), &item.id);
+    let message = format!("Created item '{}'", item.name);
+    let args = [("id", item.id.clone()), ("name", item.name.clone())];
+    Ok(ApiResponse::created(ctx.correlation_id, item, message)
+        .message_key("item.created", args)
+        .links(links))
+}
@@ -0,0 +1,357 @@
+//! API versions. Each is served under its own prefix, `/api/v1` and
+//! `/api/v2`, with the bare `/api` kept as an alias of v1 for clients that
+//! predate versioning. Requests to the alias may pick another version with
+//! an `Api-Version: 2` header or by accepting `application/vnd.crud.v2+json`.
+
+use std::{
+    convert::Infallible,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use axum::{
+    extract::{FromRef, FromRequestParts, Request, State},
+    http::{
+        HeaderMap, HeaderValue,
+        header::{ACCEPT, VARY},
+        request::Parts,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::{OpenApi, ToSchema};
+
+use super::{API_PREFIX, API_V1_PREFIX, API_V2_PREFIX};
+use crate::{
+    config::Config,
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode, ErrorCode},
+        http::{ApiResponse, ApiResult, Response as Envelope},
+    },
+};
+
+pub const API_VERSION_HEADER: &str = "Api-Version";
+pub const DEPRECATION_HEADER: &str = "Deprecation";
+pub const SUNSET_HEADER: &str = "Sunset";
+/// Vendor media type naming a version, e.g. `application/vnd.crud.v2+json`.
+const VENDOR_MEDIA_TYPE: (&str, &str) = ("application/vnd.crud.", "+json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// Current response shapes.
+    V1,
+    /// Preview of the next breaking release, errors are always Problem Details.
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: [Self; 2] = [Self::V1, Self::V2];
+
+    /// Accepts `v2` as well as `2`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.strip_prefix('v').unwrap_or(&value) {
+            "1" => Some(Self::V1),
+            "2" => Some(Self::V2),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+            Self::V2 => "v2",
+        }
+    }
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::V1 => API_V1_PREFIX,
+            Self::V2 => API_V2_PREFIX,
+        }
+    }
+}
+
+/// Version and prefix of the router a request went through, so handlers can
+/// link to resources outside their own router under the same prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiMount {
+    pub version: ApiVersion,
+    pub prefix: &'static str,
+}
+
+impl Default for ApiMount {
+    fn default() -> Self {
+        Self::new(ApiVersion::V1, API_PREFIX)
+    }
+}
+
+impl ApiMount {
+    pub const fn new(version: ApiVersion, prefix: &'static str) -> Self {
+        Self { version, prefix }
+    }
+
+    /// The canonical mount of `version`.
+    pub fn of(version: ApiVersion) -> Self {
+        Self::new(version, version.prefix())
+    }
+
+    /// `path` under this mount's prefix, e.g. `/api/v1/items`.
+    pub fn path(&self, path: &str) -> String {
+        format!("{}{}", self.prefix, path)
+    }
+}
+
+/// Set by [`version_middleware`], routers mounted without it count as the
+/// unversioned v1 alias.
+impl<S: Send + Sync> FromRequestParts<S> for ApiMount {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().copied().unwrap_or_default())
+    }
+}
+
+/// Which versions are served or deprecated, and how often each is requested.
+#[derive(Debug, Default)]
+pub struct ApiVersions {
+    v2_enabled: bool,
+    deprecated: Vec<ApiVersion>,
+    sunset: Option<HeaderValue>,
+    requests: [AtomicU64; ApiVersion::ALL.len()],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct VersionUsage {
+    pub version: String,
+    pub enabled: bool,
+    pub deprecated: bool,
+    /// Requests served since the process started.
+    pub requests: u64,
+}
+
+impl ApiVersions {
+    pub fn from_config(config: &Config) -> Result<Self, AppError> {
+        let invalid = |message: String| AppError {
+            code: AppErrorCode::InvalidInput,
+            message,
+            error_code: None,
+        };
+        let deprecated = config
+            .api_deprecated_versions
+            .iter()
+            .map(|v| {
+                ApiVersion::parse(v).ok_or_else(|| invalid(format!("Unknown API version '{}'", v)))
+            })
+            .collect::<Result<_, _>>()?;
+        let sunset = match &config.api_sunset {
+            Some(date) => Some(
+                HeaderValue::from_str(date)
+                    .map_err(|_| invalid(format!("Invalid API sunset date '{}'", date)))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            v2_enabled: config.api_v2_enabled,
+            deprecated,
+            sunset,
+            ..Default::default()
+        })
+    }
+
+    pub fn is_enabled(&self, version: ApiVersion) -> bool {
+        version == ApiVersion::V1 || self.v2_enabled
+    }
+
+    pub fn is_deprecated(&self, version: ApiVersion) -> bool {
+        self.deprecated.contains(&version)
+    }
+
+    pub fn usage(&self) -> Vec<VersionUsage> {
+        ApiVersion::ALL
+            .into_iter()
+            .map(|version| VersionUsage {
+                version: version.as_str().into(),
+                enabled: self.is_enabled(version),
+                deprecated: self.is_deprecated(version),
+                requests: self.requests[version as usize].load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    fn record(&self, version: ApiVersion) {
+        self.requests[version as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Versioned prefixes serve their own version, the unversioned alias the
+    /// one asked for by `Api-Version` or the vendor media type, else v1.
+    fn negotiate(&self, mount: ApiMount, headers: &HeaderMap) -> Result<ApiVersion, AppError> {
+        if mount.prefix != API_PREFIX {
+            return Ok(mount.version);
+        }
+        let requested = match headers.get(API_VERSION_HEADER) {
+            Some(value) => Some(value.to_str().unwrap_or_default().to_string()),
+            None => headers
+                .get_all(ACCEPT)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .filter_map(|media_type| {
+                    let media_type = media_type.split(';').next().unwrap_or_default().trim();
+                    media_type
+                        .strip_prefix(VENDOR_MEDIA_TYPE.0)?
+                        .strip_suffix(VENDOR_MEDIA_TYPE.1)
+                        .map(String::from)
+                })
+                .next(),
+        };
+        let Some(requested) = requested else {
+            return Ok(mount.version);
+        };
+        match ApiVersion::parse(&requested) {
+            Some(version) if self.is_enabled(version) => Ok(version),
+            _ => Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                message: format!("Unsupported API version '{}'", requested),
+                error_code: Some(ErrorCode::UnsupportedApiVersion),
+            }),
+        }
+    }
+}
+
+/// Resolves the version of the request, counts it and tags the request and
+/// its response with the mount. The response copy lets outer middleware such
+/// as Problem Details adapt to the version. Deprecated versions are logged
+/// and answered with `Deprecation` and, when configured, `Sunset` headers.
+pub async fn version_middleware(
+    State((mount, versions)): State<(ApiMount, Arc<ApiVersions>)>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let mount = match versions.negotiate(mount, req.headers()) {
+        Ok(version) => ApiMount { version, ..mount },
+        Err(e) => {
+            let correlation_id = req
+                .extensions()
+                .get::<Ctx>()
+                .map(|ctx| ctx.correlation_id.clone())
+                .unwrap_or_default();
+            return ApiResponse::<()>::error(correlation_id, e).into_response();
+        }
+    };
+    versions.record(mount.version);
+    let deprecated = versions.is_deprecated(mount.version);
+    if deprecated {
+        tracing::warn!(
+            version = mount.version.as_str(),
+            path = %req.uri().path(),
+            "Deprecated API version requested"
+        );
+    }
+
+    req.extensions_mut().insert(mount);
+    let mut res = next.run(req).await;
+    res.extensions_mut().insert(mount);
+    let headers = res.headers_mut();
+    headers.insert(
+        API_VERSION_HEADER,
+        HeaderValue::from_static(mount.version.as_str()),
+    );
+    if mount.prefix == API_PREFIX {
+        headers.append(VARY, HeaderValue::from_static("Api-Version, Accept"));
+    }
+    if deprecated {
+        headers.insert(DEPRECATION_HEADER, HeaderValue::from_static("true"));
+        if let Some(sunset) = &versions.sunset {
+            headers.insert(SUNSET_HEADER, sunset.clone());
+        }
+    }
+    res
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(list_versions))]
+pub struct VersionApi;
+
+/// Usage of each API version, to tell when a deprecated one can be retired.
+pub fn router_setup_versions<S>() -> axum::Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<ApiVersions>: FromRef<S>,
+{
+    axum::Router::new().route("/", axum::routing::get(list_versions))
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "admin",
+    responses((status = 200, description = "Requests served per API version", body = Envelope<Vec<VersionUsage>>)),
+)]
+async fn list_versions(
+    State(versions): State<Arc<ApiVersions>>,
+    ctx: Ctx,
+) -> ApiResult<Vec<VersionUsage>> {
+    Ok(ApiResponse::ok(ctx.correlation_id, versions.usage()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header::HeaderName;
+
+    use super::*;
+
+    fn headers(name: HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let versions = ApiVersions::from_config(&Config {
+            api_v2_enabled: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let alias = ApiMount::default();
+        let version_header = HeaderName::from_static("api-version");
+
+        let negotiate = |mount, headers| versions.negotiate(mount, &headers).ok();
+        assert_eq!(negotiate(alias, HeaderMap::new()), Some(ApiVersion::V1));
+        assert_eq!(
+            negotiate(alias, headers(version_header.clone(), "2")),
+            Some(ApiVersion::V2)
+        );
+        assert_eq!(
+            negotiate(alias, headers(ACCEPT, "application/vnd.crud.v2+json; q=1")),
+            Some(ApiVersion::V2)
+        );
+        assert_eq!(
+            negotiate(alias, headers(version_header.clone(), "v3")),
+            None
+        );
+        // Explicit prefixes are not overridden.
+        let v1 = ApiMount::of(ApiVersion::V1);
+        assert_eq!(
+            negotiate(v1, headers(version_header, "2")),
+            Some(ApiVersion::V1)
+        );
+    }
+
+    #[test]
+    fn test_disabled_version_is_unsupported() {
+        let versions = ApiVersions::default();
+        let err = versions
+            .negotiate(
+                ApiMount::default(),
+                &headers(HeaderName::from_static("api-version"), "v2"),
+            )
+            .unwrap_err();
+        assert_eq!(err.get_error_code(), ErrorCode::UnsupportedApiVersion);
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use tokio::fs;
+
+use crate::{
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode, ErrorCode, FieldError},
+        import::{ImportResult, ImportRowError},
+        job::Job,
+    },
+    repository::erased::DynJobRepository,
+    service::ServiceApi,
+    worker::JobHandler,
+};
+
+/// Job kind creating an item per row of an uploaded CSV file, handled by
+/// [`ItemImportHandler`].
+pub const IMPORT_ITEMS_JOB: &str = "import.items";
+
+/// Rows processed between two checkpoints.
+const BATCH_SIZE: u64 = 100;
+
+/// Row failures kept on the job, the rest are only counted.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// Where the upload of import job `id` is kept until it is processed.
+pub fn import_path(dir: impl AsRef<Path>, id: &str) -> PathBuf {
+    dir.as_ref().join(format!("{}.csv", id))
+}
+
+/// Position of the `name` column, the only one an item import needs.
+pub fn name_column(data: &[u8]) -> Result<usize, AppError> {
+    let mut reader = csv::Reader::from_reader(data);
+    let headers = reader.headers().map_err(|e| {
+        AppError::validation(vec![FieldError::new(
+            "file",
+            "invalid",
+            format!("Invalid CSV header: {}", e),
+        )])
+    })?;
+    headers
+        .iter()
+        .position(|header| header.trim().eq_ignore_ascii_case("name"))
+        .ok_or_else(|| {
+            AppError::validation(vec![FieldError::new(
+                "file",
+                "invalid",
+                "CSV header needs a 'name' column",
+            )])
+        })
+}
+
+/// Whether a failure belongs to the row, the import then moves on, rather
+/// than to the infrastructure, where the job is retried from its checkpoint.
+fn is_row_error(e: &AppError) -> bool {
+    matches!(
+        e.code,
+        AppErrorCode::InvalidInput | AppErrorCode::Validation(_) | AppErrorCode::Conflict
+    )
+}
+
+/// The field messages of a validation error, which say more than its summary.
+fn row_message(e: &AppError) -> String {
+    let fields = e.get_field_errors();
+    if fields.is_empty() {
+        return e.get_message();
+    }
+    fields
+        .into_iter()
+        .map(|field| field.message)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Creates the items of an uploaded CSV through the service, so they are
+/// validated and announced like any other. Progress and row errors are
+/// checkpointed every [`BATCH_SIZE`] rows.
+///
+/// A run that dies between two checkpoints has created some of the rows
+/// after the last one. The next run replays them, and a taken name within
+/// that batch counts as already imported rather than as a failure.
+pub struct ItemImportHandler {
+    service: Arc<dyn ServiceApi>,
+    jobs: Arc<dyn DynJobRepository>,
+    dir: PathBuf,
+}
+
+impl ItemImportHandler {
+    pub fn new(
+        service: Arc<dyn ServiceApi>,
+        jobs: Arc<dyn DynJobRepository>,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            service,
+            jobs,
+            dir: dir.into(),
+        }
+    }
+
+    async fn checkpoint(
+        &self,
+        job: &Job,
+        result: &ImportResult,
+        total: u64,
+    ) -> Result<(), AppError> {
+        let progress = (result.rows_processed * 100 / total.max(1)) as i32;
+        let result = serde_json::to_value(result).ok();
+        self.jobs.progress(&job.id, progress, result).await
+    }
+
+    async fn import_row(
+        &self,
+        ctx: &Ctx,
+        record: csv::Result<csv::StringRecord>,
+        column: usize,
+    ) -> Result<(), AppError> {
+        let record = record.map_err(|e| AppError {
+            code: AppErrorCode::InvalidInput,
+            message: format!("Invalid CSV row: {}", e),
+            error_code: None,
+        })?;
+        let name = record.get(column).unwrap_or_default();
+        self.service
+            .create_item(ctx, name.to_string())
+            .await
+            .map(|_| ())
+    }
+}
+
+#[async_trait]
+impl JobHandler for ItemImportHandler {
+    async fn handle(&self, job: &Job) -> Result<(), AppError> {
+        let path = import_path(&self.dir, &job.id);
+        let data = fs::read(&path).await.map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: format!("Import file of job {} is missing", job.id),
+            error_code: None,
+        })?;
+        let column = name_column(&data)?;
+        let records: Vec<_> = csv::Reader::from_reader(&data[..]).into_records().collect();
+        let total = records.len() as u64;
+
+        let ctx = Ctx::new(job.id.clone());
+        // Every run checkpoints before its first row, so a stored result
+        // means an earlier run may have got past the checkpoint.
+        let resumed = job.result.is_some();
+        let mut result: ImportResult = job
+            .result
+            .clone()
+            .and_then(|result| serde_json::from_value(result).ok())
+            .unwrap_or_default();
+        self.checkpoint(job, &result, total).await?;
+        let done = result.rows_processed as usize;
+        let replayed = if resumed {
+            done + BATCH_SIZE as usize
+        } else {
+            done
+        };
+        for (i, record) in records.into_iter().enumerate().skip(done) {
+            match self.import_row(&ctx, record, column).await {
+                Ok(()) => {}
+                Err(e) if i < replayed && e.error_code == Some(ErrorCode::NameTaken) => {}
+                Err(e) if is_row_error(&e) => {
+                    result.rows_failed += 1;
+                    if result.errors.len() < MAX_REPORTED_ERRORS {
+                        result.errors.push(ImportRowError {
+                            row: i as u64 + 1,
+                            message: row_message(&e),
+                        });
+                    }
+                }
+                Err(e) => {
+                    self.checkpoint(job, &result, total).await?;
+                    return Err(e);
+                }
+            }
+            result.rows_processed += 1;
+            if result.rows_processed.is_multiple_of(BATCH_SIZE) {
+                self.checkpoint(job, &result, total).await?;
+            }
+        }
+        self.checkpoint(job, &result, total).await?;
+        tracing::info!(
+            job_id = %job.id,
+            rows_processed = result.rows_processed,
+            rows_failed = result.rows_failed,
+            "Import finished"
+        );
+        fs::remove_file(&path).await.ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        model::{
+            import::ImportJob,
+            item::{Item, slugify},
+            job::NewJob,
+        },
+        repository::job::InMemoryJobRepository,
+        service::{item::ItemNameRules, registry::MockServiceApi},
+    };
+
+    async fn setup(
+        csv: &str,
+        result: Option<ImportResult>,
+    ) -> (Arc<InMemoryJobRepository>, Job, PathBuf) {
+        let jobs = Arc::new(InMemoryJobRepository::new());
+        let mut job = Job::from(NewJob::new(IMPORT_ITEMS_JOB, json!({})));
+        job.result = result.map(|result| serde_json::to_value(result).unwrap());
+        let job = jobs.add(job).await.unwrap();
+        let dir = std::env::temp_dir().join(format!("crud-import-{}", job.id));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(import_path(&dir, &job.id), csv).unwrap();
+        (jobs, job, dir)
+    }
+
+    /// Creates items under the default name rules, the names in `existing`
+    /// are taken.
+    fn created(names: Arc<std::sync::Mutex<Vec<String>>>, existing: &[&str]) -> MockServiceApi {
+        let rules = ItemNameRules::default();
+        let existing: Vec<String> = existing.iter().map(|name| name.to_string()).collect();
+        let mut service = MockServiceApi::new();
+        service.expect_create_item().returning(move |_, name| {
+            let name = rules.normalize(&name);
+            if let Some(error) = rules.check(&name) {
+                return Box::pin(async move { Err(AppError::validation(vec![error])) });
+            }
+            if existing.contains(&name) {
+                return Box::pin(async move { Err(taken(&name)) });
+            }
+            names.lock().unwrap().push(name.clone());
+            Box::pin(async move {
+                Ok(Item {
+                    id: "1".into(),
+                    slug: slugify(&name),
+                    name,
+                    owner_id: None,
+                    version: 1,
+                })
+            })
+        });
+        service
+    }
+
+    fn taken(name: &str) -> AppError {
+        AppError {
+            code: AppErrorCode::Conflict,
+            message: format!("Item name {} is taken", name),
+            error_code: Some(ErrorCode::NameTaken),
+        }
+    }
+
+    #[test]
+    fn test_name_column() {
+        assert_eq!(name_column(b"id,Name\n1,book\n").unwrap(), 1);
+        assert!(name_column(b"id,title\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_imports_rows_and_reports_failures() {
+        let (jobs, job, dir) = setup("name\nbook\n\npen\n\" \"\n", None).await;
+        let names = Arc::new(std::sync::Mutex::new(vec![]));
+        let handler = ItemImportHandler::new(
+            Arc::new(created(names.clone(), &["pen"])),
+            jobs.clone(),
+            &dir,
+        );
+
+        handler.handle(&job).await.unwrap();
+
+        assert_eq!(*names.lock().unwrap(), vec!["book"]);
+        let stored = ImportJob::from(jobs.get(&job.id).await.unwrap());
+        assert_eq!(stored.progress, 100);
+        assert_eq!(stored.result.rows_processed, 3);
+        assert_eq!(stored.result.rows_failed, 2);
+        assert_eq!(stored.result.errors[0].row, 2);
+        assert_eq!(stored.result.errors[0].message, "Item name pen is taken");
+        assert_eq!(stored.result.errors[1].row, 3);
+        assert_eq!(stored.result.errors[1].message, "Item name cannot be empty");
+        assert!(!import_path(&dir, &job.id).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resumes_after_checkpoint() {
+        let checkpoint = ImportResult {
+            rows_processed: 1,
+            ..Default::default()
+        };
+        let (jobs, job, dir) = setup("name\nbook\npen\n", Some(checkpoint)).await;
+        let names = Arc::new(std::sync::Mutex::new(vec![]));
+        let handler = ItemImportHandler::new(Arc::new(created(names.clone(), &[])), jobs, &dir);
+
+        handler.handle(&job).await.unwrap();
+
+        assert_eq!(*names.lock().unwrap(), vec!["pen"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replayed_rows_already_created_are_not_failures() {
+        let checkpoint = ImportResult {
+            rows_processed: 1,
+            ..Default::default()
+        };
+        // The crashed run created "pen" after checkpointing "book".
+        let (jobs, job, dir) = setup(
+            "name
+book
+pen
+cup
+",
+            Some(checkpoint),
+        )
+        .await;
+        let names = Arc::new(std::sync::Mutex::new(vec![]));
+        let handler = ItemImportHandler::new(
+            Arc::new(created(names.clone(), &["book", "pen"])),
+            jobs.clone(),
+            &dir,
+        );
+
+        handler.handle(&job).await.unwrap();
+
+        assert_eq!(*names.lock().unwrap(), vec!["cup"]);
+        let stored = ImportJob::from(jobs.get(&job.id).await.unwrap());
+        assert_eq!(stored.result.rows_processed, 3);
+        assert_eq!(stored.result.rows_failed, 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_service_fails_job_with_checkpoint() {
+        let (jobs, job, dir) = setup("name\nbook\n", None).await;
+        let mut service = MockServiceApi::new();
+        service.expect_create_item().returning(|_, _| {
+            Box::pin(async {
+                Err(AppError {
+                    code: AppErrorCode::Unavailable,
+                    message: "Database unavailable".to_string(),
+                    error_code: None,
+                })
+            })
+        });
+        let handler = ItemImportHandler::new(Arc::new(service), jobs.clone(), &dir);
+
+        assert!(handler.handle(&job).await.is_err());
+        let stored = jobs.get(&job.id).await.unwrap();
+        assert_eq!(
+            stored.result,
+            Some(json!({"rows_processed": 0, "rows_failed": 0, "errors": []}))
+        );
+        assert!(import_path(&dir, &job.id).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
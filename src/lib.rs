@@ -1,7 +1,38 @@
+// Lets `crud_resource` expansions name `::crud_rust` from inside this crate too.
+extern crate self as crud_rust;
+
+pub use crud_rust_macros::crud_resource;
+
+pub mod app;
+pub mod cache;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod config;
+pub mod container;
+pub mod event;
+pub mod export;
+pub mod extract;
 pub mod handler;
+pub mod import;
+pub mod limit;
+pub mod mail;
+pub mod messages;
 pub mod middleware;
+pub mod mirror;
 pub mod model;
+pub mod notify;
+pub mod openapi;
+pub mod patch;
+pub mod proxy;
 pub mod repository;
+pub mod scaffold;
+pub mod schema;
+pub mod search;
+pub mod server;
 pub mod service;
 pub mod state;
+pub mod storage;
+pub mod testing;
+pub mod thumbnail;
+pub mod timeout;
+pub mod worker;
@@ -0,0 +1,390 @@
+//! Per client limits: a cap on the requests in flight at once, so one
+//! misbehaving integrator can't hold every database connection, and a
+//! request budget per time window. Clients are told apart by their API key
+//! (`X-Api-Key`, or a bearer token), then by peer address.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{
+        HeaderMap, HeaderValue,
+        header::{AUTHORIZATION, RETRY_AFTER},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+        http::ApiResponse,
+    },
+    proxy::ClientIp,
+};
+
+pub const X_API_KEY: &str = "X-Api-Key";
+pub const X_RATELIMIT_LIMIT: &str = "X-RateLimit-Limit";
+pub const X_RATELIMIT_REMAINING: &str = "X-RateLimit-Remaining";
+/// Seconds until the current window ends and the budget is full again.
+pub const X_RATELIMIT_RESET: &str = "X-RateLimit-Reset";
+
+/// Identifies the caller, clients without a key or a known address share
+/// the `anonymous` slot.
+pub fn client_key(headers: &HeaderMap, ip: Option<IpAddr>) -> String {
+    let api_key = headers
+        .get(X_API_KEY)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    match (api_key, ip) {
+        (Some(key), _) => format!("key:{}", key),
+        (None, Some(ip)) => format!("ip:{}", ip),
+        (None, None) => "anonymous".to_string(),
+    }
+}
+
+/// In-flight requests per client, a `max` of 0 lets everything through.
+#[derive(Debug, Default)]
+pub struct ConcurrencyLimiter {
+    max: usize,
+    in_flight: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+/// Slot of an in-flight request, given back when dropped.
+#[derive(Debug)]
+pub struct Permit {
+    key: String,
+    in_flight: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let Ok(mut in_flight) = self.in_flight.lock() else {
+            return;
+        };
+        if let Some(count) = in_flight.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max > 0
+    }
+
+    /// A slot for `key`, `None` when the client already uses all of its own.
+    pub fn try_acquire(&self, key: &str) -> Option<Permit> {
+        let mut in_flight = self.in_flight.lock().ok()?;
+        let count = in_flight.entry(key.to_string()).or_default();
+        if *count >= self.max {
+            return None;
+        }
+        *count += 1;
+        Some(Permit {
+            key: key.to_string(),
+            in_flight: self.in_flight.clone(),
+        })
+    }
+
+    pub fn in_flight(&self, key: &str) -> usize {
+        self.in_flight
+            .lock()
+            .map(|in_flight| in_flight.get(key).copied().unwrap_or_default())
+            .unwrap_or_default()
+    }
+}
+
+/// Keys by the address [`crate::proxy::client_ip_middleware`] resolved, the connection's
+/// one is a proxy's when behind one.
+fn request_client_key(req: &Request) -> String {
+    let ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ip| ip.0)
+        .or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|info| info.0.ip())
+        });
+    client_key(req.headers(), ip)
+}
+
+fn too_many_requests(req: &Request, message: &str) -> Response {
+    let correlation_id = req
+        .extensions()
+        .get::<Ctx>()
+        .map(|ctx| ctx.correlation_id.clone())
+        .unwrap_or_default();
+    let e = AppError {
+        code: AppErrorCode::TooManyRequests,
+        message: message.to_string(),
+        error_code: None,
+    };
+    ApiResponse::<()>::error(correlation_id, e).into_response()
+}
+
+/// Answers `429` while the client already has its share of requests in
+/// flight. The slot is held until the handler returns, streamed bodies
+/// don't keep it.
+pub async fn concurrency_middleware(
+    State(limiter): State<Arc<ConcurrencyLimiter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !limiter.is_enabled() {
+        return next.run(req).await;
+    }
+    let key = request_client_key(&req);
+    let Some(_permit) = limiter.try_acquire(&key) else {
+        return too_many_requests(&req, "Too many concurrent requests");
+    };
+    next.run(req).await
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    requests: u64,
+}
+
+/// Where a client stands in its current window, after counting a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset: Duration,
+}
+
+impl RateLimitStatus {
+    /// `X-RateLimit-*` headers, plus `Retry-After` once the budget is spent.
+    pub fn headers(&self) -> HeaderMap {
+        let reset = self.reset.as_secs_f64().ceil() as u64;
+        let mut headers = HeaderMap::new();
+        headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(self.limit));
+        headers.insert(X_RATELIMIT_REMAINING, HeaderValue::from(self.remaining));
+        headers.insert(X_RATELIMIT_RESET, HeaderValue::from(reset));
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, HeaderValue::from(reset));
+        }
+        headers
+    }
+}
+
+/// Fixed window budget of `limit` requests per client, a `limit` of 0 lets
+/// everything through.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: u64,
+    window: Duration,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u64, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.limit > 0 && !self.window.is_zero()
+    }
+
+    /// Counts a request of `key`, rejected ones included.
+    pub fn check(&self, key: &str) -> RateLimitStatus {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> RateLimitStatus {
+        let Ok(mut windows) = self.windows.lock() else {
+            return RateLimitStatus {
+                allowed: true,
+                limit: self.limit,
+                remaining: self.limit,
+                reset: self.window,
+            };
+        };
+        if !windows.contains_key(key) {
+            // Forget clients whose window is over before tracking a new one.
+            windows.retain(|_, w| now.duration_since(w.started) < self.window);
+        }
+        let window = windows.entry(key.to_string()).or_insert(Window {
+            started: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            *window = Window {
+                started: now,
+                requests: 0,
+            };
+        }
+        window.requests += 1;
+        RateLimitStatus {
+            allowed: window.requests <= self.limit,
+            limit: self.limit,
+            remaining: self.limit.saturating_sub(window.requests),
+            reset: self.window - now.duration_since(window.started),
+        }
+    }
+}
+
+/// Answers `429` once the client has spent its budget for the window, and
+/// tells every client where it stands through the `X-RateLimit-*` headers.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !limiter.is_enabled() {
+        return next.run(req).await;
+    }
+    let status = limiter.check(&request_client_key(&req));
+    let mut res = if status.allowed {
+        next.run(req).await
+    } else {
+        too_many_requests(&req, "Rate limit exceeded")
+    };
+    res.headers_mut().extend(status.headers());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::{HeaderValue, StatusCode},
+        middleware::from_fn_with_state,
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_client_key() {
+        let addr = Some(IpAddr::from([10, 0, 0, 1]));
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_key(&headers, None), "anonymous");
+        assert_eq!(client_key(&headers, addr), "ip:10.0.0.1");
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        assert_eq!(client_key(&headers, addr), "key:abc");
+        headers.insert(X_API_KEY, HeaderValue::from_static("xyz"));
+        assert_eq!(client_key(&headers, addr), "key:xyz");
+    }
+
+    #[test]
+    fn test_permits_are_given_back() {
+        let limiter = ConcurrencyLimiter::new(2);
+        let a = limiter.try_acquire("a").unwrap();
+        let _b = limiter.try_acquire("a").unwrap();
+        assert!(limiter.try_acquire("a").is_none());
+        // Other clients have their own slots.
+        assert!(limiter.try_acquire("b").is_some());
+        drop(a);
+        assert_eq!(limiter.in_flight("a"), 1);
+        assert!(limiter.try_acquire("a").is_some());
+    }
+
+    fn send(app: &Router, key: &str) -> impl Future<Output = StatusCode> + use<> {
+        let req = Request::get("/slow")
+            .header(X_API_KEY, key)
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move { app.oneshot(req).await.unwrap().status() }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_past_the_limit() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(1));
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }),
+            )
+            .layer(from_fn_with_state(limiter.clone(), concurrency_middleware));
+
+        let first = tokio::spawn(send(&app, "a"));
+        while limiter.in_flight("key:a") == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(send(&app, "a").await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(send(&app, "b").await, StatusCode::OK);
+        assert_eq!(first.await.unwrap(), StatusCode::OK);
+        assert_eq!(send(&app, "a").await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_rate_limit_windows() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        let first = limiter.check_at("a", start);
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+        assert_eq!(first.reset, Duration::from_secs(60));
+        assert!(limiter.check_at("a", start).allowed);
+        let third = limiter.check_at("a", start + Duration::from_secs(10));
+        assert!(!third.allowed);
+        assert_eq!(third.remaining, 0);
+        assert_eq!(third.reset, Duration::from_secs(50));
+        assert!(limiter.check_at("b", start).allowed);
+        // A new window starts with the full budget.
+        let next = limiter.check_at("a", start + Duration::from_secs(60));
+        assert!(next.allowed);
+        assert_eq!(next.remaining, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers() {
+        let limiter = Arc::new(RateLimiter::new(1, Duration::from_secs(60)));
+        let app = Router::new()
+            .route("/", get(|| async {}))
+            .layer(from_fn_with_state(limiter, rate_limit_middleware));
+        let send = || {
+            let req = Request::get("/").body(Body::empty()).unwrap();
+            app.clone().oneshot(req)
+        };
+
+        let res = send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[X_RATELIMIT_LIMIT], "1");
+        assert_eq!(res.headers()[X_RATELIMIT_REMAINING], "0");
+        assert_eq!(res.headers()[X_RATELIMIT_RESET], "60");
+        assert!(res.headers().get(RETRY_AFTER).is_none());
+
+        let res = send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[X_RATELIMIT_REMAINING], "0");
+        assert_eq!(res.headers()[RETRY_AFTER], res.headers()[X_RATELIMIT_RESET]);
+    }
+}
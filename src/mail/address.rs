@@ -0,0 +1,193 @@
+//! Email address checks shared by the user payloads and the user service.
+
+use std::{collections::HashSet, sync::OnceLock};
+
+use email_address::{EmailAddress, Options};
+use hickory_resolver::TokioResolver;
+use validator::ValidationError;
+
+use crate::{config::Config, model::error::FieldError};
+
+/// Field error code for addresses that don't parse.
+pub const INVALID_EMAIL: &str = "invalid_email";
+/// Field error code for domains that can't receive mail.
+pub const UNDELIVERABLE_EMAIL: &str = "undeliverable_email";
+/// Field error code for domains refused by the [`DomainPolicy`].
+pub const EMAIL_DOMAIN_NOT_ALLOWED: &str = "email_domain_not_allowed";
+
+const DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+
+/// Parses an RFC 5322 address. Display names and domain literals are
+/// rejected and the domain needs a TLD, `a@localhost` is refused.
+pub fn parse(address: &str) -> Result<EmailAddress, FieldError> {
+    let options = Options::default()
+        .without_display_text()
+        .without_domain_literal()
+        .with_required_tld();
+    EmailAddress::parse_with_options(address, options)
+        .map_err(|_| FieldError::new("email", INVALID_EMAIL, "Email is invalid"))
+}
+
+/// `validator` custom rule running [`parse`].
+pub fn validate(address: &str) -> Result<(), ValidationError> {
+    parse(address)
+        .map(|_| ())
+        .map_err(|e| ValidationError::new(INVALID_EMAIL).with_message(e.message.into()))
+}
+
+/// Which domains user emails may be at. Entries match the domain itself and
+/// its subdomains, case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct DomainPolicy {
+    /// Empty allows every domain.
+    pub allow: Vec<String>,
+    pub block: Vec<String>,
+    pub block_disposable: bool,
+}
+
+impl DomainPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        let domains = |list: &[String]| {
+            list.iter()
+                .map(|d| d.trim_start_matches('@').to_ascii_lowercase())
+                .collect()
+        };
+        Self {
+            allow: domains(&config.email_domain_allowlist),
+            block: domains(&config.email_domain_blocklist),
+            block_disposable: config.email_block_disposable,
+        }
+    }
+
+    pub fn check(&self, address: &EmailAddress) -> Result<(), FieldError> {
+        let domain = address.domain().to_ascii_lowercase();
+        let allowed = (self.allow.is_empty() || self.allow.iter().any(|d| matches(&domain, d)))
+            && !self.block.iter().any(|d| matches(&domain, d))
+            && !(self.block_disposable && is_disposable(&domain));
+        if allowed {
+            return Ok(());
+        }
+        Err(FieldError::new(
+            "email",
+            EMAIL_DOMAIN_NOT_ALLOWED,
+            format!("Emails at {} are not allowed", address.domain()),
+        ))
+    }
+}
+
+/// `domain` is `entry` or one of its subdomains.
+fn matches(domain: &str, entry: &str) -> bool {
+    domain
+        .strip_suffix(entry)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+fn is_disposable(domain: &str) -> bool {
+    static DOMAINS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    let domains = DOMAINS.get_or_init(|| {
+        DISPOSABLE_DOMAINS
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect()
+    });
+    // The domain and each of its parents.
+    std::iter::successors(Some(domain), |d| {
+        d.split_once('.').map(|(_, parent)| parent)
+    })
+    .any(|d| domains.contains(d))
+}
+
+/// Checks the domain has an MX record, or an address record as the implicit
+/// MX of RFC 5321. Lookups failing for other reasons than a missing record
+/// let the address through, a DNS outage should not block signups.
+pub async fn verify_domain(address: &EmailAddress) -> Result<(), FieldError> {
+    let Some(resolver) = resolver() else {
+        return Ok(());
+    };
+    // Fully qualified, so the resolver's search domains are not appended.
+    let domain = format!("{}.", address.domain());
+    let found = match resolver.mx_lookup(domain.as_str()).await {
+        Ok(_) => Ok(()),
+        Err(e) if e.is_no_records_found() => resolver.lookup_ip(domain.as_str()).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    match found {
+        Ok(()) => Ok(()),
+        Err(e) if e.is_no_records_found() => Err(FieldError::new(
+            "email",
+            UNDELIVERABLE_EMAIL,
+            "Email domain does not accept mail",
+        )),
+        Err(e) => {
+            tracing::warn!(domain = %address.domain(), error = %e, "Email domain lookup failed");
+            Ok(())
+        }
+    }
+}
+
+fn resolver() -> Option<&'static TokioResolver> {
+    static RESOLVER: OnceLock<Option<TokioResolver>> = OnceLock::new();
+    RESOLVER
+        .get_or_init(|| match TokioResolver::builder_tokio() {
+            Ok(builder) => Some(builder.build()),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read the DNS configuration, email domains are not verified");
+                None
+            }
+        })
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("a@b.com").is_ok());
+        assert!(parse("first.last+tag@sub.example.co").is_ok());
+        assert!(parse("\"quoted local\"@example.com").is_ok());
+
+        for address in [
+            "",
+            "plain",
+            "a@localhost",
+            "a@@b.com",
+            "a..b@example.com",
+            "Someone <a@b.com>",
+            "a@[127.0.0.1]",
+            "a@-example.com",
+        ] {
+            let err = parse(address).unwrap_err();
+            assert_eq!(err.code, INVALID_EMAIL, "{}", address);
+        }
+    }
+
+    #[test]
+    fn test_domain_policy() {
+        let check = |policy: &DomainPolicy, address: &str| policy.check(&parse(address).unwrap());
+
+        let open = DomainPolicy::default();
+        assert!(check(&open, "a@mailinator.com").is_ok());
+
+        let policy = DomainPolicy {
+            allow: vec!["company.com".into()],
+            ..Default::default()
+        };
+        assert!(check(&policy, "a@company.com").is_ok());
+        assert!(check(&policy, "a@eu.Company.com").is_ok());
+        assert!(check(&policy, "a@notcompany.com").is_err());
+
+        let policy = DomainPolicy {
+            block: vec!["rival.com".into()],
+            block_disposable: true,
+            ..Default::default()
+        };
+        assert!(check(&policy, "a@b.com").is_ok());
+        assert!(check(&policy, "a@sales.rival.com").is_err());
+        let err = check(&policy, "a@MAILINATOR.com").unwrap_err();
+        assert_eq!(err.code, EMAIL_DOMAIN_NOT_ALLOWED);
+        assert!(check(&policy, "a@x.yopmail.com").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+//! Plain text email templates, the first line is the subject and the rest,
+//! after a blank line, the body. `{{name}}` placeholders are substituted.
+
+use super::Email;
+
+const WELCOME: &str = include_str!("templates/welcome.txt");
+const VERIFICATION: &str = include_str!("templates/verification.txt");
+const EMAIL_CHANGE: &str = include_str!("templates/email_change.txt");
+
+pub fn welcome(app_name: &str, email: &str) -> Email {
+    render(WELCOME, email, &[("app_name", app_name), ("email", email)])
+}
+
+/// Sent when a user's address changes, so its owner can flag a takeover.
+pub fn verification(app_name: &str, email: &str) -> Email {
+    render(
+        VERIFICATION,
+        email,
+        &[("app_name", app_name), ("email", email)],
+    )
+}
+
+/// Sent to the new address of a pending change, with the token confirming it.
+pub fn email_change(app_name: &str, email: &str, token: &str, expires_at: &str) -> Email {
+    render(
+        EMAIL_CHANGE,
+        email,
+        &[
+            ("app_name", app_name),
+            ("email", email),
+            ("token", token),
+            ("expires_at", expires_at),
+        ],
+    )
+}
+
+fn render(template: &str, to: &str, vars: &[(&str, &str)]) -> Email {
+    let text = vars
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{{{}}}}}", key), value)
+        });
+    let (subject, body) = text.split_once("\n\n").unwrap_or((&text, ""));
+    Email {
+        to: to.to_string(),
+        subject: subject.trim().to_string(),
+        body: body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_welcome() {
+        let email = welcome("Shop", "a@b.com");
+        assert_eq!(email.to, "a@b.com");
+        assert_eq!(email.subject, "Welcome to Shop");
+        assert!(email.body.starts_with("Hi a@b.com,"));
+        assert!(!email.body.contains("{{"));
+    }
+
+    #[test]
+    fn test_email_change() {
+        let email = email_change("Shop", "new@b.com", "abc123", "2026-10-17T12:00:00Z");
+        assert_eq!(email.to, "new@b.com");
+        assert_eq!(email.subject, "Confirm your new Shop email address");
+        assert!(email.body.contains("\nabc123\n"));
+        assert!(!email.body.contains("{{"));
+    }
+}
@@ -1,24 +1,207 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
 
-use axum::{Extension, Json, extract::State, http::StatusCode, routing::get};
-use serde_json::{self, json};
+use clap::{Parser, Subcommand};
 use tokio::net::TcpListener;
 use tracing::{Level, info};
 use tracing_subscriber::FmtSubscriber;
+use uuid::Uuid;
 
 use crud_rust::{
+    app::build_router,
+    cache::CachePolicies,
     config::Config,
-    handler::{item::router_setup_items, user::router_setup_users},
-    middleware::{CorrelationId, request_middleware},
-    model::http::Response,
-    repository::PostgresRepository,
-    service::Service,
+    container::Container,
+    event::{
+        EventPublisher, FanoutPublisher,
+        amqp::AmqpPublisher,
+        broadcast::Broadcaster,
+        nats::{self, NatsPublisher},
+        redis::{self, RedisPublisher},
+    },
+    export::{EXPORT_ITEMS_JOB, ItemExportHandler},
+    import::{IMPORT_ITEMS_JOB, ItemImportHandler},
+    mail::{LogMailer, MailJobHandler, Mailer, SEND_EMAIL_JOB, SmtpMailer},
+    messages::MessageCatalog,
+    mirror::Mirror,
+    model::error::{AppError, AppErrorCode},
+    notify::{NOTIFY_JOB, NotificationRouter, NotifyJobHandler, NotifyRoute},
+    openapi,
+    proxy::TrustedProxies,
+    repository::{PostgresRepository, erased::DynJobRepository, job::PostgresJobRepository},
+    scaffold::{self, Entity},
+    search::{self, SearchIndexer},
+    server::{self, ServerTuning},
+    service::{Service, ServiceApi, item::ItemNameRules},
     state::AppState,
+    storage,
+    thumbnail::{GENERATE_THUMBNAILS_JOB, ThumbnailHandler, ThumbnailSize},
+    timeout::RouteTimeouts,
+    worker::JobWorker,
 };
 use sqlx::PgPool;
 
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Runs the HTTP server, the default when no command is given.
+    Serve,
+    /// Scaffolds new code into the current crate.
+    Generate {
+        #[command(subcommand)]
+        target: GenerateTarget,
+    },
+    /// Works with the OpenAPI spec without starting the server.
+    Openapi {
+        #[command(subcommand)]
+        action: OpenapiAction,
+    },
+    /// Maintains the search index configured by `OPENSEARCH_URL`.
+    Search {
+        #[command(subcommand)]
+        action: SearchAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum SearchAction {
+    /// Rebuilds the index from the items in the database, a running server
+    /// goes on from there.
+    Reindex,
+}
+
+#[derive(Subcommand)]
+enum OpenapiAction {
+    /// Writes the spec as JSON, e.g. `openapi export --out openapi.json`.
+    Export {
+        /// File to write, stdout when omitted.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GenerateTarget {
+    /// Model, migration, repository, service and handler for a new resource,
+    /// e.g. `generate entity tag --fields name:string,weight:int`.
+    Entity {
+        /// Singular snake_case name.
+        name: String,
+        /// Comma separated `name:type`, types are string, text, int, float and bool.
+        #[arg(long, value_delimiter = ',', required = true)]
+        fields: Vec<String>,
+        /// Table and route name, defaults to a naive plural of the name.
+        #[arg(long)]
+        plural: Option<String>,
+    },
+}
+
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
+    match Cli::parse().command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            serve().await;
+            ExitCode::SUCCESS
+        }
+        Command::Generate {
+            target:
+                GenerateTarget::Entity {
+                    name,
+                    fields,
+                    plural,
+                },
+        } => generate_entity(&name, plural.as_deref(), &fields),
+        Command::Openapi {
+            action: OpenapiAction::Export { out },
+        } => export_openapi(out.as_deref()),
+        Command::Search {
+            action: SearchAction::Reindex,
+        } => reindex_search().await,
+    }
+}
+
+async fn reindex_search() -> ExitCode {
+    let config = Config::new();
+    let result = async {
+        let index = search::from_config(&config)?.ok_or_else(|| AppError {
+            code: AppErrorCode::InvalidInput,
+            message: "OPENSEARCH_URL is not set".to_string(),
+            error_code: None,
+        })?;
+        let pool = PgPool::connect(&config.database_url).await?;
+        let repo = Arc::new(PostgresRepository::new(pool));
+        SearchIndexer::new(repo, index, &config.opensearch_index)
+            .reindex()
+            .await
+    }
+    .await;
+    match result {
+        Ok(indexed) => {
+            println!("indexed {} items", indexed);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{} {}", e.get_message(), e.get_error());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn export_openapi(out: Option<&Path>) -> ExitCode {
+    let result = openapi::spec_json().and_then(|json| match out {
+        Some(path) => std::fs::write(path, json).map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: format!("Failed to write {}", path.display()),
+            error_code: None,
+        }),
+        None => {
+            print!("{}", json);
+            Ok(())
+        }
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{} {}", e.get_message(), e.get_error());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn generate_entity(name: &str, plural: Option<&str>, fields: &[String]) -> ExitCode {
+    let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
+    let result = Entity::parse(name, plural, fields).and_then(|entity| {
+        Ok((
+            scaffold::generate(Path::new("."), &entity, &timestamp)?,
+            entity,
+        ))
+    });
+    match result {
+        Ok((files, entity)) => {
+            for file in files {
+                println!("created {}", file.display());
+            }
+            println!("\n{}", entity.wiring());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{} {}", e.get_message(), e.get_error());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn serve() {
     let subscriber = FmtSubscriber::builder()
         .with_max_level(Level::TRACE)
         .finish();
@@ -38,21 +221,229 @@ async fn main() {
         }
     };
 
+    // Registration order is start order, shutdown runs in reverse.
+    let mut container = Container::new();
+    container.insert_component(Arc::new(pool.clone()));
+
+    let instance_id = Uuid::new_v4().to_string();
+    let broadcaster = Arc::new(Broadcaster::default());
+    let mut sinks: Vec<Arc<dyn EventPublisher>> = Vec::new();
+    let mut nats_client = None;
+    if let Some(url) = &config.nats_url {
+        match NatsPublisher::connect(url, &config.nats_subject_prefix).await {
+            Ok(publisher) => {
+                nats_client = Some(publisher.client());
+                let publisher = container.insert_component(Arc::new(publisher));
+                sinks.push(publisher);
+            }
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        }
+    }
+    if let Some(url) = &config.amqp_url {
+        match AmqpPublisher::connect(url, &config.amqp_exchange, &config.amqp_routing_key_prefix)
+            .await
+        {
+            Ok(publisher) => {
+                let publisher = publisher
+                    .with_confirm_timeout(Duration::from_millis(config.amqp_confirm_timeout_ms));
+                let publisher = container.insert_component(Arc::new(publisher));
+                sinks.push(publisher);
+            }
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        }
+    }
+    if let Some(url) = &config.redis_url {
+        match RedisPublisher::connect(url, &config.redis_channel, &instance_id).await {
+            Ok(publisher) => sinks.push(Arc::new(publisher)),
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        }
+
+        let url = url.clone();
+        let channel = config.redis_channel.clone();
+        let instance_id = instance_id.clone();
+        let broadcaster = broadcaster.clone();
+        tokio::spawn(async move {
+            if let Err(e) = redis::run_bridge(&url, &channel, &instance_id, broadcaster).await {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+            }
+        });
+    }
+
+    let mailer: Arc<dyn Mailer> = match (&config.smtp_url, config.mail_dev_mode) {
+        (Some(url), false) => match SmtpMailer::new(url, &config.mail_from) {
+            Ok(mailer) => Arc::new(mailer),
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        },
+        _ => Arc::new(LogMailer),
+    };
+    container.insert(mailer.clone());
+
+    if let Err(e) = ItemNameRules::from_config(&config) {
+        tracing::error!("{}: {}", e.get_message(), e.get_error());
+        return;
+    }
+    if let Err(e) = RouteTimeouts::parse(&config.route_timeouts_ms)
+        .and_then(|_| CachePolicies::parse(&config.cache_policies))
+        .and_then(|_| Mirror::from_config(&config))
+        .and_then(|_| TrustedProxies::parse(&config.trusted_proxies))
+    {
+        tracing::error!("{}: {}", e.get_message(), e.get_error());
+        return;
+    }
+
+    let jobs: Arc<dyn DynJobRepository> = Arc::new(PostgresJobRepository::new(pool.clone()));
+    let notify = NotifyJobHandler::from_config(&config, mailer.clone());
+    if let Some(routes) = &config.notify_routes {
+        let routes = match NotifyRoute::parse_all(routes)
+            .and_then(|routes| notify.check_routes(&routes).map(|_| routes))
+        {
+            Ok(routes) => routes,
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        };
+        sinks.push(Arc::new(NotificationRouter::new(routes, jobs.clone())));
+    }
+
+    let thumbnails = match ThumbnailSize::parse_all(&config.attachment_thumbnails) {
+        Ok(thumbnails) => thumbnails,
+        Err(e) => {
+            tracing::error!("{}: {}", e.get_message(), e.get_error());
+            return;
+        }
+    };
+    let storage = match storage::from_config(&config) {
+        Ok(storage) => storage,
+        Err(e) => {
+            tracing::error!("{}: {}", e.get_message(), e.get_error());
+            return;
+        }
+    };
+    container.insert(storage.clone());
+    let search = match search::from_config(&config) {
+        Ok(search) => search,
+        Err(e) => {
+            tracing::error!("{}: {}", e.get_message(), e.get_error());
+            return;
+        }
+    };
     let repo = Arc::new(PostgresRepository::new(pool.clone()));
-    let service = Arc::new(Service::new(config.clone(), repo.clone()));
+    if let Some(index) = &search {
+        let indexer = SearchIndexer::new(repo.clone(), index.clone(), &config.opensearch_index);
+        container.insert_component(Arc::new(indexer));
+    }
 
-    let app_state = Arc::new(AppState {
-        db_pool: pool.clone(),
-        config: config.clone(),
-        service: service.clone(),
-    });
-    let app = setup_app(app_state.clone());
+    // Built here rather than by the state builder, the job handlers go
+    // through the same service as the API.
+    sinks.insert(0, broadcaster.clone());
+    let mut service = Service::new(config.clone(), repo, Arc::new(FanoutPublisher::new(sinks)))
+        .with_storage(storage.clone());
+    if let Some(index) = search {
+        service = service.with_search(index);
+    }
+    let service: Arc<dyn ServiceApi> = Arc::new(service);
+
+    let mut worker = None;
+    if config.job_workers > 0 {
+        let job_worker = JobWorker::builder(jobs.clone())
+            .concurrency(config.job_workers)
+            .poll_interval(Duration::from_millis(config.job_poll_interval_ms))
+            .drain_timeout(Duration::from_secs(config.job_drain_timeout_secs))
+            .handler(SEND_EMAIL_JOB, Arc::new(MailJobHandler::new(mailer)))
+            .handler(NOTIFY_JOB, Arc::new(notify))
+            .handler(
+                GENERATE_THUMBNAILS_JOB,
+                Arc::new(ThumbnailHandler::new(storage, thumbnails)),
+            )
+            .handler(
+                EXPORT_ITEMS_JOB,
+                Arc::new(ItemExportHandler::new(
+                    Arc::new(PostgresRepository::new(pool.clone())),
+                    &config.export_dir,
+                )),
+            )
+            .handler(
+                IMPORT_ITEMS_JOB,
+                Arc::new(ItemImportHandler::new(
+                    service.clone(),
+                    jobs,
+                    &config.import_dir,
+                )),
+            )
+            .build();
+        let job_worker = match job_worker {
+            Ok(job_worker) => job_worker,
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        };
+        worker = Some(container.insert_component(Arc::new(job_worker)));
+    }
+
+    let messages = match &config.messages_file {
+        Some(path) => match MessageCatalog::from_file(path) {
+            Ok(messages) => messages,
+            Err(e) => {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+                return;
+            }
+        },
+        None => MessageCatalog::default(),
+    };
+
+    let app_state = match AppState::builder()
+        .config(config.clone())
+        .postgres(pool)
+        .service(service)
+        .broadcaster(broadcaster)
+        .messages(messages)
+        .container(container)
+        .build()
+    {
+        Ok(state) => state,
+        Err(e) => {
+            tracing::error!("{}: {}", e.get_message(), e.get_error());
+            return;
+        }
+    };
+
+    if app_state.container.start().await.is_err() {
+        return;
+    }
+
+    if let (Some(client), true) = (nats_client, config.nats_request_reply) {
+        let prefix = config.nats_subject_prefix.clone();
+        let concurrency = config.nats_request_concurrency;
+        let service = app_state.service.clone();
+        tokio::spawn(async move {
+            if let Err(e) = nats::serve_request_reply(client, prefix, service, concurrency).await {
+                tracing::error!("{}: {}", e.get_message(), e.get_error());
+            }
+        });
+    }
+
+    let app = build_router(app_state.clone());
 
     let addr = &app_state.config.get_addr();
     let listener = match TcpListener::bind(addr).await {
         Ok(listener) => listener,
         Err(e) => {
             tracing::error!("Failed to bind to {}: {}", addr, e);
+            app_state.container.shutdown().await;
             return;
         }
     };
@@ -63,47 +454,46 @@ async fn main() {
         "Starting server..."
     );
 
-    if let Err(e) = axum::serve(listener, app.into_make_service()).await {
-        tracing::error!("Server error: {}", e);
-        return;
-    }
+    // Workers stop claiming right away, in-flight requests and jobs then
+    // drain side by side before the components shut down.
+    let shutdown = async move {
+        shutdown_signal().await;
+        info!("Shutting down...");
+        if let Some(worker) = worker {
+            worker.stop();
+        }
+    };
+    let tuning = ServerTuning::from_config(&app_state.config);
+    server::serve(listener, app, tuning, shutdown).await;
+    app_state.container.shutdown().await;
 }
 
-fn setup_app(state: Arc<AppState>) -> axum::Router {
-    axum::Router::new()
-        .route("/", get(handler_index))
-        .route("/api/healthcheck", get(handler_healthcheck))
-        .nest("/api/items", router_setup_items())
-        .nest("/api/users", router_setup_users())
-        .layer(axum::middleware::from_fn(request_middleware))
-        .with_state(state)
-}
+/// Resolves on ctrl-c, or SIGTERM from an orchestrator on unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for ctrl-c: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
 
-async fn handler_index(
-    State(state): State<Arc<AppState>>,
-    Extension(correlation_id): Extension<CorrelationId>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    (
-        StatusCode::OK,
-        Json(json!(Response::<serde_json::Value> {
-            correlation_id,
-            message: format!("Welcome to {}!", &state.config.app_name),
-            error: "".into(),
-            data: None,
-        })),
-    )
-}
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-async fn handler_healthcheck(
-    Extension(correlation_id): Extension<CorrelationId>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    (
-        StatusCode::OK,
-        Json(json!(Response::<serde_json::Value> {
-            correlation_id,
-            message: "ok".into(),
-            error: "".into(),
-            data: None,
-        })),
-    )
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 }
@@ -0,0 +1,230 @@
+//! Operator overrides for user-facing response messages, so one binary can be
+//! white-labeled. The catalog is a JSON object of keys to templates, e.g.
+//!
+//! ```json
+//! {
+//!   "user.created": "Welcome aboard, {email}!",
+//!   "error.EMAIL_TAKEN": "That address already has an account",
+//!   "validation.name.length": "Names are 1 to 255 characters"
+//! }
+//! ```
+//!
+//! Keys are `<resource>.<action>` for success messages (see the handlers),
+//! `error.<ERROR_CODE>` for errors and `validation.<field>.<code>` for field
+//! errors. Templates may use `{message}`, the default text, plus the
+//! arguments a success key provides such as `{id}`.
+
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    middleware::{BODY_LIMIT, fits_body_limit, is_json, replace_body},
+    model::error::{AppError, AppErrorCode},
+};
+
+/// Catalog key of a success message and its template arguments, attached to
+/// the response by `ApiResponse::message_key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageKey {
+    pub key: String,
+    pub args: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MessageCatalog {
+    templates: HashMap<String, String>,
+}
+
+impl MessageCatalog {
+    pub fn new(templates: HashMap<String, String>) -> Self {
+        Self { templates }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let error = |e: String| AppError {
+            code: AppErrorCode::InternalError(e),
+            message: format!("Invalid message catalog {}", path.display()),
+            error_code: None,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let templates = serde_json::from_str(&text).map_err(|e| error(e.to_string()))?;
+        Ok(Self::new(templates))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// The overridden text for `key`, `None` when the catalog doesn't have it.
+    pub fn render(&self, key: &str, message: &str, args: &[(&str, String)]) -> Option<String> {
+        let template = self.templates.get(key)?;
+        let text = args.iter().fold(
+            template.replace("{message}", message),
+            |text, (name, value)| text.replace(&format!("{{{}}}", name), value),
+        );
+        Some(text)
+    }
+}
+
+/// Rewrites the messages of standard envelopes with the catalog's templates,
+/// sits inside the content negotiation layers so they see the final text.
+/// Bodies that may exceed [`BODY_LIMIT`] are passed on as they are.
+pub async fn messages_middleware(
+    State(catalog): State<Arc<MessageCatalog>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let res = next.run(req).await;
+    if catalog.is_empty() || !is_json(&res) || !fits_body_limit(&res) {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, BODY_LIMIT).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let Ok(mut envelope) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let key = parts.extensions.remove::<MessageKey>();
+    let message = envelope["message"].as_str().unwrap_or_default().to_string();
+    let rendered = match (&key, envelope["error_code"].as_str()) {
+        (Some(key), _) => catalog.render(&key.key, &message, &key.args),
+        (None, Some(code)) => catalog.render(&format!("error.{}", code), &message, &[]),
+        (None, None) => None,
+    };
+    if let Some(rendered) = rendered {
+        envelope["message"] = rendered.into();
+    }
+    if let Some(errors) = envelope["errors"].as_array_mut() {
+        for error in errors {
+            let key = format!(
+                "validation.{}.{}",
+                error["field"].as_str().unwrap_or_default(),
+                error["code"].as_str().unwrap_or_default()
+            );
+            let message = error["message"].as_str().unwrap_or_default().to_string();
+            if let Some(rendered) = catalog.render(&key, &message, &[]) {
+                error["message"] = rendered.into();
+            }
+        }
+    }
+
+    replace_body(parts, "application/json", &envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::to_bytes, middleware::from_fn_with_state, routing::get};
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    use crate::model::{
+        error::{ErrorCode, FieldError},
+        http::{ApiResponse, ApiResult},
+    };
+
+    fn catalog() -> MessageCatalog {
+        MessageCatalog::new(HashMap::from([
+            ("user.created".into(), "Welcome, {email}!".into()),
+            (
+                "error.USER_NOT_FOUND".into(),
+                "No such account ({message})".into(),
+            ),
+            (
+                "validation.email.email".into(),
+                "Please check the address".into(),
+            ),
+        ]))
+    }
+
+    async fn created() -> ApiResult<Value> {
+        Ok(
+            ApiResponse::created("abc".into(), json!({}), "User created successfully")
+                .message_key("user.created", [("email", "a@b.com".to_string())]),
+        )
+    }
+
+    async fn missing() -> ApiResult<Value> {
+        Err(ApiResponse::error(
+            "abc".into(),
+            AppError {
+                code: AppErrorCode::NotFound,
+                message: "User with id 1 not found".into(),
+                error_code: Some(ErrorCode::UserNotFound),
+            },
+        ))
+    }
+
+    async fn invalid() -> ApiResult<Value> {
+        Err(ApiResponse::error(
+            "abc".into(),
+            AppError::validation(vec![
+                FieldError::new("email", "email", "Email is invalid"),
+                FieldError::new("name", "length", "Name is too long"),
+            ]),
+        ))
+    }
+
+    async fn huge() -> ApiResult<String> {
+        Ok(ApiResponse::ok("abc".into(), "x".repeat(BODY_LIMIT))
+            .message_key("user.created", [("email", "a@b.com".to_string())]))
+    }
+
+    async fn send(uri: &str) -> Value {
+        let app = Router::new()
+            .route("/created", get(created))
+            .route("/huge", get(huge))
+            .route("/missing", get(missing))
+            .route("/invalid", get(invalid))
+            .layer(from_fn_with_state(Arc::new(catalog()), messages_middleware));
+        let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_success_message_from_key() {
+        assert_eq!(send("/created").await["message"], "Welcome, a@b.com!");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_passed_through() {
+        let body = send("/huge").await;
+        assert_ne!(body["message"], "Welcome, a@b.com!");
+        assert_eq!(body["data"].as_str().unwrap().len(), BODY_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn test_error_message_from_error_code() {
+        assert_eq!(
+            send("/missing").await["message"],
+            "No such account (User with id 1 not found)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_field_messages_overridden_when_in_catalog() {
+        let body = send("/invalid").await;
+        assert_eq!(body["errors"][0]["message"], "Please check the address");
+        assert_eq!(body["errors"][1]["message"], "Name is too long");
+    }
+
+    #[test]
+    fn test_from_file_rejects_invalid_json() {
+        let path = std::env::temp_dir().join("crud-messages-invalid.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(MessageCatalog::from_file(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,43 +1,530 @@
-use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, HttpBody, to_bytes},
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+        header::{
+            ACCEPT, ACCEPT_LANGUAGE, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, ETAG,
+            IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK,
+        },
+        response::Parts,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
 use uuid::Uuid;
 
+use crate::{
+    config::Config,
+    extract::correlation_id,
+    handler::version::{ApiMount, ApiVersion},
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+        http::{ApiResponse, Links, Response as Envelope, etag_matches, parse_http_date},
+        jsonapi::{Document, JSON_API_MEDIA_TYPE, attributes_from_document},
+        problem::{PROBLEM_JSON_MEDIA_TYPE, ProblemDetails},
+        xml::{XML_MEDIA_TYPE, to_xml},
+    },
+};
+
 pub const X_CORRELATION_ID: &str = "X-Correlation-Id";
+pub const X_REQUEST_ID: &str = "X-Request-Id";
+pub const X_TENANT_ID: &str = "X-Tenant-Id";
+/// Carries the envelope `message` when responses are sent without it.
+pub const X_MESSAGE: &str = "X-Message";
 
-pub type CorrelationId = String;
+/// Longest correlation id taken from callers.
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// Same cap axum applies to request bodies by default.
+pub(crate) const BODY_LIMIT: usize = 2 * 1024 * 1024;
 
 pub async fn request_middleware(mut req: Request, next: Next) -> Response {
-    let correlation_id: CorrelationId = req
-        .headers()
-        .get(X_CORRELATION_ID)
-        .and_then(|h| h.to_str().ok())
-        .map(String::from)
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let ctx = context_from_headers(req.headers());
+    let ids = [
+        (X_CORRELATION_ID, ctx.correlation_id.clone()),
+        (X_REQUEST_ID, ctx.request_id.clone()),
+    ];
 
-    req.extensions_mut().insert(correlation_id.clone());
+    req.extensions_mut().insert(ctx);
     let mut res = next.run(req).await;
-    res.headers_mut().insert(
-        X_CORRELATION_ID,
-        HeaderValue::from_str(&correlation_id).unwrap(),
-    );
+    for (name, id) in ids {
+        if let Ok(id) = HeaderValue::from_str(&id) {
+            res.headers_mut().insert(name, id);
+        }
+    }
     res
 }
 
+/// Whether a caller's correlation id is safe to log and echo: at most
+/// 128 letters, digits, `-`, `_`, `.` or `:`.
+pub fn is_valid_correlation_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_CORRELATION_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// Builds the request context from headers with a new request id, and a new
+/// correlation id when the caller didn't send a valid one.
+pub(crate) fn context_from_headers(headers: &HeaderMap) -> Ctx {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+    };
+    Ctx {
+        correlation_id: header(X_CORRELATION_ID)
+            .filter(|id| is_valid_correlation_id(id))
+            .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        request_id: Uuid::new_v4().to_string(),
+        user: None,
+        tenant: header(X_TENANT_ID),
+        // Only the most preferred language, e.g. `en-US` from `en-US,en;q=0.9`.
+        locale: header(ACCEPT_LANGUAGE.as_str())
+            .and_then(|v| v.split([',', ';']).next().map(|l| l.trim().to_string()))
+            .filter(|l| !l.is_empty() && l != "*"),
+        deadline: None,
+        client_ip: None,
+    }
+}
+
+/// Speaks JSON:API when enabled in config or requested through `Accept`,
+/// translating request documents to plain payloads and envelopes to documents.
+pub async fn jsonapi_middleware(
+    State(config): State<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let enabled = config.jsonapi_mode || has_media_type(req.headers(), ACCEPT, JSON_API_MEDIA_TYPE);
+    let kind = resource_type(req.uri().path());
+    let self_link = req.uri().to_string();
+
+    let correlation_id = correlation_id(req.extensions());
+
+    let req = if has_media_type(req.headers(), CONTENT_TYPE, JSON_API_MEDIA_TYPE) {
+        match unwrap_jsonapi_request(req).await {
+            Ok(req) => req,
+            Err(res) => return res,
+        }
+    } else {
+        req
+    };
+
+    let res = next.run(req).await;
+    if !enabled || !is_json(&res) || !fits_body_limit(&res) {
+        return res;
+    }
+
+    match read_envelope(res, correlation_id).await {
+        Ok((parts, envelope)) => {
+            let document = Document::from_envelope(&kind, parts.status, &self_link, envelope);
+            replace_body(parts, JSON_API_MEDIA_TYPE, &document)
+        }
+        Err(res) => res,
+    }
+}
+
+/// Sends successful responses as their bare `data` when the envelope is
+/// turned off in config or by `?envelope=false`, `?envelope=true` restores it.
+/// The message moves to `X-Message` and the links to a `Link` header, the
+/// correlation id already has its own. Errors keep their envelope.
+pub async fn envelope_middleware(
+    State(config): State<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let envelope = query_param(&req, "envelope")
+        .and_then(|value| value.parse::<bool>().ok())
+        .unwrap_or(config.response_envelope);
+
+    let correlation_id = correlation_id(req.extensions());
+    let res = next.run(req).await;
+    if envelope || !res.status().is_success() || !is_json(&res) || !fits_body_limit(&res) {
+        return res;
+    }
+
+    match read_envelope(res, correlation_id).await {
+        Ok((mut parts, envelope)) => {
+            if let Ok(message) = HeaderValue::from_bytes(envelope.message.as_bytes()) {
+                parts.headers.insert(X_MESSAGE, message);
+            }
+            if let Some(links) = envelope.links.as_ref().map(Links::header_value)
+                && let Ok(links) = HeaderValue::from_str(&links)
+            {
+                parts.headers.insert(LINK, links);
+            }
+            replace_body(parts, "application/json", &envelope.data)
+        }
+        Err(res) => res,
+    }
+}
+
+/// Renders error envelopes as RFC 7807 `application/problem+json` when enabled
+/// in config or requested through `Accept`.
+pub async fn problem_details_middleware(
+    State(config): State<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let enabled =
+        config.problem_details || has_media_type(req.headers(), ACCEPT, PROBLEM_JSON_MEDIA_TYPE);
+    let instance = req.uri().path().to_string();
+
+    let correlation_id = correlation_id(req.extensions());
+    let res = next.run(req).await;
+    let enabled = enabled
+        || res
+            .extensions()
+            .get::<ApiMount>()
+            .is_some_and(|mount| mount.version >= ApiVersion::V2);
+    let failed = res.status().is_client_error() || res.status().is_server_error();
+    if !enabled || !failed || !is_json(&res) || !fits_body_limit(&res) {
+        return res;
+    }
+
+    match read_envelope(res, correlation_id).await {
+        Ok((parts, envelope)) => {
+            let problem = ProblemDetails::from_envelope(parts.status, &instance, envelope);
+            replace_body(parts, PROBLEM_JSON_MEDIA_TYPE, &problem)
+        }
+        Err(res) => res,
+    }
+}
+
+/// Resources whose responses can be rendered as XML.
+const XML_RESOURCES: [&str; 2] = ["items", "users"];
+
+/// Renders item and user responses, errors included, as XML when the client
+/// asks for `application/xml` in `Accept`. JSON that can't be expressed in XML,
+/// or is too large to convert, is sent as is.
+pub async fn xml_middleware(req: Request, next: Next) -> Response {
+    let enabled = has_media_type(req.headers(), ACCEPT, XML_MEDIA_TYPE)
+        && XML_RESOURCES.contains(&resource_type(req.uri().path()).as_str());
+    let correlation_id = correlation_id(req.extensions());
+
+    let res = next.run(req).await;
+    if !enabled || !is_json(&res) || !fits_body_limit(&res) {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let bytes = match to_bytes(body, BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => return unreadable_body(correlation_id, e.to_string()),
+    };
+    let Some(xml) = serde_json::from_slice(&bytes)
+        .ok()
+        .and_then(|body| to_xml(body).ok())
+    else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(XML_MEDIA_TYPE));
+    Response::from_parts(parts, Body::from(xml))
+}
+
+/// Answers `304 Not Modified` to a `GET` or `HEAD` whose `If-None-Match`
+/// names the response's `ETag`, as set by `ApiResponse::etag`, or else whose
+/// `If-Modified-Since` is no older than its `Last-Modified`, as set by
+/// `ApiResponse::last_modified`. `If-None-Match` takes precedence, handlers
+/// may also answer it themselves.
+pub async fn not_modified_middleware(req: Request, next: Next) -> Response {
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD);
+    let none_match = req.headers().get(IF_NONE_MATCH).filter(|_| safe).cloned();
+    let since = req
+        .headers()
+        .get(IF_MODIFIED_SINCE)
+        .filter(|_| safe && none_match.is_none())
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_http_date);
+
+    let res = next.run(req).await;
+    if res.status() != StatusCode::OK {
+        return res;
+    }
+    let unchanged = if let Some(none_match) = none_match {
+        res.headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|etag| etag_matches(Some(&none_match), etag))
+    } else if let Some(since) = since {
+        res.headers()
+            .get(LAST_MODIFIED)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_http_date)
+            .is_some_and(|modified| modified <= since)
+    } else {
+        false
+    };
+    if !unchanged {
+        return res;
+    }
+
+    let (mut parts, _) = res.into_parts();
+    parts.status = StatusCode::NOT_MODIFIED;
+    for name in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_DISPOSITION, LINK] {
+        parts.headers.remove(name);
+    }
+    Response::from_parts(parts, Body::empty())
+}
+
+/// Answers successful deletes with `204 No Content` when `DELETE_NO_CONTENT`
+/// is set, unless the deleted entity was asked for with
+/// `?return=representation`.
+pub async fn delete_no_content_middleware(
+    State(config): State<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let no_content = config.delete_no_content
+        && req.method() == Method::DELETE
+        && query_param(&req, "return") != Some("representation");
+
+    let res = next.run(req).await;
+    if !no_content || res.status() != StatusCode::OK {
+        return res;
+    }
+    let (mut parts, _) = res.into_parts();
+    parts.status = StatusCode::NO_CONTENT;
+    for name in [CONTENT_TYPE, CONTENT_LENGTH] {
+        parts.headers.remove(name);
+    }
+    Response::from_parts(parts, Body::empty())
+}
+
+/// Value of the first `name` parameter of the request's query.
+fn query_param<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.split_once('=').filter(|(key, _)| *key == name))
+        .map(|(_, value)| value)
+}
+
+async fn unwrap_jsonapi_request(req: Request) -> Result<Request, Response> {
+    let (mut parts, body) = req.into_parts();
+    let bad_request = || {
+        let error = AppError {
+            code: AppErrorCode::InvalidInput,
+            message: "Invalid JSON:API request document".to_string(),
+            error_code: None,
+        };
+        ApiResponse::error(correlation_id(&parts.extensions), error).into_response()
+    };
+    let bytes = to_bytes(body, BODY_LIMIT)
+        .await
+        .map_err(|_| bad_request())?;
+    let attributes = serde_json::from_slice(&bytes)
+        .ok()
+        .and_then(attributes_from_document)
+        .ok_or_else(bad_request)?;
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok(Request::from_parts(
+        parts,
+        Body::from(serde_json::to_vec(&attributes).unwrap_or_default()),
+    ))
+}
+
+pub(crate) fn is_json(res: &Response) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"))
+}
+
+/// Whether the body of `res` surely fits in [`BODY_LIMIT`], larger or
+/// unsized ones are better passed on than buffered.
+pub(crate) fn fits_body_limit(res: &Response) -> bool {
+    res.body()
+        .size_hint()
+        .upper()
+        .is_some_and(|size| size <= BODY_LIMIT as u64)
+}
+
+/// Buffers a JSON response and parses it as the standard envelope, handing the
+/// response back untouched when the body is something else. A body that fails
+/// to read is answered with a 500 envelope.
+async fn read_envelope(
+    res: Response,
+    correlation_id: String,
+) -> Result<(Parts, Envelope<serde_json::Value>), Response> {
+    let (parts, body) = res.into_parts();
+    let bytes = match to_bytes(body, BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => return Err(unreadable_body(correlation_id, e.to_string())),
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(envelope) => Ok((parts, envelope)),
+        Err(_) => Err(Response::from_parts(parts, Body::from(bytes))),
+    }
+}
+
+/// 500 for a response whose body couldn't be read back, rather than sending
+/// it on empty.
+fn unreadable_body(correlation_id: String, error: String) -> Response {
+    let error = AppError {
+        code: AppErrorCode::InternalError(error),
+        message: "Failed to read response body".to_string(),
+        error_code: None,
+    };
+    ApiResponse::error(correlation_id, error).into_response()
+}
+
+pub(crate) fn replace_body<T: Serialize>(
+    mut parts: Parts,
+    content_type: &'static str,
+    body: &T,
+) -> Response {
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    Response::from_parts(
+        parts,
+        Body::from(serde_json::to_vec(body).unwrap_or_default()),
+    )
+}
+
+fn has_media_type(headers: &HeaderMap, name: HeaderName, media_type: &str) -> bool {
+    headers
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|v| v.contains(media_type))
+}
+
+/// Resource type from the path, e.g. `items` for `/api/items/{id}` as well
+/// as `/api/v1/items/{id}`.
+fn resource_type(path: &str) -> String {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .filter(|s| !s.starts_with('v') || ApiVersion::parse(s).is_none())
+        .nth(1)
+        .unwrap_or_default()
+        .to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use axum::{
-        Router,
+        Json, Router,
         body::Body,
         http::{Request as HttpRequest, StatusCode},
-        middleware::from_fn,
-        routing::get,
+        middleware::{from_fn, from_fn_with_state},
+        routing::{get, post},
     };
+    use serde_json::{Value, json};
     use tower::ServiceExt;
 
     async fn handler() -> StatusCode {
         StatusCode::OK
     }
 
+    async fn echo_item(Json(payload): Json<Value>) -> Json<Value> {
+        Json(json!({
+            "correlation_id": "abc",
+            "message": "ok",
+            "error": "",
+            "data": {"id": "1", "name": payload["name"]},
+        }))
+    }
+
+    async fn missing_item() -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "correlation_id": "abc",
+                "message": "Item with id 1 not found",
+                "error": "",
+                "data": null,
+            })),
+        )
+    }
+
+    /// Error envelope past [`BODY_LIMIT`].
+    async fn huge_error() -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "correlation_id": "abc",
+                "message": "x".repeat(BODY_LIMIT),
+                "error": "",
+                "data": null,
+            })),
+        )
+    }
+
+    fn problem_app(config: Config) -> Router {
+        Router::new()
+            .route("/api/items/1", get(missing_item))
+            .route("/api/items/2", get(huge_error))
+            .layer(from_fn_with_state(
+                Arc::new(config),
+                problem_details_middleware,
+            ))
+    }
+
+    fn envelope_app(config: Config) -> Router {
+        let item = || async {
+            Json(json!({
+                "correlation_id": "abc",
+                "message": "Item fetched",
+                "error": "",
+                "data": {"id": "1", "name": "book"},
+                "links": {"self": "/api/v1/items/1", "collection": "/api/v1/items"},
+            }))
+        };
+        Router::new()
+            .route("/api/items", get(huge_items))
+            .route("/api/items/1", get(item))
+            .route("/api/items/2", get(missing_item))
+            .layer(from_fn_with_state(Arc::new(config), envelope_middleware))
+    }
+
+    /// Envelope past [`BODY_LIMIT`], which the rewriting middlewares must
+    /// pass on as it is.
+    async fn huge_items() -> Json<Value> {
+        Json(json!({
+            "correlation_id": "abc",
+            "message": "Items fetched",
+            "error": "",
+            "data": [{"id": "1", "name": "x".repeat(BODY_LIMIT)}],
+        }))
+    }
+
+    async fn assert_passed_through(res: Response) {
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+        let body = body_json(res).await;
+        assert_eq!(body["message"], "Items fetched");
+        assert_eq!(body["data"][0]["name"].as_str().unwrap().len(), BODY_LIMIT);
+    }
+
+    fn jsonapi_app(config: Config) -> Router {
+        Router::new()
+            .route("/api/items", post(echo_item).get(huge_items))
+            .layer(from_fn_with_state(Arc::new(config), jsonapi_middleware))
+    }
+
+    async fn body_json(res: Response) -> Value {
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
     #[tokio::test]
     async fn test_middleware_adds_correlation_id_when_not_present() {
         let app = Router::new()
@@ -71,5 +558,362 @@ mod tests {
         assert!(res.headers().contains_key(X_CORRELATION_ID));
         let response_correlation_id = res.headers().get(X_CORRELATION_ID).unwrap();
         assert_eq!(response_correlation_id.to_str().unwrap(), correlation_id);
+        let request_id = res.headers().get(X_REQUEST_ID).unwrap();
+        assert_ne!(request_id, correlation_id);
+        assert!(Uuid::parse_str(request_id.to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_correlation_id_is_replaced() {
+        assert!(is_valid_correlation_id("req-42_a.b:c"));
+        for id in ["a b", "<script>", "é", &"a".repeat(129)] {
+            assert!(!is_valid_correlation_id(id), "{}", id);
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                X_CORRELATION_ID,
+                HeaderValue::from_bytes(id.as_bytes()).unwrap(),
+            );
+            let ctx = context_from_headers(&headers);
+            assert!(Uuid::parse_str(&ctx.correlation_id).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_context_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_CORRELATION_ID, HeaderValue::from_static("abc"));
+        headers.insert(X_TENANT_ID, HeaderValue::from_static("acme"));
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("id-ID,en;q=0.8"));
+
+        let ctx = context_from_headers(&headers);
+
+        assert_eq!(ctx.correlation_id, "abc");
+        assert_eq!(ctx.tenant.as_deref(), Some("acme"));
+        assert_eq!(ctx.locale.as_deref(), Some("id-ID"));
+        assert!(ctx.user.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_envelope_turned_off_by_query() {
+        let req = HttpRequest::get("/api/items/1?envelope=false")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = envelope_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()[X_MESSAGE], "Item fetched");
+        assert_eq!(
+            res.headers()[LINK],
+            r#"</api/v1/items/1>; rel="self", </api/v1/items>; rel="collection""#
+        );
+        assert_eq!(body_json(res).await, json!({"id": "1", "name": "book"}));
+    }
+
+    #[tokio::test]
+    async fn test_envelope_kept_on_oversized_body() {
+        let req = HttpRequest::get("/api/items?envelope=false")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = envelope_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert!(!res.headers().contains_key(X_MESSAGE));
+        assert_passed_through(res).await;
+    }
+
+    #[tokio::test]
+    async fn test_envelope_deployment_default_and_errors() {
+        let config = Config {
+            response_envelope: false,
+            ..Default::default()
+        };
+        let get = |uri: &str| HttpRequest::get(uri).body(Body::empty()).unwrap();
+
+        let res = envelope_app(config.clone())
+            .oneshot(get("/api/items/1"))
+            .await
+            .unwrap();
+        assert_eq!(body_json(res).await["name"], "book");
+
+        let res = envelope_app(config.clone())
+            .oneshot(get("/api/items/1?envelope=true"))
+            .await
+            .unwrap();
+        assert_eq!(body_json(res).await["data"]["name"], "book");
+
+        let res = envelope_app(config)
+            .oneshot(get("/api/items/2"))
+            .await
+            .unwrap();
+        assert_eq!(body_json(res).await["message"], "Item with id 1 not found");
+    }
+
+    #[tokio::test]
+    async fn test_jsonapi_negotiated_by_accept_header() {
+        let req = HttpRequest::builder()
+            .method("POST")
+            .uri("/api/items")
+            .header(ACCEPT, JSON_API_MEDIA_TYPE)
+            .header(CONTENT_TYPE, JSON_API_MEDIA_TYPE)
+            .body(Body::from(
+                json!({"data": {"type": "items", "attributes": {"name": "book"}}}).to_string(),
+            ))
+            .unwrap();
+
+        let res = jsonapi_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            JSON_API_MEDIA_TYPE
+        );
+        let body = body_json(res).await;
+        assert_eq!(
+            body["data"],
+            json!({"type": "items", "id": "1", "attributes": {"name": "book"}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jsonapi_passes_oversized_body_through() {
+        let req = HttpRequest::builder()
+            .uri("/api/items")
+            .header(ACCEPT, JSON_API_MEDIA_TYPE)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = jsonapi_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert_passed_through(res).await;
+    }
+
+    #[tokio::test]
+    async fn test_jsonapi_invalid_document_rejected_with_envelope() {
+        let req = HttpRequest::builder()
+            .method("POST")
+            .uri("/api/items")
+            .header(CONTENT_TYPE, JSON_API_MEDIA_TYPE)
+            .body(Body::from(json!({"name": "book"}).to_string()))
+            .unwrap();
+
+        let res = jsonapi_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(res).await;
+        assert_eq!(body["message"], "Invalid JSON:API request document");
+        assert!(body["correlation_id"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_jsonapi_disabled_keeps_envelope() {
+        let req = HttpRequest::builder()
+            .method("POST")
+            .uri("/api/items")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({"name": "book"}).to_string()))
+            .unwrap();
+
+        let res = jsonapi_app(Config::default()).oneshot(req).await.unwrap();
+
+        let body = body_json(res).await;
+        assert_eq!(body["data"], json!({"id": "1", "name": "book"}));
+    }
+
+    #[tokio::test]
+    async fn test_problem_details_pass_oversized_body_through() {
+        let req = HttpRequest::builder()
+            .uri("/api/items/2")
+            .header(ACCEPT, PROBLEM_JSON_MEDIA_TYPE)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = problem_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+        let body = body_json(res).await;
+        assert_eq!(body["message"].as_str().unwrap().len(), BODY_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn test_problem_details_enabled_by_config() {
+        let config = Config {
+            problem_details: true,
+            ..Config::default()
+        };
+        let req = HttpRequest::builder()
+            .uri("/api/items/1")
+            .body(Body::empty())
+            .unwrap();
+
+        let res = problem_app(config).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON_MEDIA_TYPE
+        );
+        assert_eq!(
+            body_json(res).await,
+            json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "Item with id 1 not found",
+                "instance": "/api/items/1",
+                "correlation_id": "abc",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_problem_details_negotiated_by_accept_header() {
+        let req = HttpRequest::builder()
+            .uri("/api/items/1")
+            .header(ACCEPT, PROBLEM_JSON_MEDIA_TYPE)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = problem_app(Config::default()).oneshot(req).await.unwrap();
+
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON_MEDIA_TYPE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xml_passes_oversized_body_through() {
+        let app = Router::new()
+            .route("/api/v1/items", get(huge_items))
+            .layer(from_fn(xml_middleware));
+        let req = HttpRequest::builder()
+            .uri("/api/v1/items")
+            .header(ACCEPT, XML_MEDIA_TYPE)
+            .body(Body::empty())
+            .unwrap();
+
+        let res = app.oneshot(req).await.unwrap();
+
+        assert_passed_through(res).await;
+    }
+
+    #[tokio::test]
+    async fn test_xml_negotiated_on_item_and_user_routes() {
+        let app = Router::new()
+            .route("/api/v1/items/1", get(missing_item))
+            .route("/api/events", get(missing_item))
+            .layer(from_fn(xml_middleware));
+        let request = |uri| {
+            HttpRequest::builder()
+                .uri(uri)
+                .header(ACCEPT, XML_MEDIA_TYPE)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let res = app
+            .clone()
+            .oneshot(request("/api/v1/items/1"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), XML_MEDIA_TYPE);
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(
+            String::from_utf8_lossy(&body).contains("<message>Item with id 1 not found</message>")
+        );
+
+        let res = app.oneshot(request("/api/events")).await.unwrap();
+        assert!(is_json(&res));
+    }
+
+    #[test]
+    fn test_resource_type_skips_version() {
+        assert_eq!(resource_type("/api/items/1"), "items");
+        assert_eq!(resource_type("/api/v1/users"), "users");
+        assert_eq!(resource_type("/api/items/v1"), "items");
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_since_last_modified() {
+        use crate::model::http::ApiResponse;
+        use axum::response::IntoResponse;
+
+        let app = Router::new()
+            .route(
+                "/items/1",
+                get(|| async {
+                    let modified = parse_http_date("Wed, 01 Jul 2026 10:00:00 GMT").unwrap();
+                    ApiResponse::ok("abc".into(), json!({"id": "1"}))
+                        .last_modified(modified)
+                        .into_response()
+                }),
+            )
+            .layer(from_fn(not_modified_middleware));
+        let send = |headers: &[(&str, &str)]| {
+            let mut req = HttpRequest::get("/items/1");
+            for (name, value) in headers {
+                req = req.header(*name, *value);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let res = send(&[("if-modified-since", "Wed, 01 Jul 2026 10:00:00 GMT")])
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            res.headers()[LAST_MODIFIED],
+            "Wed, 01 Jul 2026 10:00:00 GMT"
+        );
+        assert!(res.headers().get(CONTENT_TYPE).is_none());
+        let res = send(&[("if-modified-since", "Wed, 01 Jul 2026 09:59:59 GMT")])
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send(&[
+            ("if-modified-since", "Wed, 01 Jul 2026 10:00:00 GMT"),
+            ("if-none-match", "\"other\""),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = send(&[("if-modified-since", "garbage")]).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_if_none_match() {
+        use crate::model::http::ApiResponse;
+        use axum::response::IntoResponse;
+
+        let app = Router::new()
+            .route(
+                "/items/1",
+                get(|| async {
+                    ApiResponse::ok("abc".into(), json!({"id": "1"}))
+                        .etag(1)
+                        .into_response()
+                }),
+            )
+            .layer(from_fn(not_modified_middleware));
+        let send = |none_match: &str| {
+            let req = HttpRequest::get("/items/1").header(IF_NONE_MATCH, none_match);
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let etag = send("\"other\"").await.unwrap().headers()[ETAG].clone();
+        let res = send(etag.to_str().unwrap()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[ETAG], etag);
+        assert!(res.headers().get(CONTENT_TYPE).is_none());
+        let tags = format!(
+            "\"other\", {}",
+            etag.to_str().unwrap().trim_start_matches("W/")
+        );
+        assert_eq!(
+            send(&tags).await.unwrap().status(),
+            StatusCode::NOT_MODIFIED
+        );
     }
 }
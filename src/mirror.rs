@@ -0,0 +1,224 @@
+//! Copies a share of the API traffic to a secondary deployment, e.g. a new
+//! release or one on another repository backend, to compare it against
+//! production. Mirrored requests are sent in the background and their
+//! answers ignored, clients only ever see the primary's response.
+//!
+//! Writes are mirrored too, so the secondary needs its own database.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{CONNECTION, CONTENT_LENGTH, HOST, TRANSFER_ENCODING},
+    },
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    config::Config,
+    handler::chaos::roll,
+    middleware::X_CORRELATION_ID,
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+    },
+};
+
+/// Sent with every mirrored request so the secondary can tell them apart.
+pub const X_MIRRORED: &str = "X-Mirrored";
+
+/// Larger bodies, and those of unknown length, are not mirrored rather than
+/// buffered.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Mirror {
+    client: reqwest::Client,
+    base_url: String,
+    /// Share of the requests mirrored, in `[0, 1]`.
+    rate: f64,
+}
+
+impl Mirror {
+    pub fn new(base_url: &str, percent: f64) -> Result<Self, AppError> {
+        let invalid = |message: String| AppError {
+            code: AppErrorCode::InvalidInput,
+            message,
+            error_code: None,
+        };
+        reqwest::Url::parse(base_url)
+            .map_err(|_| invalid(format!("Invalid mirror URL '{}'", base_url)))?;
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(invalid(format!(
+                "Mirror percent must be within 0 and 100, got {}",
+                percent
+            )));
+        }
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            rate: percent / 100.0,
+        })
+    }
+
+    /// The configured mirror, `None` when `MIRROR_URL` is unset.
+    pub fn from_config(config: &Config) -> Result<Option<Self>, AppError> {
+        config
+            .mirror_url
+            .as_deref()
+            .map(|url| Self::new(url, config.mirror_percent))
+            .transpose()
+    }
+
+    fn send(&self, req: &Request, headers: HeaderMap, body: axum::body::Bytes) {
+        let path = req
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let request = self
+            .client
+            .request(req.method().clone(), format!("{}{}", self.base_url, path))
+            .headers(headers)
+            .body(body);
+        tokio::spawn(async move {
+            if let Err(e) = request.send().await {
+                tracing::debug!("Mirrored request failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Mirrors the sampled share of requests, then serves them as usual.
+pub async fn mirror_middleware(
+    State(mirror): State<Arc<Mirror>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let len = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let chunked = req.headers().contains_key(TRANSFER_ENCODING);
+    if !roll(mirror.rate) || chunked || len.is_some_and(|len| len > MAX_BODY_BYTES) {
+        return next.run(req).await;
+    }
+
+    let (parts, body) = req.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY_BYTES).await else {
+        // Only possible past a lying Content-Length, the request is gone.
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::empty())
+            .unwrap();
+    };
+    let req = Request::from_parts(parts, Body::from(bytes.clone()));
+
+    let mut headers = req.headers().clone();
+    for name in [HOST, CONNECTION, CONTENT_LENGTH] {
+        headers.remove(name);
+    }
+    headers.insert(X_MIRRORED, HeaderValue::from_static("true"));
+    if let Some(ctx) = req.extensions().get::<Ctx>()
+        && let Ok(correlation_id) = HeaderValue::from_str(&ctx.correlation_id)
+    {
+        headers.insert(X_CORRELATION_ID, correlation_id);
+    }
+    mirror.send(&req, headers, bytes);
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, http::Method, middleware::from_fn_with_state, routing::any};
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert!(Mirror::new("http://shadow:3000/", 10.0).is_ok());
+        assert!(Mirror::new("shadow", 10.0).is_err());
+        assert!(Mirror::new("http://shadow:3000", 150.0).is_err());
+        assert!(Mirror::new("http://shadow:3000", -1.0).is_err());
+    }
+
+    /// A secondary that reports every request it receives.
+    async fn secondary() -> (
+        String,
+        mpsc::UnboundedReceiver<(Method, String, HeaderMap, String)>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = Router::new().fallback(any(move |req: Request| {
+            let tx = tx.clone();
+            async move {
+                let (parts, body) = req.into_parts();
+                let body = to_bytes(body, usize::MAX).await.unwrap();
+                let _ = tx.send((
+                    parts.method,
+                    parts.uri.to_string(),
+                    parts.headers,
+                    String::from_utf8_lossy(&body).to_string(),
+                ));
+                "ignored"
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}", addr), rx)
+    }
+
+    #[tokio::test]
+    async fn test_mirrors_requests() {
+        let (url, mut received) = secondary().await;
+        let mirror = Arc::new(Mirror::new(&url, 100.0).unwrap());
+        let app = Router::new()
+            .route("/api/items", any(|body: String| async move { body }))
+            .layer(from_fn_with_state(mirror, mirror_middleware));
+
+        let req = Request::post("/api/items?dry_run=true")
+            .header(CONTENT_LENGTH, "15")
+            .body(Body::from(r#"{"name":"book"}"#))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, r#"{"name":"book"}"#);
+
+        let (method, uri, headers, body) = received.recv().await.unwrap();
+        assert_eq!(method, Method::POST);
+        assert_eq!(uri, "/api/items?dry_run=true");
+        assert_eq!(headers[X_MIRRORED], "true");
+        assert_eq!(body, r#"{"name":"book"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_skips_unsampled_and_large_requests() {
+        let (url, mut received) = secondary().await;
+        let app = |percent| {
+            let mirror = Arc::new(Mirror::new(&url, percent).unwrap());
+            Router::new()
+                .route("/api/items", any(|| async {}))
+                .layer(from_fn_with_state(mirror, mirror_middleware))
+        };
+
+        let req = Request::get("/api/items").body(Body::empty()).unwrap();
+        app(0.0).oneshot(req).await.unwrap();
+        let req = Request::post("/api/items")
+            .header(CONTENT_LENGTH, (MAX_BODY_BYTES + 1).to_string())
+            .body(Body::from(vec![b'a'; MAX_BODY_BYTES + 1]))
+            .unwrap();
+        app(100.0).oneshot(req).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(received.try_recv().is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::event::EventAction;
+/// One committed write of an item or user, `data` being the record as the
+/// write left it, `None` for deletions.
+/// change, `None` for deletions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Change {
+    /// Orders the changes, later ones have larger numbers.
+    pub seq: i64,
+    pub entity: String,
+    pub entity_id: String,
+    #[schema(value_type = String, example = "updated")]
+    pub action: EventAction,
+    pub data: Option<Value>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// One page of changes for a syncing client, which passes `cursor` as
+/// `since` to get the next one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SyncPage {
+    /// Oldest first. Deleted records show up as `deleted` changes without
+    /// data.
+    pub changes: Vec<Change>,
+    /// `seq` of the last change, or the given `since` when there are none.
+    pub cursor: i64,
+    /// More changes follow, fetch them right away.
+    pub has_more: bool,
+}
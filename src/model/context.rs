@@ -0,0 +1,96 @@
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use crate::model::error::AppError;
+
+/// Identity of the caller, inserted into request extensions by authentication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthUser {
+    pub id: String,
+}
+
+/// Per-request context handed from the handlers down to the services.
+#[derive(Debug, Clone, Default)]
+pub struct Ctx {
+    /// Id of the whole operation, propagated from the caller when it sent one.
+    pub correlation_id: String,
+    /// Id of this one request, always generated here.
+    pub request_id: String,
+    pub user: Option<AuthUser>,
+    pub tenant: Option<String>,
+    pub locale: Option<String>,
+    pub deadline: Option<Instant>,
+    /// Address of the client, resolved through trusted proxies.
+    pub client_ip: Option<IpAddr>,
+}
+
+impl Ctx {
+    pub fn new(correlation_id: impl Into<String>) -> Self {
+        Self {
+            correlation_id: correlation_id.into(),
+            request_id: Uuid::new_v4().to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Time left before the deadline, `None` when the request has none.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_some_and(|left| left.is_zero())
+    }
+
+    /// Runs `fut`, e.g. a repository query, within the time left and fails
+    /// with `DeadlineExceeded` past the deadline.
+    pub async fn with_deadline<T>(
+        &self,
+        fut: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        let Some(left) = self.remaining() else {
+            return fut.await;
+        };
+        tokio::time::timeout(left, fut)
+            .await
+            .unwrap_or_else(|_| Err(AppError::deadline_exceeded()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline() {
+        let ctx = Ctx::new("abc");
+        assert!(ctx.remaining().is_none());
+        assert!(!ctx.is_expired());
+
+        let ctx = Ctx {
+            deadline: Some(Instant::now()),
+            ..Ctx::new("abc")
+        };
+        assert!(ctx.is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_with_deadline() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(())
+        };
+        let ctx = Ctx {
+            deadline: Some(Instant::now() + Duration::from_millis(10)),
+            ..Ctx::new("abc")
+        };
+        let e = ctx.with_deadline(slow).await.unwrap_err();
+        assert_eq!(e.get_http_status(), axum::http::StatusCode::GATEWAY_TIMEOUT);
+        assert!(Ctx::new("abc").with_deadline(async { Ok(1) }).await.is_ok());
+    }
+}
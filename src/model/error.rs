@@ -1,23 +1,131 @@
 use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum AppErrorCode {
+    #[error("not found")]
     NotFound,
+    #[error("invalid input")]
     InvalidInput,
+    #[error("validation failed")]
+    Validation(Vec<FieldError>),
+    /// Well-formed but can't be carried out, e.g. a JSON Patch operation
+    /// whose path doesn't exist.
+    #[error("unprocessable")]
+    Unprocessable(Vec<FieldError>),
+    #[error("conflict")]
+    Conflict,
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("forbidden")]
+    Forbidden,
+    #[error("method not allowed")]
+    MethodNotAllowed,
+    #[error("unsupported media type")]
+    UnsupportedMediaType,
+    #[error("too many requests")]
+    TooManyRequests,
+    #[error("precondition failed")]
+    PreconditionFailed,
+    #[error("service unavailable")]
+    Unavailable,
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+    #[error("{0}")]
     InternalError(String),
 }
 
-#[derive(Debug, Clone)]
+/// One invalid input field, e.g. `{"field": "email", "code": "required", ...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Stable code sent to clients as `error_code`, unlike `message` it never
+/// changes wording so clients can branch on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    ValidationFailed,
+    InvalidId,
+    NotFound,
+    ItemNotFound,
+    UserNotFound,
+    EmailTaken,
+    SlugTaken,
+    NameTaken,
+    PatchFailed,
+    InvalidToken,
+    UnsupportedApiVersion,
+    Conflict,
+    Unprocessable,
+    Unauthorized,
+    Forbidden,
+    MethodNotAllowed,
+    UnsupportedMediaType,
+    RateLimited,
+    PreconditionFailed,
+    ServiceUnavailable,
+    Timeout,
+    DeadlineExceeded,
+    InternalError,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{message}")]
 pub struct AppError {
     pub code: AppErrorCode,
     pub message: String,
+    /// Overrides the generic code derived from `code`, e.g. `ITEM_NOT_FOUND`.
+    pub error_code: Option<ErrorCode>,
 }
 
 impl AppError {
+    /// Aggregates every invalid field into a single error.
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        AppError {
+            code: AppErrorCode::Validation(errors),
+            message: "Validation failed".to_string(),
+            error_code: None,
+        }
+    }
+
+    /// The caller's deadline passed before the request was done.
+    pub fn deadline_exceeded() -> Self {
+        AppError {
+            code: AppErrorCode::DeadlineExceeded,
+            message: "Request deadline exceeded".to_string(),
+            error_code: None,
+        }
+    }
+
     pub fn get_http_status(&self) -> StatusCode {
-        match self.code {
+        match &self.code {
             AppErrorCode::NotFound => StatusCode::NOT_FOUND,
-            AppErrorCode::InvalidInput => StatusCode::BAD_REQUEST,
+            AppErrorCode::InvalidInput | AppErrorCode::Validation(_) => StatusCode::BAD_REQUEST,
+            AppErrorCode::Conflict => StatusCode::CONFLICT,
+            AppErrorCode::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            AppErrorCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            AppErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppErrorCode::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            AppErrorCode::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            AppErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            AppErrorCode::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
             AppErrorCode::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -26,6 +134,36 @@ impl AppError {
         self.message.clone()
     }
 
+    pub fn get_error_code(&self) -> ErrorCode {
+        if let Some(error_code) = self.error_code {
+            return error_code;
+        }
+        match &self.code {
+            AppErrorCode::NotFound => ErrorCode::NotFound,
+            AppErrorCode::InvalidInput | AppErrorCode::Validation(_) => ErrorCode::ValidationFailed,
+            AppErrorCode::Conflict => ErrorCode::Conflict,
+            AppErrorCode::Unprocessable(_) => ErrorCode::Unprocessable,
+            AppErrorCode::Unauthorized => ErrorCode::Unauthorized,
+            AppErrorCode::Forbidden => ErrorCode::Forbidden,
+            AppErrorCode::MethodNotAllowed => ErrorCode::MethodNotAllowed,
+            AppErrorCode::UnsupportedMediaType => ErrorCode::UnsupportedMediaType,
+            AppErrorCode::TooManyRequests => ErrorCode::RateLimited,
+            AppErrorCode::PreconditionFailed => ErrorCode::PreconditionFailed,
+            AppErrorCode::Unavailable => ErrorCode::ServiceUnavailable,
+            AppErrorCode::DeadlineExceeded => ErrorCode::DeadlineExceeded,
+            AppErrorCode::InternalError(_) => ErrorCode::InternalError,
+        }
+    }
+
+    pub fn get_field_errors(&self) -> Vec<FieldError> {
+        match &self.code {
+            AppErrorCode::Validation(errors) | AppErrorCode::Unprocessable(errors) => {
+                errors.clone()
+            }
+            _ => vec![],
+        }
+    }
+
     pub fn get_error(&self) -> String {
         match &self.code {
             AppErrorCode::InternalError(e) => e.into(),
@@ -33,3 +171,145 @@ impl AppError {
         }
     }
 }
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => AppError {
+                code: AppErrorCode::NotFound,
+                message: "Record not found".to_string(),
+                error_code: None,
+            },
+            sqlx::Error::Database(db) if db.is_unique_violation() => AppError {
+                code: AppErrorCode::Conflict,
+                message: "Record already exists".to_string(),
+                error_code: None,
+            },
+            sqlx::Error::Database(db) if db.is_foreign_key_violation() => AppError {
+                code: AppErrorCode::InvalidInput,
+                message: "Referenced record does not exist".to_string(),
+                error_code: None,
+            },
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed => AppError {
+                code: AppErrorCode::Unavailable,
+                message: "Database unavailable".to_string(),
+                error_code: None,
+            },
+            _ => AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Database error".to_string(),
+                error_code: None,
+            },
+        }
+    }
+}
+
+impl From<validator::ValidationErrors> for AppError {
+    fn from(e: validator::ValidationErrors) -> Self {
+        let mut errors: Vec<FieldError> = e
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                errors.iter().map(move |e| {
+                    FieldError::new(
+                        &field,
+                        &e.code,
+                        e.message
+                            .as_ref()
+                            .map(|m| m.to_string())
+                            .unwrap_or_else(|| format!("{} is invalid", field)),
+                    )
+                })
+            })
+            .collect();
+        errors.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::validation(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_not_found_maps_to_not_found() {
+        let e = AppError::from(sqlx::Error::RowNotFound);
+        assert_eq!(e.get_http_status(), StatusCode::NOT_FOUND);
+        assert_eq!(e.get_error(), "");
+    }
+
+    #[test]
+    fn test_pool_timeout_maps_to_unavailable() {
+        let e = AppError::from(sqlx::Error::PoolTimedOut);
+        assert_eq!(e.get_http_status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(e.to_string(), "Database unavailable");
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        let cases = [
+            (AppErrorCode::Conflict, StatusCode::CONFLICT),
+            (
+                AppErrorCode::Unprocessable(vec![]),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (AppErrorCode::Unauthorized, StatusCode::UNAUTHORIZED),
+            (AppErrorCode::Forbidden, StatusCode::FORBIDDEN),
+            (
+                AppErrorCode::MethodNotAllowed,
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+            (
+                AppErrorCode::UnsupportedMediaType,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ),
+            (AppErrorCode::TooManyRequests, StatusCode::TOO_MANY_REQUESTS),
+            (
+                AppErrorCode::PreconditionFailed,
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            (AppErrorCode::Unavailable, StatusCode::SERVICE_UNAVAILABLE),
+            (AppErrorCode::DeadlineExceeded, StatusCode::GATEWAY_TIMEOUT),
+        ];
+        for (code, status) in cases {
+            let e = AppError {
+                code,
+                message: "".into(),
+                error_code: None,
+            };
+            assert_eq!(e.get_http_status(), status);
+        }
+    }
+
+    #[test]
+    fn test_validation_error() {
+        let e = AppError::validation(vec![
+            FieldError::new("id", "required", "Item ID cannot be empty"),
+            FieldError::new("name", "required", "Item name cannot be empty"),
+        ]);
+        assert_eq!(e.get_http_status(), StatusCode::BAD_REQUEST);
+        assert_eq!(e.get_error_code(), ErrorCode::ValidationFailed);
+        assert_eq!(e.get_field_errors().len(), 2);
+        assert_eq!(e.get_field_errors()[1].field, "name");
+    }
+
+    #[test]
+    fn test_error_code() {
+        let mut e = AppError {
+            code: AppErrorCode::NotFound,
+            message: "Item with id 1 not found".into(),
+            error_code: None,
+        };
+        assert_eq!(e.get_error_code(), ErrorCode::NotFound);
+
+        e.error_code = Some(ErrorCode::ItemNotFound);
+        assert_eq!(e.get_error_code(), ErrorCode::ItemNotFound);
+        assert_eq!(
+            serde_json::to_value(e.get_error_code()).unwrap(),
+            "ITEM_NOT_FOUND"
+        );
+    }
+}
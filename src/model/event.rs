@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl EventAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventAction::Created => "created",
+            EventAction::Updated => "updated",
+            EventAction::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "created" => Some(EventAction::Created),
+            "updated" => Some(EventAction::Updated),
+            "deleted" => Some(EventAction::Deleted),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub action: EventAction,
+    pub data: Option<serde_json::Value>,
+}
+
+impl Event {
+    pub fn new<T: Serialize>(
+        entity: &str,
+        action: EventAction,
+        entity_id: &str,
+        data: Option<&T>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            entity: entity.into(),
+            entity_id: entity_id.into(),
+            action,
+            data: data.and_then(|d| serde_json::to_value(d).ok()),
+        }
+    }
+
+    /// Routing key in the form `<entity>.<action>`, e.g. `item.created`.
+    pub fn topic(&self) -> String {
+        format!("{}.{}", self.entity, self.action.as_str())
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::job::{Job, JobStatus};
+
+/// File format an export is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// Columnar, for loading straight into Spark, DuckDB and the like.
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "csv" => Some(ExportFormat::Csv),
+            "parquet" => Some(ExportFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    /// The format an export job writes, CSV for jobs queued before formats
+    /// existed.
+    pub fn of(job: &Job) -> Self {
+        job.payload["format"]
+            .as_str()
+            .and_then(Self::parse)
+            .unwrap_or_default()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// Client view of an export job. `download_url` is set once the file is ready.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ExportJob {
+    pub id: String,
+    pub format: ExportFormat,
+    /// `pending`, `running`, `done` or `failed`.
+    pub status: String,
+    /// Percent done.
+    pub progress: i32,
+    pub error: Option<String>,
+    pub download_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ExportJob {
+    /// `path` is where the export jobs are served, e.g. `/api/export-jobs`.
+    pub fn from_job(job: Job, path: &str) -> Self {
+        let download_url =
+            (job.status == JobStatus::Done).then(|| format!("{}/{}/download", path, job.id));
+        Self {
+            format: ExportFormat::of(&job),
+            status: job.status.as_str().to_string(),
+            progress: job.progress,
+            error: job.last_error,
+            download_url,
+            created_at: job.created_at,
+            id: job.id,
+        }
+    }
+}
@@ -1,9 +1,613 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    Json,
+    http::{
+        HeaderValue, StatusCode,
+        header::{ETAG, LAST_MODIFIED, LINK},
+    },
+    response::{IntoResponse, Response as AxumResponse},
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, value::RawValue};
+use utoipa::ToSchema;
+
+use super::error::{AppError, ErrorCode, FieldError};
+use crate::messages::MessageKey;
+
+/// Formats `time` as an HTTP date, e.g. `Thu, 31 Dec 2026 23:59:59 GMT`.
+pub fn http_date(time: DateTime<Utc>) -> String {
+    time.format(HTTP_DATE_FORMAT).to_string()
+}
+
+/// Strong validator of an entity at `version`, e.g. `"3"`. Every update bumps
+/// the version, so the same tag answers `If-None-Match` on reads and
+/// `If-Match` on updates.
+pub fn version_etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// The version a [`version_etag`] stands for, `None` for any other tag, weak
+/// ones included.
+pub fn parse_version_etag(tag: &str) -> Option<i64> {
+    tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()
+}
+
+/// Whether an `If-None-Match` or `If-Range` list names `etag`, comparing
+/// weakly, i.e. ignoring `W/` on either side.
+pub fn etag_matches(header: Option<&HeaderValue>, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    header
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        })
+}
+
+/// Parses an HTTP date as sent in `If-Modified-Since`.
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value.trim(), HTTP_DATE_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
 
-#[derive(Serialize, Deserialize)]
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct Response<T> {
     pub correlation_id: String,
     pub message: String,
     pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
     pub data: Option<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Links>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Links {
+    #[serde(rename = "self")]
+    pub self_link: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub related: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last: Option<String>,
+}
+
+impl Links {
+    /// Links for a collection mounted at `path`, e.g. `/api/items`.
+    pub fn collection(path: &str) -> Self {
+        Self {
+            self_link: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Links for the keyset page at `cursor` of the collection mounted at
+    /// `path`, keeping its `limit` and `fields`. `prev`, `next` and `last`
+    /// are the cursors of the neighbouring pages, each left out when there
+    /// is no such page.
+    pub fn cursor_page(
+        path: &str,
+        cursor: Option<&str>,
+        prev: Option<String>,
+        next: Option<String>,
+        last: Option<String>,
+        limit: i64,
+        fields: &Fields,
+    ) -> Self {
+        let fields = fields
+            .names()
+            .map(|names| format!("&fields={}", names.join(",")))
+            .unwrap_or_default();
+        let page = |cursor: Option<&str>| match cursor {
+            Some(cursor) => format!("{}?cursor={}&limit={}{}", path, cursor, limit, fields),
+            None => format!("{}?limit={}{}", path, limit, fields),
+        };
+        Self {
+            self_link: page(cursor),
+            next: next.as_deref().map(|next| page(Some(next))),
+            prev: prev.as_deref().map(|prev| page(Some(prev))),
+            first: Some(page(None)),
+            last: last.as_deref().map(|last| page(Some(last))),
+            ..Default::default()
+        }
+    }
+
+    /// Links for a single resource of the collection mounted at `path`.
+    pub fn resource(path: &str, id: &str) -> Self {
+        Self {
+            self_link: format!("{}/{}", path, id),
+            collection: Some(path.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_related(mut self, name: &str, href: &str) -> Self {
+        self.related.insert(name.into(), href.into());
+        self
+    }
+
+    /// Whether these are the links of one page of a collection.
+    pub fn is_paginated(&self) -> bool {
+        [&self.next, &self.prev, &self.first, &self.last]
+            .iter()
+            .any(|href| href.is_some())
+    }
+
+    /// RFC 8288 `Link` value, e.g. `</api/v1/items/1>; rel="self"`.
+    pub fn header_value(&self) -> String {
+        let mut values = vec![format!("<{}>; rel=\"self\"", self.self_link)];
+        let named = [
+            ("collection", &self.collection),
+            ("first", &self.first),
+            ("prev", &self.prev),
+            ("next", &self.next),
+            ("last", &self.last),
+        ];
+        for (rel, href) in named {
+            if let Some(href) = href {
+                values.push(format!("<{}>; rel=\"{}\"", href, rel));
+            }
+        }
+        for (title, href) in &self.related {
+            values.push(format!("<{}>; rel=\"related\"; title=\"{}\"", href, title));
+        }
+        values.join(", ")
+    }
+}
+
+/// Entities whose top-level fields can be picked with `?fields=`.
+pub trait Projectable {
+    /// Every field the entity may serialize, optional ones included.
+    const FIELDS: &'static [&'static str];
+}
+
+/// Sparse fieldset, e.g. `?fields=id,name`: only these top-level fields of
+/// `data`, or of each of its elements when it's a list, are sent. The default
+/// sends every field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields(Option<Vec<String>>);
+
+impl Fields {
+    /// Parses a comma separated list, names outside `known` are rejected.
+    pub fn parse(value: Option<&str>, known: &[&str]) -> Result<Self, AppError> {
+        let Some(value) = value else {
+            return Ok(Self::default());
+        };
+        let names: Vec<String> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect();
+        if names.is_empty() {
+            return Err(AppError::validation(vec![FieldError::new(
+                "fields",
+                "required",
+                "Fields must name at least one field",
+            )]));
+        }
+        let errors: Vec<FieldError> = names
+            .iter()
+            .filter(|name| !known.contains(&name.as_str()))
+            .map(|name| {
+                FieldError::new(
+                    "fields",
+                    "unknown",
+                    format!("Unknown field {}, expected {}", name, known.join(", ")),
+                )
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+        Ok(Self(Some(names)))
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// The picked fields, for links that should keep them.
+    pub fn names(&self) -> Option<&[String]> {
+        self.0.as_deref()
+    }
+
+    /// Also keeps `name` when only some fields are picked, e.g. a related
+    /// resource embedded on request.
+    pub fn keep(mut self, name: &str) -> Self {
+        if let Some(names) = &mut self.0
+            && !names.iter().any(|n| n == name)
+        {
+            names.push(name.into());
+        }
+        self
+    }
+
+    /// Drops the fields that weren't picked from `data`.
+    pub fn project(&self, data: Value) -> Value {
+        let Some(names) = &self.0 else {
+            return data;
+        };
+        match data {
+            Value::Object(mut object) => {
+                object.retain(|key, _| names.contains(key));
+                Value::Object(object)
+            }
+            Value::Array(elements) => {
+                Value::Array(elements.into_iter().map(|e| self.project(e)).collect())
+            }
+            data => data,
+        }
+    }
+}
+
+/// Standard envelope paired with its status code, handlers return this instead
+/// of assembling `(StatusCode, Json<_>)` tuples by hand. The typed body is
+/// serialized straight to bytes, without a `serde_json::Value` in between.
+pub struct ApiResponse<T> {
+    pub status: StatusCode,
+    pub body: Response<T>,
+    /// Lets the message catalog override `body.message`.
+    pub message_key: Option<MessageKey>,
+    /// Sent as `Last-Modified`, conditional requests are then answered by
+    /// [`crate::middleware::not_modified_middleware`].
+    pub last_modified: Option<DateTime<Utc>>,
+    /// Sent as `ETag`, `If-None-Match` is then answered by
+    /// [`crate::middleware::not_modified_middleware`] as well.
+    pub etag: Option<String>,
+    /// Fields of `data` to send, the body then goes through a
+    /// `serde_json::Value` to drop the others.
+    pub fields: Fields,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn new(status: StatusCode, correlation_id: String, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: Response {
+                correlation_id,
+                message: message.into(),
+                error: "".into(),
+                error_code: None,
+                errors: vec![],
+                data: None,
+                links: None,
+            },
+            message_key: None,
+            last_modified: None,
+            etag: None,
+            fields: Fields::default(),
+        }
+    }
+
+    /// `200 OK` carrying `data`.
+    pub fn ok(correlation_id: String, data: T) -> Self {
+        Self::new(StatusCode::OK, correlation_id, "ok").data(data)
+    }
+
+    /// `201 Created` carrying the new entity.
+    pub fn created(correlation_id: String, data: T, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CREATED, correlation_id, message).data(data)
+    }
+
+    pub fn data(mut self, data: T) -> Self {
+        self.body.data = Some(data);
+        self
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.body.message = message.into();
+        self
+    }
+
+    pub fn links(mut self, links: Links) -> Self {
+        self.body.links = Some(links);
+        self
+    }
+
+    pub fn last_modified(mut self, time: DateTime<Utc>) -> Self {
+        self.last_modified = Some(time);
+        self
+    }
+
+    /// Tags the response with the entity's `version`, see [`version_etag`],
+    /// so polling clients get a body-less `304 Not Modified` while it stays
+    /// the same and updates can name it in `If-Match`.
+    pub fn etag(mut self, version: i64) -> Self {
+        self.etag = Some(version_etag(version));
+        self
+    }
+
+    pub fn fields(mut self, fields: Fields) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Names the message in the catalog, e.g. `user.created`, with the
+    /// values its template may refer to.
+    pub fn message_key<const N: usize>(
+        mut self,
+        key: impl Into<String>,
+        args: [(&'static str, String); N],
+    ) -> Self {
+        self.message_key = Some(MessageKey {
+            key: key.into(),
+            args: args.into(),
+        });
+        self
+    }
+}
+
+impl ApiResponse<()> {
+    /// `200 OK` with only a message, e.g. after a delete.
+    pub fn done(correlation_id: String, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::OK, correlation_id, message)
+    }
+
+    /// `204 No Content`, the envelope is not sent.
+    pub fn no_content(correlation_id: String) -> Self {
+        Self::new(StatusCode::NO_CONTENT, correlation_id, "")
+    }
+
+    pub fn error(correlation_id: String, e: AppError) -> Self {
+        let mut res = Self::new(e.get_http_status(), correlation_id, e.get_message());
+        res.body.error = e.get_error();
+        res.body.error_code = Some(e.get_error_code());
+        res.body.errors = e.get_field_errors();
+        res
+    }
+}
+
+/// Serializes `data` once, keeping only the requested `fields`; the envelope
+/// embeds these bytes as they are.
+fn encode_data<D: Serialize>(data: D, fields: &Fields) -> serde_json::Result<Box<RawValue>> {
+    if fields.is_all() {
+        serde_json::value::to_raw_value(&data)
+    } else {
+        serde_json::value::to_raw_value(&fields.project(serde_json::to_value(data)?))
+    }
+}
+
+/// Handler return type, both arms render the standard envelope.
+pub type ApiResult<T> = Result<ApiResponse<T>, ApiResponse<()>>;
+
+impl<T: Serialize> IntoResponse for ApiResponse<T> {
+    fn into_response(self) -> AxumResponse {
+        if self.status == StatusCode::NO_CONTENT {
+            return self.status.into_response();
+        }
+        // Pages also link their neighbours in a header, for generic clients.
+        let link = self
+            .body
+            .links
+            .as_ref()
+            .filter(|links| links.is_paginated())
+            .and_then(|links| HeaderValue::from_str(&links.header_value()).ok());
+        let data = match self.body.data.map(|data| encode_data(data, &self.fields)) {
+            Some(Ok(data)) => Some(data),
+            Some(Err(e)) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            None => None,
+        };
+        let body = Response {
+            data,
+            correlation_id: self.body.correlation_id,
+            message: self.body.message,
+            error: self.body.error,
+            error_code: self.body.error_code,
+            errors: self.body.errors,
+            links: self.body.links,
+        };
+        let mut res = (self.status, Json(body)).into_response();
+        if let Some(link) = link {
+            res.headers_mut().insert(LINK, link);
+        }
+        if let Some(key) = self.message_key {
+            res.extensions_mut().insert(key);
+        }
+        if let Some(time) = self.last_modified
+            && let Ok(value) = HeaderValue::from_str(&http_date(time))
+        {
+            res.headers_mut().insert(LAST_MODIFIED, value);
+        }
+        if let Some(value) = self.etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+            res.headers_mut().insert(ETAG, value);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::error::AppErrorCode;
+    use axum::body::to_bytes;
+    use serde_json::json;
+
+    async fn body_json(res: AxumResponse) -> Value {
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_response_created() {
+        let res = ApiResponse::created("abc".into(), json!({"id": "1"}), "Created")
+            .links(Links::resource("/api/items", "1"))
+            .into_response();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(
+            body_json(res).await,
+            json!({
+                "correlation_id": "abc",
+                "message": "Created",
+                "error": "",
+                "data": {"id": "1"},
+                "links": {"self": "/api/items/1", "collection": "/api/items"},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_response_error() {
+        let e = AppError {
+            code: AppErrorCode::NotFound,
+            message: "Item with id 1 not found".into(),
+            error_code: Some(ErrorCode::ItemNotFound),
+        };
+        let res = ApiResponse::error("abc".into(), e).into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body = body_json(res).await;
+        assert_eq!(body["message"], "Item with id 1 not found");
+        assert_eq!(body["error_code"], "ITEM_NOT_FOUND");
+        assert_eq!(body["data"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_api_response_field_errors() {
+        let e = AppError::validation(vec![FieldError::new(
+            "name",
+            "required",
+            "Name is required",
+        )]);
+        let body = body_json(ApiResponse::error("abc".into(), e).into_response()).await;
+        assert_eq!(body["error_code"], "VALIDATION_FAILED");
+        assert_eq!(
+            body["errors"],
+            json!([{"field": "name", "code": "required", "message": "Name is required"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_response_no_content_has_empty_body() {
+        let res = ApiResponse::no_content("abc".into()).into_response();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_api_response_etag_is_the_version() {
+        let etag = |res: ApiResponse<Value>| res.into_response().headers().get(ETAG).cloned();
+        let item = json!({"id": "1", "name": "book", "version": 3});
+
+        assert!(etag(ApiResponse::ok("abc".into(), item.clone())).is_none());
+        let tagged = etag(ApiResponse::ok("abc".into(), item.clone()).etag(3)).unwrap();
+        assert_eq!(tagged, "\"3\"");
+        assert_eq!(tagged, version_etag(3));
+        // It names the entity's state, whichever fields are sent.
+        let fields = Fields::parse(Some("id"), &["id", "name"]).unwrap();
+        let sparse = ApiResponse::ok("abc".into(), item).fields(fields).etag(3);
+        assert_eq!(etag(sparse), Some(tagged));
+    }
+
+    #[test]
+    fn test_resource_links() {
+        let links = Links::resource("/api/items", "1");
+        assert_eq!(
+            json!(links),
+            json!({"self": "/api/items/1", "collection": "/api/items"})
+        );
+    }
+
+    #[test]
+    fn test_collection_links_with_related() {
+        let links = Links::collection("/").with_related("items", "/api/items");
+        assert_eq!(
+            json!(links),
+            json!({"self": "/", "related": {"items": "/api/items"}})
+        );
+    }
+
+    #[test]
+    fn test_fields_parse() {
+        let known = ["id", "name"];
+        assert!(Fields::parse(None, &known).unwrap().is_all());
+        assert_eq!(
+            Fields::parse(Some(" id, ,name"), &known).unwrap(),
+            Fields(Some(vec!["id".into(), "name".into()]))
+        );
+        let err = Fields::parse(Some("id,price"), &known).unwrap_err();
+        assert_eq!(err.get_field_errors()[0].code, "unknown");
+        assert!(Fields::parse(Some(","), &known).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_api_response_fields() {
+        let fields = Fields::parse(Some("id"), &["id", "name"]).unwrap();
+        let data = vec![json!({"id": "1", "name": "book"}), json!({"id": "2"})];
+        let res = ApiResponse::ok("abc".into(), data)
+            .fields(fields)
+            .into_response();
+        assert_eq!(
+            body_json(res).await["data"],
+            json!([{"id": "1"}, {"id": "2"}])
+        );
+    }
+
+    #[test]
+    fn test_http_date() {
+        let time = DateTime::parse_from_rfc3339("2026-12-31T23:59:59Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(http_date(time), "Thu, 31 Dec 2026 23:59:59 GMT");
+        assert_eq!(parse_http_date("Thu, 31 Dec 2026 23:59:59 GMT"), Some(time));
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_api_response_last_modified() {
+        let time = DateTime::parse_from_rfc3339("2026-12-31T23:59:59.5Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let res = ApiResponse::ok("abc".into(), json!({}))
+            .last_modified(time)
+            .into_response();
+        assert_eq!(
+            res.headers()[LAST_MODIFIED],
+            "Thu, 31 Dec 2026 23:59:59 GMT"
+        );
+    }
+
+    #[test]
+    fn test_api_response_pagination_links() {
+        let links = Links {
+            next: Some("/api/items?page=3".into()),
+            prev: Some("/api/items?page=1".into()),
+            first: Some("/api/items?page=1".into()),
+            last: Some("/api/items?page=9".into()),
+            ..Links::collection("/api/items?page=2")
+        };
+        let res = ApiResponse::ok("abc".into(), json!([]))
+            .links(links)
+            .into_response();
+        assert_eq!(
+            res.headers()[LINK],
+            "</api/items?page=2>; rel=\"self\", </api/items?page=1>; rel=\"first\", \
+             </api/items?page=1>; rel=\"prev\", </api/items?page=3>; rel=\"next\", \
+             </api/items?page=9>; rel=\"last\""
+        );
+
+        let res = ApiResponse::ok("abc".into(), json!([]))
+            .links(Links::collection("/api/items"))
+            .into_response();
+        assert!(res.headers().get(LINK).is_none());
+    }
 }
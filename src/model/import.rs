@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::job::Job;
+
+/// A CSV row that could not be imported, `row` counts data rows from 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ImportRowError {
+    pub row: u64,
+    pub message: String,
+}
+
+/// Running tally of an import, stored on the job as it progresses so a
+/// retried job resumes after the last processed row.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ImportResult {
+    pub rows_processed: u64,
+    pub rows_failed: u64,
+    /// The first failures only, see `rows_failed` for the total.
+    pub errors: Vec<ImportRowError>,
+}
+
+/// Client view of an import job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ImportJob {
+    pub id: String,
+    /// `pending`, `running`, `done` or `failed`.
+    pub status: String,
+    /// Percent done.
+    pub progress: i32,
+    #[serde(flatten)]
+    pub result: ImportResult,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Job> for ImportJob {
+    fn from(job: Job) -> Self {
+        Self {
+            status: job.status.as_str().to_string(),
+            progress: job.progress,
+            result: job
+                .result
+                .and_then(|result| serde_json::from_value(result).ok())
+                .unwrap_or_default(),
+            error: job.last_error,
+            created_at: job.created_at,
+            id: job.id,
+        }
+    }
+}
@@ -1,7 +1,114 @@
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize, Clone, Debug)]
+use super::{http::Projectable, user::User};
+
+/// Longest slug generated from a name, before any suffix making it unique.
+pub const SLUG_MAX_LEN: usize = 80;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
 pub struct Item {
     pub id: String,
     pub name: String,
+    /// URL-safe, unique name kept across renames, see `GET /items/slug/{slug}`.
+    pub slug: String,
+    /// User the item belongs to, see `GET /users/{id}/items`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_id: Option<String>,
+    /// Starts at 1 and goes up with every update. Reads send it quoted as the
+    /// `ETag`, send that in `If-Match` to update only the version read.
+    #[serde(default = "first_version")]
+    pub version: i64,
+}
+
+impl Projectable for Item {
+    const FIELDS: &'static [&'static str] = &["id", "name", "slug", "owner_id", "version"];
+}
+
+/// Item with the related resources asked for with `?include=` embedded,
+/// serialized as the plain item when none were.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct ExpandedItem {
+    #[serde(flatten)]
+    pub item: Item,
+    /// With `?include=owner`, left out for items without an owner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<User>,
+}
+
+impl From<Item> for ExpandedItem {
+    fn from(item: Item) -> Self {
+        Self { item, owner: None }
+    }
+}
+
+/// Version of new entities, and of ones stored before versions were tracked.
+pub fn first_version() -> i64 {
+    1
+}
+
+/// Narrows an item list, unset fields match every item.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListItemFilter {
+    /// Part of the name, ignoring case.
+    pub name_contains: Option<String>,
+    /// Only items created strictly after this moment.
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+/// Lowercase ASCII letters and digits of `name`, other runs of characters
+/// becoming a single `-`, e.g. `"Blue Mug (XL)"` is `blue-mug-xl`. Names
+/// without any give `item`.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(SLUG_MAX_LEN);
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "item".into()
+    } else {
+        slug.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Blue Mug (XL)"), "blue-mug-xl");
+        assert_eq!(slugify("--Hello, World!--"), "hello-world");
+        assert_eq!(slugify("Crème brûlée"), "cr-me-br-l-e");
+        assert_eq!(slugify("ÄÖ"), "item");
+        assert_eq!(slugify(&"a".repeat(300)).len(), SLUG_MAX_LEN);
+        assert_eq!(slugify(&format!("{} b", "a".repeat(79))), "a".repeat(79));
+    }
+
+    #[test]
+    fn test_fields_cover_serialization() {
+        let item = Item {
+            id: "1".into(),
+            name: "book".into(),
+            slug: "book".into(),
+            owner_id: Some("u1".into()),
+            version: 1,
+        };
+        let value = serde_json::to_value(item).unwrap();
+        let keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(keys.len(), Item::FIELDS.len());
+        assert!(keys.iter().all(|key| Item::FIELDS.contains(key)));
+    }
 }
@@ -0,0 +1,275 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{error::ErrorCode, http::Response};
+
+pub const JSON_API_MEDIA_TYPE: &str = "application/vnd.api+json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resource {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    pub attributes: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub relationships: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PrimaryData {
+    One(Resource),
+    Many(Vec<Resource>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorObject {
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ErrorSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorSource {
+    pub pointer: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Links {
+    #[serde(rename = "self")]
+    pub self_link: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<PrimaryData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<ErrorObject>>,
+    pub meta: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<Links>,
+}
+
+impl Resource {
+    /// Splits a flat entity into `id` and `attributes`, returns `None` when it has no id.
+    pub fn from_value(kind: &str, value: Value) -> Option<Self> {
+        let Value::Object(mut attributes) = value else {
+            return None;
+        };
+        let id = match attributes.remove("id")? {
+            Value::String(id) => id,
+            other => other.to_string(),
+        };
+        Some(Self {
+            kind: kind.into(),
+            id,
+            attributes,
+            relationships: Map::new(),
+        })
+    }
+}
+
+impl Document {
+    /// Converts the standard response envelope into a JSON:API top-level document.
+    pub fn from_envelope(
+        kind: &str,
+        status: StatusCode,
+        self_link: &str,
+        envelope: Response<Value>,
+    ) -> Self {
+        let mut meta = Map::new();
+        meta.insert(
+            "correlation_id".into(),
+            Value::String(envelope.correlation_id),
+        );
+
+        if status.is_client_error() || status.is_server_error() {
+            let status = status.as_u16().to_string();
+            let errors = if envelope.errors.is_empty() {
+                vec![ErrorObject {
+                    status,
+                    code: envelope.error_code,
+                    title: envelope.message,
+                    detail: Some(envelope.error).filter(|e| !e.is_empty()),
+                    source: None,
+                }]
+            } else {
+                // One error object per invalid field, pointing at its attribute.
+                envelope
+                    .errors
+                    .into_iter()
+                    .map(|e| ErrorObject {
+                        status: status.clone(),
+                        code: envelope.error_code,
+                        title: envelope.message.clone(),
+                        detail: Some(e.message),
+                        source: Some(ErrorSource {
+                            pointer: format!("/data/attributes/{}", e.field),
+                        }),
+                    })
+                    .collect()
+            };
+            return Self {
+                data: None,
+                errors: Some(errors),
+                meta,
+                links: None,
+            };
+        }
+
+        meta.insert("message".into(), Value::String(envelope.message));
+        let pages = envelope.links.unwrap_or_default();
+        let data = match envelope.data {
+            Some(Value::Array(values)) => Some(PrimaryData::Many(
+                values
+                    .into_iter()
+                    .filter_map(|v| Resource::from_value(kind, v))
+                    .collect(),
+            )),
+            Some(value) => Resource::from_value(kind, value).map(PrimaryData::One),
+            None => None,
+        };
+        Self {
+            data,
+            errors: None,
+            meta,
+            links: Some(Links {
+                self_link: self_link.into(),
+                next: pages.next,
+                prev: pages.prev,
+                first: pages.first,
+                last: pages.last,
+            }),
+        }
+    }
+}
+
+/// Extracts `data.attributes` from a JSON:API request document, so handlers
+/// can keep deserializing their plain payload types.
+pub fn attributes_from_document(document: Value) -> Option<Value> {
+    match document {
+        Value::Object(mut doc) => match doc.remove("data")? {
+            Value::Object(mut data) => data.remove("attributes"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::error::FieldError;
+    use serde_json::json;
+
+    fn envelope(data: Option<Value>) -> Response<Value> {
+        Response {
+            correlation_id: "abc".into(),
+            message: "ok".into(),
+            error: "".into(),
+            error_code: None,
+            errors: vec![],
+            data,
+            links: None,
+        }
+    }
+
+    #[test]
+    fn test_single_resource() {
+        let doc = Document::from_envelope(
+            "items",
+            StatusCode::OK,
+            "/api/items/1",
+            envelope(Some(json!({"id": "1", "name": "book"}))),
+        );
+        assert_eq!(
+            json!(doc),
+            json!({
+                "data": {"type": "items", "id": "1", "attributes": {"name": "book"}},
+                "meta": {"correlation_id": "abc", "message": "ok"},
+                "links": {"self": "/api/items/1"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_collection() {
+        let doc = Document::from_envelope(
+            "users",
+            StatusCode::OK,
+            "/api/users",
+            envelope(Some(json!([{"id": "1", "email": "a@b.com"}]))),
+        );
+        assert_eq!(
+            json!(doc)["data"],
+            json!([{"type": "users", "id": "1", "attributes": {"email": "a@b.com"}}])
+        );
+    }
+
+    #[test]
+    fn test_error_document() {
+        let mut env = envelope(None);
+        env.message = "Item with id 1 not found".into();
+        env.error_code = Some(ErrorCode::ItemNotFound);
+        let doc = Document::from_envelope("items", StatusCode::NOT_FOUND, "/api/items/1", env);
+        assert_eq!(
+            json!(doc),
+            json!({
+                "errors": [{
+                    "status": "404",
+                    "code": "ITEM_NOT_FOUND",
+                    "title": "Item with id 1 not found",
+                }],
+                "meta": {"correlation_id": "abc"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_field_errors_point_at_attributes() {
+        let mut env = envelope(None);
+        env.message = "Validation failed".into();
+        env.error_code = Some(ErrorCode::ValidationFailed);
+        env.errors = vec![FieldError::new(
+            "name",
+            "required",
+            "Item name cannot be empty",
+        )];
+        let doc = Document::from_envelope("items", StatusCode::BAD_REQUEST, "/api/items", env);
+        assert_eq!(
+            json!(doc)["errors"],
+            json!([{
+                "status": "400",
+                "code": "VALIDATION_FAILED",
+                "title": "Validation failed",
+                "detail": "Item name cannot be empty",
+                "source": {"pointer": "/data/attributes/name"},
+            }])
+        );
+    }
+
+    #[test]
+    fn test_attributes_from_document() {
+        let body = json!({"data": {"type": "items", "attributes": {"name": "book"}}});
+        assert_eq!(
+            attributes_from_document(body),
+            Some(json!({"name": "book"}))
+        );
+        assert_eq!(attributes_from_document(json!({"name": "book"})), None);
+    }
+}
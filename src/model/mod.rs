@@ -1,4 +1,15 @@
+pub mod attachment;
+pub mod change;
+pub mod context;
 pub mod error;
+pub mod event;
+pub mod export;
 pub mod http;
+pub mod import;
 pub mod item;
+pub mod job;
+pub mod jsonapi;
+pub mod problem;
+pub mod sort;
 pub mod user;
+pub mod xml;
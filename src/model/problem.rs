@@ -0,0 +1,43 @@
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::{
+    error::{ErrorCode, FieldError},
+    http::Response,
+};
+
+pub const PROBLEM_JSON_MEDIA_TYPE: &str = "application/problem+json";
+
+/// RFC 7807 problem document, `correlation_id`, `error_code` and the field
+/// level `errors` are carried as extension members.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub instance: String,
+    pub correlation_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ProblemDetails {
+    pub fn from_envelope(status: StatusCode, instance: &str, envelope: Response<Value>) -> Self {
+        Self {
+            problem_type: "about:blank".into(),
+            title: status.canonical_reason().unwrap_or_default().into(),
+            status: status.as_u16(),
+            detail: envelope.message,
+            instance: instance.into(),
+            correlation_id: envelope.correlation_id,
+            error_code: envelope.error_code,
+            errors: envelope.errors,
+        }
+    }
+}
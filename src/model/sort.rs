@@ -0,0 +1,154 @@
+use super::error::{AppError, FieldError};
+
+/// Direction of a sorted list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn parse(order: &str) -> Option<Self> {
+        match order {
+            "asc" => Some(SortOrder::Asc),
+            "desc" => Some(SortOrder::Desc),
+            _ => None,
+        }
+    }
+
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// The columns an entity's lists may be sorted by. Only these names ever
+/// reach the SQL, never the client's text.
+pub trait SortColumn: Copy + Default + 'static {
+    const ALL: &'static [Self];
+
+    /// Column name, also what clients pass as `sort`.
+    fn as_str(&self) -> &'static str;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ItemSortColumn {
+    #[default]
+    Name,
+    Slug,
+    Id,
+}
+
+impl SortColumn for ItemSortColumn {
+    const ALL: &'static [Self] = &[Self::Name, Self::Slug, Self::Id];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Slug => "slug",
+            Self::Id => "id",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UserSortColumn {
+    #[default]
+    Email,
+    Id,
+}
+
+impl SortColumn for UserSortColumn {
+    const ALL: &'static [Self] = &[Self::Email, Self::Id];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Id => "id",
+        }
+    }
+}
+
+/// Order of a list, ties are broken by id in the same direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sort<C: SortColumn> {
+    pub column: C,
+    pub order: SortOrder,
+}
+
+pub type ItemSort = Sort<ItemSortColumn>;
+pub type UserSort = Sort<UserSortColumn>;
+
+impl<C: SortColumn> Sort<C> {
+    /// Checks the client's `sort` and `order` against the allowlist, either
+    /// falls back to the default when left out.
+    pub fn parse(sort: Option<&str>, order: Option<&str>) -> Result<Self, AppError> {
+        let mut errors = vec![];
+        let column = match sort {
+            None => Some(C::default()),
+            Some(sort) => C::ALL.iter().copied().find(|c| c.as_str() == sort),
+        };
+        if column.is_none() {
+            let allowed: Vec<&str> = C::ALL.iter().map(|c| c.as_str()).collect();
+            errors.push(FieldError::new(
+                "sort",
+                "invalid",
+                format!(
+                    "Can't sort by '{}', expected one of {}",
+                    sort.unwrap_or_default(),
+                    allowed.join(", ")
+                ),
+            ));
+        }
+        let order = order.map_or(Some(SortOrder::Asc), SortOrder::parse);
+        if order.is_none() {
+            errors.push(FieldError::new(
+                "order",
+                "invalid",
+                "Order must be asc or desc",
+            ));
+        }
+        match (column, order) {
+            (Some(column), Some(order)) => Ok(Self { column, order }),
+            _ => Err(AppError::validation(errors)),
+        }
+    }
+
+    /// `ORDER BY` clause built from the allowlisted column.
+    pub fn to_sql(&self) -> String {
+        let order = self.order.as_sql();
+        if self.column.as_str() == "id" {
+            format!("ORDER BY id {}", order)
+        } else {
+            format!("ORDER BY {} {}, id {}", self.column.as_str(), order, order)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ItemSort::parse(None, None).unwrap(), ItemSort::default());
+        let sort = ItemSort::parse(Some("slug"), Some("desc")).unwrap();
+        assert_eq!(sort.to_sql(), "ORDER BY slug DESC, id DESC");
+        assert_eq!(
+            UserSort::parse(Some("id"), None).unwrap().to_sql(),
+            "ORDER BY id ASC"
+        );
+
+        let err = ItemSort::parse(Some("name; DROP TABLE items"), Some("up")).unwrap_err();
+        let fields: Vec<_> = err
+            .get_field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["sort", "order"]);
+        assert!(UserSort::parse(Some("name"), None).is_err());
+    }
+}
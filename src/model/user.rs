@@ -1,7 +1,28 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+use super::{http::Projectable, item::first_version};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub struct User {
     pub id: String,
     pub email: String,
+    /// Starts at 1 and goes up with every update. Reads send it quoted as the
+    /// `ETag`, send that in `If-Match` to update only the version read.
+    #[serde(default = "first_version")]
+    pub version: i64,
+}
+
+impl Projectable for User {
+    const FIELDS: &'static [&'static str] = &["id", "email", "version"];
+}
+
+/// New address of a user waiting to be confirmed with the token mailed to
+/// it. Only the token's hash is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEmail {
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
 }
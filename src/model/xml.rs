@@ -0,0 +1,70 @@
+use serde_json::{Map, Value};
+
+use super::error::{AppError, AppErrorCode};
+
+pub const XML_MEDIA_TYPE: &str = "application/xml";
+
+/// Renders a JSON body as XML under a `<response>` root for consumers that
+/// can't read JSON. Object members become elements of the same name and list
+/// elements are each wrapped in `<item>`, e.g. `{"data": [{"id": "1"}]}` is
+/// `<response><data><item><id>1</id></item></data></response>`.
+pub fn to_xml(body: Value) -> Result<String, AppError> {
+    let xml = quick_xml::se::to_string_with_root("response", &wrap_lists(body)).map_err(|e| {
+        AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to render the response as XML".to_string(),
+            error_code: None,
+        }
+    })?;
+    Ok(format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml))
+}
+
+/// Lists serialize as repeated elements named after their member, an `item`
+/// member in between keeps one element lists apart from single values.
+fn wrap_lists(value: Value) -> Value {
+    match value {
+        Value::Array(elements) => {
+            let elements = elements.into_iter().map(wrap_lists).collect();
+            Value::Object(Map::from_iter([(
+                "item".to_string(),
+                Value::Array(elements),
+            )]))
+        }
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, wrap_lists(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_to_xml() {
+        let body = json!({
+            "message": "ok",
+            "data": [{"id": "1", "name": "a & b"}],
+            "links": {"self": "/api/items"},
+        });
+        assert_eq!(
+            to_xml(body).unwrap(),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "<response><data><item><id>1</id><name>a &amp; b</name></item></data>",
+                "<links><self>/api/items</self></links><message>ok</message></response>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_xml_rejects_invalid_names() {
+        assert!(to_xml(json!({"not a name": 1})).is_err());
+    }
+}
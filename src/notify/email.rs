@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::{Notification, Notifier};
+use crate::{
+    mail::{Email, Mailer},
+    model::error::AppError,
+};
+
+/// Emails every notification to a fixed list of recipients, e.g. an ops inbox.
+pub struct EmailNotifier {
+    mailer: Arc<dyn Mailer>,
+    recipients: Vec<String>,
+}
+
+impl EmailNotifier {
+    pub fn new(mailer: Arc<dyn Mailer>, recipients: Vec<String>) -> Self {
+        Self { mailer, recipients }
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), AppError> {
+        let mut body = notification.body.clone();
+        if let Some(data) = &notification.data {
+            body.push_str("\n\n");
+            body.push_str(&serde_json::to_string_pretty(data).unwrap_or_default());
+        }
+        for to in &self.recipients {
+            let email = Email {
+                to: to.clone(),
+                subject: notification.title.clone(),
+                body: body.clone(),
+            };
+            self.mailer.send(&email).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::MockMailer;
+
+    #[tokio::test]
+    async fn test_emails_every_recipient() {
+        let mut mailer = MockMailer::new();
+        mailer
+            .expect_send()
+            .withf(|email| email.subject == "item deleted" && email.body == "item 1 was deleted")
+            .times(2)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let notifier = EmailNotifier::new(
+            Arc::new(mailer),
+            vec!["ops@example.com".into(), "dev@example.com".into()],
+        );
+
+        let notification = Notification {
+            topic: "item.deleted".into(),
+            title: "item deleted".into(),
+            body: "item 1 was deleted".into(),
+            data: None,
+        };
+        assert!(notifier.notify(&notification).await.is_ok());
+    }
+}
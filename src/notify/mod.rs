@@ -0,0 +1,297 @@
+pub mod email;
+pub mod slack;
+pub mod webhook;
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    config::Config,
+    event::EventPublisher,
+    mail::Mailer,
+    model::{
+        error::{AppError, AppErrorCode},
+        event::Event,
+        job::{Job, NewJob},
+    },
+    repository::erased::DynJobRepository,
+    worker::JobHandler,
+};
+
+/// Job kind delivering one [`Notification`] over one channel.
+pub const NOTIFY_JOB: &str = "notify";
+
+/// Human readable summary of a domain event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub topic: String,
+    pub title: String,
+    pub body: String,
+    pub data: Option<Value>,
+}
+
+impl From<&Event> for Notification {
+    fn from(event: &Event) -> Self {
+        let action = event.action.as_str();
+        Self {
+            topic: event.topic(),
+            title: format!("{} {}", event.entity, action),
+            body: format!("{} {} was {}", event.entity, event.entity_id, action),
+            data: event.data.clone(),
+        }
+    }
+}
+
+#[async_trait]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, notification: &Notification) -> Result<(), AppError>;
+}
+
+/// Sends events matching `pattern` to the named channels. Patterns are a
+/// topic (`user.created`), an entity wildcard (`user.*`) or `*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifyRoute {
+    pub pattern: String,
+    pub channels: Vec<String>,
+}
+
+impl NotifyRoute {
+    /// Parses `user.created=email,slack;item.*=webhook`.
+    pub fn parse_all(routes: &str) -> Result<Vec<Self>, AppError> {
+        routes
+            .split(';')
+            .map(str::trim)
+            .filter(|route| !route.is_empty())
+            .map(|route| {
+                let (pattern, channels) = route.split_once('=').ok_or_else(|| AppError {
+                    code: AppErrorCode::InvalidInput,
+                    message: format!("Invalid notification route '{}'", route),
+                    error_code: None,
+                })?;
+                Ok(Self {
+                    pattern: pattern.trim().to_string(),
+                    channels: channels
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(String::from)
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        match self.pattern.strip_suffix(".*") {
+            _ if self.pattern == "*" => true,
+            Some(entity) => topic.split_once('.').is_some_and(|(e, _)| e == entity),
+            None => self.pattern == topic,
+        }
+    }
+}
+
+/// Event sink queueing a [`NOTIFY_JOB`] per routed channel, so slow or
+/// failing deliveries are retried by the job workers.
+pub struct NotificationRouter {
+    routes: Vec<NotifyRoute>,
+    jobs: Arc<dyn DynJobRepository>,
+}
+
+impl NotificationRouter {
+    pub fn new(routes: Vec<NotifyRoute>, jobs: Arc<dyn DynJobRepository>) -> Self {
+        Self { routes, jobs }
+    }
+
+    fn channels(&self, topic: &str) -> Vec<&str> {
+        let mut channels: Vec<&str> = vec![];
+        for route in self.routes.iter().filter(|route| route.matches(topic)) {
+            for channel in &route.channels {
+                if !channels.contains(&channel.as_str()) {
+                    channels.push(channel);
+                }
+            }
+        }
+        channels
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NotificationRouter {
+    async fn publish(&self, event: &Event) -> Result<(), AppError> {
+        let notification = Notification::from(event);
+        for channel in self.channels(&notification.topic) {
+            let payload = json!({"channel": channel, "notification": notification});
+            self.jobs
+                .add(Job::from(NewJob::new(NOTIFY_JOB, payload)))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct NotifyPayload {
+    channel: String,
+    notification: Notification,
+}
+
+/// Delivers [`NOTIFY_JOB`] jobs through the notifier registered for their channel.
+#[derive(Default)]
+pub struct NotifyJobHandler {
+    channels: HashMap<String, Arc<dyn Notifier>>,
+}
+
+impl NotifyJobHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the `email`, `webhook` and `slack` channels that are configured.
+    pub fn from_config(config: &Config, mailer: Arc<dyn Mailer>) -> Self {
+        let mut handler = Self::new();
+        if !config.notify_email_to.is_empty() {
+            let notifier = email::EmailNotifier::new(mailer, config.notify_email_to.clone());
+            handler = handler.channel("email", Arc::new(notifier));
+        }
+        if let Some(url) = &config.notify_webhook_url {
+            handler = handler.channel("webhook", Arc::new(webhook::WebhookNotifier::new(url)));
+        }
+        if let Some(url) = &config.notify_slack_webhook_url {
+            handler = handler.channel("slack", Arc::new(slack::SlackNotifier::new(url)));
+        }
+        handler
+    }
+
+    /// Fails on routes naming a channel that is not registered.
+    pub fn check_routes(&self, routes: &[NotifyRoute]) -> Result<(), AppError> {
+        for route in routes {
+            if let Some(channel) = route.channels.iter().find(|c| !self.has_channel(c)) {
+                return Err(AppError {
+                    code: AppErrorCode::InvalidInput,
+                    message: format!(
+                        "Notification route '{}' uses unconfigured channel '{}'",
+                        route.pattern, channel
+                    ),
+                    error_code: None,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn channel(mut self, name: &str, notifier: Arc<dyn Notifier>) -> Self {
+        self.channels.insert(name.to_string(), notifier);
+        self
+    }
+
+    pub fn has_channel(&self, name: &str) -> bool {
+        self.channels.contains_key(name)
+    }
+}
+
+#[async_trait]
+impl JobHandler for NotifyJobHandler {
+    async fn handle(&self, job: &Job) -> Result<(), AppError> {
+        let payload: NotifyPayload =
+            serde_json::from_value(job.payload.clone()).map_err(|e| AppError {
+                code: AppErrorCode::InvalidInput,
+                message: format!("Invalid notification job payload: {}", e),
+                error_code: None,
+            })?;
+        let notifier = self
+            .channels
+            .get(&payload.channel)
+            .ok_or_else(|| AppError {
+                code: AppErrorCode::InternalError(payload.channel.clone()),
+                message: format!("Unknown notification channel {}", payload.channel),
+                error_code: None,
+            })?;
+        notifier.notify(&payload.notification).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{event::EventAction, user::User},
+        repository::job::InMemoryJobRepository,
+    };
+
+    fn user_created() -> Event {
+        let user = User {
+            id: "1".into(),
+            email: "a@b.com".into(),
+            version: 1,
+        };
+        Event::new("user", EventAction::Created, &user.id, Some(&user))
+    }
+
+    #[test]
+    fn test_parse_and_match_routes() {
+        let routes =
+            NotifyRoute::parse_all("user.created=email, slack; item.*=webhook;*=log").unwrap();
+        assert_eq!(routes[0].channels, vec!["email", "slack"]);
+        assert!(routes[0].matches("user.created"));
+        assert!(!routes[0].matches("user.deleted"));
+        assert!(routes[1].matches("item.updated"));
+        assert!(!routes[1].matches("items.updated"));
+        assert!(routes[2].matches("anything.else"));
+        assert!(NotifyRoute::parse_all("user.created").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_router_queues_one_job_per_channel() {
+        let jobs = Arc::new(InMemoryJobRepository::new());
+        let routes =
+            NotifyRoute::parse_all("user.created=email,slack;user.*=slack;item.*=webhook").unwrap();
+        let router = NotificationRouter::new(routes, jobs.clone());
+
+        router.publish(&user_created()).await.unwrap();
+
+        let jobs = jobs.jobs.lock().unwrap();
+        let channels: Vec<_> = jobs.iter().map(|j| j.payload["channel"].clone()).collect();
+        assert_eq!(channels, vec!["email", "slack"]);
+        assert_eq!(jobs[0].kind, NOTIFY_JOB);
+        assert_eq!(jobs[0].payload["notification"]["title"], "user created");
+    }
+
+    #[tokio::test]
+    async fn test_handler_delivers_to_channel() {
+        let mut slack = MockNotifier::new();
+        slack
+            .expect_notify()
+            .withf(|n| n.topic == "user.created")
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let handler = NotifyJobHandler::new().channel("slack", Arc::new(slack));
+        let notification = Notification::from(&user_created());
+
+        let job = |channel: &str| {
+            Job::from(NewJob::new(
+                NOTIFY_JOB,
+                json!({"channel": channel, "notification": notification}),
+            ))
+        };
+        assert!(handler.handle(&job("slack")).await.is_ok());
+        assert!(handler.handle(&job("pager")).await.is_err());
+    }
+
+    #[test]
+    fn test_check_routes_against_configured_channels() {
+        let config = Config {
+            notify_slack_webhook_url: Some("http://localhost/hook".into()),
+            ..Default::default()
+        };
+        let handler = NotifyJobHandler::from_config(&config, Arc::new(crate::mail::LogMailer));
+
+        let routes = NotifyRoute::parse_all("user.*=slack").unwrap();
+        assert!(handler.check_routes(&routes).is_ok());
+        let routes = NotifyRoute::parse_all("user.*=slack,email").unwrap();
+        assert!(handler.check_routes(&routes).is_err());
+    }
+}
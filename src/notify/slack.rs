@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{Notification, Notifier, webhook::post_json};
+use crate::model::error::AppError;
+
+/// Posts to a Slack incoming webhook, the title in bold above the body.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), AppError> {
+        let text = format!("*{}*\n{}", notification.title, notification.body);
+        post_json(&self.client, &self.webhook_url, &json!({ "text": text })).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::notify::webhook::tests::capture;
+
+    #[tokio::test]
+    async fn test_posts_slack_message() {
+        let (url, received) = capture(StatusCode::OK).await;
+        let notification = Notification {
+            topic: "user.deleted".into(),
+            title: "user deleted".into(),
+            body: "user 1 was deleted".into(),
+            data: None,
+        };
+
+        SlackNotifier::new(&url)
+            .notify(&notification)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            received.lock().unwrap()[0],
+            json!({"text": "*user deleted*\nuser 1 was deleted"})
+        );
+    }
+}
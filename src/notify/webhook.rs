@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use super::{Notification, Notifier};
+use crate::model::error::{AppError, AppErrorCode};
+
+/// POSTs the notification as JSON to an HTTP endpoint.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), AppError> {
+        post_json(&self.client, &self.url, notification).await
+    }
+}
+
+/// Fails on transport errors and on any non 2xx answer, so the job is retried.
+pub(super) async fn post_json<T: Serialize + Sync>(
+    client: &reqwest::Client,
+    url: &str,
+    body: &T,
+) -> Result<(), AppError> {
+    let response = client
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| AppError {
+            code: AppErrorCode::Unavailable,
+            message: format!("Failed to reach webhook: {}", e),
+            error_code: None,
+        })?;
+    if !response.status().is_success() {
+        return Err(AppError {
+            code: AppErrorCode::Unavailable,
+            message: format!("Webhook answered {}", response.status()),
+            error_code: None,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Local endpoint recording the JSON bodies it receives.
+    pub(in crate::notify) async fn capture(status: StatusCode) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let received = Arc::new(Mutex::new(vec![]));
+        let app = Router::new()
+            .route(
+                "/",
+                post(
+                    move |State(received): State<Arc<Mutex<Vec<Value>>>>,
+                          Json(body): Json<Value>| async move {
+                        received.lock().unwrap().push(body);
+                        status
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    fn notification() -> Notification {
+        Notification {
+            topic: "item.created".into(),
+            title: "item created".into(),
+            body: "item 1 was created".into(),
+            data: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_posts_notification() {
+        let (url, received) = capture(StatusCode::OK).await;
+
+        WebhookNotifier::new(&url)
+            .notify(&notification())
+            .await
+            .unwrap();
+
+        assert_eq!(received.lock().unwrap()[0]["topic"], "item.created");
+    }
+
+    #[tokio::test]
+    async fn test_error_status_fails() {
+        let (url, _) = capture(StatusCode::BAD_GATEWAY).await;
+
+        let err = WebhookNotifier::new(&url)
+            .notify(&notification())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.get_message(), "Webhook answered 502 Bad Gateway");
+    }
+}
@@ -0,0 +1,119 @@
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    handler::{
+        attachment::AttachmentApi, export::ExportApi, import::ImportApi, index, item::ItemApi,
+        job::JobApi, sync::SyncApi, user::UserApi, user_item::UserItemApi, version::VersionApi,
+    },
+    model::{
+        error::{AppError, AppErrorCode},
+        problem::ProblemDetails,
+    },
+    state::AppState,
+};
+
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+pub const DOCS_PATH: &str = "/api/docs";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "crud-rust"),
+    paths(index::index, index::healthcheck),
+    nest(
+        (path = "/api/v1/items", api = ItemApi),
+        (path = "/api/v1/items", api = AttachmentApi),
+        (path = "/api/v1/users", api = UserApi),
+        (path = "/api/v1/users", api = UserItemApi),
+        (path = "/api/v1/export-jobs", api = ExportApi),
+        (path = "/api/v1/import-jobs", api = ImportApi),
+        (path = "/api/v1/sync", api = SyncApi),
+        (path = "/api/v1/admin/jobs", api = JobApi),
+        (path = "/api/v1/admin/versions", api = VersionApi),
+    ),
+    components(schemas(ProblemDetails)),
+    tags(
+        (name = "meta", description = "Entry point and health"),
+        (name = "items", description = "Item management"),
+        (name = "attachments", description = "Files attached to items"),
+        (name = "users", description = "User management"),
+        (name = "exports", description = "Background export jobs"),
+        (name = "imports", description = "Background import jobs"),
+        (name = "sync", description = "Incremental sync for offline clients"),
+        (name = "admin", description = "Background job administration"),
+    )
+)]
+pub struct ApiDoc;
+
+/// The spec as pretty JSON ending in a newline, identical from run to run so
+/// it can be committed and diffed by build pipelines.
+pub fn spec_json() -> Result<String, AppError> {
+    let json = ApiDoc::openapi().to_pretty_json().map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to serialize the OpenAPI spec".to_string(),
+        error_code: None,
+    })?;
+    Ok(json + "\n")
+}
+
+/// Serves the generated spec and Swagger UI on top of it.
+pub fn router_setup_docs() -> axum::Router<AppState> {
+    SwaggerUi::new(DOCS_PATH)
+        .url(OPENAPI_PATH, ApiDoc::openapi())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_contains_all_routes() {
+        let spec = ApiDoc::openapi();
+        let paths: Vec<&String> = spec.paths.paths.keys().collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/",
+                "/api/healthcheck",
+                "/api/v1/admin/jobs",
+                "/api/v1/admin/jobs/{id}",
+                "/api/v1/admin/jobs/{id}/cancel",
+                "/api/v1/admin/jobs/{id}/retry",
+                "/api/v1/admin/versions",
+                "/api/v1/export-jobs/{id}",
+                "/api/v1/export-jobs/{id}/download",
+                "/api/v1/import-jobs/{id}",
+                "/api/v1/items",
+                "/api/v1/items/bulk",
+                "/api/v1/items/export-jobs",
+                "/api/v1/items/export.csv",
+                "/api/v1/items/import",
+                "/api/v1/items/import-jobs",
+                "/api/v1/items/search",
+                "/api/v1/items/slug/{slug}",
+                "/api/v1/items/{id}",
+                "/api/v1/items/{id}/attachments",
+                "/api/v1/items/{id}/attachments/{attachment_id}",
+                "/api/v1/items/{id}/attachments/{attachment_id}/download",
+                "/api/v1/items/{id}/regenerate-slug",
+                "/api/v1/sync",
+                "/api/v1/users",
+                "/api/v1/users/by-email/{email}",
+                "/api/v1/users/export.csv",
+                "/api/v1/users/search",
+                "/api/v1/users/{id}",
+                "/api/v1/users/{id}/confirm-email",
+                "/api/v1/users/{id}/items"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spec_json_is_stable() {
+        let json = spec_json().unwrap();
+        assert_eq!(json, spec_json().unwrap());
+        let spec: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(spec["info"]["title"], "crud-rust");
+    }
+}
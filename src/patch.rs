@@ -0,0 +1,415 @@
+//! Partial updates of stored entities. Update routes take a JSON Merge Patch
+//! (RFC 7396) when sent as `application/merge-patch+json` and a JSON Patch
+//! (RFC 6902) as `application/json-patch+json`, see
+//! [`crate::extract::UpdateBody`]. The services apply it to the stored
+//! entity and read the result as the usual update payload, so the same
+//! validation applies as for a full update.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::model::error::{AppError, AppErrorCode, ErrorCode, FieldError};
+
+pub const MERGE_PATCH_JSON: &str = "application/merge-patch+json";
+pub const JSON_PATCH_JSON: &str = "application/json-patch+json";
+
+/// One operation of a JSON Patch, paths are JSON Pointers (RFC 6901).
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    Merge(Value),
+    Json(Vec<PatchOp>),
+}
+
+impl Patch {
+    /// Applies the patch to `target`. A JSON Patch is applied as a whole or
+    /// not at all, failing with [`AppErrorCode::Unprocessable`] whose field
+    /// points at the failed operation, e.g. `/2`.
+    pub fn apply(&self, target: &mut Value) -> Result<(), AppError> {
+        match self {
+            Patch::Merge(patch) => {
+                merge(target, patch);
+                Ok(())
+            }
+            Patch::Json(ops) => {
+                let mut patched = target.clone();
+                for (index, op) in ops.iter().enumerate() {
+                    apply_op(&mut patched, op).map_err(|(code, message)| AppError {
+                        code: AppErrorCode::Unprocessable(vec![FieldError::new(
+                            &format!("/{}", index),
+                            code,
+                            message.clone(),
+                        )]),
+                        message: format!("Patch operation {} failed: {}", index, message),
+                        error_code: Some(ErrorCode::PatchFailed),
+                    })?;
+                }
+                *target = patched;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Merges `patch` into `target`: objects merge key by key, `null` removes a
+/// key and any other value replaces the target.
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Why an operation failed, as a field error code and message.
+type OpError = (&'static str, String);
+
+fn missing(path: &str) -> OpError {
+    ("path_not_found", format!("Nothing at '{}'", path))
+}
+
+/// The reference tokens of a JSON Pointer, `~1` and `~0` unescaped.
+fn tokens(pointer: &str) -> Result<Vec<String>, OpError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err((
+            "invalid_path",
+            format!("'{}' is not a JSON Pointer", pointer),
+        ));
+    };
+    Ok(rest
+        .split('/')
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn pointer(tokens: &[String]) -> String {
+    tokens
+        .iter()
+        .map(|t| format!("/{}", t.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Position `token` names in an array `len` long, `-` being past the end.
+/// Past the end is only valid when `append` is set.
+fn index(token: &str, len: usize, append: bool) -> Option<usize> {
+    if token == "-" {
+        return append.then_some(len);
+    }
+    if token.starts_with('+') || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|i| *i < len || (append && *i == len))
+}
+
+/// The container `path` points into, and the last token of `path`.
+fn parent<'a>(doc: &'a mut Value, path: &str) -> Result<(&'a mut Value, String), OpError> {
+    let mut tokens = tokens(path)?;
+    let Some(last) = tokens.pop() else {
+        return Err(("invalid_path", "The document root has no parent".into()));
+    };
+    let parent = doc
+        .pointer_mut(&pointer(&tokens))
+        .ok_or_else(|| missing(path))?;
+    Ok((parent, last))
+}
+
+fn get<'a>(doc: &'a Value, path: &str) -> Result<&'a Value, OpError> {
+    tokens(path)?;
+    doc.pointer(path).ok_or_else(|| missing(path))
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(), OpError> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    match parent(doc, path)? {
+        (Value::Object(object), key) => {
+            object.insert(key, value);
+        }
+        (Value::Array(array), token) => {
+            let i = index(&token, array.len(), true).ok_or_else(|| missing(path))?;
+            array.insert(i, value);
+        }
+        _ => return Err(missing(path)),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, OpError> {
+    match parent(doc, path)? {
+        (Value::Object(object), key) => object.remove(&key).ok_or_else(|| missing(path)),
+        (Value::Array(array), token) => {
+            let i = index(&token, array.len(), false).ok_or_else(|| missing(path))?;
+            Ok(array.remove(i))
+        }
+        _ => Err(missing(path)),
+    }
+}
+
+fn apply_op(doc: &mut Value, op: &PatchOp) -> Result<(), OpError> {
+    match op {
+        PatchOp::Add { path, value } => add(doc, path, value.clone()),
+        PatchOp::Remove { path } => remove(doc, path).map(drop),
+        PatchOp::Replace { path, value } => {
+            tokens(path)?;
+            let target = doc.pointer_mut(path).ok_or_else(|| missing(path))?;
+            *target = value.clone();
+            Ok(())
+        }
+        PatchOp::Move { from, path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(("invalid_path", format!("Can't move '{}' into itself", from)));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOp::Copy { from, path } => {
+            let value = get(doc, from)?.clone();
+            add(doc, path, value)
+        }
+        PatchOp::Test { path, value } => {
+            if get(doc, path)? == value {
+                Ok(())
+            } else {
+                Err(("test_failed", format!("'{}' is not {}", path, value)))
+            }
+        }
+    }
+}
+
+/// `entity` with `patch` applied, read as the update payload `T`. Fields in
+/// `read_only` must come out unchanged.
+pub fn patched<T: DeserializeOwned>(
+    entity: &impl Serialize,
+    patch: &Patch,
+    read_only: &[&str],
+) -> Result<T, AppError> {
+    let original = serde_json::to_value(entity).map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to serialize entity".to_string(),
+        error_code: None,
+    })?;
+    let mut patched = original.clone();
+    patch.apply(&mut patched)?;
+    let errors: Vec<FieldError> = read_only
+        .iter()
+        .filter(|field| patched.get(**field) != original.get(**field))
+        .map(|field| FieldError::new(field, "read_only", format!("{} can't be changed", field)))
+        .collect();
+    if !errors.is_empty() {
+        return Err(AppError::validation(errors));
+    }
+    serde_json::from_value(patched).map_err(|e| AppError {
+        code: AppErrorCode::InvalidInput,
+        message: format!("Invalid patch: {}", e),
+        error_code: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        // The examples of RFC 7396, appendix A.
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!("string"),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+        for (mut target, patch, expected) in cases {
+            merge(&mut target, &patch);
+            assert_eq!(target, expected, "patch {}", patch);
+        }
+    }
+
+    fn json_patch(ops: Value) -> Patch {
+        Patch::Json(serde_json::from_value(ops).unwrap())
+    }
+
+    #[test]
+    fn test_json_patch() {
+        // Mostly the examples of RFC 6902, appendix A.
+        let cases = [
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux"}]),
+                json!({"foo": "bar", "baz": "qux"}),
+            ),
+            (
+                json!({"foo": ["bar", "baz"]}),
+                json!([{"op": "add", "path": "/foo/1", "value": "qux"}]),
+                json!({"foo": ["bar", "qux", "baz"]}),
+            ),
+            (
+                json!({"foo": ["bar"]}),
+                json!([{"op": "add", "path": "/foo/-", "value": ["abc"]}]),
+                json!({"foo": ["bar", ["abc"]]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "remove", "path": "/baz"}]),
+                json!({"foo": "bar"}),
+            ),
+            (
+                json!({"foo": ["bar", "qux", "baz"]}),
+                json!([{"op": "remove", "path": "/foo/1"}]),
+                json!({"foo": ["bar", "baz"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "replace", "path": "/baz", "value": "boo"}]),
+                json!({"baz": "boo", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}),
+                json!([{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]),
+                json!({"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}),
+            ),
+            (
+                json!({"foo": ["all", "grass", "cows", "eat"]}),
+                json!([{"op": "move", "from": "/foo/1", "path": "/foo/3"}]),
+                json!({"foo": ["all", "cows", "eat", "grass"]}),
+            ),
+            (
+                json!({"a/b": 1, "m~n": 2}),
+                json!([
+                    {"op": "copy", "from": "/a~1b", "path": "/c"},
+                    {"op": "test", "path": "/m~0n", "value": 2}
+                ]),
+                json!({"a/b": 1, "m~n": 2, "c": 1}),
+            ),
+        ];
+        for (mut target, ops, expected) in cases {
+            json_patch(ops.clone()).apply(&mut target).unwrap();
+            assert_eq!(target, expected, "patch {}", ops);
+        }
+    }
+
+    #[test]
+    fn test_json_patch_errors() {
+        let cases = [
+            (
+                json!([{"op": "add", "path": "/baz/bat", "value": "qux"}]),
+                "/0",
+                "path_not_found",
+            ),
+            (
+                json!([{"op": "remove", "path": "/nope"}]),
+                "/0",
+                "path_not_found",
+            ),
+            (
+                json!([{"op": "replace", "path": "nope", "value": 1}]),
+                "/0",
+                "invalid_path",
+            ),
+            (
+                json!([
+                    {"op": "replace", "path": "/foo", "value": 2},
+                    {"op": "test", "path": "/foo", "value": 1}
+                ]),
+                "/1",
+                "test_failed",
+            ),
+            (
+                json!([{"op": "move", "from": "/foo", "path": "/foo/a"}]),
+                "/0",
+                "invalid_path",
+            ),
+            (
+                json!([{"op": "add", "path": "/list/5", "value": 1}]),
+                "/0",
+                "path_not_found",
+            ),
+        ];
+        for (ops, field, code) in cases {
+            let mut target = json!({"foo": 1, "list": []});
+            let err = json_patch(ops.clone()).apply(&mut target).unwrap_err();
+            assert_eq!(err.get_http_status(), StatusCode::UNPROCESSABLE_ENTITY);
+            assert_eq!(err.get_field_errors()[0].field, field, "patch {}", ops);
+            assert_eq!(err.get_field_errors()[0].code, code, "patch {}", ops);
+            // Nothing is applied when an operation fails.
+            assert_eq!(target, json!({"foo": 1, "list": []}));
+        }
+    }
+
+    #[test]
+    fn test_patched() {
+        #[derive(Debug, Deserialize)]
+        struct Rename {
+            name: String,
+        }
+        let entity = json!({"id": "1", "name": "book"});
+
+        let patch = Patch::Merge(json!({"name": "notebook"}));
+        let rename: Rename = patched(&entity, &patch, &["id"]).unwrap();
+        assert_eq!(rename.name, "notebook");
+        let patch = Patch::Merge(json!({"id": "2"}));
+        let err = patched::<Rename>(&entity, &patch, &["id"]).unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "id");
+        let patch = Patch::Merge(json!({"name": null}));
+        let err = patched::<Rename>(&entity, &patch, &["id"]).unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::InvalidInput));
+
+        let patch = json_patch(json!([{"op": "replace", "path": "/name", "value": "pen"}]));
+        let rename: Rename = patched(&entity, &patch, &["id"]).unwrap();
+        assert_eq!(rename.name, "pen");
+    }
+}
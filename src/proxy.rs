@@ -0,0 +1,343 @@
+//! Client address behind reverse proxies. `Forwarded` and `X-Forwarded-For`
+//! are only believed when the connection comes from a proxy listed in
+//! `TRUSTED_PROXIES`, anyone else could send them to pose as another client.
+//!
+//! Load balancers working at the TCP level, e.g. HAProxy or an AWS NLB,
+//! announce the client with the PROXY protocol instead, read by
+//! [`read_proxy_header`] when `PROXY_PROTOCOL` is enabled.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, header::FORWARDED},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use crate::model::{
+    context::Ctx,
+    error::{AppError, AppErrorCode},
+};
+
+pub const X_FORWARDED_FOR: &str = "X-Forwarded-For";
+
+/// Address of the client that sent the request, inserted into the request
+/// extensions by [`client_ip_middleware`] and copied to [`Ctx::client_ip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Networks of the reverse proxies in front of the API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    /// Parses `10.0.0.0/8, 192.168.1.10`, a bare address is a network of one.
+    pub fn parse(proxies: &str) -> Result<Self, AppError> {
+        let networks = proxies
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                p.parse::<IpNet>()
+                    .or_else(|_| p.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| AppError {
+                        code: AppErrorCode::InvalidInput,
+                        message: format!("Invalid trusted proxy '{}'", p),
+                        error_code: None,
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { networks })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.networks.iter().any(|net| net.contains(&ip))
+    }
+
+    /// The client behind `peer`: walking the forwarded chain from the nearest
+    /// hop, the first address that isn't a trusted proxy.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+        let chain = forwarded_for(headers);
+        chain
+            .iter()
+            .rev()
+            .find(|ip| !self.contains(**ip))
+            .or(chain.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+}
+
+/// Addresses from `Forwarded`, or `X-Forwarded-For` without it, farthest hop
+/// first. Unparsable entries, e.g. obfuscated identifiers, are skipped.
+fn forwarded_for(headers: &HeaderMap) -> Vec<IpAddr> {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+    };
+    let forwarded = values(FORWARDED.as_str());
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .filter_map(|element| {
+                element.split(';').find_map(|pair| {
+                    let (key, value) = pair.split_once('=')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("for")
+                        .then(|| parse_node(value.trim().trim_matches('"')))
+                        .flatten()
+                })
+            })
+            .collect();
+    }
+    values(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|v| parse_node(v))
+        .collect()
+}
+
+/// `192.0.2.1`, `192.0.2.1:4711`, `2001:db8::1` or `[2001:db8::1]:4711`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    node.parse::<IpAddr>()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            node.strip_prefix('[')
+                .and_then(|n| n.strip_suffix(']'))
+                .and_then(|n| n.parse().ok())
+        })
+}
+
+/// Resolves the client address of requests served with connect info.
+pub async fn client_ip_middleware(
+    State(proxies): State<Arc<TrustedProxies>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    if let Some(peer) = peer {
+        let ip = proxies.resolve(peer, req.headers());
+        req.extensions_mut().insert(ClientIp(ip));
+        if let Some(ctx) = req.extensions_mut().get_mut::<Ctx>() {
+            ctx.client_ip = Some(ip);
+        }
+    }
+    next.run(req).await
+}
+
+const PROXY_V1: &[u8] = b"PROXY ";
+const PROXY_V2: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+/// Longest v1 header allowed by the spec, CRLF included.
+const MAX_V1_LEN: usize = 107;
+
+/// Reads the PROXY protocol v1 or v2 header a connection starts with,
+/// returning the client's address. `None` for health checks of the proxy
+/// itself (`LOCAL`, `UNKNOWN`) and non-IP transports, which keep the peer's.
+/// Connections without a header are an error, they didn't come through the
+/// proxy.
+pub async fn read_proxy_header<R>(io: &mut R) -> io::Result<Option<SocketAddr>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut signature = [0u8; 12];
+    io.read_exact(&mut signature).await?;
+
+    if signature == PROXY_V2 {
+        let mut header = [0u8; 4];
+        io.read_exact(&mut header).await?;
+        let [version_command, family, len @ ..] = header;
+        if version_command >> 4 != 2 {
+            return Err(invalid("Unsupported PROXY protocol version"));
+        }
+        let mut body = vec![0u8; u16::from_be_bytes(len) as usize];
+        io.read_exact(&mut body).await?;
+        // LOCAL connections are the proxy's own.
+        if version_command & 0x0f == 0 {
+            return Ok(None);
+        }
+        let port = |at: usize| u16::from_be_bytes([body[at], body[at + 1]]);
+        return match family >> 4 {
+            1 if body.len() >= 12 => {
+                let ip = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+                Ok(Some(SocketAddr::new(ip.into(), port(8))))
+            }
+            2 if body.len() >= 36 => {
+                let octets: [u8; 16] = body[..16].try_into().unwrap();
+                Ok(Some(SocketAddr::new(
+                    Ipv6Addr::from(octets).into(),
+                    port(32),
+                )))
+            }
+            1 | 2 => Err(invalid("Truncated PROXY protocol addresses")),
+            _ => Ok(None),
+        };
+    }
+
+    if !signature.starts_with(PROXY_V1) {
+        return Err(invalid("Missing PROXY protocol header"));
+    }
+    let mut line = signature.to_vec();
+    (&mut *io)
+        .take((MAX_V1_LEN - line.len()) as u64)
+        .read_until(b'\n', &mut line)
+        .await?;
+    let line = std::str::from_utf8(&line)
+        .ok()
+        .and_then(|l| l.strip_suffix("\r\n"))
+        .ok_or_else(|| invalid("Invalid PROXY protocol header"))?;
+    // `PROXY TCP4 <src> <dst> <src port> <dst port>`
+    let fields: Vec<&str> = line.split(' ').collect();
+    match fields[..] {
+        [_, "TCP4" | "TCP6", src, _, src_port, _] => {
+            let ip = src.parse::<IpAddr>();
+            let port = src_port.parse::<u16>();
+            match (ip, port) {
+                (Ok(ip), Ok(port)) => Ok(Some(SocketAddr::new(ip, port))),
+                _ => Err(invalid("Invalid PROXY protocol address")),
+            }
+        }
+        [_, "UNKNOWN", ..] => Ok(None),
+        _ => Err(invalid("Invalid PROXY protocol header")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8, 192.168.1.10,::1").unwrap();
+        assert!(proxies.contains(ip("10.1.2.3")));
+        assert!(proxies.contains(ip("192.168.1.10")));
+        assert!(proxies.contains(ip("::ffff:10.0.0.1")));
+        assert!(!proxies.contains(ip("192.168.1.11")));
+        assert!(TrustedProxies::parse("").unwrap().networks.is_empty());
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("proxy").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let forwarded = headers(&[(X_FORWARDED_FOR, "203.0.113.7, 198.51.100.1, 10.0.0.2")]);
+
+        // Only trusted peers are believed.
+        assert_eq!(
+            proxies.resolve(ip("198.51.100.9"), &forwarded),
+            ip("198.51.100.9")
+        );
+        // The nearest untrusted hop, earlier ones could be forged by it.
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &forwarded),
+            ip("198.51.100.1")
+        );
+        let internal = headers(&[(X_FORWARDED_FOR, "10.0.0.3, 10.0.0.2")]);
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &internal), ip("10.0.0.3"));
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn test_forwarded_header() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let forwarded = headers(&[
+            ("forwarded", r#"for="[2001:db8::1]:4711";proto=https"#),
+            ("forwarded", "For=192.0.2.60:80;by=10.0.0.1, for=_hidden"),
+            (X_FORWARDED_FOR, "198.51.100.1"),
+        ]);
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &forwarded),
+            ip("192.0.2.60")
+        );
+        assert_eq!(
+            forwarded_for(&forwarded),
+            vec![ip("2001:db8::1"), ip("192.0.2.60")]
+        );
+    }
+
+    async fn read(header: &[u8]) -> io::Result<Option<SocketAddr>> {
+        let mut io = tokio::io::BufReader::new(header);
+        let addr = read_proxy_header(&mut io).await?;
+        // The request that follows is left in place.
+        let mut rest = String::new();
+        io.read_to_string(&mut rest).await?;
+        assert_eq!(rest, "GET /");
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol_v1() {
+        let addr = read(b"PROXY TCP4 203.0.113.7 10.0.0.1 56324 443\r\nGET /").await;
+        assert_eq!(addr.unwrap(), Some("203.0.113.7:56324".parse().unwrap()));
+        let addr = read(b"PROXY TCP6 2001:db8::1 ::1 56324 443\r\nGET /").await;
+        assert_eq!(addr.unwrap(), Some("[2001:db8::1]:56324".parse().unwrap()));
+        assert_eq!(read(b"PROXY UNKNOWN\r\nGET /").await.unwrap(), None);
+        assert!(
+            read(b"PROXY TCP4 nope 10.0.0.1 1 2\r\nGET /")
+                .await
+                .is_err()
+        );
+        assert!(read(b"GET / HTTP/1.1\r\n\r\n").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol_v2() {
+        let mut header = PROXY_V2.to_vec();
+        // PROXY command over TCP/IPv4, then addresses, ports and a TLV.
+        header.extend([0x21, 0x11, 0, 16]);
+        header.extend([203, 0, 113, 7, 10, 0, 0, 1]);
+        header.extend(56324u16.to_be_bytes());
+        header.extend(443u16.to_be_bytes());
+        header.extend([0x04, 0, 1, 0]);
+        header.extend(b"GET /");
+        let addr = read(&header).await.unwrap();
+        assert_eq!(addr, Some("203.0.113.7:56324".parse().unwrap()));
+
+        let mut local = PROXY_V2.to_vec();
+        local.extend([0x20, 0x00, 0, 0]);
+        local.extend(b"GET /");
+        assert_eq!(read(&local).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,158 @@
+use sqlx::PgPool;
+use std::sync::Mutex;
+
+use crate::model::{
+    attachment::Attachment,
+    error::{AppError, AppErrorCode},
+};
+
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait AttachmentRepository: Send + Sync {
+    fn add(
+        &self,
+        attachment: Attachment,
+    ) -> impl Future<Output = Result<Attachment, AppError>> + Send;
+    /// Oldest first.
+    fn list(&self, item_id: &str)
+    -> impl Future<Output = Result<Vec<Attachment>, AppError>> + Send;
+    fn get(
+        &self,
+        item_id: &str,
+        id: &str,
+    ) -> impl Future<Output = Result<Attachment, AppError>> + Send;
+    fn delete(&self, item_id: &str, id: &str) -> impl Future<Output = Result<(), AppError>> + Send;
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::NotFound,
+        message: format!("Attachment with id {} not found", id),
+        error_code: None,
+    }
+}
+
+fn lock_error(e: impl ToString) -> AppError {
+    AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to lock attachments".to_string(),
+        error_code: None,
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAttachmentRepository {
+    pub attachments: Mutex<Vec<Attachment>>,
+}
+
+impl InMemoryAttachmentRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AttachmentRepository for InMemoryAttachmentRepository {
+    async fn add(&self, attachment: Attachment) -> Result<Attachment, AppError> {
+        let mut attachments = self.attachments.lock().map_err(lock_error)?;
+        attachments.push(attachment.clone());
+        Ok(attachment)
+    }
+
+    async fn list(&self, item_id: &str) -> Result<Vec<Attachment>, AppError> {
+        let attachments = self.attachments.lock().map_err(lock_error)?;
+        Ok(attachments
+            .iter()
+            .filter(|a| a.item_id == item_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, item_id: &str, id: &str) -> Result<Attachment, AppError> {
+        let attachments = self.attachments.lock().map_err(lock_error)?;
+        attachments
+            .iter()
+            .find(|a| a.item_id == item_id && a.id == id)
+            .cloned()
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn delete(&self, item_id: &str, id: &str) -> Result<(), AppError> {
+        let mut attachments = self.attachments.lock().map_err(lock_error)?;
+        attachments.retain(|a| !(a.item_id == item_id && a.id == id));
+        Ok(())
+    }
+}
+
+pub struct PostgresAttachmentRepository {
+    db: PgPool,
+}
+
+impl PostgresAttachmentRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+impl AttachmentRepository for PostgresAttachmentRepository {
+    async fn add(&self, attachment: Attachment) -> Result<Attachment, AppError> {
+        let row = sqlx::query_as!(
+            Attachment,
+            r#"
+                INSERT INTO attachments (id, item_id, filename, content_type, size, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id, item_id, filename, content_type, size, created_at
+            "#,
+            attachment.id,
+            attachment.item_id,
+            attachment.filename,
+            attachment.content_type,
+            attachment.size,
+            attachment.created_at,
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(row)
+    }
+
+    async fn list(&self, item_id: &str) -> Result<Vec<Attachment>, AppError> {
+        let rows = sqlx::query_as!(
+            Attachment,
+            r#"
+                SELECT id, item_id, filename, content_type, size, created_at
+                FROM attachments
+                WHERE item_id = $1
+                ORDER BY created_at ASC
+            "#,
+            item_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn get(&self, item_id: &str, id: &str) -> Result<Attachment, AppError> {
+        let row = sqlx::query_as!(
+            Attachment,
+            r#"
+                SELECT id, item_id, filename, content_type, size, created_at
+                FROM attachments
+                WHERE item_id = $1 AND id = $2
+            "#,
+            item_id,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        row.ok_or_else(|| not_found(id))
+    }
+
+    async fn delete(&self, item_id: &str, id: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"DELETE FROM attachments WHERE item_id = $1 AND id = $2"#,
+            item_id,
+            id
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
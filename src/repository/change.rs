@@ -0,0 +1,275 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::model::{
+    change::Change,
+    error::{AppError, AppErrorCode},
+    event::{Event, EventAction},
+};
+
+/// History of the items and users, the state of a record at any moment being
+/// the data of its latest change up to then. Changes are written along with
+/// the record in the same transaction, by triggers in Postgres, so history
+/// holds exactly the committed writes.
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait ChangeRepository: Send + Sync {
+    /// The latest change of the record up to `at`, `None` when it didn't
+    /// exist yet or history doesn't reach back that far.
+    fn latest(
+        &self,
+        entity: &str,
+        entity_id: &str,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<Change>, AppError>> + Send;
+    /// The latest change up to `at` of every record of `entity` that existed
+    /// then, i.e. leaving out deleted ones.
+    fn latest_all(
+        &self,
+        entity: &str,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<Change>, AppError>> + Send;
+    /// Up to `limit` changes recorded after change `seq`, oldest first.
+    /// Changes commit in `seq` order, so none shows up later behind `seq`.
+    fn since(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Change>, AppError>> + Send;
+    /// `seq` of the latest change, 0 while history is empty.
+    fn last_seq(&self) -> impl Future<Output = Result<i64, AppError>> + Send;
+    /// The `seq` `consumer` saved last, `None` before it saved any.
+    fn cursor(&self, consumer: &str) -> impl Future<Output = Result<Option<i64>, AppError>> + Send;
+    fn save_cursor(
+        &self,
+        consumer: &str,
+        seq: i64,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+}
+
+fn lock_error(e: impl ToString) -> AppError {
+    AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to lock changes".to_string(),
+        error_code: None,
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryChangeRepository {
+    pub changes: Mutex<Vec<Change>>,
+    cursors: Mutex<HashMap<String, i64>>,
+}
+
+impl InMemoryChangeRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the change, the in-memory item and user repositories call it
+    /// with every write while still holding their lock.
+    pub fn record(&self, event: &Event) -> Result<Change, AppError> {
+        let mut changes = self.changes.lock().map_err(lock_error)?;
+        let change = Change {
+            seq: changes.len() as i64 + 1,
+            entity: event.entity.clone(),
+            entity_id: event.entity_id.clone(),
+            action: event.action,
+            data: event.data.clone(),
+            changed_at: Utc::now(),
+        };
+        changes.push(change.clone());
+        Ok(change)
+    }
+}
+
+impl ChangeRepository for InMemoryChangeRepository {
+    async fn latest(
+        &self,
+        entity: &str,
+        entity_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<Change>, AppError> {
+        let changes = self.changes.lock().map_err(lock_error)?;
+        Ok(changes
+            .iter()
+            .rev()
+            .find(|c| c.entity == entity && c.entity_id == entity_id && c.changed_at <= at)
+            .cloned())
+    }
+
+    async fn latest_all(&self, entity: &str, at: DateTime<Utc>) -> Result<Vec<Change>, AppError> {
+        let changes = self.changes.lock().map_err(lock_error)?;
+        let mut latest: Vec<Change> = Vec::new();
+        for change in changes
+            .iter()
+            .rev()
+            .filter(|c| c.entity == entity && c.changed_at <= at)
+        {
+            if !latest.iter().any(|c| c.entity_id == change.entity_id) {
+                latest.push(change.clone());
+            }
+        }
+        latest.retain(|c| c.action != EventAction::Deleted);
+        latest.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+        Ok(latest)
+    }
+
+    async fn since(&self, seq: i64, limit: i64) -> Result<Vec<Change>, AppError> {
+        let changes = self.changes.lock().map_err(lock_error)?;
+        Ok(changes
+            .iter()
+            .filter(|c| c.seq > seq)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn last_seq(&self) -> Result<i64, AppError> {
+        let changes = self.changes.lock().map_err(lock_error)?;
+        Ok(changes.last().map_or(0, |c| c.seq))
+    }
+
+    async fn cursor(&self, consumer: &str) -> Result<Option<i64>, AppError> {
+        let cursors = self.cursors.lock().map_err(lock_error)?;
+        Ok(cursors.get(consumer).copied())
+    }
+
+    async fn save_cursor(&self, consumer: &str, seq: i64) -> Result<(), AppError> {
+        let mut cursors = self.cursors.lock().map_err(lock_error)?;
+        cursors.insert(consumer.to_string(), seq);
+        Ok(())
+    }
+}
+
+struct ChangeRow {
+    seq: i64,
+    entity: String,
+    entity_id: String,
+    action: String,
+    data: Option<Value>,
+    changed_at: DateTime<Utc>,
+}
+
+impl From<ChangeRow> for Change {
+    fn from(row: ChangeRow) -> Self {
+        Self {
+            seq: row.seq,
+            entity: row.entity,
+            entity_id: row.entity_id,
+            action: EventAction::parse(&row.action).unwrap_or(EventAction::Updated),
+            data: row.data,
+            changed_at: row.changed_at,
+        }
+    }
+}
+
+pub struct PostgresChangeRepository {
+    db: PgPool,
+}
+
+impl PostgresChangeRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+impl ChangeRepository for PostgresChangeRepository {
+    async fn latest(
+        &self,
+        entity: &str,
+        entity_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<Change>, AppError> {
+        let row = sqlx::query_as!(
+            ChangeRow,
+            r#"
+                SELECT seq, entity, entity_id, action, data, changed_at
+                FROM changes
+                WHERE entity = $1 AND entity_id = $2 AND changed_at <= $3
+                ORDER BY seq DESC
+                LIMIT 1
+            "#,
+            entity,
+            entity_id,
+            at
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row.map(Change::from))
+    }
+
+    async fn latest_all(&self, entity: &str, at: DateTime<Utc>) -> Result<Vec<Change>, AppError> {
+        let rows = sqlx::query_as!(
+            ChangeRow,
+            r#"
+                SELECT seq AS "seq!", entity AS "entity!", entity_id AS "entity_id!",
+                    action AS "action!", data, changed_at AS "changed_at!"
+                FROM (
+                    SELECT DISTINCT ON (entity_id) seq, entity, entity_id, action, data, changed_at
+                    FROM changes
+                    WHERE entity = $1 AND changed_at <= $2
+                    ORDER BY entity_id, seq DESC
+                ) latest
+                WHERE action <> 'deleted'
+                ORDER BY entity_id
+            "#,
+            entity,
+            at
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows.into_iter().map(Change::from).collect())
+    }
+
+    async fn since(&self, seq: i64, limit: i64) -> Result<Vec<Change>, AppError> {
+        let rows = sqlx::query_as!(
+            ChangeRow,
+            r#"
+                SELECT seq, entity, entity_id, action, data, changed_at
+                FROM changes
+                WHERE seq > $1
+                ORDER BY seq
+                LIMIT $2
+            "#,
+            seq,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows.into_iter().map(Change::from).collect())
+    }
+
+    async fn last_seq(&self) -> Result<i64, AppError> {
+        let seq = sqlx::query_scalar!(r#"SELECT COALESCE(MAX(seq), 0) AS "seq!" FROM changes"#)
+            .fetch_one(&self.db)
+            .await?;
+        Ok(seq)
+    }
+
+    async fn cursor(&self, consumer: &str) -> Result<Option<i64>, AppError> {
+        let seq = sqlx::query_scalar!(
+            "SELECT seq FROM change_cursors WHERE consumer = $1",
+            consumer
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(seq)
+    }
+
+    async fn save_cursor(&self, consumer: &str, seq: i64) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO change_cursors (consumer, seq)
+                VALUES ($1, $2)
+                ON CONFLICT (consumer) DO UPDATE SET seq = $2, updated_at = now()
+            "#,
+            consumer,
+            seq
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
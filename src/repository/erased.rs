@@ -0,0 +1,654 @@
+//! Object safe counterparts of the repository traits. Those return plain
+//! futures for static dispatch, the `Dyn` traits box them so repository
+//! parts can be picked at runtime.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::{
+    Repository, attachment::AttachmentRepository, change::ChangeRepository, item::ItemRepository,
+    job::JobRepository, user::UserRepository,
+};
+use crate::model::{
+    attachment::Attachment,
+    change::Change,
+    error::AppError,
+    item::{Item, ListItemFilter},
+    job::{Job, JobStatus},
+    sort::{ItemSort, UserSort},
+    user::{PendingEmail, User},
+};
+
+/// Repository whose parts are dispatched dynamically, for a backing store
+/// picked at runtime. [`ErasedRepository`] turns any other repository into one.
+pub type DynRepository = dyn Repository<
+        Items = dyn DynItemRepository,
+        Users = dyn DynUserRepository,
+        Jobs = dyn DynJobRepository,
+        Attachments = dyn DynAttachmentRepository,
+        Changes = dyn DynChangeRepository,
+    >;
+
+/// Hands out the parts of `R` as trait objects, see [`DynRepository`].
+pub struct ErasedRepository<R: ?Sized>(Arc<R>);
+
+impl<R: ?Sized> ErasedRepository<R> {
+    pub fn new(repo: Arc<R>) -> Self {
+        Self(repo)
+    }
+}
+
+impl<R> Repository for ErasedRepository<R>
+where
+    R: Repository<Items: Sized, Users: Sized, Jobs: Sized, Attachments: Sized, Changes: Sized>
+        + ?Sized,
+{
+    type Items = dyn DynItemRepository;
+    type Users = dyn DynUserRepository;
+    type Jobs = dyn DynJobRepository;
+    type Attachments = dyn DynAttachmentRepository;
+    type Changes = dyn DynChangeRepository;
+
+    fn item(&self) -> Arc<Self::Items> {
+        self.0.item()
+    }
+
+    fn user(&self) -> Arc<Self::Users> {
+        self.0.user()
+    }
+
+    fn job(&self) -> Arc<Self::Jobs> {
+        self.0.job()
+    }
+
+    fn attachment(&self) -> Arc<Self::Attachments> {
+        self.0.attachment()
+    }
+
+    fn change(&self) -> Arc<Self::Changes> {
+        self.0.change()
+    }
+}
+
+/// Object safe [`ItemRepository`], see [`DynRepository`].
+#[async_trait]
+pub trait DynItemRepository: Send + Sync {
+    async fn add(&self, item: Item) -> Result<Item, AppError>;
+    async fn list(&self, sort: ItemSort, filter: ListItemFilter) -> Result<Vec<Item>, AppError>;
+    async fn get(&self, id: &str) -> Result<Item, AppError>;
+    async fn exists(&self, id: &str) -> Result<bool, AppError>;
+    async fn get_by_slug(&self, slug: &str) -> Result<Item, AppError>;
+    async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<Item>, AppError>;
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<Item>, AppError>;
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError>;
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError>;
+    async fn update(&self, id: &str, name: String, version: Option<i64>) -> Result<Item, AppError>;
+    async fn update_many(&self, updates: Vec<(String, String)>) -> Result<Vec<Item>, AppError>;
+    async fn add_many(&self, items: Vec<Item>) -> Result<Vec<Item>, AppError>;
+    async fn set_slug(&self, id: &str, slug: String) -> Result<Item, AppError>;
+    async fn delete(&self, id: &str) -> Result<Option<Item>, AppError>;
+}
+
+#[async_trait]
+impl<T: ItemRepository> DynItemRepository for T {
+    async fn add(&self, item: Item) -> Result<Item, AppError> {
+        ItemRepository::add(self, item).await
+    }
+
+    async fn list(&self, sort: ItemSort, filter: ListItemFilter) -> Result<Vec<Item>, AppError> {
+        ItemRepository::list(self, sort, filter).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Item, AppError> {
+        ItemRepository::get(self, id).await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        ItemRepository::exists(self, id).await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> Result<Item, AppError> {
+        ItemRepository::get_by_slug(self, slug).await
+    }
+
+    async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<Item>, AppError> {
+        ItemRepository::list_by_owner(self, owner_id).await
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<Item>, AppError> {
+        ItemRepository::search(self, query, limit).await
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        ItemRepository::list_after(self, after, limit).await
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        ItemRepository::list_before(self, before, limit).await
+    }
+
+    async fn update(&self, id: &str, name: String, version: Option<i64>) -> Result<Item, AppError> {
+        ItemRepository::update(self, id, name, version).await
+    }
+
+    async fn update_many(&self, updates: Vec<(String, String)>) -> Result<Vec<Item>, AppError> {
+        ItemRepository::update_many(self, updates).await
+    }
+
+    async fn add_many(&self, items: Vec<Item>) -> Result<Vec<Item>, AppError> {
+        ItemRepository::add_many(self, items).await
+    }
+
+    async fn set_slug(&self, id: &str, slug: String) -> Result<Item, AppError> {
+        ItemRepository::set_slug(self, id, slug).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<Item>, AppError> {
+        ItemRepository::delete(self, id).await
+    }
+}
+
+impl ItemRepository for dyn DynItemRepository {
+    async fn add(&self, item: Item) -> Result<Item, AppError> {
+        DynItemRepository::add(self, item).await
+    }
+
+    async fn list(&self, sort: ItemSort, filter: ListItemFilter) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::list(self, sort, filter).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Item, AppError> {
+        DynItemRepository::get(self, id).await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        DynItemRepository::exists(self, id).await
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> Result<Item, AppError> {
+        DynItemRepository::get_by_slug(self, slug).await
+    }
+
+    async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::list_by_owner(self, owner_id).await
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::search(self, query, limit).await
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::list_after(self, after, limit).await
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::list_before(self, before, limit).await
+    }
+
+    async fn update(&self, id: &str, name: String, version: Option<i64>) -> Result<Item, AppError> {
+        DynItemRepository::update(self, id, name, version).await
+    }
+
+    async fn update_many(&self, updates: Vec<(String, String)>) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::update_many(self, updates).await
+    }
+
+    async fn add_many(&self, items: Vec<Item>) -> Result<Vec<Item>, AppError> {
+        DynItemRepository::add_many(self, items).await
+    }
+
+    async fn set_slug(&self, id: &str, slug: String) -> Result<Item, AppError> {
+        DynItemRepository::set_slug(self, id, slug).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<Item>, AppError> {
+        DynItemRepository::delete(self, id).await
+    }
+}
+
+/// Object safe [`UserRepository`], see [`DynRepository`].
+#[async_trait]
+pub trait DynUserRepository: Send + Sync {
+    async fn add(&self, user: User) -> Result<User, AppError>;
+    async fn list(&self, sort: UserSort) -> Result<Vec<User>, AppError>;
+    async fn get(&self, id: &str) -> Result<User, AppError>;
+    async fn get_many(&self, ids: &[String]) -> Result<Vec<User>, AppError>;
+    async fn exists(&self, id: &str) -> Result<bool, AppError>;
+    async fn get_by_email(&self, email: &str) -> Result<User, AppError>;
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError>;
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError>;
+    async fn search(&self, query: &str, threshold: f64, limit: i64) -> Result<Vec<User>, AppError>;
+    async fn update(&self, id: &str, email: String, version: Option<i64>)
+    -> Result<User, AppError>;
+    async fn delete(&self, id: &str) -> Result<Option<User>, AppError>;
+    async fn set_pending_email(&self, id: &str, pending: PendingEmail) -> Result<(), AppError>;
+    async fn get_pending_email(&self, id: &str) -> Result<Option<PendingEmail>, AppError>;
+    async fn confirm_pending_email(&self, id: &str) -> Result<User, AppError>;
+}
+
+#[async_trait]
+impl<T: UserRepository> DynUserRepository for T {
+    async fn add(&self, user: User) -> Result<User, AppError> {
+        UserRepository::add(self, user).await
+    }
+
+    async fn list(&self, sort: UserSort) -> Result<Vec<User>, AppError> {
+        UserRepository::list(self, sort).await
+    }
+
+    async fn get(&self, id: &str) -> Result<User, AppError> {
+        UserRepository::get(self, id).await
+    }
+
+    async fn get_many(&self, ids: &[String]) -> Result<Vec<User>, AppError> {
+        UserRepository::get_many(self, ids).await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        UserRepository::exists(self, id).await
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<User, AppError> {
+        UserRepository::get_by_email(self, email).await
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        UserRepository::list_after(self, after, limit).await
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        UserRepository::list_before(self, before, limit).await
+    }
+
+    async fn search(&self, query: &str, threshold: f64, limit: i64) -> Result<Vec<User>, AppError> {
+        UserRepository::search(self, query, threshold, limit).await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        email: String,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        UserRepository::update(self, id, email, version).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<User>, AppError> {
+        UserRepository::delete(self, id).await
+    }
+
+    async fn set_pending_email(&self, id: &str, pending: PendingEmail) -> Result<(), AppError> {
+        UserRepository::set_pending_email(self, id, pending).await
+    }
+
+    async fn get_pending_email(&self, id: &str) -> Result<Option<PendingEmail>, AppError> {
+        UserRepository::get_pending_email(self, id).await
+    }
+
+    async fn confirm_pending_email(&self, id: &str) -> Result<User, AppError> {
+        UserRepository::confirm_pending_email(self, id).await
+    }
+}
+
+impl UserRepository for dyn DynUserRepository {
+    async fn add(&self, user: User) -> Result<User, AppError> {
+        DynUserRepository::add(self, user).await
+    }
+
+    async fn list(&self, sort: UserSort) -> Result<Vec<User>, AppError> {
+        DynUserRepository::list(self, sort).await
+    }
+
+    async fn get(&self, id: &str) -> Result<User, AppError> {
+        DynUserRepository::get(self, id).await
+    }
+
+    async fn get_many(&self, ids: &[String]) -> Result<Vec<User>, AppError> {
+        DynUserRepository::get_many(self, ids).await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        DynUserRepository::exists(self, id).await
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<User, AppError> {
+        DynUserRepository::get_by_email(self, email).await
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        DynUserRepository::list_after(self, after, limit).await
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        DynUserRepository::list_before(self, before, limit).await
+    }
+
+    async fn search(&self, query: &str, threshold: f64, limit: i64) -> Result<Vec<User>, AppError> {
+        DynUserRepository::search(self, query, threshold, limit).await
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        email: String,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        DynUserRepository::update(self, id, email, version).await
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<User>, AppError> {
+        DynUserRepository::delete(self, id).await
+    }
+
+    async fn set_pending_email(&self, id: &str, pending: PendingEmail) -> Result<(), AppError> {
+        DynUserRepository::set_pending_email(self, id, pending).await
+    }
+
+    async fn get_pending_email(&self, id: &str) -> Result<Option<PendingEmail>, AppError> {
+        DynUserRepository::get_pending_email(self, id).await
+    }
+
+    async fn confirm_pending_email(&self, id: &str) -> Result<User, AppError> {
+        DynUserRepository::confirm_pending_email(self, id).await
+    }
+}
+
+/// Object safe [`JobRepository`], see [`DynRepository`].
+#[async_trait]
+pub trait DynJobRepository: Send + Sync {
+    async fn add(&self, job: Job) -> Result<Job, AppError>;
+    async fn get(&self, id: &str) -> Result<Job, AppError>;
+    async fn list(&self, status: Option<JobStatus>, limit: i64) -> Result<Vec<Job>, AppError>;
+    async fn claim(&self, locked_until: DateTime<Utc>) -> Result<Option<Job>, AppError>;
+    async fn heartbeat(&self, id: &str, locked_until: DateTime<Utc>) -> Result<(), AppError>;
+    async fn complete(&self, id: &str) -> Result<(), AppError>;
+    async fn progress(
+        &self,
+        id: &str,
+        progress: i32,
+        result: Option<Value>,
+    ) -> Result<(), AppError>;
+    async fn fail(&self, id: &str, error: &str, retry_at: DateTime<Utc>) -> Result<(), AppError>;
+    async fn release(&self, id: &str) -> Result<(), AppError>;
+    async fn retry(&self, id: &str) -> Result<Option<Job>, AppError>;
+    async fn cancel(&self, id: &str) -> Result<Option<Job>, AppError>;
+}
+
+#[async_trait]
+impl<T: JobRepository> DynJobRepository for T {
+    async fn add(&self, job: Job) -> Result<Job, AppError> {
+        JobRepository::add(self, job).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Job, AppError> {
+        JobRepository::get(self, id).await
+    }
+
+    async fn list(&self, status: Option<JobStatus>, limit: i64) -> Result<Vec<Job>, AppError> {
+        JobRepository::list(self, status, limit).await
+    }
+
+    async fn claim(&self, locked_until: DateTime<Utc>) -> Result<Option<Job>, AppError> {
+        JobRepository::claim(self, locked_until).await
+    }
+
+    async fn heartbeat(&self, id: &str, locked_until: DateTime<Utc>) -> Result<(), AppError> {
+        JobRepository::heartbeat(self, id, locked_until).await
+    }
+
+    async fn complete(&self, id: &str) -> Result<(), AppError> {
+        JobRepository::complete(self, id).await
+    }
+
+    async fn progress(
+        &self,
+        id: &str,
+        progress: i32,
+        result: Option<Value>,
+    ) -> Result<(), AppError> {
+        JobRepository::progress(self, id, progress, result).await
+    }
+
+    async fn fail(&self, id: &str, error: &str, retry_at: DateTime<Utc>) -> Result<(), AppError> {
+        JobRepository::fail(self, id, error, retry_at).await
+    }
+
+    async fn release(&self, id: &str) -> Result<(), AppError> {
+        JobRepository::release(self, id).await
+    }
+
+    async fn retry(&self, id: &str) -> Result<Option<Job>, AppError> {
+        JobRepository::retry(self, id).await
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Option<Job>, AppError> {
+        JobRepository::cancel(self, id).await
+    }
+}
+
+impl JobRepository for dyn DynJobRepository {
+    async fn add(&self, job: Job) -> Result<Job, AppError> {
+        DynJobRepository::add(self, job).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Job, AppError> {
+        DynJobRepository::get(self, id).await
+    }
+
+    async fn list(&self, status: Option<JobStatus>, limit: i64) -> Result<Vec<Job>, AppError> {
+        DynJobRepository::list(self, status, limit).await
+    }
+
+    async fn claim(&self, locked_until: DateTime<Utc>) -> Result<Option<Job>, AppError> {
+        DynJobRepository::claim(self, locked_until).await
+    }
+
+    async fn heartbeat(&self, id: &str, locked_until: DateTime<Utc>) -> Result<(), AppError> {
+        DynJobRepository::heartbeat(self, id, locked_until).await
+    }
+
+    async fn complete(&self, id: &str) -> Result<(), AppError> {
+        DynJobRepository::complete(self, id).await
+    }
+
+    async fn progress(
+        &self,
+        id: &str,
+        progress: i32,
+        result: Option<Value>,
+    ) -> Result<(), AppError> {
+        DynJobRepository::progress(self, id, progress, result).await
+    }
+
+    async fn fail(&self, id: &str, error: &str, retry_at: DateTime<Utc>) -> Result<(), AppError> {
+        DynJobRepository::fail(self, id, error, retry_at).await
+    }
+
+    async fn release(&self, id: &str) -> Result<(), AppError> {
+        DynJobRepository::release(self, id).await
+    }
+
+    async fn retry(&self, id: &str) -> Result<Option<Job>, AppError> {
+        DynJobRepository::retry(self, id).await
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Option<Job>, AppError> {
+        DynJobRepository::cancel(self, id).await
+    }
+}
+
+/// Object safe [`AttachmentRepository`], see [`DynRepository`].
+#[async_trait]
+pub trait DynAttachmentRepository: Send + Sync {
+    async fn add(&self, attachment: Attachment) -> Result<Attachment, AppError>;
+    async fn list(&self, item_id: &str) -> Result<Vec<Attachment>, AppError>;
+    async fn get(&self, item_id: &str, id: &str) -> Result<Attachment, AppError>;
+    async fn delete(&self, item_id: &str, id: &str) -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl<T: AttachmentRepository> DynAttachmentRepository for T {
+    async fn add(&self, attachment: Attachment) -> Result<Attachment, AppError> {
+        AttachmentRepository::add(self, attachment).await
+    }
+
+    async fn list(&self, item_id: &str) -> Result<Vec<Attachment>, AppError> {
+        AttachmentRepository::list(self, item_id).await
+    }
+
+    async fn get(&self, item_id: &str, id: &str) -> Result<Attachment, AppError> {
+        AttachmentRepository::get(self, item_id, id).await
+    }
+
+    async fn delete(&self, item_id: &str, id: &str) -> Result<(), AppError> {
+        AttachmentRepository::delete(self, item_id, id).await
+    }
+}
+
+impl AttachmentRepository for dyn DynAttachmentRepository {
+    async fn add(&self, attachment: Attachment) -> Result<Attachment, AppError> {
+        DynAttachmentRepository::add(self, attachment).await
+    }
+
+    async fn list(&self, item_id: &str) -> Result<Vec<Attachment>, AppError> {
+        DynAttachmentRepository::list(self, item_id).await
+    }
+
+    async fn get(&self, item_id: &str, id: &str) -> Result<Attachment, AppError> {
+        DynAttachmentRepository::get(self, item_id, id).await
+    }
+
+    async fn delete(&self, item_id: &str, id: &str) -> Result<(), AppError> {
+        DynAttachmentRepository::delete(self, item_id, id).await
+    }
+}
+
+/// Object safe [`ChangeRepository`], see [`DynRepository`].
+#[async_trait]
+pub trait DynChangeRepository: Send + Sync {
+    async fn latest(
+        &self,
+        entity: &str,
+        entity_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<Change>, AppError>;
+    async fn latest_all(&self, entity: &str, at: DateTime<Utc>) -> Result<Vec<Change>, AppError>;
+    async fn since(&self, seq: i64, limit: i64) -> Result<Vec<Change>, AppError>;
+    async fn last_seq(&self) -> Result<i64, AppError>;
+    async fn cursor(&self, consumer: &str) -> Result<Option<i64>, AppError>;
+    async fn save_cursor(&self, consumer: &str, seq: i64) -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl<T: ChangeRepository> DynChangeRepository for T {
+    async fn latest(
+        &self,
+        entity: &str,
+        entity_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<Change>, AppError> {
+        ChangeRepository::latest(self, entity, entity_id, at).await
+    }
+
+    async fn latest_all(&self, entity: &str, at: DateTime<Utc>) -> Result<Vec<Change>, AppError> {
+        ChangeRepository::latest_all(self, entity, at).await
+    }
+
+    async fn since(&self, seq: i64, limit: i64) -> Result<Vec<Change>, AppError> {
+        ChangeRepository::since(self, seq, limit).await
+    }
+
+    async fn last_seq(&self) -> Result<i64, AppError> {
+        ChangeRepository::last_seq(self).await
+    }
+
+    async fn cursor(&self, consumer: &str) -> Result<Option<i64>, AppError> {
+        ChangeRepository::cursor(self, consumer).await
+    }
+
+    async fn save_cursor(&self, consumer: &str, seq: i64) -> Result<(), AppError> {
+        ChangeRepository::save_cursor(self, consumer, seq).await
+    }
+}
+
+impl ChangeRepository for dyn DynChangeRepository {
+    async fn latest(
+        &self,
+        entity: &str,
+        entity_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<Change>, AppError> {
+        DynChangeRepository::latest(self, entity, entity_id, at).await
+    }
+
+    async fn latest_all(&self, entity: &str, at: DateTime<Utc>) -> Result<Vec<Change>, AppError> {
+        DynChangeRepository::latest_all(self, entity, at).await
+    }
+
+    async fn since(&self, seq: i64, limit: i64) -> Result<Vec<Change>, AppError> {
+        DynChangeRepository::since(self, seq, limit).await
+    }
+
+    async fn last_seq(&self) -> Result<i64, AppError> {
+        DynChangeRepository::last_seq(self).await
+    }
+
+    async fn cursor(&self, consumer: &str) -> Result<Option<i64>, AppError> {
+        DynChangeRepository::cursor(self, consumer).await
+    }
+
+    async fn save_cursor(&self, consumer: &str, seq: i64) -> Result<(), AppError> {
+        DynChangeRepository::save_cursor(self, consumer, seq).await
+    }
+}
@@ -1,30 +1,235 @@
-use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
-use std::sync::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
+use super::{
+    change::InMemoryChangeRepository,
+    user::{InMemoryUserRepository, UserRepository, user_not_found},
+};
 use crate::model::{
-    error::{AppError, AppErrorCode},
-    item::Item,
+    error::{AppError, AppErrorCode, ErrorCode, FieldError},
+    event::{Event, EventAction},
+    item::{Item, ListItemFilter},
+    sort::{ItemSort, ItemSortColumn, SortOrder},
 };
 
-#[async_trait]
-#[cfg_attr(test, mockall::automock)]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
 pub trait ItemRepository: Send + Sync {
-    async fn add(&self, item: Item) -> Result<Item, AppError>;
-    async fn list(&self) -> Result<Vec<Item>, AppError>;
-    async fn get(&self, id: &str) -> Result<Item, AppError>;
-    async fn update(&self, id: &str, name: String) -> Result<Item, AppError>;
-    async fn delete(&self, id: &str) -> Result<(), AppError>;
+    /// Stores `item`, failing with [`name_taken`] when another item has its
+    /// name, ignoring case.
+    /// Fails with [`ErrorCode::UserNotFound`] when the owner doesn't exist.
+    fn add(&self, item: Item) -> impl Future<Output = Result<Item, AppError>> + Send;
+    fn list(
+        &self,
+        sort: ItemSort,
+        filter: ListItemFilter,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    fn get(&self, id: &str) -> impl Future<Output = Result<Item, AppError>> + Send;
+    /// Whether item `id` is stored, without loading it.
+    fn exists(&self, id: &str) -> impl Future<Output = Result<bool, AppError>> + Send;
+    fn get_by_slug(&self, slug: &str) -> impl Future<Output = Result<Item, AppError>> + Send;
+    /// Items owned by user `owner_id`, by name.
+    fn list_by_owner(
+        &self,
+        owner_id: &str,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    /// Items whose name contains `query`, ignoring case, by name.
+    fn search(
+        &self,
+        query: &str,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    /// Up to `limit` items in `(name, id)` order, starting after the given
+    /// name and id.
+    fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    /// Up to `limit` items in descending `(name, id)` order, starting before
+    /// the given name and id, or at the last item without.
+    fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    /// Renames the item, its slug stays as it was. With `version`, only
+    /// while that is still the stored version, failing with
+    /// [`version_mismatch`] otherwise.
+    fn update(
+        &self,
+        id: &str,
+        name: String,
+        version: Option<i64>,
+    ) -> impl Future<Output = Result<Item, AppError>> + Send;
+    /// Renames every `(id, name)` in one go. Fails with [`row_error`] for the
+    /// first one that can't be renamed, leaving all items unchanged.
+    fn update_many(
+        &self,
+        updates: Vec<(String, String)>,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    /// Adds every item like `add`, all of them or none. Fails with
+    /// [`row_error`] for the first one that can't be added.
+    fn add_many(
+        &self,
+        items: Vec<Item>,
+    ) -> impl Future<Output = Result<Vec<Item>, AppError>> + Send;
+    /// Fails with [`ErrorCode::SlugTaken`] when another item has `slug`.
+    fn set_slug(
+        &self,
+        id: &str,
+        slug: String,
+    ) -> impl Future<Output = Result<Item, AppError>> + Send;
+    /// The deleted item, `None` when there was none to delete.
+    fn delete(&self, id: &str) -> impl Future<Output = Result<Option<Item>, AppError>> + Send;
+}
+
+/// `add` fails with it when another item has the name, ignoring case.
+pub fn name_taken(name: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::Conflict,
+        message: format!("Item {} already exists", name),
+        error_code: Some(ErrorCode::NameTaken),
+    }
+}
+
+/// `add` and `set_slug` fail with it when the slug belongs to another item.
+pub fn slug_taken(slug: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::Conflict,
+        message: format!("Slug {} is already taken", slug),
+        error_code: Some(ErrorCode::SlugTaken),
+    }
+}
+
+fn slug_not_found(slug: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::NotFound,
+        message: format!("Item with slug {} not found", slug),
+        error_code: Some(ErrorCode::ItemNotFound),
+    }
+}
+
+pub fn item_not_found(id: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::NotFound,
+        message: format!("Item with id {} not found", id),
+        error_code: Some(ErrorCode::ItemNotFound),
+    }
+}
+
+/// `update` fails with it when the item changed since `version` was read.
+pub fn version_mismatch(id: &str, version: i64) -> AppError {
+    AppError {
+        code: AppErrorCode::PreconditionFailed,
+        message: format!("Item {} is no longer at version {}", id, version),
+        error_code: None,
+    }
+}
+
+/// `update_many` and `add_many` fail with it when the row at `index` was not
+/// found or conflicts, pointing at that row and keeping the error code, e.g.
+/// [`ErrorCode::SlugTaken`]. Other errors are passed on.
+pub fn row_error(index: usize, e: AppError) -> AppError {
+    let code = match e.code {
+        AppErrorCode::NotFound => "not_found",
+        AppErrorCode::Conflict => "conflict",
+        _ => return e,
+    };
+    AppError {
+        code: AppErrorCode::Unprocessable(vec![FieldError::new(
+            &format!("/{}", index),
+            code,
+            e.message.clone(),
+        )]),
+        message: format!("Row {} failed: {}", index, e.message),
+        error_code: e.error_code,
+    }
+}
+
+/// Adds `new_item` to `items` unless one has its name already, the in-memory
+/// `add` once the owner is known to exist.
+fn insert(
+    items: &mut Vec<Item>,
+    created_at: &mut HashMap<String, DateTime<Utc>>,
+    new_item: Item,
+) -> Result<Item, AppError> {
+    if items
+        .iter()
+        .any(|item| item.name.to_lowercase() == new_item.name.to_lowercase())
+    {
+        return Err(name_taken(&new_item.name));
+    }
+    if items.iter().any(|item| item.slug == new_item.slug) {
+        return Err(slug_taken(&new_item.slug));
+    }
+    created_at.insert(new_item.id.clone(), Utc::now());
+    items.push(new_item.clone());
+    Ok(new_item)
+}
+
+/// Renames item `id` within `items` if still at `version`, the in-memory
+/// `update`.
+fn rename(
+    items: &mut [Item],
+    id: &str,
+    name: String,
+    version: Option<i64>,
+) -> Result<Item, AppError> {
+    if items
+        .iter()
+        .any(|item| item.name.to_lowercase() == name.to_lowercase() && item.id != id)
+    {
+        return Err(name_taken(&name));
+    }
+    let item = items
+        .iter_mut()
+        .find(|item| item.id == id)
+        .ok_or_else(|| item_not_found(id))?;
+    if let Some(version) = version.filter(|version| *version != item.version) {
+        return Err(version_mismatch(id, version));
+    }
+    item.name = name;
+    item.version += 1;
+    Ok(item.clone())
+}
+
+fn lock_error(e: impl ToString) -> AppError {
+    AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to lock items".to_string(),
+        error_code: None,
+    }
+}
+
+/// Escapes `%`, `_` and `\` for use in an `ILIKE` pattern.
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 pub struct InMemoryItemRepository {
     pub items: Mutex<Vec<Item>>,
+    /// Creation time by item id, for the `created_after` filter.
+    created_at: Mutex<HashMap<String, DateTime<Utc>>>,
+    /// Owners are checked against these users when set, any owner is taken
+    /// otherwise. Unlike Postgres, deleting a user keeps its items' owner.
+    users: Option<Arc<InMemoryUserRepository>>,
+    /// Change history every write is recorded in, when set.
+    changes: Option<Arc<InMemoryChangeRepository>>,
 }
 
 impl Default for InMemoryItemRepository {
     fn default() -> Self {
         Self {
             items: Mutex::new(Vec::new()),
+            created_at: Mutex::new(HashMap::new()),
+            users: None,
+            changes: None,
         }
     }
 }
@@ -33,37 +238,105 @@ impl InMemoryItemRepository {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Rejects items whose owner isn't one of `users`, as the foreign key
+    /// does in Postgres.
+    pub fn with_users(users: Arc<InMemoryUserRepository>) -> Self {
+        Self {
+            users: Some(users),
+            ..Self::default()
+        }
+    }
+
+    /// Records every write in `changes`, as the triggers do in Postgres.
+    pub fn with_changes(mut self, changes: Arc<InMemoryChangeRepository>) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    /// Records the write of item `id`, called under the items lock so
+    /// history follows the order of the writes.
+    fn record(&self, action: EventAction, id: &str, item: Option<&Item>) -> Result<(), AppError> {
+        match &self.changes {
+            Some(changes) => changes
+                .record(&Event::new("item", action, id, item))
+                .map(|_| ()),
+            None => Ok(()),
+        }
+    }
 }
 
-#[async_trait]
 impl ItemRepository for InMemoryItemRepository {
     async fn add(&self, new_item: Item) -> Result<Item, AppError> {
-        match self.items.lock() {
-            Ok(mut items) => {
-                let cur = items.iter().find(|item| item.name == new_item.name);
-                match cur {
-                    Some(item) => Ok(item.clone()),
-                    None => {
-                        items.push(new_item.clone());
-                        Ok(new_item)
-                    }
-                }
+        if let (Some(users), Some(owner_id)) = (&self.users, &new_item.owner_id)
+            && !users.exists(owner_id).await?
+        {
+            return Err(user_not_found(owner_id));
+        }
+        let mut items = self.items.lock().map_err(lock_error)?;
+        let mut created_at = self.created_at.lock().map_err(lock_error)?;
+        let item = insert(&mut items, &mut created_at, new_item)?;
+        self.record(EventAction::Created, &item.id, Some(&item))?;
+        Ok(item)
+    }
+
+    async fn add_many(&self, new_items: Vec<Item>) -> Result<Vec<Item>, AppError> {
+        for (i, item) in new_items.iter().enumerate() {
+            if let (Some(users), Some(owner_id)) = (&self.users, &item.owner_id)
+                && !users.exists(owner_id).await?
+            {
+                return Err(row_error(i, user_not_found(owner_id)));
             }
-            Err(e) => Err(AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to lock items".to_string(),
-            }),
         }
+        let mut items = self.items.lock().map_err(lock_error)?;
+        let mut created_at = self.created_at.lock().map_err(lock_error)?;
+        // Added to copies, kept only once every item is in.
+        let (mut next, mut next_created_at) = (items.clone(), created_at.clone());
+        let added = new_items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| {
+                insert(&mut next, &mut next_created_at, item).map_err(|e| row_error(i, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        *items = next;
+        *created_at = next_created_at;
+        for item in &added {
+            self.record(EventAction::Created, &item.id, Some(item))?;
+        }
+        Ok(added)
     }
 
-    async fn list(&self) -> Result<Vec<Item>, AppError> {
-        match self.items.lock() {
-            Ok(items) => Ok(items.clone()),
-            Err(e) => Err(AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to lock items".to_string(),
-            }),
+    async fn list(&self, sort: ItemSort, filter: ListItemFilter) -> Result<Vec<Item>, AppError> {
+        let name_contains = filter.name_contains.map(|name| name.to_lowercase());
+        let created_at = self.created_at.lock().map_err(lock_error)?;
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .map_err(lock_error)?
+            .iter()
+            .filter(|item| {
+                name_contains
+                    .as_ref()
+                    .is_none_or(|name| item.name.to_lowercase().contains(name))
+            })
+            .filter(|item| {
+                filter
+                    .created_after
+                    .is_none_or(|after| created_at.get(&item.id).is_some_and(|at| *at > after))
+            })
+            .cloned()
+            .collect();
+        let key = |item: &Item| match sort.column {
+            ItemSortColumn::Name => item.name.clone(),
+            ItemSortColumn::Slug => item.slug.clone(),
+            ItemSortColumn::Id => item.id.clone(),
+        };
+        items.sort_by(|a, b| (key(a), &a.id).cmp(&(key(b), &b.id)));
+        if sort.order == SortOrder::Desc {
+            items.reverse();
         }
+        Ok(items)
     }
 
     async fn get(&self, id: &str) -> Result<Item, AppError> {
@@ -75,67 +348,191 @@ impl ItemRepository for InMemoryItemRepository {
                     None => Err(AppError {
                         code: AppErrorCode::NotFound,
                         message: format!("Item with id {} not found", id),
+                        error_code: Some(ErrorCode::ItemNotFound),
                     }),
                 }
             }
             Err(e) => Err(AppError {
                 code: AppErrorCode::InternalError(e.to_string()),
                 message: "Failed to lock items".to_string(),
+                error_code: None,
             }),
         }
     }
 
-    async fn update(&self, id: &str, name: String) -> Result<Item, AppError> {
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        let items = self.items.lock().map_err(lock_error)?;
+        Ok(items.iter().any(|item| item.id == id))
+    }
+
+    async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<Item>, AppError> {
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .map_err(lock_error)?
+            .iter()
+            .filter(|item| item.owner_id.as_deref() == Some(owner_id))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+        Ok(items)
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> Result<Item, AppError> {
         match self.items.lock() {
-            Ok(mut items) => {
-                let index = match items.iter().position(|item| item.id == id) {
-                    Some(index) => index,
-                    None => {
-                        return Err(AppError {
-                            code: AppErrorCode::NotFound,
-                            message: format!("Item with id {} not found", id),
-                        });
-                    }
-                };
-                let cur = match items.get(index) {
-                    Some(item) => item.clone(),
-                    None => {
-                        return Err(AppError {
-                            code: AppErrorCode::NotFound,
-                            message: format!("Item with id {} not found", id),
-                        });
-                    }
-                };
-
-                let updated_item = Item { name, ..cur };
-                items[index] = updated_item.clone();
-                Ok(updated_item)
-            }
-            Err(e) => Err(AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to lock items".to_string(),
-            }),
+            Ok(items) => items
+                .iter()
+                .find(|item| item.slug == slug)
+                .cloned()
+                .ok_or_else(|| slug_not_found(slug)),
+            Err(e) => Err(lock_error(e)),
         }
     }
 
-    async fn delete(&self, id: &str) -> Result<(), AppError> {
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<Item>, AppError> {
+        let query = query.to_lowercase();
+        let mut found: Vec<Item> = self
+            .items
+            .lock()
+            .map_err(lock_error)?
+            .iter()
+            .filter(|item| item.name.to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found.truncate(limit.max(0) as usize);
+        Ok(found)
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .map_err(lock_error)?
+            .iter()
+            .filter(|item| {
+                after
+                    .as_ref()
+                    .is_none_or(|(name, id)| (&item.name, &item.id) > (name, id))
+            })
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+        items.truncate(limit.max(0) as usize);
+        Ok(items)
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        let mut items: Vec<Item> = self
+            .items
+            .lock()
+            .map_err(lock_error)?
+            .iter()
+            .filter(|item| {
+                before
+                    .as_ref()
+                    .is_none_or(|(name, id)| (&item.name, &item.id) < (name, id))
+            })
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| (&b.name, &b.id).cmp(&(&a.name, &a.id)));
+        items.truncate(limit.max(0) as usize);
+        Ok(items)
+    }
+
+    async fn update(&self, id: &str, name: String, version: Option<i64>) -> Result<Item, AppError> {
+        let mut items = self.items.lock().map_err(lock_error)?;
+        let item = rename(&mut items, id, name, version)?;
+        self.record(EventAction::Updated, &item.id, Some(&item))?;
+        Ok(item)
+    }
+
+    async fn update_many(&self, updates: Vec<(String, String)>) -> Result<Vec<Item>, AppError> {
+        let mut items = self.items.lock().map_err(lock_error)?;
+        // Applied to a copy, kept only when every update succeeds.
+        let mut renamed = items.clone();
+        let updated = updates
+            .into_iter()
+            .enumerate()
+            .map(|(i, (id, name))| {
+                rename(&mut renamed, &id, name, None).map_err(|e| row_error(i, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        *items = renamed;
+        for item in &updated {
+            self.record(EventAction::Updated, &item.id, Some(item))?;
+        }
+        Ok(updated)
+    }
+
+    async fn set_slug(&self, id: &str, slug: String) -> Result<Item, AppError> {
+        let mut items = self.items.lock().map_err(lock_error)?;
+        if items.iter().any(|item| item.slug == slug && item.id != id) {
+            return Err(slug_taken(&slug));
+        }
+        let item = items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| item_not_found(id))?;
+        item.slug = slug;
+        item.version += 1;
+        let item = item.clone();
+        self.record(EventAction::Updated, &item.id, Some(&item))?;
+        Ok(item)
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<Item>, AppError> {
         match self.items.lock() {
             Ok(mut items) => {
-                *items = items
-                    .clone()
-                    .into_iter()
-                    .filter(|item| item.id != id)
-                    .collect();
-                Ok(())
+                let deleted = items
+                    .iter()
+                    .position(|item| item.id == id)
+                    .map(|index| items.remove(index));
+                self.created_at.lock().map_err(lock_error)?.remove(id);
+                if deleted.is_some() {
+                    self.record(EventAction::Deleted, id, None)?;
+                }
+                Ok(deleted)
             }
             Err(e) => Err(AppError {
                 code: AppErrorCode::InternalError(e.to_string()),
                 message: "Failed to lock items".to_string(),
+                error_code: None,
             }),
         }
     }
 }
 
+/// A missing owner, taken name or taken slug of an added item, see
+/// [`slug_conflict`].
+fn add_error(e: sqlx::Error, item: &Item) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.constraint() == Some("items_owner_id_fkey") => {
+            user_not_found(item.owner_id.as_deref().unwrap_or_default())
+        }
+        sqlx::Error::Database(db) if db.constraint() == Some("items_name_lower_key") => {
+            name_taken(&item.name)
+        }
+        _ => slug_conflict(e, &item.slug),
+    }
+}
+
+/// Tells a taken slug apart from the other unique violations, e.g. of names.
+fn slug_conflict(e: sqlx::Error, slug: &str) -> AppError {
+    match &e {
+        sqlx::Error::Database(db) if db.constraint() == Some("items_slug_key") => slug_taken(slug),
+        _ => e.into(),
+    }
+}
+
 pub struct PostgresItemRepository {
     db: PgPool,
 }
@@ -146,92 +543,269 @@ impl PostgresItemRepository {
     }
 }
 
-#[async_trait]
 impl ItemRepository for PostgresItemRepository {
     async fn add(&self, item: Item) -> Result<Item, AppError> {
         let row = sqlx::query_as!(
             Item,
             r#"
-                INSERT INTO items (id, name)
-                VALUES ($1, $2)
-                ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
-                RETURNING id, name
+                INSERT INTO items (id, name, slug, owner_id)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, name, slug, owner_id, version
             "#,
             item.id,
-            item.name
+            item.name,
+            item.slug,
+            item.owner_id
         )
         .fetch_one(&self.db)
         .await
-        .map_err(|e| AppError {
-            code: AppErrorCode::InternalError(e.to_string()),
-            message: "Failed to upsert item".to_string(),
-        })?;
+        .map_err(|e| add_error(e, &item))?;
         Ok(row)
     }
 
-    async fn list(&self) -> Result<Vec<Item>, AppError> {
-        let rows = sqlx::query_as!(Item, r#"SELECT id, name FROM items ORDER BY name ASC"#)
+    async fn list(&self, sort: ItemSort, filter: ListItemFilter) -> Result<Vec<Item>, AppError> {
+        // Only the ORDER BY is spliced in, from the allowlisted columns.
+        let sql = format!(
+            r#"
+                SELECT id, name, slug, owner_id, version FROM items
+                WHERE ($1::text IS NULL OR name ILIKE $1)
+                AND ($2::timestamptz IS NULL OR created_at > $2)
+                {}
+            "#,
+            sort.to_sql()
+        );
+        let pattern = filter
+            .name_contains
+            .map(|name| format!("%{}%", like_escape(&name)));
+        let rows = sqlx::query_as::<_, (String, String, String, Option<String>, i64)>(&sql)
+            .bind(pattern)
+            .bind(filter.created_after)
             .fetch_all(&self.db)
-            .await
-            .map_err(|e| AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to fetch items".to_string(),
-            })?;
-        Ok(rows)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, name, slug, owner_id, version)| Item {
+                id,
+                name,
+                slug,
+                owner_id,
+                version,
+            })
+            .collect())
     }
 
     async fn get(&self, id: &str) -> Result<Item, AppError> {
-        let row = sqlx::query_as!(Item, r#"SELECT id, name FROM items WHERE id = $1"#, id)
-            .fetch_optional(&self.db)
-            .await
-            .map_err(|e| AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to fetch item".to_string(),
-            })?;
+        let row = sqlx::query_as!(
+            Item,
+            r#"SELECT id, name, slug, owner_id, version FROM items WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
         match row {
             Some(row) => Ok(row),
             None => Err(AppError {
                 code: AppErrorCode::NotFound,
                 message: format!("Item with id {} not found", id),
+                error_code: Some(ErrorCode::ItemNotFound),
             }),
         }
     }
 
-    async fn update(&self, id: &str, name: String) -> Result<Item, AppError> {
+    async fn add_many(&self, items: Vec<Item>) -> Result<Vec<Item>, AppError> {
+        // Dropping the transaction on an error rolls back the earlier rows.
+        let mut tx = self.db.begin().await?;
+        let mut added = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            let row = sqlx::query_as!(
+                Item,
+                r#"
+                    INSERT INTO items (id, name, slug, owner_id)
+                    VALUES ($1, $2, $3, $4)
+                    RETURNING id, name, slug, owner_id, version
+                "#,
+                item.id,
+                item.name,
+                item.slug,
+                item.owner_id
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| row_error(i, add_error(e, &item)))?;
+            added.push(row);
+        }
+        tx.commit().await?;
+        Ok(added)
+    }
+
+    async fn update_many(&self, updates: Vec<(String, String)>) -> Result<Vec<Item>, AppError> {
+        // Dropping the transaction on an error rolls back the earlier rows.
+        let mut tx = self.db.begin().await?;
+        let mut updated = Vec::with_capacity(updates.len());
+        for (i, (id, name)) in updates.into_iter().enumerate() {
+            let row = sqlx::query_as!(
+                Item,
+                r#"
+                    UPDATE items
+                    SET name = $2, version = version + 1
+                    WHERE id = $1
+                    RETURNING id, name, slug, owner_id, version
+                "#,
+                id,
+                name
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| row_error(i, e.into()))?;
+            updated.push(row.ok_or_else(|| row_error(i, item_not_found(&id)))?);
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM items WHERE id = $1) AS "exists!""#,
+            id
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(exists)
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> Result<Item, AppError> {
+        let row = sqlx::query_as!(
+            Item,
+            r#"SELECT id, name, slug, owner_id, version FROM items WHERE slug = $1"#,
+            slug
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        row.ok_or_else(|| slug_not_found(slug))
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<Item>, AppError> {
+        let pattern = format!("%{}%", like_escape(query));
+        let rows = sqlx::query_as!(
+            Item,
+            r#"SELECT id, name, slug, owner_id, version FROM items WHERE name ILIKE $1 ORDER BY name ASC LIMIT $2"#,
+            pattern,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn list_by_owner(&self, owner_id: &str) -> Result<Vec<Item>, AppError> {
+        let rows = sqlx::query_as!(
+            Item,
+            r#"SELECT id, name, slug, owner_id, version FROM items WHERE owner_id = $1 ORDER BY name, id"#,
+            owner_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        let (name, id) = after.unzip();
+        let rows = sqlx::query_as!(
+            Item,
+            r#"
+                SELECT id, name, slug, owner_id, version FROM items
+                WHERE $1::text IS NULL OR (name, id) > ($1, $2)
+                ORDER BY name, id
+                LIMIT $3
+            "#,
+            name,
+            id,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        let (name, id) = before.unzip();
+        let rows = sqlx::query_as!(
+            Item,
+            r#"
+                SELECT id, name, slug, owner_id, version FROM items
+                WHERE $1::text IS NULL OR (name, id) < ($1, $2)
+                ORDER BY name DESC, id DESC
+                LIMIT $3
+            "#,
+            name,
+            id,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn update(&self, id: &str, name: String, version: Option<i64>) -> Result<Item, AppError> {
         let row = sqlx::query_as!(
             Item,
             r#"
                 UPDATE items
-                SET name = $2
-                WHERE id = $1
-                RETURNING id, name
+                SET name = $2, version = version + 1
+                WHERE id = $1 AND ($3::bigint IS NULL OR version = $3)
+                RETURNING id, name, slug, owner_id, version
             "#,
             id,
-            name
+            name,
+            version
         )
         .fetch_optional(&self.db)
-        .await
-        .map_err(|e| AppError {
-            code: AppErrorCode::InternalError(e.to_string()),
-            message: "Failed to update item".to_string(),
-        })?;
-        match row {
-            Some(row) => Ok(row),
-            None => Err(AppError {
+        .await?;
+        match (row, version) {
+            (Some(row), _) => Ok(row),
+            (None, Some(version)) if self.exists(id).await? => Err(version_mismatch(id, version)),
+            (None, _) => Err(AppError {
                 code: AppErrorCode::NotFound,
                 message: format!("Item with id {} not found", id),
+                error_code: Some(ErrorCode::ItemNotFound),
             }),
         }
     }
 
-    async fn delete(&self, id: &str) -> Result<(), AppError> {
-        sqlx::query!("DELETE FROM items WHERE id = $1", id)
-            .execute(&self.db)
-            .await
-            .map_err(|e| AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to delete item".to_string(),
-            })?;
-        Ok(())
+    async fn set_slug(&self, id: &str, slug: String) -> Result<Item, AppError> {
+        let row = sqlx::query_as!(
+            Item,
+            r#"
+                UPDATE items
+                SET slug = $2, version = version + 1
+                WHERE id = $1
+                RETURNING id, name, slug, owner_id, version
+            "#,
+            id,
+            slug
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| slug_conflict(e, &slug))?;
+        row.ok_or_else(|| item_not_found(id))
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<Item>, AppError> {
+        let row = sqlx::query_as!(
+            Item,
+            "DELETE FROM items WHERE id = $1 RETURNING id, name, slug, owner_id, version",
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row)
     }
 }
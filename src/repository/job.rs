@@ -0,0 +1,467 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::model::{
+    error::{AppError, AppErrorCode},
+    job::{Job, JobStatus},
+};
+
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait JobRepository: Send + Sync {
+    fn add(&self, job: Job) -> impl Future<Output = Result<Job, AppError>> + Send;
+    fn get(&self, id: &str) -> impl Future<Output = Result<Job, AppError>> + Send;
+    /// Newest first, optionally only the jobs in `status`.
+    fn list(
+        &self,
+        status: Option<JobStatus>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Job>, AppError>> + Send;
+    /// Marks the oldest due job as running until `locked_until` and returns
+    /// it, `None` when nothing is due. Running jobs whose lease ran out, e.g.
+    /// because their worker died, are due again. Concurrent workers never
+    /// claim the same job.
+    fn claim(
+        &self,
+        locked_until: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<Job>, AppError>> + Send;
+    /// Extends the lease of a job still running.
+    fn heartbeat(
+        &self,
+        id: &str,
+        locked_until: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    fn complete(&self, id: &str) -> impl Future<Output = Result<(), AppError>> + Send;
+    /// Stores how far a running job got, in percent, along with its partial
+    /// result when given.
+    fn progress(
+        &self,
+        id: &str,
+        progress: i32,
+        result: Option<Value>,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    /// Records a failed attempt, the job runs again at `retry_at` unless it
+    /// has used up its attempts.
+    fn fail(
+        &self,
+        id: &str,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    /// Hands a running job back to the queue without counting the attempt,
+    /// for jobs interrupted by a shutdown.
+    fn release(&self, id: &str) -> impl Future<Output = Result<(), AppError>> + Send;
+    /// Requeues a failed or cancelled job with a fresh set of attempts,
+    /// `None` when the job is in any other state.
+    fn retry(&self, id: &str) -> impl Future<Output = Result<Option<Job>, AppError>> + Send;
+    /// Cancels a pending job, `None` when the job is in any other state.
+    fn cancel(&self, id: &str) -> impl Future<Output = Result<Option<Job>, AppError>> + Send;
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::NotFound,
+        message: format!("Job with id {} not found", id),
+        error_code: None,
+    }
+}
+
+fn lock_error(e: impl ToString) -> AppError {
+    AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: "Failed to lock jobs".to_string(),
+        error_code: None,
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryJobRepository {
+    pub jobs: Mutex<Vec<Job>>,
+    /// Lease of each running job, always locked after `jobs`.
+    pub leases: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl InMemoryJobRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl JobRepository for InMemoryJobRepository {
+    async fn add(&self, job: Job) -> Result<Job, AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        jobs.push(job.clone());
+        Ok(job)
+    }
+
+    async fn get(&self, id: &str) -> Result<Job, AppError> {
+        let jobs = self.jobs.lock().map_err(lock_error)?;
+        jobs.iter()
+            .find(|job| job.id == id)
+            .cloned()
+            .ok_or_else(|| not_found(id))
+    }
+
+    async fn list(&self, status: Option<JobStatus>, limit: i64) -> Result<Vec<Job>, AppError> {
+        let jobs = self.jobs.lock().map_err(lock_error)?;
+        let mut jobs: Vec<Job> = jobs
+            .iter()
+            .filter(|job| status.is_none_or(|status| job.status == status))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs.truncate(limit.max(0) as usize);
+        Ok(jobs)
+    }
+
+    async fn claim(&self, locked_until: DateTime<Utc>) -> Result<Option<Job>, AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        let mut leases = self.leases.lock().map_err(lock_error)?;
+        let now = Utc::now();
+        let job = jobs
+            .iter_mut()
+            .filter(|job| match job.status {
+                JobStatus::Pending => job.run_at <= now,
+                JobStatus::Running => leases.get(&job.id).is_some_and(|lease| *lease < now),
+                _ => false,
+            })
+            .min_by_key(|job| job.run_at);
+        Ok(job.map(|job| {
+            job.status = JobStatus::Running;
+            job.attempts += 1;
+            leases.insert(job.id.clone(), locked_until);
+            job.clone()
+        }))
+    }
+
+    async fn heartbeat(&self, id: &str, locked_until: DateTime<Utc>) -> Result<(), AppError> {
+        let jobs = self.jobs.lock().map_err(lock_error)?;
+        let mut leases = self.leases.lock().map_err(lock_error)?;
+        if jobs
+            .iter()
+            .any(|job| job.id == id && job.status == JobStatus::Running)
+        {
+            leases.insert(id.to_string(), locked_until);
+        }
+        Ok(())
+    }
+
+    async fn complete(&self, id: &str) -> Result<(), AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        let job = jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| not_found(id))?;
+        job.status = JobStatus::Done;
+        job.progress = 100;
+        self.leases.lock().map_err(lock_error)?.remove(id);
+        Ok(())
+    }
+
+    async fn progress(
+        &self,
+        id: &str,
+        progress: i32,
+        result: Option<Value>,
+    ) -> Result<(), AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        let job = jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| not_found(id))?;
+        job.progress = progress.clamp(0, 100);
+        if result.is_some() {
+            job.result = result;
+        }
+        Ok(())
+    }
+
+    async fn fail(&self, id: &str, error: &str, retry_at: DateTime<Utc>) -> Result<(), AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        let job = jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| not_found(id))?;
+        job.status = if job.attempts >= job.max_attempts {
+            JobStatus::Failed
+        } else {
+            JobStatus::Pending
+        };
+        job.last_error = Some(error.to_string());
+        job.run_at = retry_at;
+        self.leases.lock().map_err(lock_error)?.remove(id);
+        Ok(())
+    }
+
+    async fn release(&self, id: &str) -> Result<(), AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        if let Some(job) = jobs
+            .iter_mut()
+            .find(|job| job.id == id && job.status == JobStatus::Running)
+        {
+            job.status = JobStatus::Pending;
+            job.attempts = (job.attempts - 1).max(0);
+            job.run_at = Utc::now();
+            self.leases.lock().map_err(lock_error)?.remove(id);
+        }
+        Ok(())
+    }
+
+    async fn retry(&self, id: &str) -> Result<Option<Job>, AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        let job = jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .filter(|job| matches!(job.status, JobStatus::Failed | JobStatus::Cancelled));
+        Ok(job.map(|job| {
+            job.status = JobStatus::Pending;
+            job.attempts = 0;
+            job.run_at = Utc::now();
+            job.clone()
+        }))
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Option<Job>, AppError> {
+        let mut jobs = self.jobs.lock().map_err(lock_error)?;
+        let job = jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .filter(|job| job.status == JobStatus::Pending);
+        Ok(job.map(|job| {
+            job.status = JobStatus::Cancelled;
+            job.clone()
+        }))
+    }
+}
+
+struct JobRow {
+    id: String,
+    kind: String,
+    payload: Value,
+    status: String,
+    attempts: i32,
+    max_attempts: i32,
+    progress: i32,
+    result: Option<Value>,
+    last_error: Option<String>,
+    run_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<JobRow> for Job {
+    fn from(row: JobRow) -> Self {
+        Self {
+            id: row.id,
+            kind: row.kind,
+            payload: row.payload,
+            status: JobStatus::parse(&row.status).unwrap_or(JobStatus::Failed),
+            attempts: row.attempts,
+            max_attempts: row.max_attempts,
+            progress: row.progress,
+            result: row.result,
+            last_error: row.last_error,
+            run_at: row.run_at,
+            created_at: row.created_at,
+        }
+    }
+}
+
+pub struct PostgresJobRepository {
+    db: PgPool,
+}
+
+impl PostgresJobRepository {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+impl JobRepository for PostgresJobRepository {
+    async fn add(&self, job: Job) -> Result<Job, AppError> {
+        let row = sqlx::query_as!(
+            JobRow,
+            r#"
+                INSERT INTO jobs (id, kind, payload, status, attempts, max_attempts, run_at, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING id, kind, payload, status, attempts, max_attempts, progress, result, last_error, run_at, created_at
+            "#,
+            job.id,
+            job.kind,
+            job.payload,
+            job.status.as_str(),
+            job.attempts,
+            job.max_attempts,
+            job.run_at,
+            job.created_at,
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(row.into())
+    }
+
+    async fn get(&self, id: &str) -> Result<Job, AppError> {
+        let row = sqlx::query_as!(
+            JobRow,
+            r#"
+                SELECT id, kind, payload, status, attempts, max_attempts, progress, result, last_error, run_at, created_at
+                FROM jobs
+                WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        row.map(Job::from).ok_or_else(|| not_found(id))
+    }
+
+    async fn list(&self, status: Option<JobStatus>, limit: i64) -> Result<Vec<Job>, AppError> {
+        let rows = sqlx::query_as!(
+            JobRow,
+            r#"
+                SELECT id, kind, payload, status, attempts, max_attempts, progress, result, last_error, run_at, created_at
+                FROM jobs
+                WHERE $1::text IS NULL OR status = $1
+                ORDER BY created_at DESC
+                LIMIT $2
+            "#,
+            status.map(|status| status.as_str()),
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows.into_iter().map(Job::from).collect())
+    }
+
+    async fn claim(&self, locked_until: DateTime<Utc>) -> Result<Option<Job>, AppError> {
+        let row = sqlx::query_as!(
+            JobRow,
+            r#"
+                UPDATE jobs
+                SET status = 'running', attempts = attempts + 1, locked_until = $1, updated_at = now()
+                WHERE id = (
+                    SELECT id FROM jobs
+                    WHERE (status = 'pending' AND run_at <= now())
+                       OR (status = 'running' AND locked_until < now())
+                    ORDER BY run_at
+                    LIMIT 1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, kind, payload, status, attempts, max_attempts, progress, result, last_error, run_at, created_at
+            "#,
+            locked_until
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row.map(Job::from))
+    }
+
+    async fn heartbeat(&self, id: &str, locked_until: DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE jobs SET locked_until = $2 WHERE id = $1 AND status = 'running'",
+            id,
+            locked_until
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn complete(&self, id: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE jobs SET status = 'done', progress = 100, locked_until = NULL, updated_at = now() WHERE id = $1",
+            id
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn progress(
+        &self,
+        id: &str,
+        progress: i32,
+        result: Option<Value>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                UPDATE jobs
+                SET progress = $2, result = COALESCE($3, result), updated_at = now()
+                WHERE id = $1
+            "#,
+            id,
+            progress.clamp(0, 100),
+            result
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn fail(&self, id: &str, error: &str, retry_at: DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                UPDATE jobs
+                SET status = CASE WHEN attempts >= max_attempts THEN 'failed' ELSE 'pending' END,
+                    last_error = $2,
+                    run_at = $3,
+                    locked_until = NULL,
+                    updated_at = now()
+                WHERE id = $1
+            "#,
+            id,
+            error,
+            retry_at
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn release(&self, id: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                UPDATE jobs
+                SET status = 'pending', attempts = GREATEST(attempts - 1, 0), run_at = now(),
+                    locked_until = NULL, updated_at = now()
+                WHERE id = $1 AND status = 'running'
+            "#,
+            id
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn retry(&self, id: &str) -> Result<Option<Job>, AppError> {
+        let row = sqlx::query_as!(
+            JobRow,
+            r#"
+                UPDATE jobs
+                SET status = 'pending', attempts = 0, run_at = now(), updated_at = now()
+                WHERE id = $1 AND status IN ('failed', 'cancelled')
+                RETURNING id, kind, payload, status, attempts, max_attempts, progress, result, last_error, run_at, created_at
+            "#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row.map(Job::from))
+    }
+
+    async fn cancel(&self, id: &str) -> Result<Option<Job>, AppError> {
+        let row = sqlx::query_as!(
+            JobRow,
+            r#"
+                UPDATE jobs
+                SET status = 'cancelled', updated_at = now()
+                WHERE id = $1 AND status = 'pending'
+                RETURNING id, kind, payload, status, attempts, max_attempts, progress, result, last_error, run_at, created_at
+            "#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row.map(Job::from))
+    }
+}
@@ -1,5 +1,10 @@
+pub mod attachment;
+pub mod change;
+pub mod erased;
 pub mod item;
+pub mod job;
 pub mod registry;
 pub mod user;
 
-pub use registry::{PostgresRepository, Repository};
+pub use erased::{DynRepository, ErasedRepository};
+pub use registry::{InMemoryRepository, PostgresRepository, Repository};
@@ -3,29 +3,70 @@ use std::sync::Arc;
 use sqlx::PgPool;
 
 use super::{
-    item::{ItemRepository, PostgresItemRepository},
-    user::{PostgresUserRepository, UserRepository},
+    attachment::{
+        AttachmentRepository, InMemoryAttachmentRepository, PostgresAttachmentRepository,
+    },
+    change::{ChangeRepository, InMemoryChangeRepository, PostgresChangeRepository},
+    item::{InMemoryItemRepository, ItemRepository, PostgresItemRepository},
+    job::{InMemoryJobRepository, JobRepository, PostgresJobRepository},
+    user::{InMemoryUserRepository, PostgresUserRepository, UserRepository},
 };
 
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock(
+    type Items = dyn super::erased::DynItemRepository;
+    type Users = dyn super::erased::DynUserRepository;
+    type Jobs = dyn super::erased::DynJobRepository;
+    type Attachments = dyn super::erased::DynAttachmentRepository;
+    type Changes = dyn super::erased::DynChangeRepository;
+))]
 pub trait Repository: Send + Sync {
-    fn item(&self) -> Arc<dyn ItemRepository>;
-    fn user(&self) -> Arc<dyn UserRepository>;
+    type Items: ItemRepository + ?Sized + 'static;
+    type Users: UserRepository + ?Sized + 'static;
+    type Jobs: JobRepository + ?Sized + 'static;
+    type Attachments: AttachmentRepository + ?Sized + 'static;
+    type Changes: ChangeRepository + ?Sized + 'static;
+
+    fn item(&self) -> Arc<Self::Items>;
+    fn user(&self) -> Arc<Self::Users>;
+    fn job(&self) -> Arc<Self::Jobs>;
+    fn attachment(&self) -> Arc<Self::Attachments>;
+    fn change(&self) -> Arc<Self::Changes>;
 }
 
 pub struct PostgresRepository {
     pub item: Arc<PostgresItemRepository>,
     pub user: Arc<PostgresUserRepository>,
+    pub job: Arc<PostgresJobRepository>,
+    pub attachment: Arc<PostgresAttachmentRepository>,
+    pub change: Arc<PostgresChangeRepository>,
 }
 
-#[cfg_attr(test, mockall::automock)]
 impl Repository for PostgresRepository {
-    fn item(&self) -> Arc<dyn ItemRepository> {
+    type Items = PostgresItemRepository;
+    type Users = PostgresUserRepository;
+    type Jobs = PostgresJobRepository;
+    type Attachments = PostgresAttachmentRepository;
+    type Changes = PostgresChangeRepository;
+
+    fn item(&self) -> Arc<Self::Items> {
         self.item.clone()
     }
 
-    fn user(&self) -> Arc<dyn UserRepository> {
+    fn user(&self) -> Arc<Self::Users> {
         self.user.clone()
     }
+
+    fn job(&self) -> Arc<Self::Jobs> {
+        self.job.clone()
+    }
+
+    fn attachment(&self) -> Arc<Self::Attachments> {
+        self.attachment.clone()
+    }
+
+    fn change(&self) -> Arc<Self::Changes> {
+        self.change.clone()
+    }
 }
 
 impl PostgresRepository {
@@ -33,6 +74,68 @@ impl PostgresRepository {
         Self {
             item: Arc::new(PostgresItemRepository::new(db.clone())),
             user: Arc::new(PostgresUserRepository::new(db.clone())),
+            job: Arc::new(PostgresJobRepository::new(db.clone())),
+            attachment: Arc::new(PostgresAttachmentRepository::new(db.clone())),
+            change: Arc::new(PostgresChangeRepository::new(db.clone())),
         }
     }
 }
+
+/// Process-local repository, used by tests and demos that shouldn't need Postgres.
+pub struct InMemoryRepository {
+    pub item: Arc<InMemoryItemRepository>,
+    pub user: Arc<InMemoryUserRepository>,
+    pub job: Arc<InMemoryJobRepository>,
+    pub attachment: Arc<InMemoryAttachmentRepository>,
+    pub change: Arc<InMemoryChangeRepository>,
+}
+
+impl Default for InMemoryRepository {
+    fn default() -> Self {
+        let change = Arc::new(InMemoryChangeRepository::default());
+        let user = Arc::new(InMemoryUserRepository::default().with_changes(change.clone()));
+        Self {
+            item: Arc::new(
+                InMemoryItemRepository::with_users(user.clone()).with_changes(change.clone()),
+            ),
+            user,
+            job: Arc::default(),
+            attachment: Arc::default(),
+            change,
+        }
+    }
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Repository for InMemoryRepository {
+    type Items = InMemoryItemRepository;
+    type Users = InMemoryUserRepository;
+    type Jobs = InMemoryJobRepository;
+    type Attachments = InMemoryAttachmentRepository;
+    type Changes = InMemoryChangeRepository;
+
+    fn item(&self) -> Arc<Self::Items> {
+        self.item.clone()
+    }
+
+    fn user(&self) -> Arc<Self::Users> {
+        self.user.clone()
+    }
+
+    fn job(&self) -> Arc<Self::Jobs> {
+        self.job.clone()
+    }
+
+    fn attachment(&self) -> Arc<Self::Attachments> {
+        self.attachment.clone()
+    }
+
+    fn change(&self) -> Arc<Self::Changes> {
+        self.change.clone()
+    }
+}
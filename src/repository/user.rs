@@ -1,19 +1,451 @@
-use async_trait::async_trait;
 use sqlx::PgPool;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
+use super::change::InMemoryChangeRepository;
 use crate::model::{
-    error::{AppError, AppErrorCode},
-    user::User,
+    error::{AppError, AppErrorCode, ErrorCode},
+    event::{Event, EventAction},
+    sort::{SortOrder, UserSort, UserSortColumn},
+    user::{PendingEmail, User},
 };
 
-#[async_trait]
-#[cfg_attr(test, mockall::automock)]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
 pub trait UserRepository: Send + Sync {
-    async fn add(&self, user: User) -> Result<User, AppError>;
-    async fn list(&self) -> Result<Vec<User>, AppError>;
-    async fn get(&self, id: &str) -> Result<User, AppError>;
-    async fn update(&self, id: &str, name: String) -> Result<User, AppError>;
-    async fn delete(&self, id: &str) -> Result<(), AppError>;
+    fn add(&self, user: User) -> impl Future<Output = Result<User, AppError>> + Send;
+    fn list(&self, sort: UserSort) -> impl Future<Output = Result<Vec<User>, AppError>> + Send;
+    fn get(&self, id: &str) -> impl Future<Output = Result<User, AppError>> + Send;
+    /// The users among `ids` in one lookup, in no particular order. Unknown
+    /// ids are skipped.
+    fn get_many(&self, ids: &[String]) -> impl Future<Output = Result<Vec<User>, AppError>> + Send;
+    /// Whether user `id` is stored, without loading it.
+    fn exists(&self, id: &str) -> impl Future<Output = Result<bool, AppError>> + Send;
+    /// Matches `email` case-insensitively, as the unique index does.
+    fn get_by_email(&self, email: &str) -> impl Future<Output = Result<User, AppError>> + Send;
+    /// Up to `limit` users in `(email, id)` order, starting after the given
+    /// email and id.
+    fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<User>, AppError>> + Send;
+    /// Up to `limit` users in descending `(email, id)` order, starting before
+    /// the given email and id, or at the last user without.
+    fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<User>, AppError>> + Send;
+    /// Users whose email has a trigram similarity of at least `threshold`
+    /// to `query`, as `pg_trgm` measures it, most similar first.
+    fn search(
+        &self,
+        query: &str,
+        threshold: f64,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<User>, AppError>> + Send;
+    /// Changes the email. With `version`, only while that is still the stored
+    /// version, failing with [`version_mismatch`] otherwise.
+    fn update(
+        &self,
+        id: &str,
+        email: String,
+        version: Option<i64>,
+    ) -> impl Future<Output = Result<User, AppError>> + Send;
+    /// The deleted user, `None` when there was none to delete.
+    fn delete(&self, id: &str) -> impl Future<Output = Result<Option<User>, AppError>> + Send;
+    /// Stores `pending` in place of any earlier unconfirmed change.
+    fn set_pending_email(
+        &self,
+        id: &str,
+        pending: PendingEmail,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    fn get_pending_email(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<Option<PendingEmail>, AppError>> + Send;
+    /// Swaps in the pending address and forgets the change.
+    fn confirm_pending_email(
+        &self,
+        id: &str,
+    ) -> impl Future<Output = Result<User, AppError>> + Send;
+}
+
+pub fn user_not_found(id: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::NotFound,
+        message: format!("User with id {} not found", id),
+        error_code: Some(ErrorCode::UserNotFound),
+    }
+}
+
+/// `update` fails with it when the user changed since `version` was read.
+pub fn version_mismatch(id: &str, version: i64) -> AppError {
+    AppError {
+        code: AppErrorCode::PreconditionFailed,
+        message: format!("User {} is no longer at version {}", id, version),
+        error_code: None,
+    }
+}
+
+fn email_not_found(email: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::NotFound,
+        message: format!("User with email {} not found", email),
+        error_code: Some(ErrorCode::UserNotFound),
+    }
+}
+
+/// Trigrams of the lowercased words of `text`, each padded with two spaces
+/// in front and one behind, as `pg_trgm` builds them.
+fn trigrams(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded: Vec<char> = format!("  {} ", word).chars().collect();
+            padded
+                .windows(3)
+                .map(|w| w.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Shared trigrams over all trigrams of both, `pg_trgm`'s `similarity`.
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let shared = a.intersection(&b).count();
+    let all = a.len() + b.len() - shared;
+    if all == 0 {
+        0.0
+    } else {
+        shared as f64 / all as f64
+    }
+}
+
+pub struct InMemoryUserRepository {
+    pub users: Mutex<Vec<User>>,
+    pub pending: Mutex<HashMap<String, PendingEmail>>,
+    /// Change history every write is recorded in, when set.
+    changes: Option<Arc<InMemoryChangeRepository>>,
+}
+
+impl Default for InMemoryUserRepository {
+    fn default() -> Self {
+        Self {
+            users: Mutex::new(Vec::new()),
+            pending: Mutex::new(HashMap::new()),
+            changes: None,
+        }
+    }
+}
+
+impl InMemoryUserRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records every write in `changes`, as the triggers do in Postgres.
+    pub fn with_changes(mut self, changes: Arc<InMemoryChangeRepository>) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    /// Records the write of user `id`, called under the users lock so
+    /// history follows the order of the writes.
+    fn record(&self, action: EventAction, id: &str, user: Option<&User>) -> Result<(), AppError> {
+        match &self.changes {
+            Some(changes) => changes
+                .record(&Event::new("user", action, id, user))
+                .map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl UserRepository for InMemoryUserRepository {
+    async fn add(&self, new_user: User) -> Result<User, AppError> {
+        match self.users.lock() {
+            Ok(mut users) => match users
+                .iter()
+                .find(|user| user.email.to_lowercase() == new_user.email.to_lowercase())
+            {
+                Some(user) => Ok(user.clone()),
+                None => {
+                    users.push(new_user.clone());
+                    self.record(EventAction::Created, &new_user.id, Some(&new_user))?;
+                    Ok(new_user)
+                }
+            },
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn list(&self, sort: UserSort) -> Result<Vec<User>, AppError> {
+        match self.users.lock() {
+            Ok(users) => {
+                let mut users = users.clone();
+                let key = |user: &User| match sort.column {
+                    UserSortColumn::Email => user.email.clone(),
+                    UserSortColumn::Id => user.id.clone(),
+                };
+                users.sort_by(|a, b| (key(a), &a.id).cmp(&(key(b), &b.id)));
+                if sort.order == SortOrder::Desc {
+                    users.reverse();
+                }
+                Ok(users)
+            }
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<User, AppError> {
+        match self.users.lock() {
+            Ok(users) => match users.iter().find(|user| user.id == id) {
+                Some(user) => Ok(user.clone()),
+                None => Err(AppError {
+                    code: AppErrorCode::NotFound,
+                    message: format!("User with id {} not found", id),
+                    error_code: Some(ErrorCode::UserNotFound),
+                }),
+            },
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn get_many(&self, ids: &[String]) -> Result<Vec<User>, AppError> {
+        match self.users.lock() {
+            Ok(users) => Ok(users
+                .iter()
+                .filter(|user| ids.contains(&user.id))
+                .cloned()
+                .collect()),
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        match self.users.lock() {
+            Ok(users) => Ok(users.iter().any(|user| user.id == id)),
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<User, AppError> {
+        match self.users.lock() {
+            Ok(users) => users
+                .iter()
+                .find(|user| user.email.to_lowercase() == email.to_lowercase())
+                .cloned()
+                .ok_or_else(|| email_not_found(email)),
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        match self.users.lock() {
+            Ok(users) => {
+                let mut users: Vec<User> = users
+                    .iter()
+                    .filter(|user| {
+                        after
+                            .as_ref()
+                            .is_none_or(|(email, id)| (&user.email, &user.id) > (email, id))
+                    })
+                    .cloned()
+                    .collect();
+                users.sort_by(|a, b| (&a.email, &a.id).cmp(&(&b.email, &b.id)));
+                users.truncate(limit.max(0) as usize);
+                Ok(users)
+            }
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        match self.users.lock() {
+            Ok(users) => {
+                let mut users: Vec<User> = users
+                    .iter()
+                    .filter(|user| {
+                        before
+                            .as_ref()
+                            .is_none_or(|(email, id)| (&user.email, &user.id) < (email, id))
+                    })
+                    .cloned()
+                    .collect();
+                users.sort_by(|a, b| (&b.email, &b.id).cmp(&(&a.email, &a.id)));
+                users.truncate(limit.max(0) as usize);
+                Ok(users)
+            }
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn search(&self, query: &str, threshold: f64, limit: i64) -> Result<Vec<User>, AppError> {
+        let users = self.users.lock().map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to lock users".to_string(),
+            error_code: None,
+        })?;
+        let mut found: Vec<(f64, User)> = users
+            .iter()
+            .map(|user| (similarity(&user.email, query), user.clone()))
+            .filter(|(score, _)| *score >= threshold)
+            .collect();
+        found.sort_by(|(a, x), (b, y)| b.total_cmp(a).then_with(|| x.email.cmp(&y.email)));
+        found.truncate(limit.max(0) as usize);
+        Ok(found.into_iter().map(|(_, user)| user).collect())
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        email: String,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        match self.users.lock() {
+            Ok(mut users) => {
+                if users
+                    .iter()
+                    .any(|user| user.email.to_lowercase() == email.to_lowercase() && user.id != id)
+                {
+                    return Err(AppError {
+                        code: AppErrorCode::Conflict,
+                        message: format!("Email {} is already taken", email),
+                        error_code: Some(ErrorCode::EmailTaken),
+                    });
+                }
+                match users.iter_mut().find(|user| user.id == id) {
+                    Some(user) => {
+                        if let Some(version) = version.filter(|version| *version != user.version) {
+                            return Err(version_mismatch(id, version));
+                        }
+                        user.email = email;
+                        user.version += 1;
+                        let user = user.clone();
+                        self.record(EventAction::Updated, id, Some(&user))?;
+                        Ok(user)
+                    }
+                    None => Err(AppError {
+                        code: AppErrorCode::NotFound,
+                        message: format!("User with id {} not found", id),
+                        error_code: Some(ErrorCode::UserNotFound),
+                    }),
+                }
+            }
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn delete(&self, id: &str) -> Result<Option<User>, AppError> {
+        match self.users.lock() {
+            Ok(mut users) => {
+                let deleted = users
+                    .iter()
+                    .position(|user| user.id == id)
+                    .map(|index| users.remove(index));
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(id);
+                }
+                if deleted.is_some() {
+                    self.record(EventAction::Deleted, id, None)?;
+                }
+                Ok(deleted)
+            }
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn set_pending_email(&self, id: &str, change: PendingEmail) -> Result<(), AppError> {
+        self.get(id).await?;
+        match self.pending.lock() {
+            Ok(mut pending) => {
+                pending.insert(id.to_string(), change);
+                Ok(())
+            }
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn get_pending_email(&self, id: &str) -> Result<Option<PendingEmail>, AppError> {
+        self.get(id).await?;
+        match self.pending.lock() {
+            Ok(pending) => Ok(pending.get(id).cloned()),
+            Err(e) => Err(AppError {
+                code: AppErrorCode::InternalError(e.to_string()),
+                message: "Failed to lock users".to_string(),
+                error_code: None,
+            }),
+        }
+    }
+
+    async fn confirm_pending_email(&self, id: &str) -> Result<User, AppError> {
+        let change = self
+            .get_pending_email(id)
+            .await?
+            .ok_or_else(|| user_not_found(id))?;
+        let user = self.update(id, change.email, None).await?;
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(id);
+        }
+        Ok(user)
+    }
 }
 
 pub struct PostgresUserRepository {
@@ -26,7 +458,6 @@ impl PostgresUserRepository {
     }
 }
 
-#[async_trait]
 impl UserRepository for PostgresUserRepository {
     async fn add(&self, user: User) -> Result<User, AppError> {
         let row = sqlx::query_as!(
@@ -34,84 +465,280 @@ impl UserRepository for PostgresUserRepository {
             r#"
                 INSERT INTO users (id, email)
                 VALUES ($1, $2)
-                ON CONFLICT (email) DO UPDATE SET email = EXCLUDED.email
-                RETURNING id, email
+                ON CONFLICT ((LOWER(email))) DO UPDATE SET email = users.email
+                RETURNING id, email, version
             "#,
             user.id,
             user.email,
         )
         .fetch_one(&self.db)
-        .await
-        .map_err(|e| AppError {
-            code: AppErrorCode::InternalError(e.to_string()),
-            message: "Failed to upsert user".to_string(),
-        })?;
+        .await?;
         Ok(row)
     }
 
-    async fn list(&self) -> Result<Vec<User>, AppError> {
-        let rows = sqlx::query_as!(User, r#"SELECT id, email FROM users ORDER BY email ASC"#)
+    async fn list(&self, sort: UserSort) -> Result<Vec<User>, AppError> {
+        let sql = format!("SELECT id, email, version FROM users {}", sort.to_sql());
+        let rows = sqlx::query_as::<_, (String, String, i64)>(&sql)
             .fetch_all(&self.db)
-            .await
-            .map_err(|e| AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to fetch users".to_string(),
-            })?;
-        Ok(rows)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(id, email, version)| User { id, email, version })
+            .collect())
     }
 
     async fn get(&self, id: &str) -> Result<User, AppError> {
-        let row = sqlx::query_as!(User, r#"SELECT id, email FROM users WHERE id = $1"#, id)
-            .fetch_optional(&self.db)
-            .await
-            .map_err(|e| AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to fetch user".to_string(),
-            })?;
+        let row = sqlx::query_as!(
+            User,
+            r#"SELECT id, email, version FROM users WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
         match row {
             Some(row) => Ok(row),
             None => Err(AppError {
                 code: AppErrorCode::NotFound,
                 message: format!("User with id {} not found", id),
+                error_code: Some(ErrorCode::UserNotFound),
             }),
         }
     }
 
-    async fn update(&self, id: &str, email: String) -> Result<User, AppError> {
+    async fn get_many(&self, ids: &[String]) -> Result<Vec<User>, AppError> {
+        let rows = sqlx::query_as!(
+            User,
+            r#"SELECT id, email, version FROM users WHERE id = ANY($1)"#,
+            ids
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool, AppError> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE id = $1) AS "exists!""#,
+            id
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(exists)
+    }
+
+    async fn get_by_email(&self, email: &str) -> Result<User, AppError> {
+        let row = sqlx::query_as!(
+            User,
+            r#"SELECT id, email, version FROM users WHERE LOWER(email) = LOWER($1)"#,
+            email
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        row.ok_or_else(|| email_not_found(email))
+    }
+
+    async fn list_after(
+        &self,
+        after: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        let (email, id) = after.unzip();
+        let rows = sqlx::query_as!(
+            User,
+            r#"
+                SELECT id, email, version FROM users
+                WHERE $1::text IS NULL OR (email, id) > ($1, $2)
+                ORDER BY email, id
+                LIMIT $3
+            "#,
+            email,
+            id,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn list_before(
+        &self,
+        before: Option<(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        let (email, id) = before.unzip();
+        let rows = sqlx::query_as!(
+            User,
+            r#"
+                SELECT id, email, version FROM users
+                WHERE $1::text IS NULL OR (email, id) < ($1, $2)
+                ORDER BY email DESC, id DESC
+                LIMIT $3
+            "#,
+            email,
+            id,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn search(&self, query: &str, threshold: f64, limit: i64) -> Result<Vec<User>, AppError> {
+        // `%` is served by the trigram index but reads its threshold from a
+        // setting, scoped to this transaction.
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            "SELECT set_config('pg_trgm.similarity_threshold', $1, true)",
+            threshold.to_string()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let rows = sqlx::query_as!(
+            User,
+            r#"
+                SELECT id, email, version FROM users
+                WHERE email % $1
+                ORDER BY similarity(email, $1) DESC, email
+                LIMIT $2
+            "#,
+            query,
+            limit
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    async fn update(
+        &self,
+        id: &str,
+        email: String,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
         let row = sqlx::query_as!(
             User,
             r#"
                 UPDATE users
-                SET email = $2
-                WHERE id = $1
-                RETURNING id, email
+                SET email = $2, version = version + 1
+                WHERE id = $1 AND ($3::bigint IS NULL OR version = $3)
+                RETURNING id, email, version
             "#,
             id,
-            email
+            email,
+            version
         )
         .fetch_optional(&self.db)
         .await
-        .map_err(|e| AppError {
-            code: AppErrorCode::InternalError(e.to_string()),
-            message: "Failed to update user".to_string(),
+        .map_err(|e| match AppError::from(e) {
+            AppError {
+                code: AppErrorCode::Conflict,
+                ..
+            } => AppError {
+                code: AppErrorCode::Conflict,
+                message: format!("Email {} is already taken", email),
+                error_code: Some(ErrorCode::EmailTaken),
+            },
+            e => e,
         })?;
-        match row {
-            Some(row) => Ok(row),
-            None => Err(AppError {
+        match (row, version) {
+            (Some(row), _) => Ok(row),
+            (None, Some(version)) if self.exists(id).await? => Err(version_mismatch(id, version)),
+            (None, _) => Err(AppError {
                 code: AppErrorCode::NotFound,
                 message: format!("User with id {} not found", id),
+                error_code: Some(ErrorCode::UserNotFound),
             }),
         }
     }
 
-    async fn delete(&self, id: &str) -> Result<(), AppError> {
-        sqlx::query!(r#"DELETE FROM users WHERE id = $1"#, id)
-            .execute(&self.db)
-            .await
-            .map_err(|e| AppError {
-                code: AppErrorCode::InternalError(e.to_string()),
-                message: "Failed to delete user".to_string(),
-            })?;
+    async fn delete(&self, id: &str) -> Result<Option<User>, AppError> {
+        let row = sqlx::query_as!(
+            User,
+            r#"DELETE FROM users WHERE id = $1 RETURNING id, email, version"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row)
+    }
+
+    async fn set_pending_email(&self, id: &str, pending: PendingEmail) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET pending_email = $2, email_token_hash = $3, email_token_expires_at = $4
+                WHERE id = $1
+            "#,
+            id,
+            pending.email,
+            pending.token_hash,
+            pending.expires_at,
+        )
+        .execute(&self.db)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(user_not_found(id));
+        }
         Ok(())
     }
+
+    async fn get_pending_email(&self, id: &str) -> Result<Option<PendingEmail>, AppError> {
+        let row = sqlx::query!(
+            r#"
+                SELECT pending_email, email_token_hash, email_token_expires_at
+                FROM users
+                WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| user_not_found(id))?;
+        Ok(
+            match (
+                row.pending_email,
+                row.email_token_hash,
+                row.email_token_expires_at,
+            ) {
+                (Some(email), Some(token_hash), Some(expires_at)) => Some(PendingEmail {
+                    email,
+                    token_hash,
+                    expires_at,
+                }),
+                _ => None,
+            },
+        )
+    }
+
+    async fn confirm_pending_email(&self, id: &str) -> Result<User, AppError> {
+        let row = sqlx::query_as!(
+            User,
+            r#"
+                UPDATE users
+                SET email = pending_email,
+                    version = version + 1,
+                    pending_email = NULL,
+                    email_token_hash = NULL,
+                    email_token_expires_at = NULL
+                WHERE id = $1 AND pending_email IS NOT NULL
+                RETURNING id, email, version
+            "#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| match AppError::from(e) {
+            AppError {
+                code: AppErrorCode::Conflict,
+                ..
+            } => AppError {
+                code: AppErrorCode::Conflict,
+                message: "Email is already taken".to_string(),
+                error_code: Some(ErrorCode::EmailTaken),
+            },
+            e => e,
+        })?;
+        row.ok_or_else(|| user_not_found(id))
+    }
 }
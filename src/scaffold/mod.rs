@@ -0,0 +1,437 @@
+//! `generate entity` scaffolding: renders a new resource following the
+//! items/users layout, from model and migration down to the handler module.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::model::error::{AppError, AppErrorCode};
+
+const MODEL: &str = include_str!("templates/model.rs.tmpl");
+const MIGRATION: &str = include_str!("templates/migration.sql.tmpl");
+const REPOSITORY: &str = include_str!("templates/repository.rs.tmpl");
+const SERVICE: &str = include_str!("templates/service.rs.tmpl");
+const HANDLER: &str = include_str!("templates/handler.rs.tmpl");
+const WIRING: &str = include_str!("templates/wiring.md.tmpl");
+
+const RESERVED: &[&str] = &[
+    "id", "as", "async", "await", "crate", "enum", "fn", "impl", "let", "match", "mod", "move",
+    "ref", "self", "struct", "super", "trait", "type", "use", "where",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Text,
+    Int,
+    Float,
+    Bool,
+}
+
+impl FieldType {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "string" => Some(Self::String),
+            "text" => Some(Self::Text),
+            "int" => Some(Self::Int),
+            "float" => Some(Self::Float),
+            "bool" => Some(Self::Bool),
+            _ => None,
+        }
+    }
+
+    fn rust(&self) -> &'static str {
+        match self {
+            Self::String | Self::Text => "String",
+            Self::Int => "i64",
+            Self::Float => "f64",
+            Self::Bool => "bool",
+        }
+    }
+
+    fn sql(&self) -> &'static str {
+        match self {
+            Self::String => "VARCHAR(255) NOT NULL",
+            Self::Text => "TEXT NOT NULL",
+            Self::Int => "BIGINT NOT NULL",
+            Self::Float => "DOUBLE PRECISION NOT NULL",
+            Self::Bool => "BOOLEAN NOT NULL",
+        }
+    }
+
+    fn validation(&self) -> Option<&'static str> {
+        match self {
+            Self::String => Some("#[validate(length(min = 1, max = 255))]"),
+            Self::Text => Some("#[validate(length(min = 1))]"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: FieldType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entity {
+    /// Singular snake_case name, e.g. `order_line`.
+    pub name: String,
+    pub plural: String,
+    pub fields: Vec<Field>,
+}
+
+fn invalid(message: String) -> AppError {
+    AppError {
+        code: AppErrorCode::InvalidInput,
+        message,
+        error_code: None,
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase())
+        && s.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !RESERVED.contains(&s)
+}
+
+fn pluralize(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix('y')
+        && !stem.ends_with(['a', 'e', 'i', 'o', 'u'])
+    {
+        return format!("{}ies", stem);
+    }
+    if name.ends_with(['s', 'x', 'z']) || name.ends_with("ch") || name.ends_with("sh") {
+        return format!("{}es", name);
+    }
+    format!("{}s", name)
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+impl Entity {
+    /// Parses `name` and `field:type` specs, types are string, text, int, float and bool.
+    pub fn parse(name: &str, plural: Option<&str>, fields: &[String]) -> Result<Self, AppError> {
+        if !is_identifier(name) {
+            return Err(invalid(format!(
+                "Entity name '{}' must be snake_case, e.g. order_line",
+                name
+            )));
+        }
+        let plural = plural.map(String::from).unwrap_or_else(|| pluralize(name));
+        if !is_identifier(&plural) || plural == name {
+            return Err(invalid(format!("Invalid plural '{}'", plural)));
+        }
+        if fields.is_empty() {
+            return Err(invalid("At least one field is required".to_string()));
+        }
+
+        let mut parsed: Vec<Field> = Vec::new();
+        for spec in fields {
+            let (field, ty) = spec.split_once(':').unwrap_or((spec, "string"));
+            let field = field.trim();
+            if !is_identifier(field) {
+                return Err(invalid(format!("Invalid field name '{}'", field)));
+            }
+            if parsed.iter().any(|f| f.name == field) {
+                return Err(invalid(format!("Duplicate field '{}'", field)));
+            }
+            let ty = FieldType::parse(ty.trim()).ok_or_else(|| {
+                invalid(format!(
+                    "Unknown type '{}' for field '{}', expected string, text, int, float or bool",
+                    ty, field
+                ))
+            })?;
+            parsed.push(Field {
+                name: field.to_string(),
+                ty,
+            });
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            plural,
+            fields: parsed,
+        })
+    }
+
+    fn render(&self, template: &str) -> String {
+        let columns: Vec<&str> = self.fields.iter().map(|f| f.name.as_str()).collect();
+        let struct_fields: String = self
+            .fields
+            .iter()
+            .map(|f| format!("    pub {}: {},\n", f.name, f.ty.rust()))
+            .collect();
+        let payload_fields: String = self
+            .fields
+            .iter()
+            .map(|f| match f.ty.validation() {
+                Some(rule) => format!("    {}\n    pub {}: {},\n", rule, f.name, f.ty.rust()),
+                None => format!("    pub {}: {},\n", f.name, f.ty.rust()),
+            })
+            .collect();
+        let sql_columns = self
+            .fields
+            .iter()
+            .map(|f| format!("    {} {}", f.name, f.ty.sql()))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let placeholders = (0..columns.len())
+            .map(|i| format!("${}", i + 2))
+            .collect::<Vec<_>>();
+        let assignments = columns
+            .iter()
+            .zip(&placeholders)
+            .map(|(c, p)| format!("{} = {}", c, p))
+            .collect::<Vec<_>>();
+        let entity_args: String = columns
+            .iter()
+            .map(|c| format!("            {}.{},\n", self.name, c))
+            .collect();
+        let payload_moves: String = columns
+            .iter()
+            .map(|c| format!("            {}: payload.{},\n", c, c))
+            .collect();
+        let title = self.name.replace('_', " ");
+        let title_case = {
+            let mut chars = title.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        };
+
+        template
+            .replace("{{struct_fields}}", &struct_fields)
+            .replace("{{payload_fields}}", &payload_fields)
+            .replace("{{payload_moves}}", &payload_moves)
+            .replace("{{sql_columns}}", &sql_columns)
+            .replace("{{column_list}}", &columns.join(", "))
+            .replace("{{insert_placeholders}}", &placeholders.join(", "))
+            .replace("{{update_assignments}}", &assignments.join(", "))
+            .replace("{{entity_args}}", &entity_args)
+            .replace("{{ENTITIES}}", &self.plural.to_uppercase())
+            .replace("{{Entities}}", &pascal_case(&self.plural))
+            .replace("{{Entity}}", &pascal_case(&self.name))
+            .replace("{{entities}}", &self.plural)
+            .replace("{{entity}}", &self.name)
+            .replace("{{Title}}", &title_case)
+            .replace("{{title}}", &title)
+    }
+
+    /// Files to create, relative to the crate root.
+    pub fn files(&self, timestamp: &str) -> Vec<(PathBuf, String)> {
+        let name = &self.name;
+        vec![
+            (
+                PathBuf::from(format!("src/model/{}.rs", name)),
+                self.render(MODEL),
+            ),
+            (
+                PathBuf::from(format!(
+                    "migrations/{}_{}_table.sql",
+                    timestamp, self.plural
+                )),
+                self.render(MIGRATION),
+            ),
+            (
+                PathBuf::from(format!("src/repository/{}.rs", name)),
+                self.render(REPOSITORY),
+            ),
+            (
+                PathBuf::from(format!("src/service/{}.rs", name)),
+                self.render(SERVICE),
+            ),
+            (
+                PathBuf::from(format!("src/handler/{}.rs", name)),
+                self.render(HANDLER),
+            ),
+        ]
+    }
+
+    /// Remaining manual steps, printed after generation.
+    pub fn wiring(&self) -> String {
+        self.render(WIRING)
+    }
+}
+
+/// Adds `pub mod <name>;` to a module file, keeping the declarations sorted.
+fn declare_module(path: &Path, name: &str) -> Result<(), AppError> {
+    let source = read(path)?;
+    let declaration = format!("pub mod {};", name);
+    if source.lines().any(|l| l.trim() == declaration) {
+        return Ok(());
+    }
+    let mut lines: Vec<String> = source.lines().map(String::from).collect();
+    let position = lines
+        .iter()
+        .position(|l| l.starts_with("pub mod ") && l.as_str() > declaration.as_str())
+        .or_else(|| {
+            lines
+                .iter()
+                .rposition(|l| l.starts_with("pub mod "))
+                .map(|i| i + 1)
+        })
+        .unwrap_or(0);
+    lines.insert(position, declaration);
+    write(path, &(lines.join("\n") + "\n"))
+}
+
+/// Adds the `<ENTITIES>_PATH` constant next to the existing route paths.
+fn declare_path(path: &Path, entity: &Entity) -> Result<(), AppError> {
+    let source = read(path)?;
+    let name = format!("{}_PATH", entity.plural.to_uppercase());
+    if source.contains(&format!("pub const {}:", name)) {
+        return Ok(());
+    }
+    let constant = format!("pub const {}: &str = \"/{}\";", name, entity.plural);
+    let mut lines: Vec<String> = source.lines().map(String::from).collect();
+    let position = lines
+        .iter()
+        .rposition(|l| l.starts_with("pub const ") && l.contains("_PATH"))
+        .map(|i| i + 1)
+        .unwrap_or(lines.len());
+    lines.insert(position, constant);
+    write(path, &(lines.join("\n") + "\n"))
+}
+
+fn read(path: &Path) -> Result<String, AppError> {
+    fs::read_to_string(path).map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: format!("Failed to read {}", path.display()),
+        error_code: None,
+    })
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), AppError> {
+    fs::write(path, contents).map_err(|e| AppError {
+        code: AppErrorCode::InternalError(e.to_string()),
+        message: format!("Failed to write {}", path.display()),
+        error_code: None,
+    })
+}
+
+/// Writes the entity's files under `root` and declares its modules, refusing
+/// to overwrite anything that already exists.
+pub fn generate(root: &Path, entity: &Entity, timestamp: &str) -> Result<Vec<PathBuf>, AppError> {
+    let files = entity.files(timestamp);
+    if let Some((path, _)) = files.iter().find(|(path, _)| root.join(path).exists()) {
+        return Err(AppError {
+            code: AppErrorCode::Conflict,
+            message: format!("{} already exists", path.display()),
+            error_code: None,
+        });
+    }
+
+    for (path, contents) in &files {
+        write(&root.join(path), contents)?;
+    }
+    for dir in ["model", "repository", "service", "handler"] {
+        declare_module(&root.join("src").join(dir).join("mod.rs"), &entity.name)?;
+    }
+    declare_path(&root.join("src/handler/mod.rs"), entity)?;
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity() -> Entity {
+        Entity::parse(
+            "order_line",
+            None,
+            &["sku:string".into(), "quantity:int".into()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let entity = entity();
+        assert_eq!(entity.plural, "order_lines");
+        assert_eq!(entity.fields[1].ty, FieldType::Int);
+
+        assert_eq!(pluralize("category"), "categories");
+        assert_eq!(pluralize("box"), "boxes");
+        assert!(Entity::parse("Order", None, &["a".into()]).is_err());
+        assert!(Entity::parse("order", None, &["id:string".into()]).is_err());
+        assert!(Entity::parse("order", None, &["total:money".into()]).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let files = entity().files("20250101000000");
+        let (path, model) = &files[0];
+        assert_eq!(path, &PathBuf::from("src/model/order_line.rs"));
+        assert!(model.contains("pub struct OrderLine {"));
+        assert!(model.contains("    pub quantity: i64,\n"));
+
+        let (path, migration) = &files[1];
+        assert_eq!(
+            path,
+            &PathBuf::from("migrations/20250101000000_order_lines_table.sql")
+        );
+        assert!(migration.contains("    quantity BIGINT NOT NULL\n);"));
+
+        let repository = &files[2].1;
+        assert!(repository.contains("SET sku = $2, quantity = $3"));
+        for (_, contents) in &files {
+            assert!(!contents.contains("{{"), "unrendered placeholder");
+        }
+    }
+
+    #[test]
+    fn test_generate_declares_modules() {
+        let root = std::env::temp_dir().join(format!("scaffold-{}", uuid::Uuid::new_v4()));
+        for dir in [
+            "src/model",
+            "src/repository",
+            "src/service",
+            "src/handler",
+            "migrations",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for dir in ["model", "repository", "service"] {
+            fs::write(
+                root.join("src").join(dir).join("mod.rs"),
+                "pub mod item;\npub mod user;\n",
+            )
+            .unwrap();
+        }
+        fs::write(
+            root.join("src/handler/mod.rs"),
+            "pub mod item;\n\npub const ITEMS_PATH: &str = \"/items\";\n",
+        )
+        .unwrap();
+
+        generate(&root, &entity(), "20250101000000").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(root.join("src/model/mod.rs")).unwrap(),
+            "pub mod item;\npub mod order_line;\npub mod user;\n"
+        );
+        assert!(
+            fs::read_to_string(root.join("src/handler/mod.rs"))
+                .unwrap()
+                .contains("pub const ORDER_LINES_PATH: &str = \"/order_lines\";")
+        );
+        assert!(generate(&root, &entity(), "20250101000001").is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
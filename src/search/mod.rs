@@ -0,0 +1,353 @@
+//! Item search served by a dedicated engine instead of Postgres. The index is
+//! a copy kept in sync from the change history by the [`SearchIndexer`], so
+//! it lags writes slightly.
+
+pub mod opensearch;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    config::Config,
+    container::Component,
+    model::{
+        change::Change,
+        error::{AppError, AppErrorCode},
+        event::EventAction,
+        item::Item,
+    },
+    repository::{DynRepository, Repository, change::ChangeRepository, item::ItemRepository},
+};
+
+/// Changes applied, or items indexed by a reindex, per round trip.
+const BATCH_SIZE: i64 = 500;
+
+/// Longest wait before retrying a change that failed to index.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[async_trait]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait SearchIndex: Send + Sync {
+    /// Adds the item, or replaces its earlier version.
+    async fn upsert(&self, item: &Item) -> Result<(), AppError>;
+    /// Succeeds when the item isn't indexed.
+    async fn remove(&self, id: &str) -> Result<(), AppError>;
+    /// Removes every item, ahead of a reindex.
+    async fn clear(&self) -> Result<(), AppError>;
+    /// Best matches of `query` first, tolerating typos.
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<Item>, AppError>;
+}
+
+/// The index configured by `OPENSEARCH_URL`, `None` when unset.
+pub fn from_config(config: &Config) -> Result<Option<Arc<dyn SearchIndex>>, AppError> {
+    let Some(url) = &config.opensearch_url else {
+        return Ok(None);
+    };
+    let index = opensearch::OpenSearchIndex::new(url, &config.opensearch_index)?;
+    Ok(Some(Arc::new(index)))
+}
+
+/// Mirrors the item changes of the history into a [`SearchIndex`], in
+/// order. Its cursor is saved with the history under `search:<name>`, so it
+/// picks up where it left off after a restart. A change that fails to index
+/// is retried with backoff rather than skipped. Without a saved cursor, e.g.
+/// for a new index, it starts with a full [`reindex`](Self::reindex).
+pub struct SearchIndexer<R: Repository + ?Sized = DynRepository> {
+    follower: Arc<Follower<R>>,
+    poll_interval: Duration,
+    stop: watch::Sender<bool>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// What the background task of a [`SearchIndexer`] shares with it.
+struct Follower<R: Repository + ?Sized> {
+    repo: Arc<R>,
+    index: Arc<dyn SearchIndex>,
+    consumer: String,
+}
+
+impl<R: Repository + ?Sized + 'static> SearchIndexer<R> {
+    /// `name` tells the cursors of different indexes apart.
+    pub fn new(repo: Arc<R>, index: Arc<dyn SearchIndex>, name: &str) -> Self {
+        Self {
+            follower: Arc::new(Follower {
+                repo,
+                index,
+                consumer: format!("search:{}", name),
+            }),
+            poll_interval: Duration::from_secs(1),
+            stop: watch::channel(false).0,
+            task: Mutex::new(None),
+        }
+    }
+
+    /// How long to wait for new changes once caught up.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Applies the next changes after the saved cursor, saving it as they
+    /// are applied. Tells whether more changes may follow right away.
+    pub async fn sync_once(&self) -> Result<bool, AppError> {
+        self.follower.sync_once().await
+    }
+
+    /// Rebuilds the index from the items as they are now and moves the
+    /// cursor to where history stood before, the changes made meanwhile
+    /// being applied again. Returns how many items were indexed.
+    pub async fn reindex(&self) -> Result<usize, AppError> {
+        self.follower.reindex().await
+    }
+}
+
+impl<R: Repository + ?Sized> Follower<R> {
+    async fn sync_once(&self) -> Result<bool, AppError> {
+        let changes = self.repo.change();
+        let Some(cursor) = changes.cursor(&self.consumer).await? else {
+            self.reindex().await?;
+            return Ok(true);
+        };
+        let batch = changes.since(cursor, BATCH_SIZE).await?;
+        let mut applied = cursor;
+        for change in &batch {
+            if let Err(e) = apply(self.index.as_ref(), change).await {
+                if applied > cursor {
+                    changes.save_cursor(&self.consumer, applied).await?;
+                }
+                return Err(e);
+            }
+            applied = change.seq;
+        }
+        if applied > cursor {
+            changes.save_cursor(&self.consumer, applied).await?;
+        }
+        Ok(batch.len() as i64 == BATCH_SIZE)
+    }
+
+    async fn reindex(&self) -> Result<usize, AppError> {
+        let changes = self.repo.change();
+        let head = changes.last_seq().await?;
+        self.index.clear().await?;
+        let items = self.repo.item();
+        let (mut after, mut indexed) = (None, 0);
+        loop {
+            let page = items.list_after(after, BATCH_SIZE).await?;
+            for item in &page {
+                self.index.upsert(item).await?;
+            }
+            indexed += page.len();
+            match page.last() {
+                Some(last) if page.len() as i64 == BATCH_SIZE => {
+                    after = Some((last.name.clone(), last.id.clone()));
+                }
+                _ => break,
+            }
+        }
+        changes.save_cursor(&self.consumer, head).await?;
+        Ok(indexed)
+    }
+}
+
+async fn apply(index: &dyn SearchIndex, change: &Change) -> Result<(), AppError> {
+    if change.entity != "item" {
+        return Ok(());
+    }
+    match change.action {
+        EventAction::Created | EventAction::Updated => {
+            let item = change
+                .data
+                .clone()
+                .and_then(|data| serde_json::from_value::<Item>(data).ok())
+                .ok_or_else(|| AppError {
+                    code: AppErrorCode::InternalError(format!("change {}", change.seq)),
+                    message: "Change holds no item".to_string(),
+                    error_code: None,
+                })?;
+            index.upsert(&item).await
+        }
+        EventAction::Deleted => index.remove(&change.entity_id).await,
+    }
+}
+
+#[async_trait]
+impl<R: Repository + ?Sized + 'static> Component for SearchIndexer<R> {
+    fn name(&self) -> &'static str {
+        "search"
+    }
+
+    async fn start(&self) -> Result<(), AppError> {
+        let follower = self.follower.clone();
+        let poll_interval = self.poll_interval;
+        let mut stop = self.stop.subscribe();
+        let task = tokio::spawn(async move {
+            let mut retry_delay = poll_interval;
+            while !*stop.borrow() {
+                let wait = match follower.sync_once().await {
+                    Ok(true) => continue,
+                    Ok(false) => {
+                        retry_delay = poll_interval;
+                        poll_interval
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            consumer = %follower.consumer,
+                            retry_in_secs = retry_delay.as_secs(),
+                            error = %e.get_error(),
+                            "Failed to index items: {}",
+                            e.get_message()
+                        );
+                        let wait = retry_delay;
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                        wait
+                    }
+                };
+                tokio::select! {
+                    _ = stop.changed() => {}
+                    _ = tokio::time::sleep(wait) => {}
+                }
+            }
+        });
+        if let Ok(mut slot) = self.task.lock() {
+            *slot = Some(task);
+        }
+        Ok(())
+    }
+
+    /// Stops following the history, the cursor is already saved.
+    async fn shutdown(&self) -> Result<(), AppError> {
+        self.stop.send_replace(true);
+        let task = self.task.lock().ok().and_then(|mut task| task.take());
+        if let Some(task) = task {
+            task.await.ok();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::repository::InMemoryRepository;
+
+    /// Index in a map, failing every upsert while `failing` is set.
+    #[derive(Default)]
+    struct MapIndex {
+        items: Mutex<HashMap<String, Item>>,
+        failing: Mutex<bool>,
+    }
+
+    impl MapIndex {
+        fn names(&self) -> Vec<String> {
+            let mut names: Vec<_> = self
+                .items
+                .lock()
+                .unwrap()
+                .values()
+                .map(|item| item.name.clone())
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    #[async_trait]
+    impl SearchIndex for MapIndex {
+        async fn upsert(&self, item: &Item) -> Result<(), AppError> {
+            if *self.failing.lock().unwrap() {
+                return Err(AppError {
+                    code: AppErrorCode::Unavailable,
+                    message: "Index is down".into(),
+                    error_code: None,
+                });
+            }
+            self.items
+                .lock()
+                .unwrap()
+                .insert(item.id.clone(), item.clone());
+            Ok(())
+        }
+
+        async fn remove(&self, id: &str) -> Result<(), AppError> {
+            self.items.lock().unwrap().remove(id);
+            Ok(())
+        }
+
+        async fn clear(&self) -> Result<(), AppError> {
+            self.items.lock().unwrap().clear();
+            Ok(())
+        }
+
+        async fn search(&self, _: &str, _: usize) -> Result<Vec<Item>, AppError> {
+            Ok(vec![])
+        }
+    }
+
+    fn item(id: &str, name: &str) -> Item {
+        Item {
+            id: id.into(),
+            name: name.into(),
+            slug: name.into(),
+            owner_id: None,
+            version: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_indexer_follows_history() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let index = Arc::new(MapIndex::default());
+        // Already there before the indexer first runs, picked up by the reindex.
+        repo.item.add(item("1", "book")).await.unwrap();
+        let indexer = SearchIndexer::new(repo.clone(), index.clone(), "items");
+
+        indexer.sync_once().await.unwrap();
+        assert_eq!(index.names(), ["book"]);
+        assert_eq!(repo.change.cursor("search:items").await.unwrap(), Some(1));
+
+        repo.item.add(item("2", "pen")).await.unwrap();
+        repo.item.update("1", "diary".into(), None).await.unwrap();
+        repo.item.delete("2").await.unwrap();
+        assert!(!indexer.sync_once().await.unwrap());
+        assert_eq!(index.names(), ["diary"]);
+        assert_eq!(repo.change.cursor("search:items").await.unwrap(), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_indexer_retries_failed_changes() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let index = Arc::new(MapIndex::default());
+        let indexer = SearchIndexer::new(repo.clone(), index.clone(), "items");
+        indexer.sync_once().await.unwrap();
+
+        repo.item.add(item("1", "book")).await.unwrap();
+        *index.failing.lock().unwrap() = true;
+        assert!(indexer.sync_once().await.is_err());
+        assert_eq!(repo.change.cursor("search:items").await.unwrap(), Some(0));
+
+        *index.failing.lock().unwrap() = false;
+        indexer.sync_once().await.unwrap();
+        assert_eq!(index.names(), ["book"]);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_replaces_index() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let index = Arc::new(MapIndex::default());
+        index.upsert(&item("9", "stale")).await.unwrap();
+        repo.item.add(item("1", "book")).await.unwrap();
+        repo.item.add(item("2", "pen")).await.unwrap();
+        let indexer = SearchIndexer::new(repo.clone(), index.clone(), "items");
+
+        assert_eq!(indexer.reindex().await.unwrap(), 2);
+        assert_eq!(index.names(), ["book", "pen"]);
+        assert_eq!(repo.change.cursor("search:items").await.unwrap(), Some(2));
+    }
+}
@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::SearchIndex;
+use crate::model::{
+    error::{AppError, AppErrorCode},
+    item::Item,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    hits: Hits,
+}
+
+#[derive(Deserialize)]
+struct Hits {
+    hits: Vec<Hit>,
+}
+
+#[derive(Deserialize)]
+struct Hit {
+    #[serde(rename = "_source")]
+    source: Item,
+}
+
+fn unavailable(message: String) -> AppError {
+    AppError {
+        code: AppErrorCode::Unavailable,
+        message,
+        error_code: None,
+    }
+}
+
+/// Items in an OpenSearch, or Elasticsearch, index, one document per item
+/// with the item id as document id.
+pub struct OpenSearchIndex {
+    client: reqwest::Client,
+    base_url: reqwest::Url,
+    index: String,
+}
+
+impl OpenSearchIndex {
+    pub fn new(base_url: &str, index: &str) -> Result<Self, AppError> {
+        let invalid = || AppError {
+            code: AppErrorCode::InvalidInput,
+            message: format!("Invalid OpenSearch URL '{}'", base_url),
+            error_code: None,
+        };
+        let url = reqwest::Url::parse(base_url).map_err(|_| invalid())?;
+        if url.cannot_be_a_base() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            base_url: url,
+            index: index.to_string(),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &[&str],
+        body: Option<serde_json::Value>,
+    ) -> Result<reqwest::Response, AppError> {
+        // Segments are percent-encoded, ids can't escape the index.
+        let mut url = self.base_url.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().push(&self.index).extend(path);
+        }
+        let mut request = self.client.request(method, url);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| unavailable(format!("Failed to reach OpenSearch: {}", e)))
+    }
+}
+
+fn check(response: &reqwest::Response) -> Result<(), AppError> {
+    if response.status().is_success() {
+        return Ok(());
+    }
+    Err(unavailable(format!(
+        "OpenSearch answered {}",
+        response.status()
+    )))
+}
+
+#[async_trait]
+impl SearchIndex for OpenSearchIndex {
+    async fn upsert(&self, item: &Item) -> Result<(), AppError> {
+        let response = self
+            .send(Method::PUT, &["_doc", &item.id], Some(json!(item)))
+            .await?;
+        check(&response)
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), AppError> {
+        let response = self.send(Method::DELETE, &["_doc", id], None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(&response)
+    }
+
+    async fn clear(&self) -> Result<(), AppError> {
+        let body = json!({"query": {"match_all": {}}});
+        let response = self
+            .send(Method::POST, &["_delete_by_query"], Some(body))
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(&response)
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<Item>, AppError> {
+        let body = json!({
+            "size": limit,
+            "query": {
+                "multi_match": {
+                    "query": query,
+                    "fields": ["name^2", "slug"],
+                    "fuzziness": "AUTO",
+                },
+            },
+        });
+        let response = self.send(Method::POST, &["_search"], Some(body)).await?;
+        check(&response)?;
+        let response: SearchResponse = response
+            .json()
+            .await
+            .map_err(|e| unavailable(format!("Unexpected OpenSearch response: {}", e)))?;
+        Ok(response
+            .hits
+            .hits
+            .into_iter()
+            .map(|hit| hit.source)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Router, extract::Request, routing::any};
+
+    use super::*;
+
+    type Requests = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// A node recording the requests, answering searches with one hit.
+    async fn node() -> (String, Requests) {
+        let requests = Requests::default();
+        let seen = requests.clone();
+        let app = Router::new().fallback(any(move |req: Request| {
+            let seen = seen.clone();
+            async move {
+                let line = format!("{} {}", req.method(), req.uri().path());
+                let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                seen.lock()
+                    .unwrap()
+                    .push((line.clone(), String::from_utf8_lossy(&body).into()));
+                if line.starts_with("DELETE") {
+                    return (StatusCode::NOT_FOUND, String::new());
+                }
+                let hits = json!({"hits": {"hits": [
+                    {"_source": {"id": "1", "name": "book", "slug": "book"}}
+                ]}});
+                (StatusCode::OK, hits.to_string())
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{}/", addr), requests)
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(OpenSearchIndex::new("http://search:9200", "items").is_ok());
+        assert!(OpenSearchIndex::new("search", "items").is_err());
+        assert!(OpenSearchIndex::new("mailto:a@b.com", "items").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requests() {
+        let (url, requests) = node().await;
+        let index = OpenSearchIndex::new(&url, "items").unwrap();
+        let item = Item {
+            id: "1/..".into(),
+            name: "book".into(),
+            slug: "book".into(),
+            owner_id: None,
+            version: 1,
+        };
+        index.upsert(&item).await.unwrap();
+        index.remove("1").await.unwrap();
+        let found = index.search("bok", 10).await.unwrap();
+        index.clear().await.unwrap();
+        assert_eq!(found[0].name, "book");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].0, "PUT /items/_doc/1%2F..");
+        assert_eq!(requests[1].0, "DELETE /items/_doc/1");
+        assert_eq!(requests[2].0, "POST /items/_search");
+        let query: serde_json::Value = serde_json::from_str(&requests[2].1).unwrap();
+        assert_eq!(query["size"], 10);
+        assert_eq!(query["query"]["multi_match"]["query"], "bok");
+        assert_eq!(requests[3].0, "POST /items/_delete_by_query");
+    }
+}
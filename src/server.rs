@@ -0,0 +1,238 @@
+//! Accept loop with the connection settings from config, where
+//! `axum::serve` only offers hyper's defaults. Those suit neither chatty
+//! microservice traffic on a LAN nor slow clients on the public internet.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{Router, extract::ConnectInfo, http::Request};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::{conn::auto, graceful::GracefulShutdown},
+};
+use tokio::{io::BufReader, net::TcpListener, sync::Semaphore};
+use tower::ServiceExt;
+
+use crate::{config::Config, proxy::read_proxy_header};
+
+/// Time a connection has to send its PROXY header.
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connection settings, see the matching [`Config`] fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerTuning {
+    pub tcp_nodelay: bool,
+    pub http1_keep_alive: bool,
+    /// Time a client has to send the request headers, `None` waits forever.
+    pub header_read_timeout: Option<Duration>,
+    /// Interval of HTTP/2 keep-alive pings, `None` sends none.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Time to wait for a ping to be acknowledged before closing.
+    pub http2_keep_alive_timeout: Duration,
+    /// Connections served at once, further ones wait to be accepted. 0 is
+    /// unlimited.
+    pub max_connections: usize,
+    /// Connections start with a PROXY protocol header naming the client.
+    pub proxy_protocol: bool,
+}
+
+impl ServerTuning {
+    pub fn from_config(config: &Config) -> Self {
+        let duration = |ms: u64| Some(Duration::from_millis(ms)).filter(|d| !d.is_zero());
+        Self {
+            tcp_nodelay: config.tcp_nodelay,
+            http1_keep_alive: config.http1_keep_alive,
+            header_read_timeout: duration(config.header_read_timeout_ms),
+            http2_keep_alive_interval: duration(config.http2_keep_alive_interval_ms),
+            http2_keep_alive_timeout: Duration::from_millis(config.http2_keep_alive_timeout_ms),
+            max_connections: config.max_connections,
+            proxy_protocol: config.proxy_protocol,
+        }
+    }
+
+    fn builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(self.http1_keep_alive)
+            .header_read_timeout(self.header_read_timeout);
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .keep_alive_interval(self.http2_keep_alive_interval)
+            .keep_alive_timeout(self.http2_keep_alive_timeout);
+        builder
+    }
+}
+
+/// Serves `app` until `shutdown` resolves, then waits for the open
+/// connections to finish their requests. Requests carry the peer address as
+/// [`ConnectInfo`] of `SocketAddr`, like `into_make_service_with_connect_info`.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tuning: ServerTuning,
+    shutdown: impl Future<Output = ()>,
+) {
+    let builder = tuning.builder();
+    let slots =
+        (tuning.max_connections > 0).then(|| Arc::new(Semaphore::new(tuning.max_connections)));
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+    loop {
+        let permit = match &slots {
+            Some(slots) => tokio::select! {
+                permit = slots.clone().acquire_owned() => permit.ok(),
+                _ = &mut shutdown => break,
+            },
+            None => None,
+        };
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // Mostly out of file descriptors, give some a chance to close.
+                    tracing::error!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+        if let Err(e) = stream.set_nodelay(tuning.tcp_nodelay) {
+            tracing::debug!("Failed to set TCP_NODELAY: {}", e);
+        }
+
+        let app = app.clone();
+        let builder = builder.clone();
+        let watcher = graceful.watcher();
+        let proxy_protocol = tuning.proxy_protocol;
+        tokio::spawn(async move {
+            // Buffered for the PROXY header, hyper reads on from the buffer.
+            let mut io = BufReader::new(stream);
+            let addr = if proxy_protocol {
+                let header = tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_proxy_header(&mut io));
+                match header.await {
+                    Ok(Ok(client)) => client.unwrap_or(addr),
+                    Ok(Err(e)) => {
+                        tracing::debug!("Dropped connection from {}: {}", addr, e);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!("Dropped connection from {}: no PROXY header", addr);
+                        return;
+                    }
+                }
+            } else {
+                addr
+            };
+            let service = hyper::service::service_fn(move |mut req: Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(addr));
+                app.clone().oneshot(req)
+            });
+            let conn = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+            if let Err(e) = watcher.watch(conn.into_owned()).await {
+                tracing::debug!("Connection from {} closed: {}", addr, e);
+            }
+            drop(permit);
+        });
+    }
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::routing::get;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::oneshot,
+    };
+
+    use super::*;
+
+    async fn start(tuning: ServerTuning) -> (SocketAddr, oneshot::Sender<()>) {
+        let app = Router::new().route(
+            "/",
+            get(|ConnectInfo(addr): ConnectInfo<SocketAddr>| async move { addr.ip().to_string() }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel();
+        tokio::spawn(serve(listener, app, tuning, async {
+            let _ = stopped.await;
+        }));
+        (addr, stop)
+    }
+
+    fn tuning() -> ServerTuning {
+        ServerTuning::from_config(&Config::default())
+    }
+
+    #[tokio::test]
+    async fn test_serves_with_connect_info() {
+        let (addr, stop) = start(tuning()).await;
+        let body = reqwest::get(format!("http://{}/", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "127.0.0.1");
+        stop.send(()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol() {
+        let (addr, _stop) = start(ServerTuning {
+            proxy_protocol: true,
+            ..tuning()
+        })
+        .await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let req = "PROXY TCP4 203.0.113.7 127.0.0.1 56324 80\r\n\
+                   GET / HTTP/1.1\r\nhost: x\r\nconnection: close\r\n\r\n";
+        stream.write_all(req.as_bytes()).await.unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        assert!(res.ends_with("203.0.113.7"), "{}", res);
+
+        // Without the header the connection is dropped.
+        let res = reqwest::get(format!("http://{}/", addr)).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_closes_slow_header_reads() {
+        let (addr, _stop) = start(ServerTuning {
+            header_read_timeout: Some(Duration::from_millis(50)),
+            ..tuning()
+        })
+        .await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf));
+        assert!(read.await.is_ok(), "connection was left open");
+    }
+
+    #[tokio::test]
+    async fn test_max_connections() {
+        let (addr, _stop) = start(ServerTuning {
+            max_connections: 1,
+            ..tuning()
+        })
+        .await;
+        let _held = TcpStream::connect(addr).await.unwrap();
+        let req = reqwest::get(format!("http://{}/", addr));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), req)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    extract::normalized,
+    model::{
+        attachment::Attachment,
+        context::Ctx,
+        error::{AppError, AppErrorCode, FieldError},
+        job::{Job, NewJob},
+    },
+    repository::{
+        DynRepository, Repository, attachment::AttachmentRepository, item::ItemRepository,
+        job::JobRepository,
+    },
+    storage::{ObjectInfo, Storage, local::LocalStorage},
+    thumbnail::{self, GENERATE_THUMBNAILS_JOB, ThumbnailPayload, ThumbnailSize},
+};
+
+/// File received for an item, before it is validated and stored.
+#[derive(Debug, Clone)]
+pub struct NewAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// How an attachment's bytes reach the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentData {
+    Bytes(Vec<u8>),
+    /// Presigned URL of the storage backend to redirect to.
+    Url(String),
+}
+
+pub struct AttachmentService<R: Repository + ?Sized = DynRepository> {
+    config: Arc<Config>,
+    repo: Arc<R>,
+    storage: Arc<dyn Storage>,
+    thumbnails: Vec<ThumbnailSize>,
+}
+
+impl<R: Repository + ?Sized> AttachmentService<R> {
+    /// Stores files under `attachment_dir` until [`Self::with_storage`] says
+    /// otherwise. Invalid thumbnail sizes disable thumbnails, `main` refuses
+    /// to start on them.
+    pub fn new(config: Arc<Config>, repo: Arc<R>) -> Self {
+        let storage = Arc::new(LocalStorage::new(&config.attachment_dir));
+        let thumbnails =
+            ThumbnailSize::parse_all(&config.attachment_thumbnails).unwrap_or_default();
+        Self {
+            config,
+            repo,
+            storage,
+            thumbnails,
+        }
+    }
+
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// `image/png; charset=x` and `IMAGE/PNG` both become `image/png`.
+    fn media_type(content_type: &str) -> String {
+        content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    }
+
+    /// Allowed entries are exact types or a whole family, e.g. `image/*`.
+    fn is_allowed(&self, media_type: &str) -> bool {
+        self.config.attachment_allowed_types.iter().any(|allowed| {
+            match allowed.strip_suffix("/*") {
+                Some(family) => media_type
+                    .split_once('/')
+                    .is_some_and(|(kind, _)| kind == family),
+                None => allowed == media_type,
+            }
+        })
+    }
+
+    /// Keeps the last path segment of the client's name, so it can't point
+    /// elsewhere once used in a `Content-Disposition`.
+    fn filename(name: &str) -> String {
+        let name = normalized(name.rsplit(['/', '\\']).next().unwrap_or_default());
+        let name: String = name
+            .chars()
+            .filter(|c| !c.is_control() && *c != '"')
+            .take(255)
+            .collect();
+        if name.is_empty() {
+            "file".to_string()
+        } else {
+            name
+        }
+    }
+
+    pub async fn upload(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+        file: NewAttachment,
+    ) -> Result<Attachment, AppError> {
+        self.repo.item().get(item_id).await?;
+
+        let media_type = Self::media_type(&file.content_type);
+        let error = if file.data.is_empty() {
+            Some(FieldError::new("file", "required", "File is empty"))
+        } else if file.data.len() > self.config.attachment_max_bytes {
+            Some(FieldError::new(
+                "file",
+                "too_large",
+                format!(
+                    "File must be at most {} bytes",
+                    self.config.attachment_max_bytes
+                ),
+            ))
+        } else if !self.is_allowed(&media_type) {
+            Some(FieldError::new(
+                "file",
+                "unsupported_type",
+                format!("Files of type '{}' are not allowed", media_type),
+            ))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return Err(AppError::validation(vec![error]));
+        }
+
+        let attachment = Attachment {
+            id: Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            filename: Self::filename(&file.filename),
+            content_type: media_type,
+            size: file.data.len() as i64,
+            created_at: Utc::now(),
+        };
+        let info = ObjectInfo {
+            content_type: attachment.content_type.clone(),
+            filename: attachment.filename.clone(),
+        };
+        let id = attachment.id.clone();
+        self.storage.put(&id, file.data, &info).await?;
+        let attachment = match self.repo.attachment().add(attachment).await {
+            Ok(attachment) => attachment,
+            Err(e) => {
+                let _ = self.storage.delete(&id).await;
+                return Err(e);
+            }
+        };
+        tracing::info!(
+            correlation_id = %ctx.correlation_id,
+            item_id = %item_id,
+            attachment_id = %attachment.id,
+            "Attachment uploaded"
+        );
+        if let Err(e) = self.queue_thumbnails(&attachment).await {
+            tracing::warn!(attachment_id = %attachment.id, error = %e.get_error(), "Failed to queue thumbnails");
+        }
+        Ok(attachment)
+    }
+
+    fn has_thumbnails(&self, attachment: &Attachment) -> bool {
+        !self.thumbnails.is_empty() && thumbnail::is_supported(&attachment.content_type)
+    }
+
+    /// Thumbnails are rendered by the job workers, the upload doesn't wait.
+    async fn queue_thumbnails(&self, attachment: &Attachment) -> Result<(), AppError> {
+        if !self.has_thumbnails(attachment) {
+            return Ok(());
+        }
+        let payload = ThumbnailPayload {
+            key: attachment.id.clone(),
+            content_type: attachment.content_type.clone(),
+            filename: attachment.filename.clone(),
+        };
+        let job = NewJob::new(GENERATE_THUMBNAILS_JOB, json!(payload));
+        self.repo.job().add(Job::from(job)).await?;
+        Ok(())
+    }
+
+    pub async fn list(&self, _: &Ctx, item_id: &str) -> Result<Vec<Attachment>, AppError> {
+        self.repo.item().get(item_id).await?;
+        self.repo.attachment().list(item_id).await
+    }
+
+    pub async fn get(&self, _: &Ctx, item_id: &str, id: &str) -> Result<Attachment, AppError> {
+        self.repo.attachment().get(item_id, id).await
+    }
+
+    /// The attachment along with its bytes, or where to fetch them when the
+    /// storage backend hands out presigned URLs. A `size` picks one of the
+    /// configured thumbnails instead of the original.
+    pub async fn read(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+        id: &str,
+        size: Option<&str>,
+    ) -> Result<(Attachment, AttachmentData), AppError> {
+        let mut attachment = self.get(ctx, item_id, id).await?;
+        let key = match size {
+            None => id.to_string(),
+            Some(size) => {
+                if !self.thumbnails.iter().any(|s| s.name == size) {
+                    let names: Vec<_> = self.thumbnails.iter().map(|s| s.name.as_str()).collect();
+                    return Err(AppError::validation(vec![FieldError::new(
+                        "size",
+                        "invalid",
+                        format!(
+                            "Unknown size '{}', expected one of: {}",
+                            size,
+                            names.join(", ")
+                        ),
+                    )]));
+                }
+                if !self.has_thumbnails(&attachment) {
+                    return Err(AppError {
+                        code: AppErrorCode::NotFound,
+                        message: format!("Attachment {} has no thumbnails", id),
+                        error_code: None,
+                    });
+                }
+                attachment.content_type =
+                    thumbnail::thumbnail_content_type(&attachment.content_type).to_string();
+                thumbnail::thumbnail_key(id, size)
+            }
+        };
+        if let Some(url) = self.storage.presigned_url(&key).await? {
+            return Ok((attachment, AttachmentData::Url(url)));
+        }
+        let data = self.storage.get(&key).await.map_err(|e| match size {
+            Some(size) if matches!(e.code, AppErrorCode::NotFound) => AppError {
+                code: AppErrorCode::NotFound,
+                message: format!("Thumbnail '{}' of attachment {} is not ready yet", size, id),
+                error_code: None,
+            },
+            _ => e,
+        })?;
+        Ok((attachment, AttachmentData::Bytes(data)))
+    }
+
+    pub async fn delete(&self, ctx: &Ctx, item_id: &str, id: &str) -> Result<(), AppError> {
+        let attachment = self.get(ctx, item_id, id).await?;
+        self.repo.attachment().delete(item_id, id).await?;
+        self.remove_files(&[attachment]).await;
+        tracing::info!(correlation_id = %ctx.correlation_id, attachment_id = %id, "Attachment deleted");
+        Ok(())
+    }
+
+    /// Drops the rows and files of attachments whose item is gone, the rows
+    /// may already have been removed along with it.
+    pub async fn purge(&self, attachments: &[Attachment]) {
+        for attachment in attachments {
+            if let Err(e) = self
+                .repo
+                .attachment()
+                .delete(&attachment.item_id, &attachment.id)
+                .await
+            {
+                tracing::warn!(error = %e.get_error(), "{}", e.get_message());
+            }
+        }
+        self.remove_files(attachments).await;
+    }
+
+    async fn remove_files(&self, attachments: &[Attachment]) {
+        for attachment in attachments {
+            let mut keys = vec![attachment.id.clone()];
+            if self.has_thumbnails(attachment) {
+                keys.extend(
+                    self.thumbnails
+                        .iter()
+                        .map(|size| thumbnail::thumbnail_key(&attachment.id, &size.name)),
+                );
+            }
+            for key in keys {
+                if let Err(e) = self.storage.delete(&key).await {
+                    tracing::warn!(key = %key, error = %e.get_error(), "Failed to remove attachment file");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::item::Item, repository::InMemoryRepository, storage::MockStorage};
+
+    fn make_service(dir: &std::path::Path) -> AttachmentService<InMemoryRepository> {
+        let repo = InMemoryRepository::new();
+        repo.item.items.lock().unwrap().push(Item {
+            id: "1".into(),
+            name: "book".into(),
+            slug: "book".into(),
+            owner_id: None,
+            version: 1,
+        });
+        let config = Config {
+            attachment_dir: dir.to_string_lossy().into(),
+            attachment_max_bytes: 8,
+            attachment_allowed_types: vec!["image/*".into(), "text/plain".into()],
+            ..Default::default()
+        };
+        AttachmentService::new(Arc::new(config), Arc::new(repo))
+    }
+
+    fn file(name: &str, content_type: &str, data: &[u8]) -> NewAttachment {
+        NewAttachment {
+            filename: name.into(),
+            content_type: content_type.into(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_read_and_delete() {
+        let dir = std::env::temp_dir().join(format!("attachments-{}", Uuid::new_v4()));
+        let service = make_service(&dir);
+        let ctx = Ctx::default();
+
+        let attachment = service
+            .upload(
+                &ctx,
+                "1",
+                file("../../notes.txt", "Text/Plain; charset=utf-8", b"hi"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(attachment.filename, "notes.txt");
+        assert_eq!(attachment.content_type, "text/plain");
+        assert_eq!(attachment.size, 2);
+        assert_eq!(service.list(&ctx, "1").await.unwrap().len(), 1);
+
+        let (_, data) = service.read(&ctx, "1", &attachment.id, None).await.unwrap();
+        assert_eq!(data, AttachmentData::Bytes(b"hi".to_vec()));
+
+        service.delete(&ctx, "1", &attachment.id).await.unwrap();
+        assert!(service.list(&ctx, "1").await.unwrap().is_empty());
+        assert!(!LocalStorage::new(&dir).path(&attachment.id).exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_validation() {
+        let dir = std::env::temp_dir().join(format!("attachments-{}", Uuid::new_v4()));
+        let service = make_service(&dir);
+        let ctx = Ctx::default();
+
+        for (file, code) in [
+            (file("a.txt", "text/plain", b""), "required"),
+            (file("a.txt", "text/plain", b"123456789"), "too_large"),
+            (
+                file("a.pdf", "application/pdf", b"%PDF"),
+                "unsupported_type",
+            ),
+        ] {
+            let err = service.upload(&ctx, "1", file).await.unwrap_err();
+            assert_eq!(err.get_field_errors()[0].code, code);
+        }
+        assert!(
+            service
+                .upload(&ctx, "1", file("a.png", "image/png", b"png"))
+                .await
+                .is_ok()
+        );
+
+        let err = service
+            .upload(&ctx, "2", file("a.png", "image/png", b"png"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::NotFound));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_read_prefers_presigned_url() {
+        let mut storage = MockStorage::new();
+        storage
+            .expect_put()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        storage.expect_presigned_url().returning(|key| {
+            let url = format!("http://minio:9000/attachments/{}", key);
+            Box::pin(async move { Ok(Some(url)) })
+        });
+        storage.expect_get().never();
+        let service = make_service(&std::env::temp_dir()).with_storage(Arc::new(storage));
+        let ctx = Ctx::default();
+
+        let attachment = service
+            .upload(&ctx, "1", file("a.png", "image/png", b"png"))
+            .await
+            .unwrap();
+        let (_, data) = service.read(&ctx, "1", &attachment.id, None).await.unwrap();
+
+        assert_eq!(
+            data,
+            AttachmentData::Url(format!("http://minio:9000/attachments/{}", attachment.id))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_image_upload_queues_thumbnails() {
+        let dir = std::env::temp_dir().join(format!("attachments-{}", Uuid::new_v4()));
+        let repo = InMemoryRepository::new();
+        repo.item.items.lock().unwrap().push(Item {
+            id: "1".into(),
+            name: "book".into(),
+            slug: "book".into(),
+            owner_id: None,
+            version: 1,
+        });
+        let repo = Arc::new(repo);
+        let config = Config {
+            attachment_dir: dir.to_string_lossy().into(),
+            ..Default::default()
+        };
+        let service = AttachmentService::new(Arc::new(config), repo.clone());
+        let ctx = Ctx::default();
+
+        let attachment = service
+            .upload(&ctx, "1", file("a.png", "image/png", b"png"))
+            .await
+            .unwrap();
+        let jobs = repo.job.jobs.lock().unwrap().clone();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].kind, GENERATE_THUMBNAILS_JOB);
+        assert_eq!(jobs[0].payload["key"], attachment.id.as_str());
+
+        let err = service
+            .read(&ctx, "1", &attachment.id, Some("huge"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "size");
+        let err = service
+            .read(&ctx, "1", &attachment.id, Some("thumb"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.get_message(),
+            format!(
+                "Thumbnail 'thumb' of attachment {} is not ready yet",
+                attachment.id
+            )
+        );
+
+        service
+            .upload(&ctx, "1", file("a.txt", "text/plain", b"hi"))
+            .await
+            .unwrap();
+        assert_eq!(repo.job.jobs.lock().unwrap().len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+//! Opaque cursors of keyset pagination. A cursor holds the sort key and id
+//! of the last row of a page, the next page starts right after that row
+//! however many rows were added or removed in between. Going back, a cursor
+//! holds the first row of a page and the page before ends right before it.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+use crate::model::error::{AppError, FieldError};
+
+/// Rows of a page when the client names no limit.
+pub const DEFAULT_PAGE_LIMIT: i64 = 100;
+
+/// Most rows one page holds.
+pub const MAX_PAGE_LIMIT: i64 = 1000;
+
+/// Marks a cursor paging back, before its row.
+const BEFORE: &str = "before";
+/// The cursor of the last page.
+const LAST: &str = "last";
+
+/// Where a page starts, `key` being the column the list is sorted by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    /// Right after a row, paging forward.
+    After { key: String, id: String },
+    /// Right before a row, paging back.
+    Before { key: String, id: String },
+    /// The last rows of the list.
+    Last,
+}
+
+impl Cursor {
+    pub fn after(key: impl Into<String>, id: impl Into<String>) -> Self {
+        Self::After {
+            key: key.into(),
+            id: id.into(),
+        }
+    }
+
+    pub fn before(key: impl Into<String>, id: impl Into<String>) -> Self {
+        Self::Before {
+            key: key.into(),
+            id: id.into(),
+        }
+    }
+
+    /// URL-safe text clients pass back as is.
+    pub fn encode(&self) -> String {
+        let parts: Vec<&str> = match self {
+            Self::After { key, id } => vec![key, id],
+            Self::Before { key, id } => vec![key, id, BEFORE],
+            Self::Last => vec![LAST],
+        };
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&parts).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        let parts = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Vec<String>>(&bytes).ok());
+        match parts.as_deref() {
+            Some([key, id]) => Ok(Self::after(key, id)),
+            Some([key, id, before]) if before == BEFORE => Ok(Self::before(key, id)),
+            Some([last]) if last == LAST => Ok(Self::Last),
+            _ => Err(AppError::validation(vec![FieldError::new(
+                "cursor",
+                "invalid",
+                "Cursor is malformed, pass back the one a page returned",
+            )])),
+        }
+    }
+
+    /// Whether the page is fetched backwards, from its end.
+    pub fn is_backward(&self) -> bool {
+        matches!(self, Self::Before { .. } | Self::Last)
+    }
+
+    /// The row to fetch after, for `list_after`.
+    pub fn after_row(cursor: Option<&Self>) -> Option<(String, String)> {
+        match cursor? {
+            Self::After { key, id } => Some((key.clone(), id.clone())),
+            _ => None,
+        }
+    }
+
+    /// The row to fetch before, for `list_before`, `None` from the end.
+    pub fn before_row(cursor: Option<&Self>) -> Option<(String, String)> {
+        match cursor? {
+            Self::Before { key, id } => Some((key.clone(), id.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// One page of a list with the cursors of its neighbours, `next` is `None`
+/// on the last page and `prev` on the first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub rows: Vec<T>,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+    pub last: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// A page with only a `next` cursor, for lists that only page forward.
+    pub fn forward(rows: Vec<T>, next: Option<String>) -> Self {
+        Self {
+            rows,
+            next,
+            prev: None,
+            last: None,
+        }
+    }
+}
+
+/// Checks `cursor` and `limit` of a page request.
+pub fn parse(cursor: Option<&str>, limit: i64) -> Result<Option<Cursor>, AppError> {
+    let cursor = cursor.map(Cursor::decode).transpose();
+    let mut errors = vec![];
+    if let Err(e) = &cursor {
+        errors.extend(e.get_field_errors());
+    }
+    if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+        errors.push(FieldError::new(
+            "limit",
+            "range",
+            format!("Limit must be between 1 and {}", MAX_PAGE_LIMIT),
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(AppError::validation(errors));
+    }
+    cursor
+}
+
+/// Cuts the `limit + 1` rows fetched for the page at `cursor` down to
+/// `limit`, the extra row only telling that more rows follow in the direction
+/// they were fetched. Backward fetches come in reverse order and are put back
+/// in list order. `key` gives a row's sort key and id.
+pub fn page<T>(
+    mut rows: Vec<T>,
+    limit: i64,
+    cursor: Option<&Cursor>,
+    key: impl Fn(&T) -> (String, String),
+) -> Page<T> {
+    let more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    if cursor.is_some_and(Cursor::is_backward) {
+        rows.reverse();
+    }
+    let after = |row: &T| {
+        let (key, id) = key(row);
+        Cursor::after(key, id)
+    };
+    let before = |row: &T| {
+        let (key, id) = key(row);
+        Cursor::before(key, id)
+    };
+    // Past either end of the list the page is empty, its neighbour is then
+    // whatever lies on the other side of the cursor's row.
+    let (next, prev) = match cursor {
+        None => (rows.last().filter(|_| more).map(after), None),
+        Some(Cursor::After { key, id }) => (
+            rows.last().filter(|_| more).map(after),
+            Some(rows.first().map_or_else(|| Cursor::before(key, id), before)),
+        ),
+        Some(Cursor::Before { key, id }) => (
+            Some(rows.last().map_or_else(|| Cursor::after(key, id), after)),
+            rows.first().filter(|_| more).map(before),
+        ),
+        Some(Cursor::Last) => (None, rows.first().filter(|_| more).map(before)),
+    };
+    Page {
+        rows,
+        next: next.map(|cursor| cursor.encode()),
+        prev: prev.map(|cursor| cursor.encode()),
+        last: Some(Cursor::Last.encode()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        for cursor in [
+            Cursor::after("blue mug/ä", "1"),
+            Cursor::before("blue mug/ä", "1"),
+            Cursor::Last,
+        ] {
+            let encoded = cursor.encode();
+            assert!(
+                encoded
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            );
+            assert_eq!(Cursor::decode(&encoded).unwrap(), cursor);
+        }
+
+        let bad_direction = URL_SAFE_NO_PAD.encode(r#"["a","1","around"]"#);
+        for bad in [
+            "",
+            "not a cursor",
+            &URL_SAFE_NO_PAD.encode("[1]"),
+            &bad_direction,
+        ] {
+            let err = Cursor::decode(bad).unwrap_err();
+            assert_eq!(err.get_field_errors()[0].field, "cursor");
+        }
+    }
+
+    #[test]
+    fn test_page() {
+        let key = |n: &i32| (String::new(), n.to_string());
+        let next = |rows: Vec<i32>, limit| page(rows, limit, None, key);
+        let last = next(vec![1, 2], 2);
+        assert_eq!((last.rows, last.next, last.prev), (vec![1, 2], None, None));
+        let more = next(vec![1, 2, 3], 2);
+        assert_eq!(more.rows, vec![1, 2]);
+        let after = Cursor::decode(&more.next.unwrap()).unwrap();
+        assert_eq!(after, Cursor::after("", "2"));
+        assert_eq!(Cursor::decode(&more.last.unwrap()).unwrap(), Cursor::Last);
+
+        let second = page(vec![3, 4, 5], 2, Some(&after), key);
+        assert_eq!(second.rows, vec![3, 4]);
+        let prev = Cursor::decode(&second.prev.unwrap()).unwrap();
+        assert_eq!(prev, Cursor::before("", "3"));
+
+        // Fetched backwards, a page comes in reverse.
+        let back = page(vec![2, 1], 2, Some(&prev), key);
+        assert_eq!((back.rows, back.prev), (vec![1, 2], None));
+        assert_eq!(Cursor::decode(&back.next.unwrap()).unwrap(), after);
+        let end = page(vec![5, 4, 3], 2, Some(&Cursor::Last), key);
+        assert_eq!((end.rows, end.next), (vec![4, 5], None));
+        assert_eq!(
+            Cursor::decode(&end.prev.unwrap()).unwrap(),
+            Cursor::before("", "4")
+        );
+        // Past the end, the page before leads back.
+        let past = page(vec![], 2, Some(&Cursor::after("", "9")), key);
+        assert_eq!(
+            Cursor::decode(&past.prev.unwrap()).unwrap(),
+            Cursor::before("", "9")
+        );
+
+        let err = parse(Some("%"), 0).unwrap_err();
+        assert_eq!(err.get_field_errors().len(), 2);
+    }
+}
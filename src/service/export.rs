@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use crate::{
+    config::Config,
+    export::{EXPORT_ITEMS_JOB, export_path},
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+        export::ExportFormat,
+        job::{Job, JobStatus, NewJob},
+    },
+    repository::{DynRepository, Repository, job::JobRepository},
+};
+
+pub struct ExportService<R: Repository + ?Sized = DynRepository> {
+    config: Arc<Config>,
+    repo: Arc<R>,
+}
+
+impl<R: Repository + ?Sized> ExportService<R> {
+    pub fn new(config: Arc<Config>, repo: Arc<R>) -> Self {
+        Self { config, repo }
+    }
+
+    /// Queues an export of every item, the file is written by the job workers.
+    pub async fn start_items(&self, ctx: &Ctx, format: ExportFormat) -> Result<Job, AppError> {
+        let payload = json!({ "format": format.as_str() });
+        let job = self
+            .repo
+            .job()
+            .add(Job::from(NewJob::new(EXPORT_ITEMS_JOB, payload)))
+            .await?;
+        tracing::info!(correlation_id = %ctx.correlation_id, job_id = %job.id, format = format.as_str(), "Export queued");
+        Ok(job)
+    }
+
+    pub async fn get(&self, _: &Ctx, id: &str) -> Result<Job, AppError> {
+        match self.repo.job().get(id).await {
+            Ok(job) if job.kind.starts_with("export.") => Ok(job),
+            Ok(_)
+            | Err(AppError {
+                code: AppErrorCode::NotFound,
+                ..
+            }) => Err(AppError {
+                code: AppErrorCode::NotFound,
+                message: format!("Export job with id {} not found", id),
+                error_code: None,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Contents of a finished export and their format, a conflict while it
+    /// is still running.
+    pub async fn download(&self, ctx: &Ctx, id: &str) -> Result<(ExportFormat, Vec<u8>), AppError> {
+        let job = self.get(ctx, id).await?;
+        if job.status != JobStatus::Done {
+            return Err(AppError {
+                code: AppErrorCode::Conflict,
+                message: format!("Export job {} is {}", id, job.status.as_str()),
+                error_code: None,
+            });
+        }
+        let format = ExportFormat::of(&job);
+        tokio::fs::read(export_path(&self.config.export_dir, id, format))
+            .await
+            .map(|file| (format, file))
+            .map_err(|e| AppError {
+                code: AppErrorCode::NotFound,
+                message: format!("Export file of job {} not found: {}", id, e),
+                error_code: None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{InMemoryRepository, job::JobRepository};
+
+    fn make_service(dir: &str) -> (ExportService<InMemoryRepository>, Arc<InMemoryRepository>) {
+        let repo = Arc::new(InMemoryRepository::new());
+        let config = Config {
+            export_dir: dir.into(),
+            ..Default::default()
+        };
+        (ExportService::new(Arc::new(config), repo.clone()), repo)
+    }
+
+    #[tokio::test]
+    async fn test_start_and_get() {
+        let (service, repo) = make_service("exports");
+        let ctx = Ctx::default();
+
+        let job = service.start_items(&ctx, ExportFormat::Csv).await.unwrap();
+
+        assert_eq!(job.kind, EXPORT_ITEMS_JOB);
+        assert_eq!(service.get(&ctx, &job.id).await.unwrap().id, job.id);
+
+        let other = repo
+            .job
+            .add(Job::from(NewJob::new("email.send", json!({}))))
+            .await
+            .unwrap();
+        let err = service.get(&ctx, &other.id).await.unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_download_requires_done() {
+        let dir = std::env::temp_dir().join("crud-export-service");
+        let (service, repo) = make_service(dir.to_str().unwrap());
+        let ctx = Ctx::default();
+        let job = service.start_items(&ctx, ExportFormat::Csv).await.unwrap();
+
+        let err = service.download(&ctx, &job.id).await.unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::Conflict));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(export_path(&dir, &job.id, ExportFormat::Csv), "id,name\n").unwrap();
+        repo.job.complete(&job.id).await.unwrap();
+
+        assert_eq!(
+            service.download(&ctx, &job.id).await.unwrap(),
+            (ExportFormat::Csv, b"id,name\n".to_vec())
+        );
+        std::fs::remove_file(export_path(&dir, &job.id, ExportFormat::Csv)).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use crate::{
+    config::Config,
+    import::{IMPORT_ITEMS_JOB, import_path, name_column},
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode, FieldError},
+        job::{Job, NewJob},
+    },
+    repository::{DynRepository, Repository, job::JobRepository},
+};
+
+pub struct ImportService<R: Repository + ?Sized = DynRepository> {
+    config: Arc<Config>,
+    repo: Arc<R>,
+}
+
+impl<R: Repository + ?Sized> ImportService<R> {
+    pub fn new(config: Arc<Config>, repo: Arc<R>) -> Self {
+        Self { config, repo }
+    }
+
+    /// Stores the uploaded CSV and queues its import, the job only becomes
+    /// visible to the workers once the file is on disk.
+    pub async fn start_items(&self, ctx: &Ctx, data: Vec<u8>) -> Result<Job, AppError> {
+        if data.is_empty() {
+            return Err(AppError::validation(vec![FieldError::new(
+                "file",
+                "required",
+                "CSV file cannot be empty",
+            )]));
+        }
+        name_column(&data)?;
+
+        let job = Job::from(NewJob::new(IMPORT_ITEMS_JOB, json!({"bytes": data.len()})));
+        let io_error = |e: std::io::Error| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to store import file".to_string(),
+            error_code: None,
+        };
+        tokio::fs::create_dir_all(&self.config.import_dir)
+            .await
+            .map_err(io_error)?;
+        tokio::fs::write(import_path(&self.config.import_dir, &job.id), data)
+            .await
+            .map_err(io_error)?;
+
+        let job = self.repo.job().add(job).await?;
+        tracing::info!(correlation_id = %ctx.correlation_id, job_id = %job.id, "Import queued");
+        Ok(job)
+    }
+
+    pub async fn get(&self, _: &Ctx, id: &str) -> Result<Job, AppError> {
+        match self.repo.job().get(id).await {
+            Ok(job) if job.kind.starts_with("import.") => Ok(job),
+            Ok(_)
+            | Err(AppError {
+                code: AppErrorCode::NotFound,
+                ..
+            }) => Err(AppError {
+                code: AppErrorCode::NotFound,
+                message: format!("Import job with id {} not found", id),
+                error_code: None,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::InMemoryRepository;
+
+    #[tokio::test]
+    async fn test_start_stores_upload() {
+        let dir = std::env::temp_dir().join("crud-import-service");
+        let repo = Arc::new(InMemoryRepository::new());
+        let config = Config {
+            import_dir: dir.to_str().unwrap().into(),
+            ..Default::default()
+        };
+        let service = ImportService::new(Arc::new(config), repo);
+        let ctx = Ctx::default();
+
+        let job = service
+            .start_items(&ctx, b"name\nbook\n".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(job.kind, IMPORT_ITEMS_JOB);
+        assert_eq!(service.get(&ctx, &job.id).await.unwrap().id, job.id);
+        let path = import_path(&dir, &job.id);
+        assert_eq!(std::fs::read(&path).unwrap(), b"name\nbook\n");
+        std::fs::remove_file(path).unwrap();
+
+        let err = service
+            .start_items(&ctx, b"title\nbook\n".to_vec())
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "file");
+    }
+}
@@ -1,107 +1,847 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
     config::Config,
+    event::{EventPublisher, publish_or_log},
+    extract::normalized,
+    import::name_column,
     model::{
-        error::{AppError, AppErrorCode},
-        item::Item,
+        context::Ctx,
+        error::{AppError, AppErrorCode, ErrorCode, FieldError},
+        event::{Event, EventAction},
+        import::{ImportResult, ImportRowError},
+        item::{ExpandedItem, Item, first_version, slugify},
+        sort::ItemSort,
+        user::User,
+    },
+    patch::{self, Patch},
+    repository::{
+        DynRepository, Repository,
+        change::ChangeRepository,
+        item::{ItemRepository, item_not_found},
+        user::{UserRepository, user_not_found},
+    },
+    search::SearchIndex,
+    service::{
+        cursor::{self, Cursor, Page},
+        user::invalid_id,
     },
-    repository::Repository,
 };
 
-pub struct ItemService {
-    repo: Arc<dyn Repository>,
+pub use crate::model::item::ListItemFilter;
+
+const ENTITY: &str = "item";
+
+/// Most results one search returns.
+pub const MAX_SEARCH_LIMIT: i64 = 100;
+
+/// Most items one bulk update renames.
+pub const MAX_BULK_UPDATE: usize = 100;
+
+/// Most rows one import creates, larger files go through import jobs.
+pub const MAX_IMPORT_ROWS: usize = 1000;
+
+/// The fields of an item a patch may change.
+#[derive(Deserialize)]
+struct ItemChanges {
+    name: String,
+}
+
+/// Numbered slugs tried, e.g. `mug-2` to `mug-10`, before a random suffix.
+const MAX_SLUG_SUFFIX: usize = 10;
+
+/// Runs `attempt` with the slug of `name` until one isn't taken.
+async fn claim_slug<F, Fut>(name: &str, mut attempt: F) -> Result<Item, AppError>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<Item, AppError>>,
+{
+    let taken = |result: &Result<Item, AppError>| matches!(result, Err(e) if e.error_code == Some(ErrorCode::SlugTaken));
+    let base = slugify(name);
+    let mut tries = 0;
+    loop {
+        let result = attempt(slug_candidate(&base, tries)).await;
+        if !taken(&result) || tries == MAX_SLUG_SUFFIX {
+            return result;
+        }
+        tries += 1;
+    }
+}
+
+/// Slug to try after `tries` taken ones: `base`, then `base-2` to
+/// `base-10` and finally a random suffix.
+fn slug_candidate(base: &str, tries: usize) -> String {
+    match tries {
+        0 => base.to_string(),
+        n if n < MAX_SLUG_SUFFIX => format!("{}-{}", base, n + 1),
+        _ => {
+            let suffix = Uuid::new_v4().simple().to_string();
+            format!("{}-{}", base, &suffix[..8])
+        }
+    }
+}
+
+/// Row of an `add_many` that failed because its slug is taken.
+fn taken_slug_row(e: &AppError) -> Option<usize> {
+    if e.error_code != Some(ErrorCode::SlugTaken) {
+        return None;
+    }
+    let errors = e.get_field_errors();
+    errors.first()?.field.strip_prefix('/')?.parse().ok()
+}
+
+/// Character class an item name may be made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Letter,
+    Digit,
+    Space,
+    Punct,
+}
+
+impl CharClass {
+    fn parse(name: &str) -> Result<Self, AppError> {
+        match name {
+            "letter" => Ok(Self::Letter),
+            "digit" => Ok(Self::Digit),
+            "space" => Ok(Self::Space),
+            "punct" => Ok(Self::Punct),
+            _ => Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                message: format!(
+                    "Unknown item name character class '{}', expected letter, digit, space or punct",
+                    name
+                ),
+                error_code: None,
+            }),
+        }
+    }
+
+    fn matches(self, c: char) -> bool {
+        match self {
+            Self::Letter => c.is_alphabetic(),
+            Self::Digit => c.is_numeric(),
+            Self::Space => c == ' ',
+            Self::Punct => c.is_ascii_punctuation(),
+        }
+    }
 }
 
-impl ItemService {
-    pub fn new(_: Arc<Config>, repo: Arc<dyn Repository>) -> Self {
-        Self { repo }
+/// Normalization and constraints applied to item names on create and update.
+#[derive(Debug, Clone)]
+pub struct ItemNameRules {
+    pub min_len: usize,
+    pub max_len: usize,
+    /// Any character is allowed when empty.
+    pub chars: Vec<CharClass>,
+    pub reserved: Vec<String>,
+    pub lowercase: bool,
+}
+
+impl Default for ItemNameRules {
+    fn default() -> Self {
+        Self {
+            min_len: 1,
+            max_len: 255,
+            chars: vec![],
+            reserved: vec![],
+            lowercase: true,
+        }
     }
+}
 
-    pub async fn get(&self, id: String) -> Result<Item, AppError> {
+impl ItemNameRules {
+    /// Fails on unknown character classes and on bounds the `name` column
+    /// can't hold.
+    pub fn from_config(config: &Config) -> Result<Self, AppError> {
+        let (min_len, max_len) = (config.item_name_min_len, config.item_name_max_len);
+        if min_len == 0 || min_len > max_len || max_len > 255 {
+            return Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                message: format!(
+                    "Invalid item name length bounds {}..={}, expected 1 <= min <= max <= 255",
+                    min_len, max_len
+                ),
+                error_code: None,
+            });
+        }
+        Ok(Self {
+            min_len,
+            max_len,
+            chars: config
+                .item_name_chars
+                .iter()
+                .map(|name| CharClass::parse(name))
+                .collect::<Result<_, _>>()?,
+            reserved: config
+                .item_name_reserved
+                .iter()
+                .map(|name| normalized(name).to_lowercase())
+                .collect(),
+            lowercase: config.item_name_lowercase,
+        })
+    }
+
+    /// Trims, NFC normalizes and, when enabled, lowercases `name`.
+    pub fn normalize(&self, name: &str) -> String {
+        let name = normalized(name);
+        if self.lowercase {
+            name.to_lowercase()
+        } else {
+            name
+        }
+    }
+
+    /// Checks an already normalized name.
+    pub fn check(&self, name: &str) -> Option<FieldError> {
+        let len = name.chars().count();
+        if len == 0 {
+            return Some(FieldError::new(
+                "name",
+                "required",
+                "Item name cannot be empty",
+            ));
+        }
+        if len < self.min_len {
+            return Some(FieldError::new(
+                "name",
+                "too_short",
+                format!("Item name must be at least {} characters", self.min_len),
+            ));
+        }
+        if len > self.max_len {
+            return Some(FieldError::new(
+                "name",
+                "too_long",
+                format!("Item name must be at most {} characters", self.max_len),
+            ));
+        }
+        if !self.chars.is_empty()
+            && !name
+                .chars()
+                .all(|c| self.chars.iter().any(|class| class.matches(c)))
+        {
+            return Some(FieldError::new(
+                "name",
+                "invalid_characters",
+                "Item name contains characters that are not allowed",
+            ));
+        }
+        if self.reserved.contains(&name.to_lowercase()) {
+            return Some(FieldError::new(
+                "name",
+                "reserved",
+                format!("Item name '{}' is reserved", name),
+            ));
+        }
+        None
+    }
+}
+
+pub struct ItemService<R: Repository + ?Sized = DynRepository> {
+    repo: Arc<R>,
+    events: Arc<dyn EventPublisher>,
+    rules: ItemNameRules,
+    search: Option<Arc<dyn SearchIndex>>,
+}
+
+impl<R: Repository + ?Sized> ItemService<R> {
+    /// Invalid name rules fall back to the defaults, `main` refuses to start
+    /// with them.
+    pub fn new(config: Arc<Config>, repo: Arc<R>, events: Arc<dyn EventPublisher>) -> Self {
+        Self {
+            repo,
+            events,
+            rules: ItemNameRules::from_config(&config).unwrap_or_default(),
+            search: None,
+        }
+    }
+
+    /// Serves searches from `index` rather than the repository.
+    pub fn with_search(mut self, index: Arc<dyn SearchIndex>) -> Self {
+        self.search = Some(index);
+        self
+    }
+
+    pub async fn get(&self, ctx: &Ctx, id: String) -> Result<Item, AppError> {
         let id = id.trim();
         if id.is_empty() {
             return Err(AppError {
                 code: AppErrorCode::InvalidInput,
                 message: "Item ID cannot be empty".to_string(),
+                error_code: Some(ErrorCode::InvalidId),
             });
         }
-        self.repo.item().get(id).await
+        ctx.with_deadline(self.repo.item().get(id)).await
     }
 
-    pub async fn list(&self) -> Result<Vec<Item>, AppError> {
-        self.repo.item().list().await
+    /// Whether item `id` exists, cheaper than [`ItemService::get`]. A blank id
+    /// never does.
+    pub async fn exists(&self, ctx: &Ctx, id: String) -> Result<bool, AppError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Ok(false);
+        }
+        ctx.with_deadline(self.repo.item().exists(id)).await
     }
 
-    pub async fn create(&self, name: String) -> Result<Item, AppError> {
-        let name = name.trim().to_lowercase();
-        if name.is_empty() {
+    pub async fn get_by_slug(&self, ctx: &Ctx, slug: String) -> Result<Item, AppError> {
+        let slug = slug.trim();
+        if slug.is_empty() {
             return Err(AppError {
                 code: AppErrorCode::InvalidInput,
-                message: "Item name cannot be empty".to_string(),
+                message: "Item slug cannot be empty".to_string(),
+                error_code: Some(ErrorCode::InvalidId),
             });
         }
+        ctx.with_deadline(self.repo.item().get_by_slug(slug)).await
+    }
+
+    /// Sorted by `sort`, one of [`ItemSortColumn`](crate::model::sort::ItemSortColumn), and `order`, by name
+    /// ascending when left out. A blank `name_contains` matches every item.
+    pub async fn list(
+        &self,
+        ctx: &Ctx,
+        sort: Option<String>,
+        order: Option<String>,
+        mut filter: ListItemFilter,
+    ) -> Result<Vec<Item>, AppError> {
+        let sort = ItemSort::parse(sort.as_deref(), order.as_deref())?;
+        filter.name_contains = filter
+            .name_contains
+            .map(|name| normalized(&name))
+            .filter(|name| !name.is_empty());
+        ctx.with_deadline(self.repo.item().list(sort, filter)).await
+    }
 
-        let new_item = Item {
-            id: Uuid::new_v4().to_string(),
-            name,
+    /// One page of items by name, see [`cursor`].
+    pub async fn list_page(
+        &self,
+        ctx: &Ctx,
+        cursor: Option<String>,
+        limit: i64,
+    ) -> Result<Page<Item>, AppError> {
+        let cursor = cursor::parse(cursor.as_deref(), limit)?;
+        let repo = self.repo.item();
+        let items = match &cursor {
+            Some(c) if c.is_backward() => {
+                let fetch = repo.list_before(Cursor::before_row(Some(c)), limit + 1);
+                ctx.with_deadline(fetch).await?
+            }
+            c => {
+                let fetch = repo.list_after(Cursor::after_row(c.as_ref()), limit + 1);
+                ctx.with_deadline(fetch).await?
+            }
         };
-        self.repo.item().add(new_item).await
+        Ok(cursor::page(items, limit, cursor.as_ref(), |item| {
+            (item.name.clone(), item.id.clone())
+        }))
     }
 
-    pub async fn update(&self, id: String, name: String) -> Result<Item, AppError> {
+    /// Rebuilds the item from the change history, not found when it didn't
+    /// exist at `at` or history doesn't reach back that far.
+    pub async fn get_as_of(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        at: DateTime<Utc>,
+    ) -> Result<Item, AppError> {
         let id = id.trim();
         if id.is_empty() {
             return Err(AppError {
                 code: AppErrorCode::InvalidInput,
                 message: "Item ID cannot be empty".to_string(),
+                error_code: Some(ErrorCode::InvalidId),
+            });
+        }
+        let change = ctx
+            .with_deadline(self.repo.change().latest(ENTITY, id, at))
+            .await?;
+        change
+            .filter(|change| change.action != EventAction::Deleted)
+            .and_then(|change| change.data)
+            .and_then(|data| serde_json::from_value(data).ok())
+            .ok_or_else(|| item_not_found(id))
+    }
+
+    /// The items that existed at `at`, as they were then, ordered by name.
+    pub async fn list_as_of(&self, ctx: &Ctx, at: DateTime<Utc>) -> Result<Vec<Item>, AppError> {
+        let changes = ctx
+            .with_deadline(self.repo.change().latest_all(ENTITY, at))
+            .await?;
+        let mut items: Vec<Item> = changes
+            .into_iter()
+            .filter_map(|change| serde_json::from_value(change.data?).ok())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        Ok(items)
+    }
+
+    /// Matches from the search index when there is one, falling back to the
+    /// repository's plain substring match while the index is unreachable.
+    pub async fn search(
+        &self,
+        ctx: &Ctx,
+        query: String,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        let query = normalized(&query);
+        let mut errors = vec![];
+        if query.is_empty() {
+            errors.push(FieldError::new(
+                "q",
+                "required",
+                "Search query cannot be empty",
+            ));
+        }
+        if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+            errors.push(FieldError::new(
+                "limit",
+                "out_of_range",
+                format!("Limit must be between 1 and {}", MAX_SEARCH_LIMIT),
+            ));
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+
+        if let Some(index) = &self.search {
+            match ctx
+                .with_deadline(index.search(&query, limit as usize))
+                .await
+            {
+                Ok(items) => return Ok(items),
+                Err(e) => tracing::warn!(
+                    correlation_id = %ctx.correlation_id,
+                    error = %e.get_error(),
+                    "Searching the index failed, falling back to the repository: {}",
+                    e.get_message()
+                ),
+            }
+        }
+        ctx.with_deadline(self.repo.item().search(&query, limit))
+            .await
+    }
+
+    pub async fn create(&self, ctx: &Ctx, name: String) -> Result<Item, AppError> {
+        self.insert(ctx, name, None).await
+    }
+
+    /// Creates an item owned by user `owner_id`, failing with
+    /// [`ErrorCode::UserNotFound`] when there's no such user.
+    pub async fn create_owned(
+        &self,
+        ctx: &Ctx,
+        owner_id: &str,
+        name: String,
+    ) -> Result<Item, AppError> {
+        if Uuid::parse_str(owner_id).is_err() {
+            return Err(invalid_id());
+        }
+        self.insert(ctx, name, Some(owner_id.to_string())).await
+    }
+
+    /// The items of user `owner_id`, by name.
+    pub async fn list_owned(&self, ctx: &Ctx, owner_id: &str) -> Result<Vec<Item>, AppError> {
+        if Uuid::parse_str(owner_id).is_err() {
+            return Err(invalid_id());
+        }
+        if !ctx.with_deadline(self.repo.user().exists(owner_id)).await? {
+            return Err(user_not_found(owner_id));
+        }
+        ctx.with_deadline(self.repo.item().list_by_owner(owner_id))
+            .await
+    }
+
+    /// Embeds the owner of each of `items`, looking them all up at once
+    /// rather than item by item.
+    pub async fn with_owners(
+        &self,
+        ctx: &Ctx,
+        items: Vec<Item>,
+    ) -> Result<Vec<ExpandedItem>, AppError> {
+        let mut ids: Vec<String> = items
+            .iter()
+            .filter_map(|item| item.owner_id.clone())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        let owners: HashMap<String, User> = if ids.is_empty() {
+            HashMap::new()
+        } else {
+            ctx.with_deadline(self.repo.user().get_many(&ids))
+                .await?
+                .into_iter()
+                .map(|user| (user.id.clone(), user))
+                .collect()
+        };
+        Ok(items
+            .into_iter()
+            .map(|item| {
+                let owner = item
+                    .owner_id
+                    .as_ref()
+                    .and_then(|id| owners.get(id))
+                    .cloned();
+                ExpandedItem { item, owner }
+            })
+            .collect())
+    }
+
+    async fn insert(
+        &self,
+        ctx: &Ctx,
+        name: String,
+        owner_id: Option<String>,
+    ) -> Result<Item, AppError> {
+        let name = self.rules.normalize(&name);
+        if let Some(error) = self.rules.check(&name) {
+            return Err(AppError::validation(vec![error]));
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let items = self.repo.item();
+        let item = claim_slug(&name, |slug| {
+            ctx.with_deadline(items.add(Item {
+                id: id.clone(),
+                name: name.clone(),
+                slug,
+                owner_id: owner_id.clone(),
+                version: first_version(),
+            }))
+        })
+        .await?;
+        publish_or_log(
+            self.events.as_ref(),
+            Event::new(ENTITY, EventAction::Created, &item.id, Some(&item)),
+        )
+        .await;
+        tracing::info!(correlation_id = %ctx.correlation_id, item_id = %item.id, "Item created");
+        Ok(item)
+    }
+
+    /// Renames the item, only while it's at `version` when given.
+    pub async fn update(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        name: String,
+        version: Option<i64>,
+    ) -> Result<Item, AppError> {
+        let id = id.trim();
+        let name = self.rules.normalize(&name);
+        let mut errors = vec![];
+        if id.is_empty() {
+            errors.push(FieldError::new("id", "required", "Item ID cannot be empty"));
+        }
+        errors.extend(self.rules.check(&name));
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+
+        let item = ctx
+            .with_deadline(self.repo.item().update(id, name, version))
+            .await?;
+        publish_or_log(
+            self.events.as_ref(),
+            Event::new(ENTITY, EventAction::Updated, &item.id, Some(&item)),
+        )
+        .await;
+        tracing::info!(correlation_id = %ctx.correlation_id, item_id = %item.id, "Item updated");
+        Ok(item)
+    }
+
+    /// Renames every `(id, name)` at once, either all of them are updated or
+    /// none is. Errors point at the offending update by its index, e.g.
+    /// `/2/name`.
+    pub async fn bulk_update(
+        &self,
+        ctx: &Ctx,
+        updates: Vec<(String, String)>,
+    ) -> Result<Vec<Item>, AppError> {
+        if updates.is_empty() {
+            return Err(AppError::validation(vec![FieldError::new(
+                "/",
+                "required",
+                "At least one update is required",
+            )]));
+        }
+        if updates.len() > MAX_BULK_UPDATE {
+            return Err(AppError::validation(vec![FieldError::new(
+                "/",
+                "too_many",
+                format!("At most {} items can be updated at once", MAX_BULK_UPDATE),
+            )]));
+        }
+
+        let mut errors = vec![];
+        let mut normalized = Vec::with_capacity(updates.len());
+        for (i, (id, name)) in updates.into_iter().enumerate() {
+            let id = id.trim().to_string();
+            let name = self.rules.normalize(&name);
+            if id.is_empty() {
+                errors.push(FieldError::new(
+                    &format!("/{}/id", i),
+                    "required",
+                    "Item ID cannot be empty",
+                ));
+            } else if normalized.iter().any(|(seen, _)| *seen == id) {
+                errors.push(FieldError::new(
+                    &format!("/{}/id", i),
+                    "duplicate",
+                    format!("Item {} is updated more than once", id),
+                ));
+            }
+            if let Some(error) = self.rules.check(&name) {
+                errors.push(FieldError {
+                    field: format!("/{}/name", i),
+                    ..error
+                });
+            }
+            normalized.push((id, name));
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+
+        let items = ctx
+            .with_deadline(self.repo.item().update_many(normalized))
+            .await?;
+        for item in &items {
+            publish_or_log(
+                self.events.as_ref(),
+                Event::new(ENTITY, EventAction::Updated, &item.id, Some(item)),
+            )
+            .await;
+        }
+        tracing::info!(correlation_id = %ctx.correlation_id, count = items.len(), "Items updated");
+        Ok(items)
+    }
+
+    /// Creates an item per row of a CSV with a `name` column, all of them or
+    /// none. Errors point at the offending row counting data rows from 1,
+    /// e.g. `/2/name`. A dry run only checks the rows and reports the errors
+    /// rather than failing with them.
+    pub async fn import(
+        &self,
+        ctx: &Ctx,
+        data: &[u8],
+        dry_run: bool,
+    ) -> Result<ImportResult, AppError> {
+        if data.is_empty() {
+            return Err(AppError::validation(vec![FieldError::new(
+                "file",
+                "required",
+                "CSV file cannot be empty",
+            )]));
+        }
+        let column = name_column(data)?;
+        let mut names = vec![];
+        let mut errors = vec![];
+        let mut rows = 0;
+        for (i, record) in csv::Reader::from_reader(data).into_records().enumerate() {
+            let row = i as u64 + 1;
+            rows += 1;
+            match record {
+                Ok(record) => {
+                    let name = self.rules.normalize(record.get(column).unwrap_or_default());
+                    if let Some(error) = self.rules.check(&name) {
+                        let field = format!("/{}/name", row);
+                        errors.push((row, FieldError { field, ..error }));
+                    }
+                    names.push(name);
+                }
+                Err(e) => errors.push((
+                    row,
+                    FieldError::new(
+                        &format!("/{}", row),
+                        "invalid",
+                        format!("Invalid CSV row: {}", e),
+                    ),
+                )),
+            }
+        }
+        if rows == 0 {
+            return Err(AppError::validation(vec![FieldError::new(
+                "file",
+                "required",
+                "CSV file has no rows",
+            )]));
+        }
+        if rows > MAX_IMPORT_ROWS {
+            return Err(AppError::validation(vec![FieldError::new(
+                "file",
+                "too_many",
+                format!(
+                    "At most {} rows can be imported at once, use an import job for more",
+                    MAX_IMPORT_ROWS
+                ),
+            )]));
+        }
+
+        if dry_run {
+            return Ok(ImportResult {
+                rows_processed: rows as u64,
+                rows_failed: errors.len() as u64,
+                errors: errors
+                    .into_iter()
+                    .map(|(row, e)| ImportRowError {
+                        row,
+                        message: e.message,
+                    })
+                    .collect(),
             });
         }
+        if !errors.is_empty() {
+            return Err(AppError::validation(
+                errors.into_iter().map(|(_, e)| e).collect(),
+            ));
+        }
 
-        let name = name.trim().to_lowercase();
-        if name.is_empty() {
+        let items = self.add_all(ctx, names).await?;
+        for item in &items {
+            publish_or_log(
+                self.events.as_ref(),
+                Event::new(ENTITY, EventAction::Created, &item.id, Some(item)),
+            )
+            .await;
+        }
+        tracing::info!(correlation_id = %ctx.correlation_id, count = items.len(), "Items imported");
+        Ok(ImportResult {
+            rows_processed: items.len() as u64,
+            ..Default::default()
+        })
+    }
+
+    /// Adds an item per name in one go, numbering taken slugs like
+    /// [`claim_slug`] does.
+    async fn add_all(&self, ctx: &Ctx, names: Vec<String>) -> Result<Vec<Item>, AppError> {
+        let mut items: Vec<Item> = names
+            .into_iter()
+            .map(|name| Item {
+                id: Uuid::new_v4().to_string(),
+                slug: slugify(&name),
+                name,
+                owner_id: None,
+                version: first_version(),
+            })
+            .collect();
+        let mut tries = vec![0; items.len()];
+        loop {
+            let result = ctx
+                .with_deadline(self.repo.item().add_many(items.clone()))
+                .await;
+            match result.as_ref().err().and_then(taken_slug_row) {
+                Some(i) if tries[i] < MAX_SLUG_SUFFIX => {
+                    tries[i] += 1;
+                    items[i].slug = slug_candidate(&slugify(&items[i].name), tries[i]);
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    /// Applies `patch` to the stored item, then updates it like
+    /// [`ItemService::update`]. The id and slug can't be patched.
+    pub async fn patch(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        patch: Patch,
+        version: Option<i64>,
+    ) -> Result<Item, AppError> {
+        let item = self.get(ctx, id).await?;
+        let changes: ItemChanges = patch::patched(&item, &patch, &["id", "slug", "version"])?;
+        self.update(ctx, item.id, changes.name, version).await
+    }
+
+    /// Derives the slug again from the current name, for items renamed since
+    /// they were created. Slugs that already match the name are kept.
+    pub async fn regenerate_slug(&self, ctx: &Ctx, id: String) -> Result<Item, AppError> {
+        let id = id.trim();
+        if id.is_empty() {
             return Err(AppError {
                 code: AppErrorCode::InvalidInput,
-                message: "Item name cannot be empty".to_string(),
+                message: "Item ID cannot be empty".to_string(),
+                error_code: Some(ErrorCode::InvalidId),
             });
         }
-
-        self.repo.item().update(id, name).await
+        let items = self.repo.item();
+        let item = ctx.with_deadline(items.get(id)).await?;
+        if item.slug == slugify(&item.name) {
+            return Ok(item);
+        }
+        let item = claim_slug(&item.name, |slug| {
+            ctx.with_deadline(items.set_slug(id, slug))
+        })
+        .await?;
+        publish_or_log(
+            self.events.as_ref(),
+            Event::new(ENTITY, EventAction::Updated, &item.id, Some(&item)),
+        )
+        .await;
+        tracing::info!(correlation_id = %ctx.correlation_id, item_id = %item.id, slug = %item.slug, "Item slug regenerated");
+        Ok(item)
     }
 
-    pub async fn delete(&self, id: String) -> Result<(), AppError> {
+    /// Deletes the item, returning it unless there was none.
+    pub async fn delete(&self, ctx: &Ctx, id: String) -> Result<Option<Item>, AppError> {
         let id = id.trim();
         if id.is_empty() {
             return Err(AppError {
                 code: AppErrorCode::InvalidInput,
                 message: "Item ID cannot be empty".to_string(),
+                error_code: Some(ErrorCode::InvalidId),
             });
         }
 
-        self.repo.item().delete(id).await
+        let deleted = ctx.with_deadline(self.repo.item().delete(id)).await?;
+        // Deleting an unknown id is fine, but there's nothing to announce.
+        if deleted.is_some() {
+            publish_or_log(
+                self.events.as_ref(),
+                Event::new::<Item>(ENTITY, EventAction::Deleted, id, None),
+            )
+            .await;
+            tracing::info!(correlation_id = %ctx.correlation_id, item_id = %id, "Item deleted");
+        }
+        Ok(deleted)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::repository::{
-        item::MockItemRepository, registry::MockPostgresRepository, user::MockUserRepository,
+    use crate::{
+        event::{MockEventPublisher, NoopPublisher},
+        model::user::User,
+        repository::{
+            item::MockItemRepository,
+            registry::{InMemoryRepository, MockRepository},
+            user::{MockUserRepository, UserRepository},
+        },
+        search::MockSearchIndex,
     };
 
     use super::*;
 
-    fn make_service(mock_item_repo: Arc<MockItemRepository>) -> ItemService {
+    fn make_service(mock_item_repo: Arc<MockItemRepository>) -> ItemService<MockRepository> {
         let mock_user_repo = Arc::new(MockUserRepository::new());
-        let mut mock_repo = MockPostgresRepository::new();
+        let mut mock_repo = MockRepository::new();
         mock_repo
             .expect_user()
             .returning(move || mock_user_repo.clone());
         mock_repo
             .expect_item()
             .returning(move || mock_item_repo.clone());
-        ItemService::new(Arc::new(Config::default()), Arc::new(mock_repo))
+        ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(mock_repo),
+            Arc::new(NoopPublisher),
+        )
     }
 
     #[tokio::test]
@@ -115,12 +855,73 @@ mod tests {
 
         let service = make_service(Arc::new(mock_item_repo));
         let item = service
-            .create("Test Item".to_string())
+            .create(&Ctx::default(), "Test Item".to_string())
             .await
             .expect("failed to create item");
         assert_eq!(item.name, "test item");
     }
 
+    #[tokio::test]
+    async fn test_create_item_applies_name_rules() {
+        let mut mock_item_repo = MockItemRepository::new();
+        mock_item_repo
+            .expect_add()
+            .withf(|item: &Item| item.name == "Desk 2")
+            .returning(|item| Box::pin(async move { Ok(item) }));
+        let mock_repo = {
+            let mock_item_repo = Arc::new(mock_item_repo);
+            let mut mock_repo = MockRepository::new();
+            mock_repo
+                .expect_item()
+                .returning(move || mock_item_repo.clone());
+            mock_repo
+        };
+        let config = Config {
+            item_name_min_len: 3,
+            item_name_max_len: 10,
+            item_name_chars: vec!["letter".into(), "digit".into(), "space".into()],
+            item_name_reserved: vec!["Admin".into()],
+            item_name_lowercase: false,
+            ..Default::default()
+        };
+        let service = ItemService::new(
+            Arc::new(config),
+            Arc::new(mock_repo),
+            Arc::new(NoopPublisher),
+        );
+
+        let item = service.create(&Ctx::default(), " Desk 2 ".into()).await;
+        assert_eq!(item.unwrap().name, "Desk 2");
+        for (name, code) in [
+            ("ab", "too_short"),
+            ("a very long name", "too_long"),
+            ("desk-2", "invalid_characters"),
+            ("ADMIN", "reserved"),
+        ] {
+            let err = service.create(&Ctx::default(), name.into()).await;
+            assert_eq!(
+                err.unwrap_err().get_field_errors()[0].code,
+                code,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_name_rules_reject_bad_config() {
+        let config = |chars: &str, min, max| Config {
+            item_name_chars: vec![chars.into()],
+            item_name_min_len: min,
+            item_name_max_len: max,
+            ..Default::default()
+        };
+        assert!(ItemNameRules::from_config(&config("letter", 1, 255)).is_ok());
+        assert!(ItemNameRules::from_config(&config("emoji", 1, 255)).is_err());
+        assert!(ItemNameRules::from_config(&config("letter", 5, 4)).is_err());
+        assert!(ItemNameRules::from_config(&config("letter", 1, 300)).is_err());
+    }
+
     #[tokio::test]
     async fn test_get_item() {
         let mut mock_item_repo = MockItemRepository::new();
@@ -128,6 +929,9 @@ mod tests {
         let item = Item {
             id: "123".to_string(),
             name: "test item".to_string(),
+            slug: "test-item".to_string(),
+            owner_id: None,
+            version: 1,
         };
         mock_item_repo
             .expect_get()
@@ -142,7 +946,7 @@ mod tests {
         let service = make_service(Arc::new(mock_item_repo));
 
         let fetched_item = service
-            .get("123".to_string())
+            .get(&Ctx::default(), "123".to_string())
             .await
             .expect("failed to get item");
         assert_eq!(fetched_item.id, "123");
@@ -157,13 +961,19 @@ mod tests {
             Item {
                 id: "1".to_string(),
                 name: "item one".to_string(),
+                slug: "item-one".to_string(),
+                owner_id: None,
+                version: 1,
             },
             Item {
                 id: "2".to_string(),
                 name: "item two".to_string(),
+                slug: "item-two".to_string(),
+                owner_id: None,
+                version: 1,
             },
         ];
-        mock_item_repo.expect_list().returning(move || {
+        mock_item_repo.expect_list().returning(move |_, _| {
             Box::pin({
                 let value = items.clone();
                 async move { Ok(value.clone()) }
@@ -171,7 +981,10 @@ mod tests {
         });
         let service = make_service(Arc::new(mock_item_repo));
 
-        let fetched_items = service.list().await.expect("failed to list items");
+        let fetched_items = service
+            .list(&Ctx::default(), None, None, ListItemFilter::default())
+            .await
+            .expect("failed to list items");
         assert_eq!(fetched_items.len(), 2);
         assert_eq!(fetched_items[0].name, "item one");
         assert_eq!(fetched_items[1].name, "item two");
@@ -184,11 +997,14 @@ mod tests {
         let item = Item {
             id: "123".to_string(),
             name: "updated item".to_string(),
+            slug: "updated-item".to_string(),
+            owner_id: None,
+            version: 1,
         };
         mock_item_repo
             .expect_update()
-            .withf(|id, name| id == "123" && name == "updated item")
-            .returning(move |_, _| {
+            .withf(|id, name, version| id == "123" && name == "updated item" && version.is_none())
+            .returning(move |_, _, _| {
                 Box::pin({
                     let value = item.clone();
                     async move { Ok(value.clone()) }
@@ -198,7 +1014,12 @@ mod tests {
         let service = make_service(Arc::new(mock_item_repo));
 
         let updated_item = service
-            .update("123".to_string(), "Updated Item".to_string())
+            .update(
+                &Ctx::default(),
+                "123".to_string(),
+                "Updated Item".to_string(),
+                None,
+            )
             .await
             .expect("failed to update item");
         assert_eq!(updated_item.id, "123");
@@ -212,11 +1033,620 @@ mod tests {
         mock_item_repo
             .expect_delete()
             .withf(|id| id == "123")
-            .returning(move |_| Box::pin(async move { Ok(()) }));
+            .returning(move |_| Box::pin(async move { Ok(None) }));
 
         let service = make_service(Arc::new(mock_item_repo));
 
-        let result = service.delete("123".to_string()).await;
+        let result = service.delete(&Ctx::default(), "123".to_string()).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_delete_publishes_only_deleted() {
+        let mut mock_events = MockEventPublisher::new();
+        mock_events
+            .expect_publish()
+            .withf(|event: &Event| event.topic() == "item.deleted")
+            .times(1)
+            .returning(|_| Box::pin(async move { Ok(()) }));
+        let repo = Arc::new(InMemoryRepository::new());
+        repo.item.items.lock().unwrap().push(Item {
+            id: "1".into(),
+            name: "book".into(),
+            slug: "book".into(),
+            owner_id: None,
+            version: 1,
+        });
+        let service = ItemService::new(Arc::new(Config::default()), repo, Arc::new(mock_events));
+        let ctx = Ctx::default();
+
+        assert!(service.delete(&ctx, "1".into()).await.unwrap().is_some());
+        assert!(service.delete(&ctx, "1".into()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_item_publishes_event() {
+        let mut mock_item_repo = MockItemRepository::new();
+        mock_item_repo
+            .expect_add()
+            .returning(|item| Box::pin(async move { Ok(item) }));
+
+        let mut mock_events = MockEventPublisher::new();
+        mock_events
+            .expect_publish()
+            .withf(|event: &Event| event.topic() == "item.created")
+            .times(1)
+            .returning(|_| Box::pin(async move { Ok(()) }));
+
+        let mock_user_repo = Arc::new(MockUserRepository::new());
+        let mock_item_repo = Arc::new(mock_item_repo);
+        let mut mock_repo = MockRepository::new();
+        mock_repo
+            .expect_user()
+            .returning(move || mock_user_repo.clone());
+        mock_repo
+            .expect_item()
+            .returning(move || mock_item_repo.clone());
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(mock_repo),
+            Arc::new(mock_events),
+        );
+
+        let item = service
+            .create(&Ctx::default(), "Test Item".to_string())
+            .await
+            .expect("failed to create item");
+        assert_eq!(item.name, "test item");
+    }
+
+    #[tokio::test]
+    async fn test_slugs() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        let mug = service.create(&ctx, "Blue Mug".into()).await.unwrap();
+        assert_eq!(mug.slug, "blue-mug");
+        let other = service.create(&ctx, "blue-mug".into()).await.unwrap();
+        assert_eq!(other.slug, "blue-mug-2");
+        let found = service.get_by_slug(&ctx, "blue-mug".into()).await.unwrap();
+        assert_eq!(found, mug);
+
+        let cup = service
+            .update(&ctx, mug.id.clone(), "Red Cup".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(cup.slug, "blue-mug");
+        let cup = service.regenerate_slug(&ctx, mug.id).await.unwrap();
+        assert_eq!(cup.slug, "red-cup");
+        // The freed slug is the plain one again.
+        let other = service.regenerate_slug(&ctx, other.id).await.unwrap();
+        assert_eq!(other.slug, "blue-mug");
+    }
+
+    #[tokio::test]
+    async fn test_versions() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        let mug = service.create(&ctx, "mug".into()).await.unwrap();
+        assert_eq!(mug.version, 1);
+        let cup = service
+            .update(&ctx, mug.id.clone(), "cup".into(), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(cup.version, 2);
+
+        let err = service
+            .update(&ctx, mug.id.clone(), "jug".into(), Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::PreconditionFailed));
+        let patch = Patch::Merge(serde_json::json!({"name": "jug"}));
+        let err = service
+            .patch(&ctx, mug.id.clone(), patch, Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::PreconditionFailed));
+        // The version only moves with updates.
+        let patch = Patch::Merge(serde_json::json!({"version": 7}));
+        let err = service
+            .patch(&ctx, mug.id.clone(), patch, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::Validation(_)));
+        assert_eq!(service.get(&ctx, mug.id).await.unwrap(), cup);
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let ctx = Ctx::default();
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            repo.clone(),
+            Arc::new(NoopPublisher),
+        );
+        for name in ["Notebook", "Book", "pen"] {
+            service.create(&ctx, name.into()).await.unwrap();
+        }
+        let found = service.search(&ctx, " BOOK ".into(), 10).await.unwrap();
+        let names: Vec<_> = found.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["book", "notebook"]);
+        let err = service.search(&ctx, "".into(), 0).await.unwrap_err();
+        let fields: Vec<_> = err
+            .get_field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["q", "limit"]);
+
+        // The index answers when it can, the repository when it can't.
+        let mut index = MockSearchIndex::new();
+        let mut calls = 0;
+        index.expect_search().times(2).returning(move |query, _| {
+            calls += 1;
+            let result = if calls == 1 {
+                Ok(vec![Item {
+                    id: "1".into(),
+                    name: format!("{} (indexed)", query),
+                    slug: "indexed".into(),
+                    owner_id: None,
+                    version: 1,
+                }])
+            } else {
+                Err(AppError {
+                    code: AppErrorCode::Unavailable,
+                    message: "down".into(),
+                    error_code: None,
+                })
+            };
+            Box::pin(async move { result })
+        });
+        let service = service.with_search(Arc::new(index));
+        let found = service.search(&ctx, "bok".into(), 10).await.unwrap();
+        assert_eq!(found[0].name, "bok (indexed)");
+        let found = service.search(&ctx, "pen".into(), 10).await.unwrap();
+        assert_eq!(found[0].name, "pen");
+    }
+
+    #[tokio::test]
+    async fn test_as_of() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let ctx = Ctx::default();
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            repo.clone(),
+            Arc::new(NoopPublisher),
+        );
+        let before = Utc::now();
+        let mug = service.create(&ctx, "mug".into()).await.unwrap();
+        let pen = service.create(&ctx, "pen".into()).await.unwrap();
+        let created = Utc::now();
+        service
+            .update(&ctx, mug.id.clone(), "cup".into(), None)
+            .await
+            .unwrap();
+        service.delete(&ctx, pen.id.clone()).await.unwrap();
+
+        let old = service
+            .get_as_of(&ctx, mug.id.clone(), created)
+            .await
+            .unwrap();
+        assert_eq!(old, mug);
+        let names = |items: Vec<Item>| items.into_iter().map(|i| i.name).collect::<Vec<_>>();
+        let items = service.list_as_of(&ctx, created).await.unwrap();
+        assert_eq!(names(items), vec!["mug", "pen"]);
+        let items = service.list_as_of(&ctx, Utc::now()).await.unwrap();
+        assert_eq!(names(items), vec!["cup"]);
+
+        for (id, at) in [(&mug.id, before), (&pen.id, Utc::now())] {
+            let err = service.get_as_of(&ctx, id.clone(), at).await.unwrap_err();
+            assert!(matches!(err.code, AppErrorCode::NotFound));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_holds_only_committed_writes() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let ctx = Ctx::default();
+        let mut events = MockEventPublisher::new();
+        events.expect_publish().returning(|_| {
+            Box::pin(async {
+                Err(AppError {
+                    code: AppErrorCode::InternalError("down".into()),
+                    message: "Failed to publish".into(),
+                    error_code: None,
+                })
+            })
+        });
+        let service = ItemService::new(Arc::new(Config::default()), repo.clone(), Arc::new(events));
+
+        // Recorded although no event got out.
+        let mug = service.create(&ctx, "mug".into()).await.unwrap();
+        assert!(service.create(&ctx, "MUG".into()).await.is_err());
+
+        let changes = repo.change.since(0, 10).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].entity_id, mug.id);
+        assert_eq!(changes[0].action, EventAction::Created);
+    }
+
+    #[tokio::test]
+    async fn test_list_page() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        for name in ["pen", "cup", "mug", "box", "ink"] {
+            service.create(&ctx, name.into()).await.unwrap();
+        }
+
+        let mut names = vec![];
+        let mut cursor = None;
+        loop {
+            let page = service.list_page(&ctx, cursor, 2).await.unwrap();
+            assert!(page.rows.len() <= 2);
+            names.extend(page.rows.into_iter().map(|item| item.name));
+            cursor = page.next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(names, vec!["box", "cup", "ink", "mug", "pen"]);
+
+        // Back from the last page, through the same pages in reverse.
+        let mut pages = vec![];
+        let mut cursor = service.list_page(&ctx, None, 2).await.unwrap().last;
+        while let Some(c) = cursor {
+            let page = service.list_page(&ctx, Some(c), 2).await.unwrap();
+            pages.push(
+                page.rows
+                    .into_iter()
+                    .map(|item| item.name)
+                    .collect::<Vec<_>>(),
+            );
+            cursor = page.prev;
+        }
+        assert_eq!(
+            pages,
+            vec![vec!["mug", "pen"], vec!["cup", "ink"], vec!["box"]]
+        );
+
+        let err = service
+            .list_page(&ctx, Some("bad".into()), 2)
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "cursor");
+    }
+
+    #[tokio::test]
+    async fn test_list_sorted() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        for name in ["Blue Mug", "cup", "ant"] {
+            service.create(&ctx, name.into()).await.unwrap();
+        }
+        let names = |items: Vec<Item>| items.into_iter().map(|i| i.name).collect::<Vec<_>>();
+
+        let items = service
+            .list(&ctx, None, None, ListItemFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(names(items), vec!["ant", "blue mug", "cup"]);
+        let items = service
+            .list(
+                &ctx,
+                Some("slug".into()),
+                Some("desc".into()),
+                ListItemFilter::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(names(items), vec!["cup", "blue mug", "ant"]);
+        let err = service
+            .list(&ctx, Some("price".into()), None, ListItemFilter::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "sort");
+    }
+
+    #[tokio::test]
+    async fn test_list_filtered() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        service.create(&ctx, "Blue Mug".into()).await.unwrap();
+        let start = Utc::now();
+        service.create(&ctx, "mug stand".into()).await.unwrap();
+        service.create(&ctx, "cup".into()).await.unwrap();
+        let list = |filter| async { service.list(&ctx, None, None, filter).await.unwrap() };
+
+        let items = list(ListItemFilter {
+            name_contains: Some(" MUG ".into()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(items.len(), 2);
+        let items = list(ListItemFilter {
+            name_contains: Some("mug".into()),
+            created_after: Some(start),
+        })
+        .await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "mug stand");
+        let items = list(ListItemFilter {
+            name_contains: Some(" ".into()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(items.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_patch() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        let item = service.create(&ctx, "book".into()).await.unwrap();
+
+        let patch = Patch::Merge(serde_json::json!({"name": "Notebook", "id": item.id}));
+        let patched = service
+            .patch(&ctx, item.id.clone(), patch, None)
+            .await
+            .unwrap();
+        assert_eq!(patched.name, "notebook");
+        assert_eq!(patched.slug, "book");
+
+        let patch = Patch::Merge(serde_json::json!({"slug": "other"}));
+        let err = service
+            .patch(&ctx, item.id.clone(), patch, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "slug");
+        let ops = serde_json::json!([{"op": "replace", "path": "/name", "value": ""}]);
+        let patch = Patch::Json(serde_json::from_value(ops).unwrap());
+        assert!(service.patch(&ctx, item.id, patch, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update() {
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        let book = service.create(&ctx, "book".into()).await.unwrap();
+        let pen = service.create(&ctx, "pen".into()).await.unwrap();
+
+        let items = service
+            .bulk_update(
+                &ctx,
+                vec![
+                    (book.id.clone(), "Notebook".into()),
+                    (pen.id.clone(), "pencil".into()),
+                ],
+            )
+            .await
+            .unwrap();
+        let names: Vec<&str> = items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["notebook", "pencil"]);
+
+        let err = service
+            .bulk_update(
+                &ctx,
+                vec![
+                    (book.id.clone(), "".into()),
+                    (book.id.clone(), "ink".into()),
+                ],
+            )
+            .await
+            .unwrap_err();
+        let fields: Vec<String> = err
+            .get_field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, ["/0/name", "/1/id"]);
+
+        // The first rename is rolled back along with the missing item.
+        let err = service
+            .bulk_update(
+                &ctx,
+                vec![
+                    (book.id.clone(), "book".into()),
+                    ("missing".into(), "ink".into()),
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::Unprocessable(_)));
+        assert_eq!(err.get_field_errors()[0].field, "/1");
+        assert_eq!(err.get_field_errors()[0].code, "not_found");
+        let book = service.get(&ctx, book.id).await.unwrap();
+        assert_eq!(book.name, "notebook");
+
+        assert!(service.bulk_update(&ctx, vec![]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            repo.clone(),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        service.create(&ctx, "pen-case".into()).await.unwrap();
+        let csv = b"id,name\n1,Book\n2,\n3,Pen case\n";
+
+        let report = service.import(&ctx, csv, true).await.unwrap();
+        assert_eq!(report.rows_processed, 3);
+        assert_eq!(report.rows_failed, 1);
+        assert_eq!(report.errors[0].row, 2);
+        let err = service.import(&ctx, csv, false).await.unwrap_err();
+        assert_eq!(err.get_field_errors()[0].field, "/2/name");
+        assert_eq!(
+            service.list_page(&ctx, None, 10).await.unwrap().rows.len(),
+            1
+        );
+
+        let result = service
+            .import(&ctx, b"name\nBook\nPen case\n", false)
+            .await
+            .unwrap();
+        assert_eq!(result.rows_processed, 2);
+        let pen_case = service
+            .get_by_slug(&ctx, "pen-case-2".into())
+            .await
+            .unwrap();
+        assert_eq!(pen_case.name, "pen case");
+
+        assert!(service.import(&ctx, b"name\n", false).await.is_err());
+        assert!(service.import(&ctx, b"title\nbook\n", true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_owned() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            repo.clone(),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        let owner = Uuid::new_v4().to_string();
+        repo.user
+            .add(User {
+                id: owner.clone(),
+                email: "a@b.com".into(),
+                version: 1,
+            })
+            .await
+            .unwrap();
+        service.create(&ctx, "pen".into()).await.unwrap();
+
+        let mug = service
+            .create_owned(&ctx, &owner, "mug".into())
+            .await
+            .unwrap();
+        assert_eq!(mug.owner_id.as_deref(), Some(owner.as_str()));
+        assert_eq!(service.list_owned(&ctx, &owner).await.unwrap(), vec![mug]);
+
+        let stranger = Uuid::new_v4().to_string();
+        let err = service
+            .create_owned(&ctx, &stranger, "cup".into())
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code, Some(ErrorCode::UserNotFound));
+        let err = service.list_owned(&ctx, &stranger).await.unwrap_err();
+        assert_eq!(err.error_code, Some(ErrorCode::UserNotFound));
+        let err = service.list_owned(&ctx, "nope").await.unwrap_err();
+        assert_eq!(err.error_code, Some(ErrorCode::InvalidId));
+    }
+
+    #[tokio::test]
+    async fn test_with_owners_looks_them_up_at_once() {
+        let owner = User {
+            id: "u1".into(),
+            email: "a@b.com".into(),
+            version: 1,
+        };
+        let found = owner.clone();
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_get_many()
+            .times(1)
+            .withf(|ids| ids == ["u1".to_string()])
+            .returning(move |_| {
+                let found = found.clone();
+                Box::pin(async move { Ok(vec![found]) })
+            });
+        let mock_user_repo = Arc::new(mock_user_repo);
+        let mut mock_repo = MockRepository::new();
+        mock_repo
+            .expect_user()
+            .returning(move || mock_user_repo.clone());
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            Arc::new(mock_repo),
+            Arc::new(NoopPublisher),
+        );
+        let item = |id: &str, owner_id: Option<&str>| Item {
+            id: id.into(),
+            name: id.into(),
+            slug: id.into(),
+            owner_id: owner_id.map(String::from),
+            version: 1,
+        };
+
+        let items = service
+            .with_owners(
+                &Ctx::default(),
+                vec![
+                    item("1", Some("u1")),
+                    item("2", Some("u1")),
+                    item("3", None),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(items[0].owner.as_ref(), Some(&owner));
+        assert_eq!(items[1].owner.as_ref(), Some(&owner));
+        assert_eq!(items[2].owner, None);
+    }
+
+    #[tokio::test]
+    async fn test_create_taken_name() {
+        let mut mock_events = MockEventPublisher::new();
+        mock_events
+            .expect_publish()
+            .times(1)
+            .returning(|_| Box::pin(async move { Ok(()) }));
+        let repo = Arc::new(InMemoryRepository::new());
+        let service = ItemService::new(
+            Arc::new(Config::default()),
+            repo.clone(),
+            Arc::new(mock_events),
+        );
+        let ctx = Ctx::default();
+        let owner = Uuid::new_v4().to_string();
+        repo.user
+            .add(User {
+                id: owner.clone(),
+                email: "a@b.com".into(),
+                version: 1,
+            })
+            .await
+            .unwrap();
+        service.create(&ctx, "mug".into()).await.unwrap();
+
+        // Someone else's item is neither handed out nor announced again.
+        let err = service
+            .create_owned(&ctx, &owner, "MUG".into())
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code, Some(ErrorCode::NameTaken));
+        assert!(service.list_owned(&ctx, &owner).await.unwrap().is_empty());
+    }
 }
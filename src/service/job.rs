@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use crate::{
+    config::Config,
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode, FieldError},
+        job::{Job, JobStatus, NewJob},
+    },
+    repository::{DynRepository, Repository, job::JobRepository},
+};
+
+/// Most jobs a single admin listing returns.
+pub const MAX_LIST_LIMIT: i64 = 500;
+
+pub struct JobService<R: Repository + ?Sized = DynRepository> {
+    repo: Arc<R>,
+}
+
+impl<R: Repository + ?Sized> JobService<R> {
+    pub fn new(_: Arc<Config>, repo: Arc<R>) -> Self {
+        Self { repo }
+    }
+
+    /// Queues `job` for the workers, the caller gets it back as stored.
+    pub async fn enqueue(&self, ctx: &Ctx, job: NewJob) -> Result<Job, AppError> {
+        let kind = job.kind.trim().to_string();
+        let mut errors = vec![];
+        if kind.is_empty() {
+            errors.push(FieldError::new(
+                "kind",
+                "required",
+                "Job kind cannot be empty",
+            ));
+        }
+        if job.max_attempts < 1 {
+            errors.push(FieldError::new(
+                "max_attempts",
+                "range",
+                "Job needs at least one attempt",
+            ));
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+
+        let job = self
+            .repo
+            .job()
+            .add(Job::from(NewJob { kind, ..job }))
+            .await?;
+        tracing::info!(correlation_id = %ctx.correlation_id, job_id = %job.id, kind = %job.kind, "Job enqueued");
+        Ok(job)
+    }
+
+    pub async fn list(
+        &self,
+        _: &Ctx,
+        status: Option<JobStatus>,
+        limit: i64,
+    ) -> Result<Vec<Job>, AppError> {
+        if !(1..=MAX_LIST_LIMIT).contains(&limit) {
+            return Err(AppError::validation(vec![FieldError::new(
+                "limit",
+                "range",
+                format!("Limit must be between 1 and {}", MAX_LIST_LIMIT),
+            )]));
+        }
+        self.repo.job().list(status, limit).await
+    }
+
+    pub async fn get(&self, _: &Ctx, id: &str) -> Result<Job, AppError> {
+        self.repo.job().get(id).await
+    }
+
+    /// Requeues a failed or cancelled job, other states are a conflict.
+    pub async fn retry(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        let repo = self.repo.job();
+        match repo.retry(id).await? {
+            Some(job) => {
+                tracing::info!(correlation_id = %ctx.correlation_id, job_id = %job.id, "Job retried");
+                Ok(job)
+            }
+            None => Err(invalid_transition(repo.get(id).await?, "retried")),
+        }
+    }
+
+    /// Cancels a pending job, other states are a conflict.
+    pub async fn cancel(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        let repo = self.repo.job();
+        match repo.cancel(id).await? {
+            Some(job) => {
+                tracing::info!(correlation_id = %ctx.correlation_id, job_id = %job.id, "Job cancelled");
+                Ok(job)
+            }
+            None => Err(invalid_transition(repo.get(id).await?, "cancelled")),
+        }
+    }
+}
+
+fn invalid_transition(job: Job, action: &str) -> AppError {
+    AppError {
+        code: AppErrorCode::Conflict,
+        message: format!(
+            "Job {} is {} and cannot be {}",
+            job.id,
+            job.status.as_str(),
+            action
+        ),
+        error_code: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::repository::{InMemoryRepository, job::JobRepository};
+
+    fn make_service() -> (JobService<InMemoryRepository>, Arc<InMemoryRepository>) {
+        let repo = Arc::new(InMemoryRepository::new());
+        (
+            JobService::new(Arc::new(Config::default()), repo.clone()),
+            repo,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_enqueue() {
+        let (service, repo) = make_service();
+
+        let job = service
+            .enqueue(
+                &Ctx::default(),
+                NewJob::new(" email.welcome ", json!({"to": "a@b.com"})),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(job.kind, "email.welcome");
+        assert_eq!(job.status, JobStatus::Pending);
+        assert_eq!(repo.job.jobs.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_validates() {
+        let (service, _) = make_service();
+
+        let err = service
+            .enqueue(&Ctx::default(), NewJob::new("", json!({})).max_attempts(0))
+            .await
+            .unwrap_err();
+
+        let fields: Vec<_> = err
+            .get_field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["kind", "max_attempts"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_status() {
+        let (service, _) = make_service();
+        let ctx = Ctx::default();
+        let job = service
+            .enqueue(&ctx, NewJob::new("a", json!({})))
+            .await
+            .unwrap();
+        service
+            .enqueue(&ctx, NewJob::new("b", json!({})))
+            .await
+            .unwrap();
+        service.cancel(&ctx, &job.id).await.unwrap();
+
+        let jobs = service.list(&ctx, None, 10).await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        let jobs = service
+            .list(&ctx, Some(JobStatus::Cancelled), 10)
+            .await
+            .unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, job.id);
+        assert!(service.list(&ctx, None, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_cancel_transitions() {
+        let (service, repo) = make_service();
+        let ctx = Ctx::default();
+        let job = service
+            .enqueue(&ctx, NewJob::new("a", json!({})).max_attempts(1))
+            .await
+            .unwrap();
+
+        let err = service.retry(&ctx, &job.id).await.unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::Conflict));
+
+        repo.job.claim(chrono::Utc::now()).await.unwrap();
+        repo.job
+            .fail(&job.id, "boom", chrono::Utc::now())
+            .await
+            .unwrap();
+        let err = service.cancel(&ctx, &job.id).await.unwrap_err();
+        assert_eq!(
+            err.get_message(),
+            format!("Job {} is failed and cannot be cancelled", job.id)
+        );
+
+        let retried = service.retry(&ctx, &job.id).await.unwrap();
+        assert_eq!(retried.status, JobStatus::Pending);
+        assert_eq!(retried.attempts, 0);
+        assert_eq!(retried.last_error.as_deref(), Some("boom"));
+
+        let err = service.cancel(&ctx, "missing").await.unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::NotFound));
+    }
+}
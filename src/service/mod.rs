@@ -1,5 +1,11 @@
+pub mod attachment;
+pub mod cursor;
+pub mod export;
+pub mod import;
 pub mod item;
+pub mod job;
 pub mod registry;
+pub mod sync;
 pub mod user;
 
-pub use registry::Service;
+pub use registry::{Service, ServiceApi};
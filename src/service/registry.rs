@@ -1,22 +1,565 @@
 use std::sync::Arc;
 
-use crate::repository::Repository;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
-use super::{item::ItemService, user::UserService};
+use crate::{
+    event::EventPublisher,
+    model::{
+        attachment::Attachment,
+        change::SyncPage,
+        context::Ctx,
+        error::AppError,
+        export::ExportFormat,
+        import::ImportResult,
+        item::{ExpandedItem, Item},
+        job::{Job, JobStatus, NewJob},
+        user::User,
+    },
+    patch::Patch,
+    repository::{DynRepository, Repository},
+    search::SearchIndex,
+    storage::Storage,
+};
+
+use super::{
+    attachment::{AttachmentData, AttachmentService, NewAttachment},
+    cursor::Page,
+    export::ExportService,
+    import::ImportService,
+    item::{ItemService, ListItemFilter},
+    job::JobService,
+    sync::SyncService,
+    user::{ConfirmEmail, CreateUser, UpdateUser, UserService},
+};
 use crate::config::Config;
 
-pub struct Service {
+/// Operations the handlers depend on, implemented by [`Service`] and mockable
+/// for handler-level tests.
+#[async_trait]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait ServiceApi: Send + Sync {
+    /// Filtered and sorted by an allowlisted column, see [`ItemService::list`].
+    async fn list_items(
+        &self,
+        ctx: &Ctx,
+        sort: Option<String>,
+        order: Option<String>,
+        filter: ListItemFilter,
+    ) -> Result<Vec<Item>, AppError>;
+    /// One keyset page of items, `cursor` being the `next` of the page before.
+    async fn list_items_page(
+        &self,
+        ctx: &Ctx,
+        cursor: Option<String>,
+        limit: i64,
+    ) -> Result<Page<Item>, AppError>;
+    async fn search_items(
+        &self,
+        ctx: &Ctx,
+        query: String,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError>;
+    async fn get_item(&self, ctx: &Ctx, id: String) -> Result<Item, AppError>;
+    async fn item_exists(&self, ctx: &Ctx, id: String) -> Result<bool, AppError>;
+    /// The item as it was at `at`, see [`ItemService::get_as_of`].
+    async fn get_item_as_of(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        at: DateTime<Utc>,
+    ) -> Result<Item, AppError>;
+    async fn list_items_as_of(&self, ctx: &Ctx, at: DateTime<Utc>) -> Result<Vec<Item>, AppError>;
+    /// Embeds the items' owners, see [`ItemService::with_owners`].
+    async fn include_item_owners(
+        &self,
+        ctx: &Ctx,
+        items: Vec<Item>,
+    ) -> Result<Vec<ExpandedItem>, AppError>;
+    async fn get_item_by_slug(&self, ctx: &Ctx, slug: String) -> Result<Item, AppError>;
+    async fn create_item(&self, ctx: &Ctx, name: String) -> Result<Item, AppError>;
+    /// Renames only at `version` when given, see [`ItemService::update`].
+    async fn update_item(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        name: String,
+        version: Option<i64>,
+    ) -> Result<Item, AppError>;
+    /// Merge or JSON patch, see [`ItemService::patch`].
+    async fn patch_item(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        patch: Patch,
+        version: Option<i64>,
+    ) -> Result<Item, AppError>;
+    /// Renames all or none, see [`ItemService::bulk_update`].
+    async fn bulk_update_items(
+        &self,
+        ctx: &Ctx,
+        updates: Vec<(String, String)>,
+    ) -> Result<Vec<Item>, AppError>;
+    /// Creates the items of a CSV all at once, or checks them on a dry run,
+    /// see [`ItemService::import`].
+    async fn import_items_csv(
+        &self,
+        ctx: &Ctx,
+        data: Vec<u8>,
+        dry_run: bool,
+    ) -> Result<ImportResult, AppError>;
+    /// Derives the item's slug again from its name, see
+    /// [`ItemService::regenerate_slug`].
+    async fn regenerate_item_slug(&self, ctx: &Ctx, id: String) -> Result<Item, AppError>;
+    /// The deleted item, see [`ItemService::delete`].
+    async fn delete_item(&self, ctx: &Ctx, id: String) -> Result<Option<Item>, AppError>;
+
+    async fn add_user(&self, ctx: &Ctx, payload: CreateUser) -> Result<User, AppError>;
+    async fn list_users(
+        &self,
+        ctx: &Ctx,
+        sort: Option<String>,
+        order: Option<String>,
+    ) -> Result<Vec<User>, AppError>;
+    async fn list_users_page(
+        &self,
+        ctx: &Ctx,
+        cursor: Option<String>,
+        limit: i64,
+    ) -> Result<Page<User>, AppError>;
+    /// Typo tolerant search by email, see [`UserService::search`].
+    async fn search_users(
+        &self,
+        ctx: &Ctx,
+        query: String,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError>;
+    async fn get_user(&self, ctx: &Ctx, id: &str) -> Result<User, AppError>;
+    async fn user_exists(&self, ctx: &Ctx, id: &str) -> Result<bool, AppError>;
+    /// See [`ItemService::list_owned`].
+    async fn list_user_items(&self, ctx: &Ctx, user_id: &str) -> Result<Vec<Item>, AppError>;
+    /// See [`ItemService::create_owned`].
+    async fn create_user_item(
+        &self,
+        ctx: &Ctx,
+        user_id: &str,
+        name: String,
+    ) -> Result<Item, AppError>;
+    async fn get_user_by_email(&self, ctx: &Ctx, email: &str) -> Result<User, AppError>;
+    /// Updates only at `version` when given, see [`UserService::update`].
+    async fn update_user(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        payload: UpdateUser,
+        version: Option<i64>,
+    ) -> Result<User, AppError>;
+    /// Merge or JSON patch, see [`UserService::patch`].
+    async fn patch_user(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        patch: Patch,
+        version: Option<i64>,
+    ) -> Result<User, AppError>;
+    /// Applies a pending email change, see [`UserService::confirm_email`].
+    async fn confirm_user_email(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        payload: ConfirmEmail,
+    ) -> Result<User, AppError>;
+    /// The deleted user, see [`UserService::delete`].
+    async fn delete_user(&self, ctx: &Ctx, id: &str) -> Result<Option<User>, AppError>;
+
+    /// Queues slow work, e.g. emails or exports, for the background workers.
+    async fn enqueue_job(&self, ctx: &Ctx, job: NewJob) -> Result<Job, AppError>;
+    async fn list_jobs(
+        &self,
+        ctx: &Ctx,
+        status: Option<JobStatus>,
+        limit: i64,
+    ) -> Result<Vec<Job>, AppError>;
+    async fn get_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError>;
+    async fn retry_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError>;
+    async fn cancel_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError>;
+
+    async fn export_items(&self, ctx: &Ctx, format: ExportFormat) -> Result<Job, AppError>;
+    async fn get_export_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError>;
+    async fn download_export(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+    ) -> Result<(ExportFormat, Vec<u8>), AppError>;
+
+    /// Queues the import of an uploaded CSV file with a `name` column.
+    async fn import_items(&self, ctx: &Ctx, data: Vec<u8>) -> Result<Job, AppError>;
+    async fn get_import_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError>;
+
+    async fn upload_attachment(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+        file: NewAttachment,
+    ) -> Result<Attachment, AppError>;
+    async fn list_attachments(&self, ctx: &Ctx, item_id: &str)
+    -> Result<Vec<Attachment>, AppError>;
+    /// The attachment along with its bytes or presigned URL, `size` names a
+    /// thumbnail to get instead of the original.
+    async fn download_attachment(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+        id: &str,
+        size: Option<String>,
+    ) -> Result<(Attachment, AttachmentData), AppError>;
+    async fn delete_attachment(&self, ctx: &Ctx, item_id: &str, id: &str) -> Result<(), AppError>;
+
+    /// Item and user changes after cursor `since`, see [`SyncService::changes`].
+    async fn sync_changes(&self, ctx: &Ctx, since: i64, limit: i64) -> Result<SyncPage, AppError>;
+}
+
+/// Business logic over a repository `R`. A concrete `R` such as
+/// `PostgresRepository` is dispatched statically down to its parts, `Service`
+/// alone defaults to [`DynRepository`] for repositories picked at runtime.
+pub struct Service<R: Repository + ?Sized = DynRepository> {
     pub config: Arc<Config>,
-    pub item: ItemService,
-    pub user: UserService,
+    pub item: ItemService<R>,
+    pub user: UserService<R>,
+    pub job: JobService<R>,
+    pub export: ExportService<R>,
+    pub import: ImportService<R>,
+    pub attachment: AttachmentService<R>,
+    pub sync: SyncService<R>,
 }
 
-impl Service {
-    pub fn new(config: Arc<Config>, repo: Arc<dyn Repository>) -> Self {
+impl<R: Repository + ?Sized> Service<R> {
+    pub fn new(config: Arc<Config>, repo: Arc<R>, events: Arc<dyn EventPublisher>) -> Self
+    where
+        R: 'static,
+    {
         Self {
             config: config.clone(),
-            item: ItemService::new(config.clone(), repo.clone()),
-            user: UserService::new(config.clone(), repo.clone()),
+            item: ItemService::new(config.clone(), repo.clone(), events.clone()),
+            user: UserService::new(config.clone(), repo.clone(), events.clone()),
+            job: JobService::new(config.clone(), repo.clone()),
+            export: ExportService::new(config.clone(), repo.clone()),
+            import: ImportService::new(config.clone(), repo.clone()),
+            attachment: AttachmentService::new(config.clone(), repo.clone()),
+            sync: SyncService::new(repo.clone()),
         }
     }
+
+    /// Serves item searches from `index`, see [`crate::search`].
+    pub fn with_search(mut self, index: Arc<dyn SearchIndex>) -> Self {
+        self.item = self.item.with_search(index);
+        self
+    }
+
+    /// Stores attachments in `storage` rather than the local `attachment_dir`.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.attachment = self.attachment.with_storage(storage);
+        self
+    }
+}
+
+impl Service {
+    /// Builds a dynamically dispatched service, for repositories chosen at runtime.
+    pub fn new_dyn(
+        config: Arc<Config>,
+        repo: Arc<DynRepository>,
+        events: Arc<dyn EventPublisher>,
+    ) -> Self {
+        Self::new(config, repo, events)
+    }
+}
+
+#[async_trait]
+impl<R: Repository + ?Sized> ServiceApi for Service<R> {
+    async fn list_items(
+        &self,
+        ctx: &Ctx,
+        sort: Option<String>,
+        order: Option<String>,
+        filter: ListItemFilter,
+    ) -> Result<Vec<Item>, AppError> {
+        self.item.list(ctx, sort, order, filter).await
+    }
+
+    async fn list_items_page(
+        &self,
+        ctx: &Ctx,
+        cursor: Option<String>,
+        limit: i64,
+    ) -> Result<Page<Item>, AppError> {
+        self.item.list_page(ctx, cursor, limit).await
+    }
+
+    async fn search_items(
+        &self,
+        ctx: &Ctx,
+        query: String,
+        limit: i64,
+    ) -> Result<Vec<Item>, AppError> {
+        self.item.search(ctx, query, limit).await
+    }
+
+    async fn get_item(&self, ctx: &Ctx, id: String) -> Result<Item, AppError> {
+        self.item.get(ctx, id).await
+    }
+
+    async fn item_exists(&self, ctx: &Ctx, id: String) -> Result<bool, AppError> {
+        self.item.exists(ctx, id).await
+    }
+
+    async fn get_item_as_of(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        at: DateTime<Utc>,
+    ) -> Result<Item, AppError> {
+        self.item.get_as_of(ctx, id, at).await
+    }
+
+    async fn list_items_as_of(&self, ctx: &Ctx, at: DateTime<Utc>) -> Result<Vec<Item>, AppError> {
+        self.item.list_as_of(ctx, at).await
+    }
+
+    async fn include_item_owners(
+        &self,
+        ctx: &Ctx,
+        items: Vec<Item>,
+    ) -> Result<Vec<ExpandedItem>, AppError> {
+        self.item.with_owners(ctx, items).await
+    }
+
+    async fn get_item_by_slug(&self, ctx: &Ctx, slug: String) -> Result<Item, AppError> {
+        self.item.get_by_slug(ctx, slug).await
+    }
+
+    async fn create_item(&self, ctx: &Ctx, name: String) -> Result<Item, AppError> {
+        self.item.create(ctx, name).await
+    }
+
+    async fn update_item(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        name: String,
+        version: Option<i64>,
+    ) -> Result<Item, AppError> {
+        self.item.update(ctx, id, name, version).await
+    }
+
+    async fn patch_item(
+        &self,
+        ctx: &Ctx,
+        id: String,
+        patch: Patch,
+        version: Option<i64>,
+    ) -> Result<Item, AppError> {
+        self.item.patch(ctx, id, patch, version).await
+    }
+
+    async fn bulk_update_items(
+        &self,
+        ctx: &Ctx,
+        updates: Vec<(String, String)>,
+    ) -> Result<Vec<Item>, AppError> {
+        self.item.bulk_update(ctx, updates).await
+    }
+
+    async fn import_items_csv(
+        &self,
+        ctx: &Ctx,
+        data: Vec<u8>,
+        dry_run: bool,
+    ) -> Result<ImportResult, AppError> {
+        self.item.import(ctx, &data, dry_run).await
+    }
+
+    async fn regenerate_item_slug(&self, ctx: &Ctx, id: String) -> Result<Item, AppError> {
+        self.item.regenerate_slug(ctx, id).await
+    }
+
+    async fn delete_item(&self, ctx: &Ctx, id: String) -> Result<Option<Item>, AppError> {
+        let attachments = self.attachment.list(ctx, &id).await.unwrap_or_default();
+        let deleted = self.item.delete(ctx, id).await?;
+        self.attachment.purge(&attachments).await;
+        Ok(deleted)
+    }
+
+    async fn add_user(&self, ctx: &Ctx, payload: CreateUser) -> Result<User, AppError> {
+        self.user.add(ctx, payload).await
+    }
+
+    async fn list_users(
+        &self,
+        ctx: &Ctx,
+        sort: Option<String>,
+        order: Option<String>,
+    ) -> Result<Vec<User>, AppError> {
+        self.user.list(ctx, sort, order).await
+    }
+
+    async fn list_users_page(
+        &self,
+        ctx: &Ctx,
+        cursor: Option<String>,
+        limit: i64,
+    ) -> Result<Page<User>, AppError> {
+        self.user.list_page(ctx, cursor, limit).await
+    }
+
+    async fn search_users(
+        &self,
+        ctx: &Ctx,
+        query: String,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        self.user.search(ctx, query, limit).await
+    }
+
+    async fn get_user(&self, ctx: &Ctx, id: &str) -> Result<User, AppError> {
+        self.user.get(ctx, id).await
+    }
+
+    async fn user_exists(&self, ctx: &Ctx, id: &str) -> Result<bool, AppError> {
+        self.user.exists(ctx, id).await
+    }
+
+    async fn list_user_items(&self, ctx: &Ctx, user_id: &str) -> Result<Vec<Item>, AppError> {
+        self.item.list_owned(ctx, user_id).await
+    }
+
+    async fn create_user_item(
+        &self,
+        ctx: &Ctx,
+        user_id: &str,
+        name: String,
+    ) -> Result<Item, AppError> {
+        self.item.create_owned(ctx, user_id, name).await
+    }
+
+    async fn get_user_by_email(&self, ctx: &Ctx, email: &str) -> Result<User, AppError> {
+        self.user.get_by_email(ctx, email).await
+    }
+
+    async fn update_user(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        payload: UpdateUser,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        self.user.update(ctx, id, payload, version).await
+    }
+
+    async fn patch_user(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        patch: Patch,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        self.user.patch(ctx, id, patch, version).await
+    }
+
+    async fn confirm_user_email(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        payload: ConfirmEmail,
+    ) -> Result<User, AppError> {
+        self.user.confirm_email(ctx, id, payload).await
+    }
+
+    async fn delete_user(&self, ctx: &Ctx, id: &str) -> Result<Option<User>, AppError> {
+        self.user.delete(ctx, id).await
+    }
+
+    async fn enqueue_job(&self, ctx: &Ctx, job: NewJob) -> Result<Job, AppError> {
+        self.job.enqueue(ctx, job).await
+    }
+
+    async fn list_jobs(
+        &self,
+        ctx: &Ctx,
+        status: Option<JobStatus>,
+        limit: i64,
+    ) -> Result<Vec<Job>, AppError> {
+        self.job.list(ctx, status, limit).await
+    }
+
+    async fn get_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        self.job.get(ctx, id).await
+    }
+
+    async fn retry_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        self.job.retry(ctx, id).await
+    }
+
+    async fn cancel_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        self.job.cancel(ctx, id).await
+    }
+
+    async fn export_items(&self, ctx: &Ctx, format: ExportFormat) -> Result<Job, AppError> {
+        self.export.start_items(ctx, format).await
+    }
+
+    async fn get_export_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        self.export.get(ctx, id).await
+    }
+
+    async fn download_export(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+    ) -> Result<(ExportFormat, Vec<u8>), AppError> {
+        self.export.download(ctx, id).await
+    }
+
+    async fn import_items(&self, ctx: &Ctx, data: Vec<u8>) -> Result<Job, AppError> {
+        self.import.start_items(ctx, data).await
+    }
+
+    async fn get_import_job(&self, ctx: &Ctx, id: &str) -> Result<Job, AppError> {
+        self.import.get(ctx, id).await
+    }
+
+    async fn upload_attachment(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+        file: NewAttachment,
+    ) -> Result<Attachment, AppError> {
+        self.attachment.upload(ctx, item_id, file).await
+    }
+
+    async fn list_attachments(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+    ) -> Result<Vec<Attachment>, AppError> {
+        self.attachment.list(ctx, item_id).await
+    }
+
+    async fn download_attachment(
+        &self,
+        ctx: &Ctx,
+        item_id: &str,
+        id: &str,
+        size: Option<String>,
+    ) -> Result<(Attachment, AttachmentData), AppError> {
+        self.attachment
+            .read(ctx, item_id, id, size.as_deref())
+            .await
+    }
+
+    async fn delete_attachment(&self, ctx: &Ctx, item_id: &str, id: &str) -> Result<(), AppError> {
+        self.attachment.delete(ctx, item_id, id).await
+    }
+    async fn sync_changes(&self, ctx: &Ctx, since: i64, limit: i64) -> Result<SyncPage, AppError> {
+        self.sync.changes(ctx, since, limit).await
+    }
 }
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use crate::{
+    model::{
+        change::SyncPage,
+        context::Ctx,
+        error::{AppError, FieldError},
+    },
+    repository::{DynRepository, Repository, change::ChangeRepository},
+};
+
+/// Most changes one sync page holds.
+pub const MAX_SYNC_LIMIT: i64 = 1000;
+
+/// Incremental sync of items and users for offline clients, read from the
+/// change history.
+pub struct SyncService<R: Repository + ?Sized = DynRepository> {
+    repo: Arc<R>,
+}
+
+impl<R: Repository + ?Sized> SyncService<R> {
+    pub fn new(repo: Arc<R>) -> Self {
+        Self { repo }
+    }
+
+    /// The changes after cursor `since`, 0 starting from the beginning of
+    /// history.
+    pub async fn changes(&self, ctx: &Ctx, since: i64, limit: i64) -> Result<SyncPage, AppError> {
+        let mut errors = vec![];
+        if since < 0 {
+            errors.push(FieldError::new(
+                "since",
+                "range",
+                "Cursor cannot be negative",
+            ));
+        }
+        if !(1..=MAX_SYNC_LIMIT).contains(&limit) {
+            errors.push(FieldError::new(
+                "limit",
+                "range",
+                format!("Limit must be between 1 and {}", MAX_SYNC_LIMIT),
+            ));
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+
+        let mut changes = ctx
+            .with_deadline(self.repo.change().since(since, limit + 1))
+            .await?;
+        let has_more = changes.len() as i64 > limit;
+        changes.truncate(limit as usize);
+        Ok(SyncPage {
+            cursor: changes.last().map_or(since, |c| c.seq),
+            changes,
+            has_more,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::event::{Event, EventAction},
+        repository::InMemoryRepository,
+    };
+
+    #[tokio::test]
+    async fn test_changes_pages() {
+        let repo = Arc::new(InMemoryRepository::new());
+        for (id, action) in [
+            ("1", EventAction::Created),
+            ("2", EventAction::Created),
+            ("1", EventAction::Deleted),
+        ] {
+            let event = Event::new::<()>("item", action, id, None);
+            repo.change.record(&event).unwrap();
+        }
+        let service = SyncService::new(repo);
+        let ctx = Ctx::default();
+
+        let page = service.changes(&ctx, 0, 2).await.unwrap();
+        assert_eq!(page.changes.len(), 2);
+        assert_eq!(page.cursor, 2);
+        assert!(page.has_more);
+        let page = service.changes(&ctx, page.cursor, 2).await.unwrap();
+        assert_eq!(page.changes[0].action, EventAction::Deleted);
+        assert_eq!(page.cursor, 3);
+        assert!(!page.has_more);
+        let page = service.changes(&ctx, 3, 2).await.unwrap();
+        assert!(page.changes.is_empty());
+        assert_eq!(page.cursor, 3);
+
+        let err = service.changes(&ctx, -1, 0).await.unwrap_err();
+        assert_eq!(err.get_field_errors().len(), 2);
+    }
+}
@@ -1,92 +1,410 @@
 use std::sync::Arc;
 
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
     config::Config,
+    event::{EventPublisher, publish_or_log},
+    extract::{normalized, trimmed},
+    mail::{
+        Email, SEND_EMAIL_JOB,
+        address::{self, DomainPolicy},
+        templates,
+    },
     model::{
-        error::{AppError, AppErrorCode},
-        user::User,
+        context::Ctx,
+        error::{AppError, AppErrorCode, ErrorCode, FieldError},
+        event::{Event, EventAction},
+        item::first_version,
+        job::NewJob,
+        sort::UserSort,
+        user::{PendingEmail, User},
+    },
+    patch::{self, Patch},
+    repository::{
+        DynRepository, Repository,
+        user::{UserRepository, version_mismatch},
+    },
+    service::{
+        cursor::{self, Cursor, Page},
+        item::MAX_SEARCH_LIMIT,
+        job::JobService,
     },
-    repository::Repository,
 };
 
-#[derive(Deserialize, Serialize, Clone)]
+const ENTITY: &str = "user";
+
+#[derive(Deserialize, Serialize, Clone, ToSchema, Validate)]
 pub struct CreateUser {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(
+        length(max = 255, message = "Email must be at most 255 characters"),
+        custom(function = "address::validate")
+    )]
     pub email: String,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, ToSchema, Validate)]
 pub struct UpdateUser {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(
+        length(max = 255, message = "Email must be at most 255 characters"),
+        custom(function = "address::validate")
+    )]
     pub email: String,
 }
 
-pub struct UserService {
-    repo: Arc<dyn Repository>,
+#[derive(Deserialize, Serialize, Clone, ToSchema, Validate)]
+pub struct ConfirmEmail {
+    #[serde(deserialize_with = "trimmed")]
+    #[validate(length(min = 1, message = "Token is required"))]
+    pub token: String,
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn invalid_id() -> AppError {
+    AppError {
+        code: AppErrorCode::InvalidInput,
+        message: "Invalid user ID format".into(),
+        error_code: Some(ErrorCode::InvalidId),
+    }
+}
+
+pub struct UserService<R: Repository + ?Sized = DynRepository> {
+    config: Arc<Config>,
+    repo: Arc<R>,
+    events: Arc<dyn EventPublisher>,
+    jobs: JobService<R>,
+    domains: DomainPolicy,
 }
 
-impl UserService {
-    pub fn new(_: Arc<Config>, repo: Arc<dyn Repository>) -> Self {
-        Self { repo }
+impl<R: Repository + ?Sized> UserService<R> {
+    pub fn new(config: Arc<Config>, repo: Arc<R>, events: Arc<dyn EventPublisher>) -> Self {
+        Self {
+            jobs: JobService::new(config.clone(), repo.clone()),
+            domains: DomainPolicy::from_config(&config),
+            config,
+            repo,
+            events,
+        }
+    }
+
+    /// Queues the email for the job workers, logging instead of failing the
+    /// caller when it can't be queued.
+    async fn send_email(&self, ctx: &Ctx, email: Email) {
+        let job = NewJob::new(SEND_EMAIL_JOB, json!(email));
+        if let Err(e) = self.jobs.enqueue(ctx, job).await {
+            tracing::warn!(
+                correlation_id = %ctx.correlation_id,
+                error = %e.get_error(),
+                "{}",
+                e.get_message()
+            );
+        }
     }
 
-    pub async fn add(&self, payload: CreateUser) -> Result<User, AppError> {
-        let email = payload.email.trim().to_string();
+    /// Parses `email`, applies the domain policy and, when enabled, verifies
+    /// its domain receives mail.
+    async fn check_email(&self, email: &str) -> Option<FieldError> {
+        let address = match address::parse(email) {
+            Ok(address) => address,
+            Err(error) => return Some(error),
+        };
+        if let Err(error) = self.domains.check(&address) {
+            return Some(error);
+        }
+        if !self.config.email_check_mx {
+            return None;
+        }
+        address::verify_domain(&address).await.err()
+    }
+
+    pub async fn add(&self, ctx: &Ctx, payload: CreateUser) -> Result<User, AppError> {
+        let email = normalized(&payload.email);
         if email.is_empty() {
-            return Err(AppError {
-                code: AppErrorCode::InvalidInput,
-                message: "Email is required".into(),
-            });
+            return Err(AppError::validation(vec![FieldError::new(
+                "email",
+                "required",
+                "Email is required",
+            )]));
+        }
+        if let Some(error) = self.check_email(&email).await {
+            return Err(AppError::validation(vec![error]));
         }
 
         let user = User {
             id: Uuid::new_v4().to_string(),
             email,
+            version: first_version(),
         };
-        self.repo.user().add(user).await
+        let user = ctx.with_deadline(self.repo.user().add(user)).await?;
+        publish_or_log(
+            self.events.as_ref(),
+            Event::new(ENTITY, EventAction::Created, &user.id, Some(&user)),
+        )
+        .await;
+        tracing::info!(correlation_id = %ctx.correlation_id, user_id = %user.id, "User created");
+        self.send_email(ctx, templates::welcome(&self.config.app_name, &user.email))
+            .await;
+        Ok(user)
     }
 
-    pub async fn list(&self) -> Result<Vec<User>, AppError> {
-        self.repo.user().list().await
+    /// Sorted by `sort`, one of [`UserSortColumn`](crate::model::sort::UserSortColumn), and `order`, by email
+    /// ascending when left out.
+    pub async fn list(
+        &self,
+        ctx: &Ctx,
+        sort: Option<String>,
+        order: Option<String>,
+    ) -> Result<Vec<User>, AppError> {
+        let sort = UserSort::parse(sort.as_deref(), order.as_deref())?;
+        ctx.with_deadline(self.repo.user().list(sort)).await
     }
 
-    pub async fn get(&self, id: &str) -> Result<User, AppError> {
+    /// One page of users by email, see [`cursor`].
+    pub async fn list_page(
+        &self,
+        ctx: &Ctx,
+        cursor: Option<String>,
+        limit: i64,
+    ) -> Result<Page<User>, AppError> {
+        let cursor = cursor::parse(cursor.as_deref(), limit)?;
+        let repo = self.repo.user();
+        let users = match &cursor {
+            Some(c) if c.is_backward() => {
+                let fetch = repo.list_before(Cursor::before_row(Some(c)), limit + 1);
+                ctx.with_deadline(fetch).await?
+            }
+            c => {
+                let fetch = repo.list_after(Cursor::after_row(c.as_ref()), limit + 1);
+                ctx.with_deadline(fetch).await?
+            }
+        };
+        Ok(cursor::page(users, limit, cursor.as_ref(), |user| {
+            (user.email.clone(), user.id.clone())
+        }))
+    }
+
+    /// Users with an email similar to `query`, tolerating typos, best first.
+    /// How similar is set by `user_search_similarity` in [`Config`].
+    pub async fn search(
+        &self,
+        ctx: &Ctx,
+        query: String,
+        limit: i64,
+    ) -> Result<Vec<User>, AppError> {
+        let query = normalized(&query);
+        let mut errors = vec![];
+        if query.is_empty() {
+            errors.push(FieldError::new(
+                "q",
+                "required",
+                "Search query cannot be empty",
+            ));
+        }
+        if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+            errors.push(FieldError::new(
+                "limit",
+                "out_of_range",
+                format!("Limit must be between 1 and {}", MAX_SEARCH_LIMIT),
+            ));
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+        let threshold = self.config.user_search_similarity;
+        ctx.with_deadline(self.repo.user().search(&query, threshold, limit))
+            .await
+    }
+
+    /// Applies `patch` to the stored user, then updates it like
+    /// [`UserService::update`]. The id can't be patched.
+    pub async fn patch(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        patch: Patch,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        let user = self.get(ctx, id).await?;
+        let payload: UpdateUser = patch::patched(&user, &patch, &["id", "version"])?;
+        payload.validate()?;
+        self.update(ctx, id, payload, version).await
+    }
+
+    pub async fn get(&self, ctx: &Ctx, id: &str) -> Result<User, AppError> {
         if id.is_empty() || Uuid::parse_str(id).is_err() {
-            return Err(AppError {
-                code: AppErrorCode::InvalidInput,
-                message: "Invalid user ID format".into(),
-            });
+            return Err(invalid_id());
         }
-        self.repo.user().get(id).await
+        ctx.with_deadline(self.repo.user().get(id)).await
     }
 
-    pub async fn update(&self, id: &str, payload: UpdateUser) -> Result<User, AppError> {
+    /// Whether user `id` exists, cheaper than [`UserService::get`]. Ids that
+    /// aren't UUIDs never do.
+    pub async fn exists(&self, ctx: &Ctx, id: &str) -> Result<bool, AppError> {
+        if Uuid::parse_str(id).is_err() {
+            return Ok(false);
+        }
+        ctx.with_deadline(self.repo.user().exists(id)).await
+    }
+
+    pub async fn get_by_email(&self, ctx: &Ctx, email: &str) -> Result<User, AppError> {
+        let email = normalized(email);
+        if let Err(error) = address::parse(&email) {
+            return Err(AppError::validation(vec![error]));
+        }
+        ctx.with_deadline(self.repo.user().get_by_email(&email))
+            .await
+    }
+
+    /// Changes the email, only while the user is at `version` when given.
+    pub async fn update(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        payload: UpdateUser,
+        version: Option<i64>,
+    ) -> Result<User, AppError> {
+        let email = normalized(&payload.email);
+        let mut errors = vec![];
         if id.is_empty() || Uuid::parse_str(id).is_err() {
-            return Err(AppError {
-                code: AppErrorCode::InvalidInput,
-                message: "Invalid user ID format".into(),
-            });
+            errors.push(FieldError::new(
+                "id",
+                "invalid_format",
+                "Invalid user ID format",
+            ));
         }
-        let email = payload.email.trim().to_string();
         if email.is_empty() {
-            return Err(AppError {
-                code: AppErrorCode::InvalidInput,
-                message: "Email cannot be empty".into(),
-            });
+            errors.push(FieldError::new(
+                "email",
+                "required",
+                "Email cannot be empty",
+            ));
+        } else if let Some(error) = self.check_email(&email).await {
+            errors.push(error);
+        }
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
         }
-        self.repo.user().update(id, email).await
+        if self.config.email_change_verification {
+            let user = ctx.with_deadline(self.repo.user().get(id)).await?;
+            if let Some(version) = version.filter(|version| *version != user.version) {
+                return Err(version_mismatch(id, version));
+            }
+            if !user.email.eq_ignore_ascii_case(&email) {
+                self.request_email_change(ctx, &user, email).await?;
+                return Ok(user);
+            }
+        }
+        let user = ctx
+            .with_deadline(self.repo.user().update(id, email, version))
+            .await?;
+        self.updated(ctx, &user).await;
+        Ok(user)
+    }
+
+    /// Mails a confirmation token to `email`, the user keeps the current
+    /// address until [`Self::confirm_email`] is called with it.
+    async fn request_email_change(
+        &self,
+        ctx: &Ctx,
+        user: &User,
+        email: String,
+    ) -> Result<(), AppError> {
+        let token = Uuid::new_v4().simple().to_string();
+        let expires_at =
+            Utc::now() + Duration::seconds(self.config.email_change_token_ttl_secs as i64);
+        let pending = PendingEmail {
+            email: email.clone(),
+            token_hash: hash_token(&token),
+            expires_at,
+        };
+        ctx.with_deadline(self.repo.user().set_pending_email(&user.id, pending))
+            .await?;
+        tracing::info!(correlation_id = %ctx.correlation_id, user_id = %user.id, "User email change requested");
+        self.send_email(
+            ctx,
+            templates::email_change(
+                &self.config.app_name,
+                &email,
+                &token,
+                &expires_at.to_rfc3339(),
+            ),
+        )
+        .await;
+        Ok(())
     }
 
-    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+    /// Applies the pending email change that `payload.token` was mailed for.
+    pub async fn confirm_email(
+        &self,
+        ctx: &Ctx,
+        id: &str,
+        payload: ConfirmEmail,
+    ) -> Result<User, AppError> {
         if id.is_empty() || Uuid::parse_str(id).is_err() {
+            return Err(invalid_id());
+        }
+        let pending = ctx
+            .with_deadline(self.repo.user().get_pending_email(id))
+            .await?;
+        let valid = pending.is_some_and(|pending| {
+            pending.expires_at > Utc::now() && pending.token_hash == hash_token(&payload.token)
+        });
+        if !valid {
             return Err(AppError {
                 code: AppErrorCode::InvalidInput,
-                message: "Invalid user ID format".into(),
+                message: "Invalid or expired email confirmation token".into(),
+                error_code: Some(ErrorCode::InvalidToken),
             });
         }
+        let user = ctx
+            .with_deadline(self.repo.user().confirm_pending_email(id))
+            .await?;
+        self.updated(ctx, &user).await;
+        Ok(user)
+    }
 
-        self.repo.user().delete(id).await
+    async fn updated(&self, ctx: &Ctx, user: &User) {
+        publish_or_log(
+            self.events.as_ref(),
+            Event::new(ENTITY, EventAction::Updated, &user.id, Some(user)),
+        )
+        .await;
+        tracing::info!(correlation_id = %ctx.correlation_id, user_id = %user.id, "User updated");
+        self.send_email(
+            ctx,
+            templates::verification(&self.config.app_name, &user.email),
+        )
+        .await;
+    }
+
+    /// Deletes the user, returning it unless there was none.
+    pub async fn delete(&self, ctx: &Ctx, id: &str) -> Result<Option<User>, AppError> {
+        if id.is_empty() || Uuid::parse_str(id).is_err() {
+            return Err(invalid_id());
+        }
+
+        let deleted = ctx.with_deadline(self.repo.user().delete(id)).await?;
+        // Deleting an unknown id is fine, but there's nothing to announce.
+        if deleted.is_some() {
+            publish_or_log(
+                self.events.as_ref(),
+                Event::new::<User>(ENTITY, EventAction::Deleted, id, None),
+            )
+            .await;
+            tracing::info!(correlation_id = %ctx.correlation_id, user_id = %id, "User deleted");
+        }
+        Ok(deleted)
     }
 }
 
@@ -94,22 +412,39 @@ impl UserService {
 mod tests {
     use super::*;
     use crate::config::Config;
+    use crate::event::{MockEventPublisher, NoopPublisher};
     use crate::model::user::User;
-    use crate::repository::registry::MockPostgresRepository;
-    use crate::repository::{item::MockItemRepository, user::MockUserRepository};
+    use crate::repository::registry::{InMemoryRepository, MockRepository};
+    use crate::repository::{
+        item::MockItemRepository, job::InMemoryJobRepository, user::MockUserRepository,
+    };
     use crate::service::user::UpdateUser;
     use std::sync::Arc;
 
-    fn make_service(mock_user_repo: Arc<MockUserRepository>) -> UserService {
+    fn make_service_with_jobs(
+        mock_user_repo: Arc<MockUserRepository>,
+    ) -> (UserService<MockRepository>, Arc<InMemoryJobRepository>) {
         let mock_item_repo = Arc::new(MockItemRepository::new());
-        let mut mock_repo = MockPostgresRepository::new();
+        let job_repo = Arc::new(InMemoryJobRepository::new());
+        let jobs = job_repo.clone();
+        let mut mock_repo = MockRepository::new();
         mock_repo
             .expect_user()
             .returning(move || mock_user_repo.clone());
         mock_repo
             .expect_item()
             .returning(move || mock_item_repo.clone());
-        UserService::new(Arc::new(Config::default()), Arc::new(mock_repo))
+        mock_repo.expect_job().returning(move || jobs.clone());
+        let service = UserService::new(
+            Arc::new(Config::default()),
+            Arc::new(mock_repo),
+            Arc::new(NoopPublisher),
+        );
+        (service, job_repo)
+    }
+
+    fn make_service(mock_user_repo: Arc<MockUserRepository>) -> UserService<MockRepository> {
+        make_service_with_jobs(mock_user_repo).0
     }
 
     #[tokio::test]
@@ -123,25 +458,93 @@ mod tests {
             .withf(|u| u.email == "test@example.com")
             .returning(|u| Box::pin(async move { Ok(u) }));
         let service = make_service(Arc::new(mock_user_repo));
-        let result = service.add(payload).await;
+        let result = service.add(&Ctx::default(), payload).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap().email, "test@example.com");
     }
 
+    #[tokio::test]
+    async fn test_add_user_queues_welcome_email() {
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_add()
+            .returning(|u| Box::pin(async move { Ok(u) }));
+        let (service, jobs) = make_service_with_jobs(Arc::new(mock_user_repo));
+
+        let payload = CreateUser {
+            email: "a@b.com".to_string(),
+        };
+        service.add(&Ctx::default(), payload).await.unwrap();
+
+        let jobs = jobs.jobs.lock().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].kind, SEND_EMAIL_JOB);
+        assert_eq!(jobs[0].payload["to"], "a@b.com");
+        assert_eq!(jobs[0].payload["subject"], "Welcome to my_app");
+    }
+
+    #[tokio::test]
+    async fn test_add_user_rejects_invalid_email() {
+        let service = make_service(Arc::new(MockUserRepository::new()));
+        let payload = CreateUser {
+            email: "a@localhost".to_string(),
+        };
+        let err = service.add(&Ctx::default(), payload).await.unwrap_err();
+        let errors = err.get_field_errors();
+        assert_eq!(errors[0].field, "email");
+        assert_eq!(errors[0].code, address::INVALID_EMAIL);
+    }
+
+    #[tokio::test]
+    async fn test_email_domain_policy() {
+        let config = Config {
+            email_domain_allowlist: vec!["company.com".into()],
+            ..Config::default()
+        };
+        let service = UserService::new(
+            Arc::new(config),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        let payload = |email: &str| CreateUser {
+            email: email.into(),
+        };
+        let user = service.add(&ctx, payload("a@company.com")).await.unwrap();
+
+        let err = service.add(&ctx, payload("a@gmail.com")).await.unwrap_err();
+        assert_eq!(
+            err.get_field_errors()[0].code,
+            address::EMAIL_DOMAIN_NOT_ALLOWED
+        );
+        let update = UpdateUser {
+            email: "a@gmail.com".into(),
+        };
+        let err = service
+            .update(&ctx, &user.id, update, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.get_field_errors()[0].code,
+            address::EMAIL_DOMAIN_NOT_ALLOWED
+        );
+    }
+
     #[tokio::test]
     async fn test_list_users() {
         let mut mock_user_repo = MockUserRepository::new();
         let users = vec![User {
             id: "1".to_string(),
             email: "a@b.com".to_string(),
+            version: 1,
         }];
         let users_clone = users.clone();
-        mock_user_repo.expect_list().returning(move || {
+        mock_user_repo.expect_list().returning(move |_| {
             let users = users_clone.clone();
             Box::pin(async move { Ok(users) })
         });
         let service = make_service(Arc::new(mock_user_repo));
-        let result = service.list().await;
+        let result = service.list(&Ctx::default(), None, None).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap().len(), 1);
     }
@@ -152,6 +555,7 @@ mod tests {
         let user = User {
             id: "123e4567-e89b-12d3-a456-426614174000".to_string(),
             email: "a@b.com".to_string(),
+            version: 1,
         };
         let user_clone = user.clone();
         mock_user_repo
@@ -162,11 +566,39 @@ mod tests {
                 Box::pin(async move { Ok(user) })
             });
         let service = make_service(Arc::new(mock_user_repo));
-        let result = service.get(&user.id).await;
+        let result = service.get(&Ctx::default(), &user.id).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap().email, "a@b.com");
     }
 
+    #[tokio::test]
+    async fn test_get_user_by_email() {
+        let mut mock_user_repo = MockUserRepository::new();
+        mock_user_repo
+            .expect_get_by_email()
+            .withf(|email| email == "a@b.com")
+            .returning(|email| {
+                let user = User {
+                    id: "123e4567-e89b-12d3-a456-426614174000".to_string(),
+                    email: email.to_string(),
+                    version: 1,
+                };
+                Box::pin(async move { Ok(user) })
+            });
+        let service = make_service(Arc::new(mock_user_repo));
+        let user = service
+            .get_by_email(&Ctx::default(), " a@b.com ")
+            .await
+            .unwrap();
+        assert_eq!(user.email, "a@b.com");
+
+        let err = service
+            .get_by_email(&Ctx::default(), "not-an-email")
+            .await
+            .unwrap_err();
+        assert_eq!(err.get_field_errors()[0].code, address::INVALID_EMAIL);
+    }
+
     #[tokio::test]
     async fn test_update_user() {
         let mut mock_user_repo = MockUserRepository::new();
@@ -176,30 +608,160 @@ mod tests {
         let user = User {
             id: "123e4567-e89b-12d3-a456-426614174000".to_string(),
             email: update_user.email.clone(),
+            version: 1,
         };
         let user_clone = user.clone();
         mock_user_repo
             .expect_update()
-            .withf(|id, email| id == "123e4567-e89b-12d3-a456-426614174000" && email == "new@b.com")
-            .returning(move |_, _| {
+            .withf(|id, email, version| {
+                id == "123e4567-e89b-12d3-a456-426614174000"
+                    && email == "new@b.com"
+                    && version.is_none()
+            })
+            .returning(move |_, _, _| {
                 let user = user_clone.clone();
                 Box::pin(async move { Ok(user) })
             });
         let service = make_service(Arc::new(mock_user_repo));
-        let result = service.update(&user.id, update_user).await;
+        let result = service
+            .update(&Ctx::default(), &user.id, update_user, None)
+            .await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap().email, "new@b.com");
     }
 
+    #[tokio::test]
+    async fn test_update_user_reports_every_invalid_field() {
+        let service = make_service(Arc::new(MockUserRepository::new()));
+        let result = service
+            .update(
+                &Ctx::default(),
+                "not-a-uuid",
+                UpdateUser {
+                    email: " ".to_string(),
+                },
+                None,
+            )
+            .await;
+        let fields: Vec<String> = result
+            .unwrap_err()
+            .get_field_errors()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(fields, vec!["id", "email"]);
+    }
+
     #[tokio::test]
     async fn test_delete_user() {
         let mut mock_user_repo = MockUserRepository::new();
         mock_user_repo
             .expect_delete()
             .withf(|id| id == "123e4567-e89b-12d3-a456-426614174000")
-            .returning(|_| Box::pin(async move { Ok(()) }));
-        let service = make_service(Arc::new(mock_user_repo));
-        let result = service.delete("123e4567-e89b-12d3-a456-426614174000").await;
-        assert!(result.is_ok());
+            .returning(|_| Box::pin(async move { Ok(None) }));
+        let mock_user_repo = Arc::new(mock_user_repo);
+        let mut mock_repo = MockRepository::new();
+        mock_repo
+            .expect_user()
+            .returning(move || mock_user_repo.clone());
+        // Nothing was deleted, so nothing may be published.
+        let service = UserService::new(
+            Arc::new(Config::default()),
+            Arc::new(mock_repo),
+            Arc::new(MockEventPublisher::new()),
+        );
+        let result = service
+            .delete(&Ctx::default(), "123e4567-e89b-12d3-a456-426614174000")
+            .await;
+        assert!(result.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_email_change_verification() {
+        let repo = Arc::new(InMemoryRepository::new());
+        let config = Config {
+            email_change_verification: true,
+            ..Config::default()
+        };
+        let service = UserService::new(Arc::new(config), repo.clone(), Arc::new(NoopPublisher));
+        let ctx = Ctx::default();
+        let user = service
+            .add(
+                &ctx,
+                CreateUser {
+                    email: "old@b.com".into(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let update = UpdateUser {
+            email: "new@b.com".into(),
+        };
+        let unchanged = service.update(&ctx, &user.id, update, None).await.unwrap();
+        assert_eq!(unchanged.email, "old@b.com");
+        let token = {
+            let jobs = repo.job.jobs.lock().unwrap();
+            let email = &jobs.last().unwrap().payload;
+            assert_eq!(email["to"], "new@b.com");
+            let body = email["body"].as_str().unwrap();
+            let token = body.lines().find(|l| l.len() == 32).unwrap();
+            token.to_string()
+        };
+
+        let confirm = |token: &str| ConfirmEmail {
+            token: token.into(),
+        };
+        let err = service
+            .confirm_email(&ctx, &user.id, confirm("wrong"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_code, Some(ErrorCode::InvalidToken));
+        let user = service
+            .confirm_email(&ctx, &user.id, confirm(&token))
+            .await
+            .unwrap();
+        assert_eq!(user.email, "new@b.com");
+        // Tokens are single use.
+        assert!(
+            service
+                .confirm_email(&ctx, &user.id, confirm(&token))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let service = UserService::new(
+            Arc::new(Config::default()),
+            Arc::new(InMemoryRepository::new()),
+            Arc::new(NoopPublisher),
+        );
+        let ctx = Ctx::default();
+        for email in ["alice@gmail.com", "bob@gmail.com", "carol@yahoo.com"] {
+            let payload = CreateUser {
+                email: email.into(),
+            };
+            service.add(&ctx, payload).await.unwrap();
+        }
+        let emails = |users: Vec<User>| users.into_iter().map(|u| u.email).collect::<Vec<_>>();
+
+        let users = service.search(&ctx, "gmail".into(), 20).await.unwrap();
+        // Shorter emails share a larger part of their trigrams.
+        assert_eq!(emails(users), vec!["bob@gmail.com", "alice@gmail.com"]);
+        let users = service
+            .search(&ctx, "alice@gmial.com".into(), 20)
+            .await
+            .unwrap();
+        assert_eq!(emails(users), vec!["alice@gmail.com"]);
+
+        let err = service.search(&ctx, " ".into(), 0).await.unwrap_err();
+        let fields: Vec<_> = err
+            .get_field_errors()
+            .iter()
+            .map(|e| e.field.clone())
+            .collect();
+        assert_eq!(fields, vec!["q", "limit"]);
     }
 }
@@ -1,11 +1,248 @@
 use std::sync::Arc;
 
+use axum::extract::FromRef;
 use sqlx::PgPool;
 
-use crate::{config::Config, service::Service};
+use crate::{
+    cache::CachePolicies,
+    config::Config,
+    container::Container,
+    event::{EventPublisher, FanoutPublisher, broadcast::Broadcaster},
+    handler::{chaos::Chaos, status::RequestStats, version::ApiVersions},
+    messages::MessageCatalog,
+    model::error::{AppError, AppErrorCode},
+    repository::{PostgresRepository, Repository},
+    service::{Service, ServiceApi},
+    storage::{self, Storage},
+};
 
+#[derive(Clone, FromRef)]
 pub struct AppState {
-    pub db_pool: PgPool,
+    pub db_pool: Option<PgPool>,
     pub config: Arc<Config>,
-    pub service: Arc<Service>,
+    pub service: Arc<dyn ServiceApi>,
+    pub broadcaster: Arc<Broadcaster>,
+    pub messages: Arc<MessageCatalog>,
+    pub api_versions: Arc<ApiVersions>,
+    pub stats: Arc<RequestStats>,
+    pub chaos: Arc<Chaos>,
+    /// `Cache-Control` per route group in place of `CACHE_POLICIES`, `None`
+    /// keeps the configured ones.
+    pub cache: Option<CachePolicies>,
+    /// Every other shared part, e.g. auth, mailer or jobs, resolved by type.
+    pub container: Arc<Container>,
+}
+
+impl AppState {
+    pub fn builder() -> AppStateBuilder {
+        AppStateBuilder::default()
+    }
+}
+
+/// Builds the [`Service`] over the repository handed to the builder, once the
+/// config, events and storage are known.
+type ServiceFactory =
+    Box<dyn FnOnce(Arc<Config>, Arc<dyn EventPublisher>, Arc<dyn Storage>) -> Arc<dyn ServiceApi>>;
+
+/// Assembles an [`AppState`] from pluggable parts, only the repository (or a
+/// ready-made service) is required, the cache and event bus are optional.
+#[derive(Default)]
+pub struct AppStateBuilder {
+    config: Option<Arc<Config>>,
+    repository: Option<ServiceFactory>,
+    db_pool: Option<PgPool>,
+    events: Option<Arc<dyn EventPublisher>>,
+    broadcaster: Option<Arc<Broadcaster>>,
+    service: Option<Arc<dyn ServiceApi>>,
+    storage: Option<Arc<dyn Storage>>,
+    messages: Option<MessageCatalog>,
+    cache: Option<CachePolicies>,
+    container: Option<Container>,
+}
+
+impl AppStateBuilder {
+    /// Uses the given config, defaults to [`Config::new`] reading the environment.
+    pub fn config(mut self, config: impl Into<Arc<Config>>) -> Self {
+        self.config = Some(config.into());
+        self
+    }
+
+    /// The service is dispatched statically over `R`.
+    pub fn repository<R: Repository + ?Sized + 'static>(mut self, repository: Arc<R>) -> Self {
+        self.repository = Some(Box::new(move |config, events, storage| {
+            Arc::new(Service::new(config, repository, events).with_storage(storage))
+        }));
+        self
+    }
+
+    /// Backs the state with [`PostgresRepository`] on the given pool, the
+    /// service is then dispatched statically over it.
+    pub fn postgres(mut self, pool: PgPool) -> Self {
+        self.db_pool = Some(pool);
+        self
+    }
+
+    /// Event bus receiving domain events, in addition to the realtime broadcaster.
+    pub fn events(mut self, events: Arc<dyn EventPublisher>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn broadcaster(mut self, broadcaster: Arc<Broadcaster>) -> Self {
+        self.broadcaster = Some(broadcaster);
+        self
+    }
+
+    /// Serves requests from a custom [`ServiceApi`] instead of building a
+    /// [`Service`] over the repository.
+    pub fn service(mut self, service: Arc<dyn ServiceApi>) -> Self {
+        self.service = Some(service);
+        self
+    }
+
+    /// Stores attachments in `storage`, defaults to the `STORAGE_BACKEND` one.
+    pub fn storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Overrides user-facing messages, defaults to the built-in texts.
+    pub fn messages(mut self, messages: MessageCatalog) -> Self {
+        self.messages = Some(messages);
+        self
+    }
+
+    /// Caches responses by the given policies rather than `CACHE_POLICIES`,
+    /// no cache is set by default.
+    pub fn cache(mut self, cache: CachePolicies) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Starts from a container that already holds the application's other
+    /// parts, the config, service and broadcaster get registered into it.
+    pub fn container(mut self, container: Container) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    pub fn build(self) -> Result<AppState, AppError> {
+        let config = self.config.unwrap_or_else(|| Arc::new(Config::new()));
+        let api_versions = Arc::new(ApiVersions::from_config(&config)?);
+        let broadcaster = self.broadcaster.unwrap_or_default();
+
+        let events: Arc<dyn EventPublisher> = match self.events {
+            Some(events) => Arc::new(FanoutPublisher::new(vec![broadcaster.clone(), events])),
+            None => broadcaster.clone(),
+        };
+        let custom_storage = self.storage;
+        let attachment_storage = || match &custom_storage {
+            Some(storage) => Ok(storage.clone()),
+            None => storage::from_config(&config),
+        };
+        let service: Arc<dyn ServiceApi> = match (self.service, self.repository, &self.db_pool) {
+            (Some(service), _, _) => service,
+            (None, Some(repository), _) => {
+                repository(config.clone(), events, attachment_storage()?)
+            }
+            (None, None, Some(pool)) => Arc::new(
+                Service::new(
+                    config.clone(),
+                    Arc::new(PostgresRepository::new(pool.clone())),
+                    events,
+                )
+                .with_storage(attachment_storage()?),
+            ),
+            (None, None, None) => {
+                return Err(AppError {
+                    code: AppErrorCode::InvalidInput,
+                    message: "AppState requires a repository or a service".to_string(),
+                    error_code: None,
+                });
+            }
+        };
+
+        let mut container = self.container.unwrap_or_default();
+        container.insert(config.clone());
+        container.insert(service.clone());
+        container.insert(broadcaster.clone());
+
+        Ok(AppState {
+            db_pool: self.db_pool,
+            config,
+            service,
+            broadcaster,
+            messages: Arc::new(self.messages.unwrap_or_default()),
+            api_versions,
+            stats: Arc::default(),
+            chaos: Arc::default(),
+            cache: self.cache,
+            container: Arc::new(container),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repository::registry::MockRepository, service::registry::MockServiceApi};
+
+    #[test]
+    fn test_builder_requires_repository() {
+        let result = AppState::builder().config(Config::default()).build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builder_with_repository() {
+        let config = Config {
+            app_name: "embedded".into(),
+            ..Config::default()
+        };
+        let state = AppState::builder()
+            .config(config)
+            .repository(Arc::new(MockRepository::new()))
+            .build()
+            .expect("failed to build state");
+        assert_eq!(state.config.app_name, "embedded");
+        assert!(state.db_pool.is_none());
+        assert!(state.cache.is_none());
+    }
+
+    #[test]
+    fn test_builder_with_cache() {
+        let cache = CachePolicies::parse("items=public, max-age=30").unwrap();
+        let state = AppState::builder()
+            .config(Config::default())
+            .service(Arc::new(MockServiceApi::new()))
+            .cache(cache.clone())
+            .build()
+            .unwrap();
+        assert_eq!(state.cache, Some(cache));
+    }
+
+    #[test]
+    fn test_builder_with_custom_service() {
+        let state = AppState::builder()
+            .config(Config::default())
+            .service(Arc::new(MockServiceApi::new()))
+            .build();
+        assert!(state.is_ok());
+    }
+
+    #[test]
+    fn test_builder_registers_core_parts_in_container() {
+        let mut container = Container::new();
+        container.insert(Arc::new(7u8));
+        let state = AppState::builder()
+            .config(Config::default())
+            .service(Arc::new(MockServiceApi::new()))
+            .container(container)
+            .build()
+            .unwrap();
+
+        assert_eq!(*state.container.require::<u8>().unwrap(), 7);
+        assert!(state.container.get::<dyn ServiceApi>().is_some());
+        assert!(state.container.get::<Config>().is_some());
+    }
 }
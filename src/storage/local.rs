@@ -0,0 +1,93 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+
+use super::{ObjectInfo, Storage};
+use crate::model::error::{AppError, AppErrorCode};
+
+fn io_error(e: std::io::Error) -> AppError {
+    let code = match e.kind() {
+        ErrorKind::NotFound => AppErrorCode::NotFound,
+        _ => AppErrorCode::InternalError(e.to_string()),
+    };
+    AppError {
+        code,
+        message: "Failed to access stored file".to_string(),
+        error_code: None,
+    }
+}
+
+/// Files in a directory on the local disk, named after their key.
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    /// Written aside then renamed, readers never see a partial file.
+    async fn put(&self, key: &str, data: Vec<u8>, _: &ObjectInfo) -> Result<(), AppError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(io_error)?;
+        let part = self.dir.join(format!("{}.part", key));
+        tokio::fs::write(&part, data).await.map_err(io_error)?;
+        tokio::fs::rename(&part, self.path(key))
+            .await
+            .map_err(io_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError> {
+        tokio::fs::read(self.path(key)).await.map_err(io_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(io_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn presigned_url(&self, _: &str) -> Result<Option<String>, AppError> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_delete() {
+        let dir = std::env::temp_dir().join(format!("storage-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(&dir);
+        let info = ObjectInfo {
+            content_type: "text/plain".into(),
+            filename: "a.txt".into(),
+        };
+
+        storage.put("a", b"hi".to_vec(), &info).await.unwrap();
+        assert_eq!(storage.get("a").await.unwrap(), b"hi");
+        assert_eq!(storage.presigned_url("a").await.unwrap(), None);
+
+        storage.delete("a").await.unwrap();
+        storage.delete("a").await.unwrap();
+        let err = storage.get("a").await.unwrap_err();
+        assert!(matches!(err.code, AppErrorCode::NotFound));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
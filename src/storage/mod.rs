@@ -0,0 +1,75 @@
+pub mod local;
+pub mod s3;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    config::Config,
+    model::error::{AppError, AppErrorCode},
+};
+
+/// What a stored object is served as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    pub content_type: String,
+    pub filename: String,
+}
+
+impl ObjectInfo {
+    pub fn content_disposition(&self) -> String {
+        format!("attachment; filename=\"{}\"", self.filename)
+    }
+}
+
+/// Blob store for uploaded files, keyed by an opaque id.
+#[async_trait]
+#[cfg_attr(any(test, feature = "mocks"), mockall::automock)]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, data: Vec<u8>, info: &ObjectInfo) -> Result<(), AppError>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError>;
+    /// Succeeds when the object is already gone.
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+    /// Short-lived URL clients can download the object from directly, `None`
+    /// when the backend has no such URLs and the bytes go through the app.
+    async fn presigned_url(&self, key: &str) -> Result<Option<String>, AppError>;
+}
+
+/// Picks the backend named by `STORAGE_BACKEND`, `local` or `s3`.
+pub fn from_config(config: &Config) -> Result<Arc<dyn Storage>, AppError> {
+    match config.storage_backend.as_str() {
+        "local" => Ok(Arc::new(local::LocalStorage::new(&config.attachment_dir))),
+        "s3" => Ok(Arc::new(s3::S3Storage::from_config(config)?)),
+        backend => Err(AppError {
+            code: AppErrorCode::InvalidInput,
+            message: format!("Unknown storage backend '{}'", backend),
+            error_code: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        assert!(from_config(&Config::default()).is_ok());
+
+        let config = Config {
+            storage_backend: "s3".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            from_config(&config).err().unwrap().get_message(),
+            "S3 storage requires S3_BUCKET"
+        );
+
+        let config = Config {
+            storage_backend: "ftp".into(),
+            ..Default::default()
+        };
+        assert!(from_config(&config).is_err());
+    }
+}
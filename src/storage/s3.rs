@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::http::Method;
+use object_store::{
+    Attribute, Attributes, ObjectStore, PutOptions, PutPayload,
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path,
+    signer::Signer,
+};
+
+use super::{ObjectInfo, Storage};
+use crate::{
+    config::Config,
+    model::error::{AppError, AppErrorCode},
+};
+
+fn s3_error(e: object_store::Error) -> AppError {
+    let code = match e {
+        object_store::Error::NotFound { .. } => AppErrorCode::NotFound,
+        _ => AppErrorCode::Unavailable,
+    };
+    AppError {
+        code,
+        message: format!("Object storage request failed: {}", e),
+        error_code: None,
+    }
+}
+
+/// Objects in an S3 bucket, or any S3 compatible store such as MinIO when an
+/// endpoint is configured.
+pub struct S3Storage {
+    store: AmazonS3,
+    presign_ttl: Duration,
+}
+
+impl S3Storage {
+    pub fn from_config(config: &Config) -> Result<Self, AppError> {
+        let bucket = config.s3_bucket.as_deref().ok_or_else(|| AppError {
+            code: AppErrorCode::InvalidInput,
+            message: "S3 storage requires S3_BUCKET".to_string(),
+            error_code: None,
+        })?;
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .with_region(&config.s3_region);
+        if let Some(endpoint) = &config.s3_endpoint {
+            // Self-hosted stores serve buckets as paths, often over plain http.
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"))
+                .with_virtual_hosted_style_request(false);
+        }
+        if let (Some(key_id), Some(secret)) =
+            (&config.s3_access_key_id, &config.s3_secret_access_key)
+        {
+            builder = builder
+                .with_access_key_id(key_id)
+                .with_secret_access_key(secret);
+        }
+        let store = builder.build().map_err(|e| AppError {
+            code: AppErrorCode::InvalidInput,
+            message: format!("Invalid S3 storage config: {}", e),
+            error_code: None,
+        })?;
+        Ok(Self {
+            store,
+            presign_ttl: Duration::from_secs(config.s3_presign_ttl_secs),
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    /// The content type and disposition are stored with the object, so
+    /// presigned downloads are served like the app would.
+    async fn put(&self, key: &str, data: Vec<u8>, info: &ObjectInfo) -> Result<(), AppError> {
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, info.content_type.clone().into());
+        attributes.insert(
+            Attribute::ContentDisposition,
+            info.content_disposition().into(),
+        );
+        let options = PutOptions {
+            attributes,
+            ..Default::default()
+        };
+        self.store
+            .put_opts(&Path::from(key), PutPayload::from(data), options)
+            .await
+            .map(|_| ())
+            .map_err(s3_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError> {
+        let result = self.store.get(&Path::from(key)).await.map_err(s3_error)?;
+        let bytes = result.bytes().await.map_err(s3_error)?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match self.store.delete(&Path::from(key)).await {
+            Err(e) if !matches!(e, object_store::Error::NotFound { .. }) => Err(s3_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn presigned_url(&self, key: &str) -> Result<Option<String>, AppError> {
+        let url = self
+            .store
+            .signed_url(Method::GET, &Path::from(key), self.presign_ttl)
+            .await
+            .map_err(s3_error)?;
+        Ok(Some(url.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_presigned_url_for_minio() {
+        let config = Config {
+            storage_backend: "s3".into(),
+            s3_bucket: Some("attachments".into()),
+            s3_endpoint: Some("http://localhost:9000".into()),
+            s3_access_key_id: Some("minio".into()),
+            s3_secret_access_key: Some("minio-secret".into()),
+            s3_presign_ttl_secs: 60,
+            ..Default::default()
+        };
+        let storage = S3Storage::from_config(&config).unwrap();
+
+        let url = storage.presigned_url("a1").await.unwrap().unwrap();
+
+        assert!(url.starts_with("http://localhost:9000/attachments/a1?"));
+        assert!(url.contains("X-Amz-Expires=60"));
+        assert!(url.contains("X-Amz-Signature="));
+    }
+}
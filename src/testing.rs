@@ -0,0 +1,219 @@
+//! Helpers for driving the full router in tests without a server or database.
+//!
+//! ```ignore
+//! let app = TestApp::new();
+//! let res = app.create_item("book").await;
+//! assert_eq!(res.status, StatusCode::CREATED);
+//! let item: Item = res.data();
+//! ```
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::{Body, Bytes, to_bytes},
+    http::{HeaderMap, Method, Request, StatusCode, header::CONTENT_TYPE},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use tower::ServiceExt;
+
+use crate::{
+    app::build_router,
+    config::Config,
+    handler::{API_V1_PREFIX, ITEMS_PATH, USERS_PATH},
+    model::http::Response,
+    repository::InMemoryRepository,
+    state::AppState,
+};
+
+/// Full application router over an in-memory repository, or any state given
+/// to [`TestApp::with_state`].
+pub struct TestApp {
+    pub state: AppState,
+    router: Router,
+}
+
+/// Captured response, the body is buffered so it can be inspected repeatedly.
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    /// Parses the body as JSON, panicking with the raw body when it isn't.
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| {
+            panic!(
+                "response body is not the expected JSON ({}): {}",
+                e,
+                String::from_utf8_lossy(&self.body)
+            )
+        })
+    }
+
+    pub fn envelope<T: DeserializeOwned>(&self) -> Response<T> {
+        self.json()
+    }
+
+    /// The envelope's `data`, panicking when it is absent.
+    pub fn data<T: DeserializeOwned>(&self) -> T {
+        self.envelope::<T>()
+            .data
+            .expect("response envelope has no data")
+    }
+}
+
+impl Default for TestApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestApp {
+    /// App backed by a fresh [`InMemoryRepository`] and default config.
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        let state = AppState::builder()
+            .config(config)
+            .repository(Arc::new(InMemoryRepository::new()))
+            .build()
+            .expect("failed to build test state");
+        Self::with_state(state)
+    }
+
+    pub fn with_state(state: AppState) -> Self {
+        Self {
+            router: build_router(state.clone()),
+            state,
+        }
+    }
+
+    pub async fn request(&self, req: Request<Body>) -> TestResponse {
+        let res = self
+            .router
+            .clone()
+            .oneshot(req)
+            .await
+            .expect("router is infallible");
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = to_bytes(res.into_body(), usize::MAX)
+            .await
+            .expect("failed to read response body");
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    pub async fn get(&self, uri: &str) -> TestResponse {
+        self.send(Method::GET, uri, None).await
+    }
+
+    pub async fn head(&self, uri: &str) -> TestResponse {
+        self.send(Method::HEAD, uri, None).await
+    }
+
+    pub async fn delete(&self, uri: &str) -> TestResponse {
+        self.send(Method::DELETE, uri, None).await
+    }
+
+    pub async fn post_json<T: Serialize>(&self, uri: &str, body: &T) -> TestResponse {
+        self.send(Method::POST, uri, Some(json!(body))).await
+    }
+
+    pub async fn put_json<T: Serialize>(&self, uri: &str, body: &T) -> TestResponse {
+        self.send(Method::PUT, uri, Some(json!(body))).await
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> TestResponse {
+        let req = Request::builder().method(method).uri(uri);
+        let req = match body {
+            Some(body) => req
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => req.body(Body::empty()),
+        };
+        self.request(req.expect("invalid test request")).await
+    }
+
+    pub async fn create_item(&self, name: &str) -> TestResponse {
+        self.post_json(&v1(ITEMS_PATH), &json!({ "name": name }))
+            .await
+    }
+
+    pub async fn list_items(&self) -> TestResponse {
+        self.get(&v1(ITEMS_PATH)).await
+    }
+
+    pub async fn get_item(&self, id: &str) -> TestResponse {
+        self.get(&format!("{}/{}", v1(ITEMS_PATH), id)).await
+    }
+
+    pub async fn update_item(&self, id: &str, name: &str) -> TestResponse {
+        self.put_json(
+            &format!("{}/{}", v1(ITEMS_PATH), id),
+            &json!({ "name": name }),
+        )
+        .await
+    }
+
+    pub async fn delete_item(&self, id: &str) -> TestResponse {
+        self.delete(&format!("{}/{}", v1(ITEMS_PATH), id)).await
+    }
+
+    pub async fn create_user(&self, email: &str) -> TestResponse {
+        self.post_json(&v1(USERS_PATH), &json!({ "email": email }))
+            .await
+    }
+
+    pub async fn list_users(&self) -> TestResponse {
+        self.get(&v1(USERS_PATH)).await
+    }
+
+    pub async fn get_user(&self, id: &str) -> TestResponse {
+        self.get(&format!("{}/{}", v1(USERS_PATH), id)).await
+    }
+
+    pub async fn get_user_by_email(&self, email: &str) -> TestResponse {
+        self.get(&format!("{}/by-email/{}", v1(USERS_PATH), email))
+            .await
+    }
+
+    pub async fn update_user(&self, id: &str, email: &str) -> TestResponse {
+        self.put_json(
+            &format!("{}/{}", v1(USERS_PATH), id),
+            &json!({ "email": email }),
+        )
+        .await
+    }
+
+    pub async fn confirm_user_email(&self, id: &str, token: &str) -> TestResponse {
+        self.post_json(
+            &format!("{}/{}/confirm-email", v1(USERS_PATH), id),
+            &json!({ "token": token }),
+        )
+        .await
+    }
+
+    pub async fn delete_user(&self, id: &str) -> TestResponse {
+        self.delete(&format!("{}/{}", v1(USERS_PATH), id)).await
+    }
+}
+
+/// `path` under the current API version.
+fn v1(path: &str) -> String {
+    format!("{}{}", API_V1_PREFIX, path)
+}
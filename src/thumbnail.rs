@@ -0,0 +1,221 @@
+use std::{io::Cursor, sync::Arc};
+
+use async_trait::async_trait;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::{
+        error::{AppError, AppErrorCode},
+        job::Job,
+    },
+    storage::{ObjectInfo, Storage},
+    worker::JobHandler,
+};
+
+/// Job kind rendering the thumbnails of an image attachment, handled by
+/// [`ThumbnailHandler`].
+pub const GENERATE_THUMBNAILS_JOB: &str = "attachment.thumbnails";
+
+/// Named box thumbnails are scaled down into, keeping their aspect ratio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThumbnailSize {
+    pub name: String,
+    /// Longest edge, in pixels.
+    pub max: u32,
+}
+
+impl ThumbnailSize {
+    /// Parses `thumb=256,medium=1024`.
+    pub fn parse_all(sizes: &str) -> Result<Vec<Self>, AppError> {
+        let invalid = |size: &str| AppError {
+            code: AppErrorCode::InvalidInput,
+            message: format!("Invalid thumbnail size '{}'", size),
+            error_code: None,
+        };
+        sizes
+            .split(',')
+            .map(str::trim)
+            .filter(|size| !size.is_empty())
+            .map(|size| {
+                let (name, max) = size.split_once('=').ok_or_else(|| invalid(size))?;
+                let name = name.trim();
+                let max = max.trim().parse::<u32>().map_err(|_| invalid(size))?;
+                if max == 0 || name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(invalid(size));
+                }
+                Ok(Self {
+                    name: name.to_string(),
+                    max,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Storage key of the `size` thumbnail of the object stored under `key`.
+pub fn thumbnail_key(key: &str, size: &str) -> String {
+    format!("{}.{}", key, size)
+}
+
+/// Whether thumbnails can be rendered for files of this media type.
+pub fn is_supported(content_type: &str) -> bool {
+    ImageFormat::from_mime_type(content_type).is_some_and(|format| format.reading_enabled())
+}
+
+/// JPEG photos stay JPEG, everything else becomes PNG to keep transparency.
+pub fn thumbnail_content_type(content_type: &str) -> &'static str {
+    match content_type {
+        "image/jpeg" => "image/jpeg",
+        _ => "image/png",
+    }
+}
+
+fn image_error(e: image::ImageError) -> AppError {
+    AppError {
+        code: AppErrorCode::InvalidInput,
+        message: format!("Failed to render thumbnail: {}", e),
+        error_code: None,
+    }
+}
+
+/// Scales `image` into the `max` box, smaller images are only re-encoded.
+fn render(image: &DynamicImage, max: u32, content_type: &str) -> Result<Vec<u8>, AppError> {
+    let image = if image.width() > max || image.height() > max {
+        image.thumbnail(max, max)
+    } else {
+        image.clone()
+    };
+    let mut out = Cursor::new(vec![]);
+    match content_type {
+        "image/jpeg" => {
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut out, ImageFormat::Jpeg)
+        }
+        _ => image.write_to(&mut out, ImageFormat::Png),
+    }
+    .map_err(image_error)?;
+    Ok(out.into_inner())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThumbnailPayload {
+    pub key: String,
+    pub content_type: String,
+    pub filename: String,
+}
+
+/// Renders every configured size of an image stored under the payload key,
+/// next to the original.
+pub struct ThumbnailHandler {
+    storage: Arc<dyn Storage>,
+    sizes: Vec<ThumbnailSize>,
+}
+
+impl ThumbnailHandler {
+    pub fn new(storage: Arc<dyn Storage>, sizes: Vec<ThumbnailSize>) -> Self {
+        Self { storage, sizes }
+    }
+}
+
+#[async_trait]
+impl JobHandler for ThumbnailHandler {
+    async fn handle(&self, job: &Job) -> Result<(), AppError> {
+        let payload: ThumbnailPayload =
+            serde_json::from_value(job.payload.clone()).map_err(|e| AppError {
+                code: AppErrorCode::InvalidInput,
+                message: format!("Invalid thumbnail job payload: {}", e),
+                error_code: None,
+            })?;
+        let data = self.storage.get(&payload.key).await?;
+        let content_type = thumbnail_content_type(&payload.content_type);
+
+        // Decoding and scaling are CPU bound, keep them off the async workers.
+        let sizes = self.sizes.clone();
+        let thumbnails = tokio::task::spawn_blocking(move || {
+            let image = image::load_from_memory(&data).map_err(image_error)?;
+            sizes
+                .into_iter()
+                .map(|size| Ok((size.name, render(&image, size.max, content_type)?)))
+                .collect::<Result<Vec<_>, AppError>>()
+        })
+        .await
+        .map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Thumbnail rendering panicked".to_string(),
+            error_code: None,
+        })??;
+
+        for (name, data) in thumbnails {
+            let info = ObjectInfo {
+                content_type: content_type.to_string(),
+                filename: format!("{}-{}", name, payload.filename),
+            };
+            self.storage
+                .put(&thumbnail_key(&payload.key, &name), data, &info)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{model::job::NewJob, storage::local::LocalStorage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut out = Cursor::new(vec![]);
+        DynamicImage::new_rgba8(width, height)
+            .write_to(&mut out, ImageFormat::Png)
+            .unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_parse_sizes() {
+        let sizes = ThumbnailSize::parse_all("thumb=256, medium = 1024").unwrap();
+        assert_eq!(sizes[1].name, "medium");
+        assert_eq!(sizes[1].max, 1024);
+        assert!(ThumbnailSize::parse_all("").unwrap().is_empty());
+        assert!(ThumbnailSize::parse_all("thumb").is_err());
+        assert!(ThumbnailSize::parse_all("thumb=0").is_err());
+        assert!(ThumbnailSize::parse_all("../x=10").is_err());
+    }
+
+    #[test]
+    fn test_supported_types() {
+        assert!(is_supported("image/png"));
+        assert!(is_supported("image/jpeg"));
+        assert!(!is_supported("image/svg+xml"));
+        assert!(!is_supported("application/pdf"));
+    }
+
+    #[tokio::test]
+    async fn test_renders_every_size() {
+        let dir = std::env::temp_dir().join(format!("thumbnails-{}", uuid::Uuid::new_v4()));
+        let storage = Arc::new(LocalStorage::new(&dir));
+        let info = ObjectInfo {
+            content_type: "image/png".into(),
+            filename: "logo.png".into(),
+        };
+        storage.put("a1", png(400, 200), &info).await.unwrap();
+        let sizes = ThumbnailSize::parse_all("thumb=100,large=800").unwrap();
+        let handler = ThumbnailHandler::new(storage.clone(), sizes);
+        let payload = ThumbnailPayload {
+            key: "a1".into(),
+            content_type: "image/png".into(),
+            filename: "logo.png".into(),
+        };
+
+        let job = Job::from(NewJob::new(GENERATE_THUMBNAILS_JOB, json!(payload)));
+        handler.handle(&job).await.unwrap();
+
+        let thumb = image::load_from_memory(&storage.get("a1.thumb").await.unwrap()).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (100, 50));
+        let large = image::load_from_memory(&storage.get("a1.large").await.unwrap()).unwrap();
+        assert_eq!((large.width(), large.height()), (400, 200));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+//! Request timeouts per route group, so slow exports and a quick healthcheck
+//! don't have to share one budget. Configured with `ROUTE_TIMEOUTS_MS`, e.g.
+//! `default=30000,exports=120000,healthcheck=2000`, and applied as a layer on
+//! each group's router by [`crate::app::build_router`].
+//!
+//! Callers can shorten the budget with their own deadline, see
+//! [`deadline_middleware`].
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+
+use crate::{
+    handler::{DEFAULT_GROUP, ROUTE_GROUPS},
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode, ErrorCode},
+        http::ApiResponse,
+    },
+};
+
+/// Absolute deadline of the caller as an RFC 3339 time.
+pub const X_REQUEST_DEADLINE: &str = "X-Request-Deadline";
+/// Relative deadline as sent by gRPC clients, e.g. `250m` for 250 ms.
+pub const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/// Timeout of each route group, `None` when the group runs unbounded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteTimeouts {
+    default: Option<Duration>,
+    groups: HashMap<String, Option<Duration>>,
+}
+
+impl RouteTimeouts {
+    /// Parses `default=30000,exports=120000` in milliseconds, 0 disables
+    /// the timeout of a group.
+    pub fn parse(timeouts: &str) -> Result<Self, AppError> {
+        let invalid = |entry: &str| AppError {
+            code: AppErrorCode::InvalidInput,
+            message: format!("Invalid route timeout '{}'", entry),
+            error_code: None,
+        };
+        let mut parsed = Self::default();
+        for entry in timeouts.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (group, ms) = entry.split_once('=').ok_or_else(|| invalid(entry))?;
+            let group = group.trim();
+            let ms = ms.trim().parse::<u64>().map_err(|_| invalid(entry))?;
+            if !ROUTE_GROUPS.contains(&group) {
+                return Err(AppError {
+                    code: AppErrorCode::InvalidInput,
+                    message: format!(
+                        "Unknown route group '{}', expected one of {}",
+                        group,
+                        ROUTE_GROUPS.join(", ")
+                    ),
+                    error_code: None,
+                });
+            }
+            let timeout = Some(Duration::from_millis(ms)).filter(|t| !t.is_zero());
+            if group == DEFAULT_GROUP {
+                parsed.default = timeout;
+            } else {
+                parsed.groups.insert(group.to_string(), timeout);
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn get(&self, group: &str) -> Option<Duration> {
+        self.groups.get(group).copied().unwrap_or(self.default)
+    }
+}
+
+/// Answers `503` with `TIMEOUT` when the inner routes take longer than
+/// `timeout`, and hands the deadline to the services through [`Ctx`].
+pub async fn timeout_middleware(
+    State(timeout): State<Option<Duration>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(timeout) = timeout else {
+        return next.run(req).await;
+    };
+    let mut correlation_id = String::new();
+    if let Some(ctx) = req.extensions_mut().get_mut::<Ctx>() {
+        let deadline = Instant::now() + timeout;
+        ctx.deadline = Some(ctx.deadline.map_or(deadline, |d| d.min(deadline)));
+        correlation_id = ctx.correlation_id.clone();
+    }
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(res) => res,
+        Err(_) => {
+            let e = AppError {
+                code: AppErrorCode::Unavailable,
+                message: format!("Request timed out after {} ms", timeout.as_millis()),
+                error_code: Some(ErrorCode::Timeout),
+            };
+            ApiResponse::<()>::error(correlation_id, e).into_response()
+        }
+    }
+}
+
+/// Time the caller gave the request, zero when its deadline already passed.
+/// Malformed values are ignored.
+pub fn caller_budget(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(deadline) =
+        header(X_REQUEST_DEADLINE).and_then(|v| DateTime::parse_from_rfc3339(v.trim()).ok())
+    {
+        let left = deadline.with_timezone(&Utc) - Utc::now();
+        return Some(left.to_std().unwrap_or_default());
+    }
+    header(GRPC_TIMEOUT).and_then(parse_grpc_timeout)
+}
+
+/// At most 8 digits and a unit: `H`ours, `M`inutes, `S`econds, `m`illis,
+/// `u`micros or `n`anos.
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let (digits, unit) = value.split_at_checked(value.len().checked_sub(1)?)?;
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n = digits.parse::<u64>().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(n * 3600),
+        "M" => Duration::from_secs(n * 60),
+        "S" => Duration::from_secs(n),
+        "m" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    })
+}
+
+/// Honors the caller's deadline: answers `504` right away when it already
+/// passed, and otherwise once it does, handing it to the services through
+/// [`Ctx`] so their queries stop in time too.
+pub async fn deadline_middleware(mut req: Request, next: Next) -> Response {
+    let Some(budget) = caller_budget(req.headers()) else {
+        return next.run(req).await;
+    };
+    let mut correlation_id = String::new();
+    if let Some(ctx) = req.extensions_mut().get_mut::<Ctx>() {
+        ctx.deadline = Some(Instant::now() + budget);
+        correlation_id = ctx.correlation_id.clone();
+    }
+    let exceeded = |correlation_id| {
+        ApiResponse::<()>::error(correlation_id, AppError::deadline_exceeded()).into_response()
+    };
+    if budget.is_zero() {
+        return exceeded(correlation_id);
+    }
+    match tokio::time::timeout(budget, next.run(req)).await {
+        Ok(res) => res,
+        Err(_) => exceeded(correlation_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::StatusCode,
+        middleware::{from_fn, from_fn_with_state},
+        routing::get,
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::middleware::request_middleware;
+
+    #[test]
+    fn test_parse() {
+        let timeouts = RouteTimeouts::parse("default=30000, exports = 120000,events=0").unwrap();
+        assert_eq!(timeouts.get("exports"), Some(Duration::from_secs(120)));
+        assert_eq!(timeouts.get("items"), Some(Duration::from_secs(30)));
+        assert_eq!(timeouts.get("events"), None);
+        assert_eq!(RouteTimeouts::parse("").unwrap().get("items"), None);
+        assert!(RouteTimeouts::parse("exports").is_err());
+        assert!(RouteTimeouts::parse("exports=soon").is_err());
+        assert!(RouteTimeouts::parse("reports=1000").is_err());
+    }
+
+    async fn send(timeout: Option<Duration>) -> (StatusCode, Value) {
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(move |ctx: Ctx| async move {
+                    assert_eq!(ctx.deadline.is_some(), timeout.is_some());
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    "done"
+                }),
+            )
+            .layer(from_fn_with_state(timeout, timeout_middleware))
+            .layer(from_fn(request_middleware));
+        let req = Request::get("/slow").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let status = res.status();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_times_out() {
+        let (status, body) = send(Some(Duration::from_millis(10))).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error_code"], "TIMEOUT");
+        assert!(!body["correlation_id"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_caller_budget() {
+        let budget = |name, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+            caller_budget(&headers)
+        };
+        assert_eq!(
+            budget(GRPC_TIMEOUT, "250m"),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(budget(GRPC_TIMEOUT, "2S"), Some(Duration::from_secs(2)));
+        assert_eq!(budget(GRPC_TIMEOUT, "123456789m"), None);
+        assert_eq!(budget(GRPC_TIMEOUT, "m"), None);
+        assert_eq!(budget(GRPC_TIMEOUT, "10x"), None);
+        assert_eq!(
+            budget(X_REQUEST_DEADLINE, "2000-01-01T00:00:00Z"),
+            Some(Duration::ZERO)
+        );
+        let later = (Utc::now() + Duration::from_secs(60)).to_rfc3339();
+        assert!(budget(X_REQUEST_DEADLINE, &later).unwrap() > Duration::from_secs(50));
+        assert_eq!(budget(X_REQUEST_DEADLINE, "tomorrow"), None);
+    }
+
+    #[tokio::test]
+    async fn test_caller_deadline() {
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(|ctx: Ctx| async move {
+                    assert!(ctx.deadline.is_some());
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    "done"
+                }),
+            )
+            .layer(from_fn(deadline_middleware))
+            .layer(from_fn(request_middleware));
+        let send = |timeout: &str| {
+            let req = Request::get("/slow")
+                .header(GRPC_TIMEOUT, timeout)
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(req)
+        };
+
+        let res = send("10m").await.unwrap();
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error_code"], "DEADLINE_EXCEEDED");
+        assert_eq!(
+            send("0m").await.unwrap().status(),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(send("5S").await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_within_budget() {
+        assert_eq!(send(Some(Duration::from_secs(5))).await.0, StatusCode::OK);
+        assert_eq!(send(None).await.0, StatusCode::OK);
+    }
+}
@@ -0,0 +1,529 @@
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    container::Component,
+    model::{
+        error::{AppError, AppErrorCode},
+        job::Job,
+    },
+    repository::erased::DynJobRepository,
+};
+
+/// Longest wait between two attempts of a failing job.
+const MAX_BACKOFF_SECS: i64 = 300;
+
+/// Runs the jobs of one kind.
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    async fn handle(&self, job: &Job) -> Result<(), AppError>;
+}
+
+/// Pool of tasks pulling due jobs and dispatching them to the handler
+/// registered for their kind. Failed jobs are retried with exponential
+/// backoff until they run out of attempts.
+pub struct JobWorker {
+    runner: Arc<Runner>,
+    concurrency: usize,
+    poll_interval: Duration,
+    drain_timeout: Duration,
+    stop: watch::Sender<bool>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+struct Runner {
+    repo: Arc<dyn DynJobRepository>,
+    handlers: HashMap<String, Arc<dyn JobHandler>>,
+    lease: Duration,
+    /// Jobs claimed and not yet completed or failed.
+    in_flight: Mutex<HashSet<String>>,
+}
+
+/// Collects the handlers and settings of a [`JobWorker`].
+pub struct JobWorkerBuilder {
+    repo: Arc<dyn DynJobRepository>,
+    handlers: HashMap<String, Arc<dyn JobHandler>>,
+    duplicates: Vec<String>,
+    concurrency: usize,
+    poll_interval: Duration,
+    drain_timeout: Duration,
+    lease: Duration,
+}
+
+impl JobWorkerBuilder {
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// How long an idle task waits before looking for due jobs again.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// How long shutdown waits for running jobs, the ones still running
+    /// afterwards are aborted and handed back to the queue.
+    pub fn drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// How long a claimed job stays locked to this worker. It is renewed
+    /// while the handler runs, so only the jobs of a dead worker expire and
+    /// get claimed again.
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    pub fn handler(mut self, kind: &str, handler: Arc<dyn JobHandler>) -> Self {
+        if self.handlers.insert(kind.to_string(), handler).is_some() {
+            self.duplicates.push(kind.to_string());
+        }
+        self
+    }
+
+    /// Fails when a kind got more than one handler.
+    pub fn build(self) -> Result<JobWorker, AppError> {
+        if !self.duplicates.is_empty() {
+            return Err(AppError {
+                code: AppErrorCode::InvalidInput,
+                message: format!(
+                    "Job kinds registered more than once: {}",
+                    self.duplicates.join(", ")
+                ),
+                error_code: None,
+            });
+        }
+        Ok(JobWorker {
+            runner: Arc::new(Runner {
+                repo: self.repo,
+                handlers: self.handlers,
+                lease: self.lease,
+                in_flight: Default::default(),
+            }),
+            concurrency: self.concurrency,
+            poll_interval: self.poll_interval,
+            drain_timeout: self.drain_timeout,
+            stop: watch::channel(false).0,
+            tasks: Default::default(),
+        })
+    }
+}
+
+impl JobWorker {
+    pub fn builder(repo: Arc<dyn DynJobRepository>) -> JobWorkerBuilder {
+        JobWorkerBuilder {
+            repo,
+            handlers: HashMap::new(),
+            duplicates: vec![],
+            concurrency: 1,
+            poll_interval: Duration::from_secs(1),
+            drain_timeout: Duration::from_secs(30),
+            lease: Duration::from_secs(60),
+        }
+    }
+
+    /// Claims and runs a single due job, returns whether there was one.
+    pub async fn run_once(&self) -> Result<bool, AppError> {
+        self.runner.run_once().await
+    }
+
+    /// Stops claiming new jobs without waiting, e.g. as soon as the shutdown
+    /// signal arrives. Jobs already running carry on.
+    pub fn stop(&self) {
+        self.stop.send_replace(true);
+    }
+}
+
+impl Runner {
+    fn locked_until(&self) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::from_std(self.lease).unwrap_or(chrono::Duration::MAX)
+    }
+
+    async fn run_once(&self) -> Result<bool, AppError> {
+        let Some(job) = self.repo.claim(self.locked_until()).await? else {
+            return Ok(false);
+        };
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.insert(job.id.clone());
+        }
+        let result = self.run(&job).await;
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&job.id);
+        }
+        result.map(|_| true)
+    }
+
+    async fn run(&self, job: &Job) -> Result<(), AppError> {
+        let result = match self.handlers.get(&job.kind) {
+            // Reclaimed after its lease ran out on the last attempt, most
+            // likely the job itself brought the worker down.
+            _ if job.attempts > job.max_attempts => Err(AppError {
+                code: AppErrorCode::InternalError(job.kind.clone()),
+                message: "Job lease expired on its last attempt".to_string(),
+                error_code: None,
+            }),
+            Some(handler) => self.handle(handler.as_ref(), job).await,
+            None => Err(AppError {
+                code: AppErrorCode::InternalError(job.kind.clone()),
+                message: format!("No handler for job kind {}", job.kind),
+                error_code: None,
+            }),
+        };
+
+        match result {
+            Ok(()) => {
+                self.repo.complete(&job.id).await?;
+                tracing::info!(job_id = %job.id, kind = %job.kind, "Job done");
+            }
+            Err(e) => {
+                let backoff = 2_i64
+                    .pow(job.attempts.clamp(0, 16) as u32)
+                    .min(MAX_BACKOFF_SECS);
+                let retry_at = Utc::now() + chrono::Duration::seconds(backoff);
+                self.repo.fail(&job.id, &e.get_message(), retry_at).await?;
+                tracing::warn!(
+                    job_id = %job.id,
+                    kind = %job.kind,
+                    attempts = job.attempts,
+                    error = %e.get_error(),
+                    "{}",
+                    e.get_message()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the handler while renewing the job's lease, a panic fails the
+    /// attempt like an error.
+    async fn handle(&self, handler: &dyn JobHandler, job: &Job) -> Result<(), AppError> {
+        let handle = AssertUnwindSafe(handler.handle(job)).catch_unwind();
+        tokio::pin!(handle);
+        let mut heartbeat = tokio::time::interval((self.lease / 3).max(Duration::from_millis(10)));
+        heartbeat.tick().await;
+        loop {
+            tokio::select! {
+                result = &mut handle => {
+                    return result.unwrap_or_else(|panic| Err(AppError {
+                        code: AppErrorCode::InternalError(panic_message(panic)),
+                        message: "Job handler panicked".to_string(),
+                        error_code: None,
+                    }));
+                }
+                _ = heartbeat.tick() => {
+                    if let Err(e) = self.repo.heartbeat(&job.id, self.locked_until()).await {
+                        tracing::warn!(job_id = %job.id, error = %e.get_error(), "{}", e.get_message());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .unwrap_or_else(|| "panic".to_string()),
+    }
+}
+
+#[async_trait]
+impl Component for JobWorker {
+    fn name(&self) -> &'static str {
+        "jobs"
+    }
+
+    async fn start(&self) -> Result<(), AppError> {
+        let mut tasks = self.tasks.lock().map_err(|e| AppError {
+            code: AppErrorCode::InternalError(e.to_string()),
+            message: "Failed to lock job workers".to_string(),
+            error_code: None,
+        })?;
+        for _ in 0..self.concurrency {
+            let runner = self.runner.clone();
+            let poll_interval = self.poll_interval;
+            let mut stop = self.stop.subscribe();
+            tasks.push(tokio::spawn(async move {
+                while !*stop.borrow() {
+                    match runner.run_once().await {
+                        // Keep draining while jobs are due.
+                        Ok(true) => continue,
+                        Ok(false) => {}
+                        Err(e) => tracing::warn!(error = %e.get_error(), "{}", e.get_message()),
+                    }
+                    tokio::select! {
+                        _ = stop.changed() => {}
+                        _ = tokio::time::sleep(poll_interval) => {}
+                    }
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    /// Stops claiming and lets running jobs finish within the drain timeout.
+    /// Jobs still running then are aborted and released back to pending, so
+    /// another instance picks them up without losing an attempt.
+    async fn shutdown(&self) -> Result<(), AppError> {
+        self.stop();
+        let tasks = match self.tasks.lock() {
+            Ok(mut tasks) => std::mem::take(&mut *tasks),
+            Err(_) => vec![],
+        };
+        let aborts: Vec<_> = tasks.iter().map(|task| task.abort_handle()).collect();
+        let drained = tokio::time::timeout(self.drain_timeout, futures::future::join_all(tasks))
+            .await
+            .is_ok();
+        if drained {
+            return Ok(());
+        }
+
+        aborts.iter().for_each(|abort| abort.abort());
+        let in_flight = match self.runner.in_flight.lock() {
+            Ok(mut in_flight) => std::mem::take(&mut *in_flight),
+            Err(_) => HashSet::new(),
+        };
+        for id in in_flight {
+            match self.runner.repo.release(&id).await {
+                Ok(()) => tracing::warn!(job_id = %id, "Job released unfinished at shutdown"),
+                Err(e) => {
+                    tracing::warn!(job_id = %id, error = %e.get_error(), "{}", e.get_message())
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        model::job::{JobStatus, NewJob},
+        repository::job::InMemoryJobRepository,
+    };
+
+    struct Recorder(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl JobHandler for Recorder {
+        async fn handle(&self, job: &Job) -> Result<(), AppError> {
+            self.0.lock().unwrap().push(job.payload["to"].to_string());
+            Ok(())
+        }
+    }
+
+    async fn enqueue(repo: &InMemoryJobRepository, job: NewJob) -> Job {
+        repo.add(Job::from(job)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_runs_due_job_with_its_handler() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("email", json!({"to": "a@b.com"}))).await;
+        let handler = Arc::new(Recorder(Mutex::new(vec![])));
+        let worker = JobWorker::builder(repo.clone())
+            .handler("email", handler.clone())
+            .build()
+            .unwrap();
+
+        assert!(worker.run_once().await.unwrap());
+        assert!(!worker.run_once().await.unwrap());
+
+        assert_eq!(*handler.0.lock().unwrap(), vec!["\"a@b.com\""]);
+        assert_eq!(repo.get(&job.id).await.unwrap().status, JobStatus::Done);
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_is_retried_until_out_of_attempts() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("unknown", json!({})).max_attempts(2)).await;
+        let worker = JobWorker::builder(repo.clone()).build().unwrap();
+
+        worker.run_once().await.unwrap();
+        let retried = repo.get(&job.id).await.unwrap();
+        assert_eq!(retried.status, JobStatus::Pending);
+        assert!(retried.run_at > Utc::now());
+        assert_eq!(
+            retried.last_error.as_deref(),
+            Some("No handler for job kind unknown")
+        );
+
+        // Make it due again for the last attempt.
+        repo.jobs.lock().unwrap()[0].run_at = Utc::now();
+        worker.run_once().await.unwrap();
+        let failed = repo.get(&job.id).await.unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_component_processes_until_shutdown() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("email", json!({"to": "a@b.com"}))).await;
+        let worker = JobWorker::builder(repo.clone())
+            .concurrency(2)
+            .poll_interval(Duration::from_millis(10))
+            .handler("email", Arc::new(Recorder(Mutex::new(vec![]))))
+            .build()
+            .unwrap();
+
+        worker.start().await.unwrap();
+        for _ in 0..100 {
+            if repo.get(&job.id).await.unwrap().status == JobStatus::Done {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        worker.shutdown().await.unwrap();
+
+        assert_eq!(repo.get(&job.id).await.unwrap().status, JobStatus::Done);
+        assert!(worker.tasks.lock().unwrap().is_empty());
+    }
+
+    struct Stuck;
+
+    #[async_trait]
+    impl JobHandler for Stuck {
+        async fn handle(&self, _: &Job) -> Result<(), AppError> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_releases_jobs_running_past_the_deadline() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("slow", json!({}))).await;
+        let worker = JobWorker::builder(repo.clone())
+            .poll_interval(Duration::from_millis(10))
+            .drain_timeout(Duration::from_millis(50))
+            .handler("slow", Arc::new(Stuck))
+            .build()
+            .unwrap();
+
+        worker.start().await.unwrap();
+        for _ in 0..100 {
+            if repo.get(&job.id).await.unwrap().status == JobStatus::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        worker.shutdown().await.unwrap();
+
+        let released = repo.get(&job.id).await.unwrap();
+        assert_eq!(released.status, JobStatus::Pending);
+        assert_eq!(released.attempts, 0);
+        assert!(worker.runner.in_flight.lock().unwrap().is_empty());
+    }
+
+    struct Panics;
+
+    #[async_trait]
+    impl JobHandler for Panics {
+        async fn handle(&self, _: &Job) -> Result<(), AppError> {
+            panic!("bad payload")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_fails_the_attempt() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("bad", json!({})).max_attempts(1)).await;
+        let worker = JobWorker::builder(repo.clone())
+            .handler("bad", Arc::new(Panics))
+            .build()
+            .unwrap();
+
+        assert!(worker.run_once().await.unwrap());
+
+        let failed = repo.get(&job.id).await.unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.last_error.as_deref(), Some("Job handler panicked"));
+        assert!(repo.leases.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_is_claimed_again() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("email", json!({"to": "a@b.com"}))).await;
+        // A worker that died right after claiming.
+        repo.claim(Utc::now() + chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        let handler = Arc::new(Recorder(Mutex::new(vec![])));
+        let worker = JobWorker::builder(repo.clone())
+            .handler("email", handler.clone())
+            .build()
+            .unwrap();
+
+        assert!(!worker.run_once().await.unwrap());
+        repo.leases
+            .lock()
+            .unwrap()
+            .insert(job.id.clone(), Utc::now() - chrono::Duration::seconds(1));
+        assert!(worker.run_once().await.unwrap());
+
+        let done = repo.get(&job.id).await.unwrap();
+        assert_eq!(done.status, JobStatus::Done);
+        assert_eq!(done.attempts, 2);
+        assert_eq!(handler.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_on_last_attempt_fails_the_job() {
+        let repo = Arc::new(InMemoryJobRepository::new());
+        let job = enqueue(&repo, NewJob::new("email", json!({})).max_attempts(1)).await;
+        repo.claim(Utc::now() - chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        let handler = Arc::new(Recorder(Mutex::new(vec![])));
+        let worker = JobWorker::builder(repo.clone())
+            .handler("email", handler.clone())
+            .build()
+            .unwrap();
+
+        assert!(worker.run_once().await.unwrap());
+
+        let failed = repo.get(&job.id).await.unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(
+            failed.last_error.as_deref(),
+            Some("Job lease expired on its last attempt")
+        );
+        assert!(handler.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_build_rejects_duplicate_handlers() {
+        let err = JobWorker::builder(Arc::new(InMemoryJobRepository::new()))
+            .handler("email", Arc::new(Stuck))
+            .handler("email", Arc::new(Stuck))
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.code, AppErrorCode::InvalidInput));
+        assert_eq!(
+            err.get_message(),
+            "Job kinds registered more than once: email"
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use crud_rust::{
+    config::Config,
+    model::{error::ErrorCode, item::Item, user::User},
+    testing::{TestApp, TestResponse},
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn item_lifecycle() {
+    let app = TestApp::new();
+
+    let res = app.create_item("Book").await;
+    assert_eq!(res.status, StatusCode::CREATED);
+    let item: Item = res.data();
+    assert_eq!(item.name, "book");
+    let res = app.create_item("BOOK").await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.envelope::<()>().error_code, Some(ErrorCode::NameTaken));
+
+    let res = app.update_item(&item.id, "notebook").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.data::<Item>().name, "notebook");
+
+    let items: Vec<Item> = app.list_items().await.data();
+    assert_eq!(items.len(), 1);
+
+    let uri = format!("/api/v1/items/{}", item.id);
+    let res = app.head(&uri).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(res.body.is_empty());
+
+    assert_eq!(app.delete_item(&item.id).await.status, StatusCode::OK);
+    assert_eq!(app.head(&uri).await.status, StatusCode::NOT_FOUND);
+    let res = app.get_item(&item.id).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        res.envelope::<()>().error_code,
+        Some(ErrorCode::ItemNotFound)
+    );
+}
+
+#[tokio::test]
+async fn user_lifecycle() {
+    let app = TestApp::new();
+
+    let user: User = app.create_user("a@b.com").await.data();
+    assert_eq!(app.get_user(&user.id).await.data::<User>(), user);
+    let res = app.head(&format!("/api/v1/users/{}", user.id)).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = app.head("/api/v1/users/not-a-uuid").await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    app.create_user("c@d.com").await;
+    let res = app.update_user(&user.id, "c@d.com").await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    let res = app.create_user("not-an-email").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn email_uniqueness_ignores_case() {
+    let app = TestApp::new();
+
+    let user: User = app.create_user("foo@example.com").await.data();
+    let same: User = app.create_user("Foo@Example.com").await.data();
+    assert_eq!(same.id, user.id);
+    assert_eq!(app.list_users().await.data::<Vec<User>>().len(), 1);
+
+    let other: User = app.create_user("bar@example.com").await.data();
+    let res = app.update_user(&other.id, "FOO@example.com").await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.envelope::<()>().error_code, Some(ErrorCode::EmailTaken));
+}
+
+#[tokio::test]
+async fn sparse_fieldsets() {
+    let app = TestApp::new();
+
+    let item: Item = app.create_item("Book").await.data();
+    let res = app
+        .get(&format!("/api/v1/items/{}?fields=id,name", item.id))
+        .await;
+    assert_eq!(
+        res.json::<serde_json::Value>()["data"],
+        serde_json::json!({"id": item.id, "name": "book"})
+    );
+    let res = app.get("/api/v1/items?fields=slug").await;
+    assert_eq!(
+        res.json::<serde_json::Value>()["data"],
+        serde_json::json!([{"slug": "book"}])
+    );
+
+    let res = app.get("/api/v1/users?fields=email,password").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.envelope::<()>().errors[0].field, "fields");
+}
+
+#[tokio::test]
+async fn include_owner() {
+    let app = TestApp::new();
+
+    let user: User = app.create_user("a@b.com").await.data();
+    app.post_json(
+        &format!("/api/v1/users/{}/items", user.id),
+        &json!({"name": "mug"}),
+    )
+    .await;
+    app.create_item("Book").await;
+
+    let res = app.get("/api/v1/items?include=owner&fields=name").await;
+    assert_eq!(
+        res.json::<serde_json::Value>()["data"],
+        json!([
+            {"name": "book"},
+            {"name": "mug", "owner": {"id": user.id, "email": "a@b.com", "version": 1}},
+        ])
+    );
+    // Without it the owner stays a bare id.
+    assert!(
+        !app.list_items().await.json::<serde_json::Value>()["data"][1]
+            .as_object()
+            .unwrap()
+            .contains_key("owner")
+    );
+
+    let res = app.get("/api/v1/items?include=category").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.envelope::<()>().errors[0].field, "include");
+    let res = app.get("/api/v1/items?include=owner&limit=1").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn csv_exports() {
+    let app = TestApp::new();
+    let item: Item = app.create_item("Pen, blue").await.data();
+    let user: User = app.create_user("foo@example.com").await.data();
+
+    let res = app.get("/api/v1/items/export.csv").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.headers["content-type"], "text/csv");
+    assert_eq!(
+        res.headers["content-disposition"],
+        "attachment; filename=\"items.csv\""
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&res.body),
+        format!(
+            "id,name,slug,owner_id\n{},\"pen, blue\",pen-blue,\n",
+            item.id
+        )
+    );
+
+    let res = app.get("/api/v1/users/export.csv").await;
+    assert_eq!(
+        String::from_utf8_lossy(&res.body),
+        format!("id,email\n{},foo@example.com\n", user.id)
+    );
+}
+
+#[tokio::test]
+async fn conditional_get() {
+    let app = TestApp::new();
+    let item: Item = app.create_item("Book").await.data();
+    let uri = format!("/api/v1/items/{}", item.id);
+    let get = |etag: String| {
+        let req = Request::get(&uri)
+            .header("if-none-match", etag)
+            .body(Body::empty())
+            .unwrap();
+        app.request(req)
+    };
+
+    let etag = app.get(&uri).await.headers["etag"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let res = get(etag.clone()).await;
+    assert_eq!(res.status, StatusCode::NOT_MODIFIED);
+    assert!(res.body.is_empty());
+
+    app.update_item(&item.id, "notebook").await;
+    assert_eq!(get(etag).await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn paged_list_links() {
+    let app = TestApp::new();
+    for name in ["Book", "Pen", "Mug"] {
+        app.create_item(name).await;
+    }
+    let link = |res: &TestResponse| res.headers["link"].to_str().unwrap().to_string();
+    let rel = |links: &str, rel: &str| {
+        links
+            .split(", ")
+            .find_map(|link| link.strip_suffix(&format!(">; rel=\"{}\"", rel)))
+            .map(|link| link.trim_start_matches('<').to_string())
+    };
+    let names = |res: &TestResponse| {
+        res.data::<Vec<serde_json::Value>>()
+            .into_iter()
+            .map(|item| item["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let first = link(&app.get("/api/v1/items?limit=2&fields=name").await);
+    assert!(first.contains("</api/v1/items?limit=2&fields=name>; rel=\"first\""));
+    assert!(first.starts_with("</api/v1/items?limit=2&fields=name>; rel=\"self\""));
+    assert_eq!(rel(&first, "prev"), None);
+    let next = rel(&first, "next").unwrap();
+    let res = app.get(&next).await;
+    assert_eq!(names(&res), ["pen"]);
+    let second = link(&res);
+    assert!(second.starts_with(&format!("<{}>; rel=\"self\"", next)));
+    assert!(second.contains("rel=\"first\""));
+    assert_eq!(rel(&second, "next"), None);
+
+    // Back from the second page to the first.
+    let res = app.get(&rel(&second, "prev").unwrap()).await;
+    assert_eq!(names(&res), ["book", "mug"]);
+    assert_eq!(rel(&link(&res), "prev"), None);
+
+    // The last page holds the last rows, however they fall into pages.
+    let res = app.get(&rel(&first, "last").unwrap()).await;
+    assert_eq!(names(&res), ["mug", "pen"]);
+    let last = link(&res);
+    assert_eq!(rel(&last, "next"), None);
+    let res = app.get(&rel(&last, "prev").unwrap()).await;
+    assert_eq!(names(&res), ["book"]);
+}
+
+#[tokio::test]
+async fn delete_semantics() {
+    let app = TestApp::with_config(Config {
+        delete_no_content: true,
+        ..Default::default()
+    });
+    let book: Item = app.create_item("Book").await.data();
+    let pen: Item = app.create_item("Pen").await.data();
+
+    let res = app.delete_item(&book.id).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    assert!(res.body.is_empty());
+
+    let uri = format!("/api/v1/items/{}?return=representation", pen.id);
+    let res = app.delete(&uri).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.data::<Item>(), pen);
+    // There's nothing left to return.
+    let res = app.delete(&uri).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        res.envelope::<()>().error_code,
+        Some(ErrorCode::ItemNotFound)
+    );
+}
+
+#[tokio::test]
+async fn stale_if_match() {
+    let app = TestApp::new();
+    let item: Item = app.create_item("Book").await.data();
+    let uri = format!("/api/v1/items/{}", item.id);
+    let put = |version: &str, name: &str| {
+        let req = Request::put(&uri)
+            .header("content-type", "application/json")
+            .header("if-match", version)
+            .body(Body::from(json!({ "name": name }).to_string()))
+            .unwrap();
+        app.request(req)
+    };
+
+    let res = put("\"1\"", "notebook").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.data::<Item>().version, 2);
+    let res = put("\"1\"", "diary").await;
+    assert_eq!(res.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(put("*", "diary").await.status, StatusCode::OK);
+    assert_eq!(put("one", "pen").await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(put("3", "pen").await.status, StatusCode::BAD_REQUEST);
+    // Weak tags never match If-Match.
+    let res = put("W/\"3\"", "pen").await;
+    assert_eq!(res.status, StatusCode::PRECONDITION_FAILED);
+}
+
+#[tokio::test]
+async fn read_etag_guards_updates() {
+    let app = TestApp::new();
+    let item: Item = app.create_item("Book").await.data();
+    let user: User = app.create_user("a@b.com").await.data();
+    // Patches go to PUT as well, told apart by their content type.
+    let update = |uri: &str, content_type: &str, body: serde_json::Value, etag: &str| {
+        let req = Request::put(uri)
+            .header("content-type", content_type)
+            .header("if-match", etag)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.request(req)
+    };
+    let etag = |res: TestResponse| res.headers["etag"].to_str().unwrap().to_string();
+
+    let uri = format!("/api/v1/items/{}", item.id);
+    let read = etag(app.get(&uri).await);
+    assert_eq!(read, "\"1\"");
+    let rename = json!({"name": "notebook"});
+    let res = update(&uri, "application/json", rename, &read).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let merge = "application/merge-patch+json";
+    let res = update(&uri, merge, json!({"name": "diary"}), &read).await;
+    assert_eq!(res.status, StatusCode::PRECONDITION_FAILED);
+    let read = etag(app.get(&uri).await);
+    let res = update(&uri, merge, json!({"name": "diary"}), &read).await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let uri = format!("/api/v1/users/{}", user.id);
+    let read = etag(app.get(&uri).await);
+    let ops = json!([{"op": "replace", "path": "/email", "value": "c@d.com"}]);
+    let json_patch = "application/json-patch+json";
+    let res = update(&uri, json_patch, ops.clone(), &read).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let res = update(&uri, json_patch, ops, &read).await;
+    assert_eq!(res.status, StatusCode::PRECONDITION_FAILED);
+}
@@ -0,0 +1,473 @@
+//! Replays requests through the router and checks each response against the
+//! published OpenAPI spec: the status must be documented and JSON bodies
+//! must match their schema. Every documented operation is exercised.
+
+use std::{collections::BTreeSet, path::PathBuf};
+
+use axum::{
+    body::Body,
+    http::{
+        Method, Request,
+        header::{CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH},
+    },
+};
+use crud_rust::{
+    config::Config,
+    model::{attachment::Attachment, item::Item, job::Job, user::User},
+    openapi,
+    testing::{TestApp, TestResponse},
+};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+const BOUNDARY: &str = "contract-boundary";
+
+struct Contract {
+    app: TestApp,
+    spec: Value,
+    dir: PathBuf,
+    covered: BTreeSet<(String, String)>,
+}
+
+impl Drop for Contract {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl Contract {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("crud-contract-{}", Uuid::new_v4()));
+        let config = Config {
+            attachment_dir: dir.join("attachments").display().to_string(),
+            export_dir: dir.join("exports").display().to_string(),
+            import_dir: dir.join("imports").display().to_string(),
+            ..Default::default()
+        };
+        Self {
+            app: TestApp::with_config(config),
+            spec: serde_json::from_str(&openapi::spec_json().unwrap()).unwrap(),
+            dir,
+            covered: BTreeSet::new(),
+        }
+    }
+
+    /// The documented path matching `path`, literal segments win over parameters.
+    fn template(&self, method: &Method, path: &str) -> String {
+        let segments: Vec<&str> = path.split('/').collect();
+        let method = method.as_str().to_lowercase();
+        self.spec["paths"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter(|(_, ops)| ops.get(&method).is_some())
+            .filter_map(|(template, _)| {
+                let parts: Vec<&str> = template.split('/').collect();
+                if parts.len() != segments.len() {
+                    return None;
+                }
+                let mut params = 0;
+                for (part, segment) in parts.iter().zip(&segments) {
+                    if part.starts_with('{') {
+                        params += 1;
+                    } else if part != segment {
+                        return None;
+                    }
+                }
+                Some((params, template.clone()))
+            })
+            .min()
+            .map(|(_, template)| template)
+            .unwrap_or_else(|| panic!("{} {} is not documented", method, path))
+    }
+
+    fn check(&mut self, method: &Method, uri: &str, res: &TestResponse) {
+        let path = uri.split('?').next().unwrap();
+        let template = self.template(method, path);
+        let operation = &self.spec["paths"][&template][method.as_str().to_lowercase()];
+        let status = res.status.as_str();
+        let documented = &operation["responses"][status];
+        assert!(
+            documented.is_object(),
+            "{} {} answered undocumented {}: {}",
+            method,
+            uri,
+            status,
+            String::from_utf8_lossy(&res.body)
+        );
+        self.covered
+            .insert((method.as_str().to_string(), template.clone()));
+
+        let Some(schema) = documented["content"]["application/json"]["schema"].as_object() else {
+            return;
+        };
+        let body: Value = res.json();
+        // Schema references resolve against the spec's components.
+        let mut root = json!({"components": self.spec["components"]});
+        for (key, value) in schema {
+            root[key] = value.clone();
+        }
+        let validator = jsonschema::validator_for(&root).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&body)
+            .map(|e| format!("{} at {}", e, e.instance_path()))
+            .collect();
+        assert!(
+            errors.is_empty(),
+            "{} {} {} does not match its schema:\n{}\n{:#}",
+            method,
+            uri,
+            status,
+            errors.join("\n"),
+            body
+        );
+    }
+
+    async fn send(&mut self, method: Method, uri: &str, req: Request<Body>) -> TestResponse {
+        let res = self.app.request(req).await;
+        self.check(&method, uri, &res);
+        res
+    }
+
+    async fn call(&mut self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        let req = Request::builder().method(method.clone()).uri(uri);
+        let req = match body {
+            Some(body) => req
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => req.body(Body::empty()),
+        };
+        self.send(method, uri, req.unwrap()).await
+    }
+
+    async fn get(&mut self, uri: &str) -> TestResponse {
+        self.call(Method::GET, uri, None).await
+    }
+
+    async fn upload(&mut self, uri: &str, filename: &str, data: &[u8]) -> TestResponse {
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: text/plain\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+        let req = Request::post(uri)
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        self.send(Method::POST, uri, req).await
+    }
+
+    async fn patch(&mut self, uri: &str, content_type: &str, body: Value) -> TestResponse {
+        let req = Request::put(uri)
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        self.send(Method::PUT, uri, req).await
+    }
+
+    fn documented(&self) -> BTreeSet<(String, String)> {
+        let mut operations = BTreeSet::new();
+        for (template, ops) in self.spec["paths"].as_object().unwrap() {
+            for method in ops.as_object().unwrap().keys() {
+                operations.insert((method.to_uppercase(), template.clone()));
+            }
+        }
+        operations
+    }
+}
+
+#[tokio::test]
+async fn responses_match_the_spec() {
+    let mut api = Contract::new();
+
+    api.get("/").await;
+    api.get("/api/healthcheck").await;
+    api.get("/api/v1/admin/versions").await;
+
+    // Items.
+    let book: Item = api
+        .call(Method::POST, "/api/v1/items", Some(json!({"name": "book"})))
+        .await
+        .data();
+    api.call(Method::POST, "/api/v1/items", Some(json!({"name": ""})))
+        .await;
+    api.call(Method::POST, "/api/v1/items", Some(json!({"name": 1})))
+        .await;
+    let req = Request::post("/api/v1/items")
+        .body(Body::from("name=book"))
+        .unwrap();
+    api.send(Method::POST, "/api/v1/items", req).await;
+    api.get("/api/v1/items").await;
+    api.get("/api/v1/items?as_of=2000-01-01T00:00:00Z").await;
+    api.get("/api/v1/items?as_of=yesterday").await;
+    api.get("/api/v1/items?limit=1").await;
+    api.get("/api/v1/items?sort=slug&order=desc").await;
+    api.get("/api/v1/items?sort=price").await;
+    api.get("/api/v1/items?name_contains=oo&created_after=2020-01-01T00:00:00Z")
+        .await;
+    api.get("/api/v1/items?created_after=yesterday").await;
+    api.get("/api/v1/items?cursor=bad").await;
+    let item_uri = format!("/api/v1/items/{}", book.id);
+    api.get(&item_uri).await;
+    api.get("/api/v1/items?fields=name,price").await;
+    api.call(Method::HEAD, &item_uri, None).await;
+    api.call(Method::HEAD, "/api/v1/items/missing", None).await;
+    let etag = api.get(&item_uri).await.headers[ETAG].clone();
+    let req = Request::get(&item_uri)
+        .header(IF_NONE_MATCH, etag)
+        .body(Body::empty())
+        .unwrap();
+    api.send(Method::GET, &item_uri, req).await;
+    api.get("/api/v1/items/missing").await;
+    api.get(&format!("{}?as_of=2000-01-01T00:00:00Z", item_uri))
+        .await;
+    api.call(Method::PUT, &item_uri, Some(json!({"name": "notebook"})))
+        .await;
+    api.call(Method::PUT, &item_uri, Some(json!({"name": " "})))
+        .await;
+    let req = Request::put(&item_uri)
+        .header(CONTENT_TYPE, "application/json")
+        .header(IF_MATCH, "\"1\"")
+        .body(Body::from(json!({"name": "pen"}).to_string()))
+        .unwrap();
+    api.send(Method::PUT, &item_uri, req).await;
+    let merge_patch = "application/merge-patch+json";
+    api.patch(&item_uri, merge_patch, json!({"name": "notebook"}))
+        .await;
+    api.patch(&item_uri, merge_patch, json!({"slug": "other"}))
+        .await;
+    let json_patch = "application/json-patch+json";
+    let ops = json!([{"op": "replace", "path": "/name", "value": "notebook"}]);
+    api.patch(&item_uri, json_patch, ops).await;
+    let ops = json!([{"op": "remove", "path": "/price"}]);
+    api.patch(&item_uri, json_patch, ops).await;
+    api.patch(&item_uri, json_patch, json!({"op": "remove"}))
+        .await;
+    api.call(
+        Method::PUT,
+        "/api/v1/items/missing",
+        Some(json!({"name": "pen"})),
+    )
+    .await;
+    let bulk = json!([{"id": book.id, "name": "notebook"}]);
+    api.call(Method::PUT, "/api/v1/items/bulk", Some(bulk))
+        .await;
+    api.call(Method::PUT, "/api/v1/items/bulk", Some(json!([])))
+        .await;
+    let bulk = json!([{"id": book.id, "name": "notebook"}, {"id": "missing", "name": "pen"}]);
+    api.call(Method::PUT, "/api/v1/items/bulk", Some(bulk))
+        .await;
+    api.get(&format!("/api/v1/items/slug/{}", book.slug)).await;
+    api.get("/api/v1/items/slug/missing").await;
+    api.get("/api/v1/items/search?q=note").await;
+    api.get("/api/v1/items/search?q=").await;
+    api.call(Method::POST, &format!("{}/regenerate-slug", item_uri), None)
+        .await;
+    api.call(Method::POST, "/api/v1/items/missing/regenerate-slug", None)
+        .await;
+
+    // Attachments.
+    let attachments_uri = format!("{}/attachments", item_uri);
+    let attachment: Attachment = api
+        .upload(&attachments_uri, "notes.txt", b"hello")
+        .await
+        .data();
+    api.upload(&attachments_uri, "empty.txt", b"").await;
+    api.get(&attachments_uri).await;
+    api.get("/api/v1/items/missing/attachments").await;
+    let attachment_uri = format!("{}/{}", attachments_uri, attachment.id);
+    api.get(&format!("{}/download", attachment_uri)).await;
+    api.get(&format!("{}/missing/download", attachments_uri))
+        .await;
+    api.call(Method::DELETE, &attachment_uri, None).await;
+
+    // Users.
+    let user: User = api
+        .call(
+            Method::POST,
+            "/api/v1/users",
+            Some(json!({"email": "a@b.com"})),
+        )
+        .await
+        .data();
+    let other: User = api
+        .call(
+            Method::POST,
+            "/api/v1/users",
+            Some(json!({"email": "c@d.com"})),
+        )
+        .await
+        .data();
+    api.call(
+        Method::POST,
+        "/api/v1/users",
+        Some(json!({"email": "nope"})),
+    )
+    .await;
+    api.get("/api/v1/users").await;
+    api.get("/api/v1/users?limit=1").await;
+    api.get("/api/v1/users?fields=password").await;
+    api.get("/api/v1/users?limit=0").await;
+    api.get("/api/v1/users?sort=id&order=desc").await;
+    api.get("/api/v1/users/search?q=exmaple").await;
+    api.get("/api/v1/users/search?q=&limit=500").await;
+    let user_uri = format!("/api/v1/users/{}", user.id);
+    api.get(&user_uri).await;
+    api.call(Method::HEAD, &user_uri, None).await;
+    api.call(Method::HEAD, "/api/v1/users/not-a-uuid", None)
+        .await;
+    api.get("/api/v1/users/not-a-uuid").await;
+    api.get(&format!("/api/v1/users/{}", Uuid::new_v4())).await;
+    api.get(&format!("/api/v1/users/by-email/{}", user.email))
+        .await;
+    api.get("/api/v1/users/by-email/nobody@example.com").await;
+    let user_items_uri = format!("{}/items", user_uri);
+    api.call(Method::POST, &user_items_uri, Some(json!({"name": "mug"})))
+        .await;
+    api.call(Method::POST, &user_items_uri, Some(json!({"name": ""})))
+        .await;
+    let missing_items_uri = format!("/api/v1/users/{}/items", Uuid::new_v4());
+    api.call(
+        Method::POST,
+        &missing_items_uri,
+        Some(json!({"name": "cup"})),
+    )
+    .await;
+    api.get(&user_items_uri).await;
+    api.get(&missing_items_uri).await;
+    api.get("/api/v1/users/not-a-uuid/items").await;
+    api.get("/api/v1/users/by-email/not-an-email").await;
+    api.call(Method::PUT, &user_uri, Some(json!({"email": "e@f.com"})))
+        .await;
+    api.patch(&user_uri, merge_patch, json!({"email": "m@f.com"}))
+        .await;
+    api.patch(&user_uri, merge_patch, json!({"email": null}))
+        .await;
+    let ops = json!([{"op": "test", "path": "/email", "value": "x@y.com"}]);
+    api.patch(&user_uri, json_patch, ops).await;
+    api.patch(&user_uri, merge_patch, json!({"email": "e@f.com"}))
+        .await;
+    api.call(
+        Method::PUT,
+        &format!("/api/v1/users/{}", other.id),
+        Some(json!({"email": "e@f.com"})),
+    )
+    .await;
+    api.call(
+        Method::PUT,
+        &format!("/api/v1/users/{}", Uuid::new_v4()),
+        Some(json!({"email": "g@h.com"})),
+    )
+    .await;
+    api.call(
+        Method::POST,
+        &format!("{}/confirm-email", user_uri),
+        Some(json!({"token": "wrong"})),
+    )
+    .await;
+    api.call(
+        Method::POST,
+        &format!("/api/v1/users/{}/confirm-email", Uuid::new_v4()),
+        Some(json!({"token": "wrong"})),
+    )
+    .await;
+    let returned = format!("{}?return=representation", user_uri);
+    api.call(Method::DELETE, &returned, None).await;
+    api.call(Method::DELETE, &returned, None).await;
+    api.call(Method::DELETE, &user_uri, None).await;
+    api.call(Method::DELETE, "/api/v1/users/not-a-uuid", None)
+        .await;
+
+    api.get("/api/v1/sync").await;
+    api.get("/api/v1/sync?since=1&limit=5000").await;
+
+    api.get("/api/v1/items/export.csv").await;
+    api.get("/api/v1/users/export.csv").await;
+
+    // Exports and imports, queued but never run here.
+    let export: Value = api
+        .call(Method::POST, "/api/v1/items/export-jobs", None)
+        .await
+        .data();
+    let export_uri = format!("/api/v1/export-jobs/{}", export["id"].as_str().unwrap());
+    api.get(&export_uri).await;
+    api.get("/api/v1/export-jobs/missing").await;
+    api.get(&format!("{}/download", export_uri)).await;
+    api.get("/api/v1/export-jobs/missing/download").await;
+    api.call(
+        Method::POST,
+        "/api/v1/items/export-jobs?format=parquet",
+        None,
+    )
+    .await;
+    api.call(Method::POST, "/api/v1/items/export-jobs?format=xml", None)
+        .await;
+    let req = Request::post("/api/v1/items/import-jobs")
+        .header(CONTENT_TYPE, "text/csv")
+        .body(Body::from("name\npen\n"))
+        .unwrap();
+    let import: Value = api
+        .send(Method::POST, "/api/v1/items/import-jobs", req)
+        .await
+        .data();
+    let req = Request::post("/api/v1/items/import-jobs")
+        .header(CONTENT_TYPE, "text/csv")
+        .body(Body::from("title\npen\n"))
+        .unwrap();
+    api.send(Method::POST, "/api/v1/items/import-jobs", req)
+        .await;
+    api.upload(
+        "/api/v1/items/import?dry_run=true",
+        "items.csv",
+        b"name\n\n",
+    )
+    .await;
+    api.upload("/api/v1/items/import", "items.csv", b"name\nink\n")
+        .await;
+    api.upload("/api/v1/items/import", "items.csv", b"title\nink\n")
+        .await;
+    api.get(&format!(
+        "/api/v1/import-jobs/{}",
+        import["id"].as_str().unwrap()
+    ))
+    .await;
+    api.get("/api/v1/import-jobs/missing").await;
+
+    // Job administration.
+    api.get("/api/v1/admin/jobs").await;
+    api.get("/api/v1/admin/jobs?status=stuck").await;
+    let jobs: Vec<Job> = api.get("/api/v1/admin/jobs?status=pending").await.data();
+    let job_uri = format!("/api/v1/admin/jobs/{}", jobs[0].id);
+    api.get(&job_uri).await;
+    api.get("/api/v1/admin/jobs/missing").await;
+    api.call(Method::POST, &format!("{}/retry", job_uri), None)
+        .await;
+    api.call(Method::POST, &format!("{}/cancel", job_uri), None)
+        .await;
+    api.call(Method::POST, &format!("{}/cancel", job_uri), None)
+        .await;
+    api.call(Method::POST, &format!("{}/retry", job_uri), None)
+        .await;
+    api.call(Method::POST, "/api/v1/admin/jobs/missing/retry", None)
+        .await;
+    api.call(Method::POST, "/api/v1/admin/jobs/missing/cancel", None)
+        .await;
+
+    api.call(Method::DELETE, &format!("{}?return=all", item_uri), None)
+        .await;
+    let returned = format!("{}?return=representation", item_uri);
+    api.call(Method::DELETE, &returned, None).await;
+    api.call(Method::DELETE, &returned, None).await;
+    api.call(Method::DELETE, &item_uri, None).await;
+
+    let missing: Vec<_> = api.documented().difference(&api.covered).cloned().collect();
+    assert!(
+        missing.is_empty(),
+        "operations never exercised: {:?}",
+        missing
+    );
+}
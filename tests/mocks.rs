@@ -0,0 +1,41 @@
+#![cfg(feature = "mocks")]
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use crud_rust::{
+    config::Config,
+    model::item::Item,
+    repository::{item::MockItemRepository, registry::MockRepository},
+    state::AppState,
+    testing::TestApp,
+};
+
+#[tokio::test]
+async fn external_tests_can_inject_mock_repository() {
+    let mut item_repo = MockItemRepository::new();
+    item_repo.expect_list().returning(|_, _| {
+        Box::pin(async {
+            Ok(vec![Item {
+                id: "1".into(),
+                name: "book".into(),
+                slug: "book".into(),
+                owner_id: None,
+                version: 1,
+            }])
+        })
+    });
+    let item_repo = Arc::new(item_repo);
+    let mut repo = MockRepository::new();
+    repo.expect_item().returning(move || item_repo.clone());
+
+    let state = AppState::builder()
+        .config(Config::default())
+        .repository(Arc::new(repo))
+        .build()
+        .unwrap();
+    let res = TestApp::with_state(state).list_items().await;
+
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.data::<Vec<Item>>()[0].name, "book");
+}
@@ -0,0 +1,627 @@
+//! Runs the Postgres repositories against a throwaway database, needs Docker:
+//! `cargo test --test postgres_repository -- --ignored`.
+
+use std::path::Path;
+
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use crud_rust::{
+    model::{
+        attachment::Attachment,
+        error::{AppErrorCode, ErrorCode},
+        event::EventAction,
+        item::{Item, ListItemFilter, slugify},
+        job::{Job, JobStatus, NewJob},
+        sort::{ItemSort, ItemSortColumn, SortOrder, UserSort},
+        user::{PendingEmail, User},
+    },
+    repository::{
+        PostgresRepository, Repository, attachment::AttachmentRepository, change::ChangeRepository,
+        item::ItemRepository, job::JobRepository, user::UserRepository,
+    },
+};
+use serde_json::json;
+use sqlx::PgPool;
+use testcontainers_modules::{
+    postgres::Postgres,
+    testcontainers::{ContainerAsync, ImageExt, runners::AsyncRunner},
+};
+
+/// A fresh Postgres with the goose migrations applied. The container lives
+/// as long as the returned handle.
+async fn database() -> (ContainerAsync<Postgres>, PgPool) {
+    // Same major version as the CI database.
+    let container = Postgres::default()
+        .with_tag("17-alpine")
+        .start()
+        .await
+        .unwrap();
+    let url = format!(
+        "postgres://postgres:postgres@{}:{}/postgres",
+        container.get_host().await.unwrap(),
+        container.get_host_port_ipv4(5432).await.unwrap()
+    );
+    let pool = PgPool::connect(&url).await.unwrap();
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+    let mut migrations: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    migrations.sort();
+    for migration in migrations {
+        let sql = std::fs::read_to_string(&migration).unwrap();
+        let up = sql
+            .split("-- +goose Down")
+            .next()
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with("-- +goose"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        sqlx::raw_sql(&up).execute(&pool).await.unwrap();
+    }
+    (container, pool)
+}
+
+fn item(id: &str, name: &str) -> Item {
+    Item {
+        id: id.into(),
+        name: name.into(),
+        slug: slugify(name),
+        owner_id: None,
+        version: 1,
+    }
+}
+
+/// Keeps a claimed job locked for the rest of the test.
+fn lease() -> DateTime<Utc> {
+    Utc::now() + Duration::hours(1)
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn item_repository() {
+    let (_container, pool) = database().await;
+    let items = PostgresRepository::new(pool).item();
+
+    let book = items.add(item("1", "book")).await.unwrap();
+    assert_eq!(book, item("1", "book"));
+    // Adding a name again, in any case, conflicts.
+    let err = items.add(item("2", "BOOK")).await.unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::Conflict));
+    assert_eq!(err.error_code, Some(ErrorCode::NameTaken));
+    items.add(item("3", "album")).await.unwrap();
+    assert_eq!(
+        items
+            .list(ItemSort::default(), ListItemFilter::default())
+            .await
+            .unwrap(),
+        vec![item("3", "album"), book.clone()]
+    );
+    assert_eq!(items.search("OO", 5).await.unwrap(), vec![book.clone()]);
+    assert_eq!(
+        items.search("b", 1).await.unwrap(),
+        vec![item("3", "album")]
+    );
+    // LIKE wildcards in the query match themselves only.
+    assert!(items.search("_ook", 5).await.unwrap().is_empty());
+    assert!(items.search("%", 5).await.unwrap().is_empty());
+    let filter = ListItemFilter {
+        name_contains: Some("OO".into()),
+        created_after: Some(Utc::now() - Duration::hours(1)),
+    };
+    assert_eq!(
+        items.list(ItemSort::default(), filter).await.unwrap(),
+        vec![book.clone()]
+    );
+    let filter = ListItemFilter {
+        created_after: Some(Utc::now() + Duration::hours(1)),
+        ..Default::default()
+    };
+    assert!(
+        items
+            .list(ItemSort::default(), filter)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let by_id_desc = ItemSort {
+        column: ItemSortColumn::Id,
+        order: SortOrder::Desc,
+    };
+    assert_eq!(
+        items
+            .list(by_id_desc, ListItemFilter::default())
+            .await
+            .unwrap(),
+        vec![item("3", "album"), book.clone()]
+    );
+    assert_eq!(
+        items.list_after(None, 1).await.unwrap(),
+        vec![item("3", "album")]
+    );
+    assert_eq!(
+        items
+            .list_after(Some(("album".into(), "3".into())), 5)
+            .await
+            .unwrap(),
+        vec![book.clone()]
+    );
+    assert_eq!(
+        items.list_before(None, 1).await.unwrap(),
+        vec![book.clone()]
+    );
+    assert_eq!(
+        items
+            .list_before(Some(("book".into(), "1".into())), 5)
+            .await
+            .unwrap(),
+        vec![item("3", "album")]
+    );
+
+    assert_eq!(items.get("1").await.unwrap(), book);
+    assert!(items.exists("1").await.unwrap());
+    assert!(!items.exists("404").await.unwrap());
+    let err = items.get("404").await.unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::NotFound));
+    assert_eq!(err.error_code, Some(ErrorCode::ItemNotFound));
+
+    let notebook = items.update("1", "notebook".into(), Some(1)).await.unwrap();
+    // Renames keep the slug.
+    assert_eq!(notebook.slug, "book");
+    assert_eq!(notebook.version, 2);
+    let err = items
+        .update("1", "diary".into(), Some(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::PreconditionFailed));
+    assert_eq!(items.get_by_slug("book").await.unwrap(), notebook);
+    let err = items.get_by_slug("nope").await.unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::ItemNotFound));
+
+    let err = items.add(item("4", "Book!")).await.unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::SlugTaken));
+    let err = items.set_slug("3", "book".into()).await.unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::SlugTaken));
+    let notebook = items.set_slug("1", "notebook".into()).await.unwrap();
+    assert_eq!(
+        notebook,
+        Item {
+            version: 3,
+            ..item("1", "notebook")
+        }
+    );
+    let err = items.update("1", "Album".into(), None).await.unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::Conflict));
+    let err = items
+        .update("404", "pen".into(), Some(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::NotFound));
+
+    // A failing row rolls back the ones before it.
+    let updates = vec![("1".into(), "diary".into()), ("404".into(), "pen".into())];
+    let err = items.update_many(updates).await.unwrap_err();
+    assert!(matches!(&err.code, AppErrorCode::Unprocessable(e) if e[0].field == "/1"));
+    assert_eq!(items.get("1").await.unwrap().name, "notebook");
+    let diary = items
+        .update_many(vec![("1".into(), "diary".into())])
+        .await
+        .unwrap();
+    assert_eq!(diary[0].name, "diary");
+
+    // A taken slug rolls back the items added before it.
+    let err = items
+        .add_many(vec![item("5", "pen"), item("6", "Album!")])
+        .await
+        .unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::SlugTaken));
+    assert_eq!(err.get_field_errors()[0].field, "/1");
+    assert!(!items.exists("5").await.unwrap());
+    // So does a taken name.
+    let err = items
+        .add_many(vec![item("5", "pen"), item("6", "ALBUM")])
+        .await
+        .unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::NameTaken));
+    assert_eq!(err.get_field_errors()[0].field, "/1");
+    assert!(!items.exists("5").await.unwrap());
+
+    let deleted = items.delete("1").await.unwrap().unwrap();
+    assert_eq!(deleted.name, "diary");
+    assert!(items.get("1").await.is_err());
+    // Deleting is idempotent.
+    assert_eq!(items.delete("1").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn item_owner() {
+    let (_container, pool) = database().await;
+    let repo = PostgresRepository::new(pool);
+    let (items, users) = (repo.item(), repo.user());
+    users
+        .add(User {
+            id: "u1".into(),
+            email: "a@b.com".into(),
+            version: 1,
+        })
+        .await
+        .unwrap();
+
+    let mug = Item {
+        owner_id: Some("u1".into()),
+        ..item("1", "mug")
+    };
+    assert_eq!(items.add(mug.clone()).await.unwrap(), mug);
+    items.add(item("2", "pen")).await.unwrap();
+    assert_eq!(items.list_by_owner("u1").await.unwrap(), vec![mug]);
+    let cup = Item {
+        owner_id: Some("u404".into()),
+        ..item("3", "cup")
+    };
+    let err = items.add(cup).await.unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::UserNotFound));
+
+    // Items outlive their owner.
+    users.delete("u1").await.unwrap();
+    assert_eq!(items.get("1").await.unwrap().owner_id, None);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn user_repository() {
+    let (_container, pool) = database().await;
+    let users = PostgresRepository::new(pool).user();
+    let user = |id: &str, email: &str| User {
+        id: id.into(),
+        email: email.into(),
+        version: 1,
+    };
+
+    let a = users.add(user("1", "a@b.com")).await.unwrap();
+    assert_eq!(users.add(user("2", "A@B.com")).await.unwrap(), a);
+    assert!(users.exists("1").await.unwrap());
+    assert!(!users.exists("2").await.unwrap());
+    users.add(user("3", "c@d.com")).await.unwrap();
+    let mut found = users
+        .get_many(&["3".into(), "404".into(), "1".into()])
+        .await
+        .unwrap();
+    found.sort_by(|a, b| a.id.cmp(&b.id));
+    assert_eq!(found, vec![a.clone(), user("3", "c@d.com")]);
+    assert!(users.get_many(&[]).await.unwrap().is_empty());
+    assert_eq!(
+        users.list(UserSort::default()).await.unwrap(),
+        vec![a.clone(), user("3", "c@d.com")]
+    );
+    assert_eq!(
+        users
+            .list_after(Some(("a@b.com".into(), "1".into())), 5)
+            .await
+            .unwrap(),
+        vec![user("3", "c@d.com")]
+    );
+    assert_eq!(
+        users
+            .list_before(Some(("c@d.com".into(), "3".into())), 5)
+            .await
+            .unwrap(),
+        vec![a.clone()]
+    );
+    assert_eq!(
+        users.search("c@dd.com", 0.5, 5).await.unwrap(),
+        vec![user("3", "c@d.com")]
+    );
+    assert!(users.search("c@dd.com", 0.9, 5).await.unwrap().is_empty());
+
+    assert_eq!(users.get("1").await.unwrap(), a);
+    let err = users.get("404").await.unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::UserNotFound));
+
+    assert_eq!(
+        users.update("1", "e@f.com".into(), Some(1)).await.unwrap(),
+        User {
+            version: 2,
+            ..user("1", "e@f.com")
+        }
+    );
+    let err = users
+        .update("1", "f@g.com".into(), Some(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::PreconditionFailed));
+    let err = users.update("1", "C@D.com".into(), None).await.unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::Conflict));
+    assert_eq!(err.error_code, Some(ErrorCode::EmailTaken));
+    let err = users
+        .update("404", "g@h.com".into(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::NotFound));
+
+    assert_eq!(users.delete("1").await.unwrap().unwrap().id, "1");
+    assert!(users.get("1").await.is_err());
+    assert_eq!(users.delete("1").await.unwrap(), None);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn job_repository() {
+    let (_container, pool) = database().await;
+    let jobs = PostgresRepository::new(pool).job();
+
+    let job = jobs
+        .add(Job::from(
+            NewJob::new("email.send", json!({"to": "a@b.com"})).max_attempts(2),
+        ))
+        .await
+        .unwrap();
+    let stored = jobs.get(&job.id).await.unwrap();
+    assert_eq!((stored.kind, stored.payload), (job.kind, job.payload));
+    assert!(matches!(
+        jobs.get("404").await.unwrap_err().code,
+        AppErrorCode::NotFound
+    ));
+
+    let claimed = jobs.claim(lease()).await.unwrap().unwrap();
+    assert_eq!(claimed.id, job.id);
+    assert_eq!(claimed.status, JobStatus::Running);
+    assert_eq!(claimed.attempts, 1);
+    assert!(jobs.claim(lease()).await.unwrap().is_none());
+
+    jobs.progress(&job.id, 150, Some(json!({"sent": 1})))
+        .await
+        .unwrap();
+    let progressed = jobs.get(&job.id).await.unwrap();
+    assert_eq!(progressed.progress, 100);
+    assert_eq!(progressed.result, Some(json!({"sent": 1})));
+
+    // Released jobs don't use up an attempt.
+    jobs.release(&job.id).await.unwrap();
+    let released = jobs.claim(lease()).await.unwrap().unwrap();
+    assert_eq!(released.attempts, 1);
+
+    // A failed attempt waits for `retry_at`, the last one fails the job.
+    jobs.fail(&job.id, "timeout", Utc::now() + Duration::hours(1))
+        .await
+        .unwrap();
+    assert!(jobs.claim(lease()).await.unwrap().is_none());
+    jobs.fail(&job.id, "timeout", Utc::now()).await.unwrap();
+    assert_eq!(jobs.get(&job.id).await.unwrap().status, JobStatus::Pending);
+    jobs.claim(lease()).await.unwrap().unwrap();
+    jobs.fail(&job.id, "timeout", Utc::now()).await.unwrap();
+    let failed = jobs.get(&job.id).await.unwrap();
+    assert_eq!(failed.status, JobStatus::Failed);
+    assert_eq!(failed.last_error.as_deref(), Some("timeout"));
+    assert!(jobs.cancel(&job.id).await.unwrap().is_none());
+
+    let retried = jobs.retry(&job.id).await.unwrap().unwrap();
+    assert_eq!(retried.status, JobStatus::Pending);
+    assert_eq!(retried.attempts, 0);
+    assert!(jobs.retry(&job.id).await.unwrap().is_none());
+    let cancelled = jobs.cancel(&job.id).await.unwrap().unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+
+    let other = jobs
+        .add(Job::from(NewJob::new("export", json!({}))))
+        .await
+        .unwrap();
+    jobs.claim(lease()).await.unwrap().unwrap();
+    jobs.complete(&other.id).await.unwrap();
+    let done = jobs.list(Some(JobStatus::Done), 10).await.unwrap();
+    assert_eq!(done.len(), 1);
+    assert_eq!(done[0].progress, 100);
+    let all = jobs.list(None, 10).await.unwrap();
+    assert_eq!(all[0].id, other.id);
+    assert_eq!(jobs.list(None, 1).await.unwrap().len(), 1);
+
+    // A running job is claimed again once its lease runs out.
+    let stranded = jobs
+        .add(Job::from(NewJob::new("import", json!({}))))
+        .await
+        .unwrap();
+    jobs.claim(lease()).await.unwrap().unwrap();
+    assert!(jobs.claim(lease()).await.unwrap().is_none());
+    jobs.heartbeat(&stranded.id, Utc::now() - Duration::seconds(1))
+        .await
+        .unwrap();
+    let reclaimed = jobs.claim(lease()).await.unwrap().unwrap();
+    assert_eq!(reclaimed.id, stranded.id);
+    assert_eq!(reclaimed.attempts, 2);
+    assert!(jobs.claim(lease()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn attachment_repository() {
+    let (_container, pool) = database().await;
+    let repository = PostgresRepository::new(pool);
+    let attachments = repository.attachment();
+    repository.item().add(item("1", "book")).await.unwrap();
+    // Postgres keeps microseconds.
+    let now = Utc::now().trunc_subsecs(6);
+    let attachment = |id: &str, minutes: i64| Attachment {
+        id: id.into(),
+        item_id: "1".into(),
+        filename: format!("{}.png", id),
+        content_type: "image/png".into(),
+        size: 42,
+        created_at: now + Duration::minutes(minutes),
+    };
+
+    let first = attachments.add(attachment("a", 0)).await.unwrap();
+    assert_eq!(first, attachment("a", 0));
+    let second = attachments.add(attachment("b", 1)).await.unwrap();
+    assert!(attachments.add(attachment("a", 2)).await.is_err());
+    assert_eq!(
+        attachments.list("1").await.unwrap(),
+        vec![first.clone(), second.clone()]
+    );
+    assert!(attachments.list("2").await.unwrap().is_empty());
+
+    assert_eq!(attachments.get("1", "a").await.unwrap(), first);
+    let err = attachments.get("2", "a").await.unwrap_err();
+    assert!(matches!(err.code, AppErrorCode::NotFound));
+
+    attachments.delete("1", "a").await.unwrap();
+    attachments.delete("1", "a").await.unwrap();
+    assert_eq!(attachments.list("1").await.unwrap(), vec![second]);
+
+    // Attachments go with their item.
+    repository.item().delete("1").await.unwrap();
+    assert!(attachments.list("1").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn changes_follow_commit_order() {
+    let (_container, pool) = database().await;
+    let repo = PostgresRepository::new(pool.clone());
+    let changes = repo.change();
+
+    // The first write is still in flight when the second one is made.
+    let mut first = pool.begin().await.unwrap();
+    sqlx::query("INSERT INTO items (id, name, slug) VALUES ('1', 'book', 'book')")
+        .execute(&mut *first)
+        .await
+        .unwrap();
+    let items = repo.item();
+    let second = tokio::spawn(async move { items.add(item("2", "pen")).await });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    // A reader never gets past a change that commits later.
+    assert!(changes.since(0, 10).await.unwrap().is_empty());
+    first.commit().await.unwrap();
+    second.await.unwrap().unwrap();
+
+    let ids: Vec<_> = changes
+        .since(0, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|change| change.entity_id)
+        .collect();
+    assert_eq!(ids, ["1", "2"]);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn change_repository() {
+    let (_container, pool) = database().await;
+    let repo = PostgresRepository::new(pool.clone());
+    let (items, users, changes) = (repo.item(), repo.user(), repo.change());
+    assert_eq!(changes.last_seq().await.unwrap(), 0);
+    assert_eq!(changes.cursor("search").await.unwrap(), None);
+
+    let before = Utc::now();
+    items.add(item("1", "book")).await.unwrap();
+    items.update("1", "novel".into(), None).await.unwrap();
+    users
+        .add(User {
+            id: "u1".into(),
+            email: "a@b.com".into(),
+            version: 1,
+        })
+        .await
+        .unwrap();
+    // Writes leaving the record's fields as they were aren't changes.
+    users
+        .set_pending_email(
+            "u1",
+            PendingEmail {
+                email: "c@d.com".into(),
+                token_hash: "hash".into(),
+                expires_at: Utc::now() + Duration::hours(1),
+            },
+        )
+        .await
+        .unwrap();
+    // Rolled back writes leave no trace.
+    let err = items
+        .add_many(vec![item("2", "pen"), item("3", "NOVEL")])
+        .await
+        .unwrap_err();
+    assert_eq!(err.error_code, Some(ErrorCode::NameTaken));
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("INSERT INTO items (id, name, slug) VALUES ('4', 'cup', 'cup')")
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+
+    let recorded = changes.since(0, 10).await.unwrap();
+    let summary: Vec<_> = recorded
+        .iter()
+        .map(|change| {
+            (
+                change.entity.as_str(),
+                change.entity_id.as_str(),
+                change.action,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("item", "1", EventAction::Created),
+            ("item", "1", EventAction::Updated),
+            ("user", "u1", EventAction::Created),
+        ]
+    );
+    assert_eq!(
+        recorded[1].data,
+        Some(json!({"id": "1", "name": "novel", "slug": "book", "version": 2}))
+    );
+    assert_eq!(
+        recorded[2].data,
+        Some(json!({"id": "u1", "email": "a@b.com", "version": 1}))
+    );
+    assert_eq!(
+        changes.since(recorded[1].seq, 10).await.unwrap(),
+        recorded[2..]
+    );
+    assert_eq!(changes.since(0, 1).await.unwrap(), recorded[..1]);
+    assert_eq!(changes.last_seq().await.unwrap(), recorded[2].seq);
+
+    let latest = changes
+        .latest("item", "1", Utc::now())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(latest, recorded[1]);
+    assert_eq!(
+        changes
+            .latest("item", "1", before - Duration::hours(1))
+            .await
+            .unwrap(),
+        None
+    );
+    let deleted_at = Utc::now();
+    items.delete("1").await.unwrap();
+    assert!(
+        changes
+            .latest_all("item", Utc::now())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        changes.latest_all("item", deleted_at).await.unwrap(),
+        recorded[1..2]
+    );
+
+    changes
+        .save_cursor("search", recorded[0].seq)
+        .await
+        .unwrap();
+    changes
+        .save_cursor("search", recorded[2].seq)
+        .await
+        .unwrap();
+    assert_eq!(
+        changes.cursor("search").await.unwrap(),
+        Some(recorded[2].seq)
+    );
+    assert_eq!(changes.cursor("sync").await.unwrap(), None);
+}
@@ -0,0 +1,133 @@
+//! Snapshots of the JSON contract, review changes with `cargo insta review`.
+
+use crud_rust::{
+    config::Config,
+    model::{item::Item, user::User},
+    testing::{TestApp, TestResponse},
+};
+use insta::assert_json_snapshot;
+use serde_json::{Value, json};
+
+/// Status and body, with the correlation id and the given generated ids
+/// replaced by placeholders so the snapshots are stable.
+fn snapshot(res: &TestResponse, ids: &[&str]) -> Value {
+    let mut body = String::from_utf8_lossy(&res.body).to_string();
+    for (i, id) in ids.iter().enumerate() {
+        body = body.replace(id, &format!("[id{}]", i + 1));
+    }
+    let mut body: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    if body.get("correlation_id").is_some() {
+        body["correlation_id"] = json!("[correlation_id]");
+    }
+    json!({"status": res.status.as_u16(), "body": body})
+}
+
+#[tokio::test]
+async fn healthcheck() {
+    let app = TestApp::new();
+    assert_json_snapshot!(snapshot(&app.get("/api/healthcheck").await, &[]));
+}
+
+#[tokio::test]
+async fn items() {
+    let app = TestApp::new();
+
+    let res = app.create_item("Book").await;
+    let book: Item = res.data();
+    let ids = [book.id.as_str()];
+    assert_json_snapshot!("create_item", snapshot(&res, &ids));
+    let album: Item = app.create_item("album").await.data();
+    assert_json_snapshot!(
+        "list_items",
+        snapshot(&app.list_items().await, &[&book.id, &album.id])
+    );
+    assert_json_snapshot!("get_item", snapshot(&app.get_item(&book.id).await, &ids));
+    assert_json_snapshot!(
+        "update_item",
+        snapshot(&app.update_item(&book.id, "notebook").await, &ids)
+    );
+    assert_json_snapshot!(
+        "delete_item",
+        snapshot(&app.delete_item(&book.id).await, &ids)
+    );
+}
+
+#[tokio::test]
+async fn item_errors() {
+    let app = TestApp::new();
+    let book: Item = app.create_item("book").await.data();
+
+    assert_json_snapshot!(
+        "item_not_found",
+        snapshot(&app.get_item("missing").await, &[])
+    );
+    assert_json_snapshot!(
+        "create_item_invalid",
+        snapshot(&app.create_item("").await, &[])
+    );
+    assert_json_snapshot!(
+        "update_item_invalid",
+        snapshot(&app.update_item(&book.id, " ").await, &[&book.id])
+    );
+}
+
+#[tokio::test]
+async fn users() {
+    let app = TestApp::new();
+
+    let res = app.create_user("a@b.com").await;
+    let user: User = res.data();
+    let ids = [user.id.as_str()];
+    assert_json_snapshot!("create_user", snapshot(&res, &ids));
+    assert_json_snapshot!("list_users", snapshot(&app.list_users().await, &ids));
+    assert_json_snapshot!("get_user", snapshot(&app.get_user(&user.id).await, &ids));
+    assert_json_snapshot!(
+        "update_user",
+        snapshot(&app.update_user(&user.id, "c@d.com").await, &ids)
+    );
+    assert_json_snapshot!(
+        "delete_user",
+        snapshot(&app.delete_user(&user.id).await, &ids)
+    );
+}
+
+#[tokio::test]
+async fn user_errors() {
+    let app = TestApp::new();
+    let a: User = app.create_user("a@b.com").await.data();
+    let b: User = app.create_user("c@d.com").await.data();
+
+    assert_json_snapshot!(
+        "user_not_found",
+        snapshot(&app.get_user("missing").await, &[])
+    );
+    assert_json_snapshot!(
+        "create_user_invalid",
+        snapshot(&app.create_user("not-an-email").await, &[])
+    );
+    assert_json_snapshot!(
+        "update_user_conflict",
+        snapshot(&app.update_user(&b.id, "A@b.com").await, &[&a.id, &b.id])
+    );
+}
+
+#[tokio::test]
+async fn admin_errors() {
+    let app = TestApp::new();
+    assert_json_snapshot!(
+        "list_jobs_invalid_status",
+        snapshot(&app.get("/api/v1/admin/jobs?status=stuck").await, &[])
+    );
+}
+
+#[tokio::test]
+async fn problem_details() {
+    let app = TestApp::with_config(Config {
+        api_v2_enabled: true,
+        ..Default::default()
+    });
+    assert_json_snapshot!(
+        "v2_item_not_found",
+        snapshot(&app.get("/api/v2/items/missing").await, &[])
+    );
+}
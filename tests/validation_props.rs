@@ -0,0 +1,160 @@
+//! Arbitrary input never panics the services, it is either accepted or
+//! answered with a classified client error.
+
+use std::{future::Future, sync::Arc};
+
+use crud_rust::{
+    config::Config,
+    event::NoopPublisher,
+    model::{
+        context::Ctx,
+        error::{AppError, AppErrorCode},
+    },
+    repository::InMemoryRepository,
+    service::{
+        Service, ServiceApi,
+        user::{CreateUser, UpdateUser},
+    },
+};
+use proptest::prelude::*;
+
+fn service() -> Service<InMemoryRepository> {
+    Service::new(
+        Arc::new(Config::default()),
+        Arc::new(InMemoryRepository::new()),
+        Arc::new(NoopPublisher),
+    )
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Validation errors only name the given fields.
+fn is_validation_of(e: &AppError, fields: &[&str]) -> bool {
+    match &e.code {
+        AppErrorCode::Validation(errors) => {
+            !errors.is_empty() && errors.iter().all(|e| fields.contains(&e.field.as_str()))
+        }
+        _ => false,
+    }
+}
+
+fn is_lookup_error(e: &AppError) -> bool {
+    matches!(e.code, AppErrorCode::NotFound | AppErrorCode::InvalidInput)
+}
+
+/// Any unicode, with whitespace, control and combining characters mixed in
+/// more often than uniform sampling would.
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<String>(),
+        "\\PC{0,300}",
+        "[ \t\n\u{200b}\u{0301}\u{00a0}a-zA-Z0-9]{0,20}",
+    ]
+}
+
+fn email() -> impl Strategy<Value = String> {
+    prop_oneof![
+        text(),
+        "[a-zA-Z0-9._%+\"@-]{0,20}@[a-z0-9.-]{0,20}(\\.[a-z]{0,5})?",
+    ]
+}
+
+fn id() -> impl Strategy<Value = String> {
+    prop_oneof![
+        text(),
+        "[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}"
+    ]
+}
+
+proptest! {
+    #[test]
+    fn item_names_are_accepted_or_rejected_on_name(name in text()) {
+        let result = block_on(async {
+            let service = service();
+            let ctx = Ctx::default();
+            let item = service.create_item(&ctx, name).await?;
+            let stored = service.get_item(&ctx, item.id.clone()).await?;
+            Ok::<_, AppError>((item, stored))
+        });
+        match result {
+            Ok((item, stored)) => {
+                prop_assert_eq!(&item, &stored);
+                prop_assert_eq!(item.name.trim(), item.name.as_str());
+                prop_assert!((1..=255).contains(&item.name.chars().count()));
+            }
+            Err(e) => prop_assert!(is_validation_of(&e, &["name"]), "{:?}", e),
+        }
+    }
+
+    #[test]
+    fn item_ids_are_looked_up_or_rejected(id in id(), name in text()) {
+        let (get, update, delete) = block_on(async {
+            let service = service();
+            let ctx = Ctx::default();
+            (
+                service.get_item(&ctx, id.clone()).await,
+                service.update_item(&ctx, id.clone(), name, None).await,
+                service.delete_item(&ctx, id).await,
+            )
+        });
+        let get = get.unwrap_err();
+        prop_assert!(is_lookup_error(&get), "{:?}", get);
+        let update = update.unwrap_err();
+        prop_assert!(
+            is_lookup_error(&update) || is_validation_of(&update, &["id", "name"]),
+            "{:?}",
+            update
+        );
+        if let Err(e) = delete {
+            prop_assert!(is_lookup_error(&e), "{:?}", e);
+        }
+    }
+
+    #[test]
+    fn emails_are_accepted_or_rejected_on_email(email in email()) {
+        let result = block_on(async {
+            let service = service();
+            let ctx = Ctx::default();
+            let user = service.add_user(&ctx, CreateUser { email }).await?;
+            let stored = service.get_user(&ctx, &user.id).await?;
+            Ok::<_, AppError>((user, stored))
+        });
+        match result {
+            Ok((user, stored)) => {
+                prop_assert_eq!(&user, &stored);
+                prop_assert!(user.email.contains('@'));
+            }
+            Err(e) => prop_assert!(is_validation_of(&e, &["email"]), "{:?}", e),
+        }
+    }
+
+    #[test]
+    fn user_ids_are_looked_up_or_rejected(id in id(), email in email()) {
+        let (get, update, delete) = block_on(async {
+            let service = service();
+            let ctx = Ctx::default();
+            (
+                service.get_user(&ctx, &id).await,
+                service.update_user(&ctx, &id, UpdateUser { email }, None).await,
+                service.delete_user(&ctx, &id).await,
+            )
+        });
+        let get = get.unwrap_err();
+        prop_assert!(is_lookup_error(&get), "{:?}", get);
+        let update = update.unwrap_err();
+        prop_assert!(
+            is_lookup_error(&update) || is_validation_of(&update, &["id", "email"]),
+            "{:?}",
+            update
+        );
+        if let Err(e) = delete {
+            prop_assert!(is_lookup_error(&e), "{:?}", e);
+        }
+    }
+}